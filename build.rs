@@ -0,0 +1,15 @@
+fn main() {
+    // Compile the protobuf contract only when the gRPC service is enabled
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/vcards.proto");
+
+        let descriptors = protox::compile(["proto/vcards.proto"], ["proto"])
+            .expect("Failed to parse proto/vcards.proto");
+
+        tonic_build::configure()
+            .build_client(false)
+            .compile_fds(descriptors)
+            .expect("Failed to generate gRPC service");
+    }
+}
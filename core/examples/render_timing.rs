@@ -0,0 +1,98 @@
+// Timing harness for render_qr_image: draws a typical card at a few sizes, grey and colored, and
+// compares it with the way renders used to be drawn, a grey render recolored pixel by pixel with
+// ImageBuffer::from_fn. Also checks both give the same pixels.
+//
+//   cargo run --release -p vcard-core --example render_timing -- [--iterations 200]
+//
+// Timings are medians, in microseconds; run on an otherwise idle machine.
+
+use image::{DynamicImage, ImageBuffer, Luma, Rgb};
+use std::hint::black_box;
+use std::time::Instant;
+use vcard_core::{build_qr_code, module_px, parse_color, render_qr_image, RenderOptions};
+
+const USAGE: &str = "Usage: render_timing [--iterations <n>]";
+
+const CONTENT: &str = "BEGIN:VCARD\nVERSION:3.0\nN:Lovelace;Ada\nFN:Ada Lovelace\nORG:Analytical Engines\n\
+TEL;TYPE=CELL:+15551234567\nEMAIL:ada@example.com\nEND:VCARD";
+const SIZES: &[u32] = &[256, 512, 2048];
+const COLOR: &str = "#1a73e8";
+
+// What render_qr_image did before drawing into raw buffers
+fn previous_render(options: &RenderOptions) -> DynamicImage {
+    let code = build_qr_code(CONTENT, options).unwrap();
+    let unit = module_px(&code, options);
+    let grey = code.render::<Luma<u8>>().module_dimensions(unit, unit).build();
+    match &options.color {
+        Some(color) => {
+            let (r, g, b) = parse_color(color);
+            DynamicImage::ImageRgb8(ImageBuffer::from_fn(grey.width(), grey.height(), |x, y| {
+                if grey.get_pixel(x, y)[0] == 0 { Rgb([r, g, b]) } else { Rgb([255, 255, 255]) }
+            }))
+        }
+        None => DynamicImage::ImageLuma8(grey),
+    }
+}
+
+fn median_us(iterations: usize, mut render: impl FnMut() -> DynamicImage) -> f64 {
+    let mut samples: Vec<f64> = (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(render());
+            start.elapsed().as_secs_f64() * 1e6
+        })
+        .collect();
+    samples.sort_by(f64::total_cmp);
+    samples[samples.len() / 2]
+}
+
+fn format_us(us: f64) -> String {
+    if us >= 1000.0 { format!("{:.2} ms", us / 1000.0) } else { format!("{:.0} us", us) }
+}
+
+fn main() {
+    let mut iterations = 200;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--iterations", Some(n)) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => iterations = n,
+                _ => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    println!("{:>7}  {:<6} {:>10} {:>10} {:>8}  same pixels", "size", "", "before", "after", "speed-up");
+    let mut all_same = true;
+    for &size in SIZES {
+        for color in [None, Some(COLOR.to_string())] {
+            let options = RenderOptions { size: Some(size), color: color.clone(), ..Default::default() };
+            let same = previous_render(&options).as_bytes() == render_qr_image(CONTENT, &options).unwrap().as_bytes();
+            all_same &= same;
+
+            let before = median_us(iterations, || previous_render(&options));
+            let after = median_us(iterations, || render_qr_image(CONTENT, &options).unwrap());
+            println!(
+                "{:>4} px  {:<6} {:>10} {:>10} {:>7.1}x  {}",
+                size,
+                if color.is_some() { "color" } else { "grey" },
+                format_us(before),
+                format_us(after),
+                before / after,
+                if same { "yes" } else { "NO" },
+            );
+        }
+    }
+
+    if !all_same {
+        eprintln!("The two renders differ");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,107 @@
+// Contrast between the dark modules and the background, as the WCAG contrast ratio (1 to 21).
+// Scanners threshold the image into dark and light, so a pale color such as yellow on white reads
+// as all light and the code is refused before it is drawn. Combinations that decode on a good phone
+// camera but fail on older scanners or in poor light only get a warning
+
+use serde::Serialize;
+
+use crate::{is_hex_color, parse_color, RenderOptions};
+
+// Below this the code is refused
+pub const MIN_CONTRAST_RATIO: f32 = 2.0;
+// Below this it is drawn, with a warning
+pub const RECOMMENDED_CONTRAST_RATIO: f32 = 4.5;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    LowContrast,
+    // Light modules on a dark background, which many scanners do not try
+    Inverted,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ContrastWarning {
+    pub code: WarningCode,
+    pub message: String,
+    // The weakest pairing, rounded to two decimals
+    pub contrast_ratio: f32,
+}
+
+fn channel(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.03928 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn relative_luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+// The colors the dark modules are drawn in, by name: a gradient's two ends, or the color
+fn dark_colors(options: &RenderOptions) -> Vec<(&'static str, (u8, u8, u8))> {
+    match (&options.gradient, &options.color) {
+        (Some(gradient), _) => vec![("gradient start", parse_color(&gradient.start)), ("gradient end", parse_color(&gradient.end))],
+        (None, Some(color)) if is_hex_color(color) => vec![("color", parse_color(color))],
+        _ => vec![("color", (0, 0, 0))],
+    }
+}
+
+// The dark color with the least contrast against the background, and that contrast. A transparent
+// code is measured against white, the page it most often lands on
+fn weakest(options: &RenderOptions) -> (&'static str, f32) {
+    let background = options.background_rgb();
+    dark_colors(options)
+        .into_iter()
+        .map(|(name, color)| (name, contrast_ratio(color, background)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or(("color", 21.0))
+}
+
+fn rounded(ratio: f32) -> f32 {
+    (ratio * 100.0).round() / 100.0
+}
+
+// Err when the colors are too close for any scanner to tell apart
+pub fn check(options: &RenderOptions) -> Result<(), String> {
+    let (name, ratio) = weakest(options);
+    if ratio < MIN_CONTRAST_RATIO {
+        return Err(format!(
+            "{} has too little contrast with the background to scan ({:.2}:1, at least {}:1 is needed); pick a darker color",
+            name, rounded(ratio), MIN_CONTRAST_RATIO,
+        ));
+    }
+    Ok(())
+}
+
+// Combinations that pass check but may not scan everywhere
+pub fn warnings(options: &RenderOptions) -> Vec<ContrastWarning> {
+    let (name, ratio) = weakest(options);
+    let mut warnings = Vec::new();
+    if ratio < RECOMMENDED_CONTRAST_RATIO {
+        warnings.push(ContrastWarning {
+            code: WarningCode::LowContrast,
+            message: format!(
+                "{} has low contrast with the background ({:.2}:1); {}:1 or more scans more reliably",
+                name, rounded(ratio), RECOMMENDED_CONTRAST_RATIO,
+            ),
+            contrast_ratio: rounded(ratio),
+        });
+    }
+    let background = options.background_rgb();
+    let lighter = dark_colors(options)
+        .into_iter()
+        .find(|(_, color)| relative_luminance(*color) > relative_luminance(background));
+    if let Some((name, color)) = lighter {
+        warnings.push(ContrastWarning {
+            code: WarningCode::Inverted,
+            message: format!("{} is lighter than the background; some scanners only read dark modules on a light background", name),
+            contrast_ratio: rounded(contrast_ratio(color, background)),
+        });
+    }
+    warnings
+}
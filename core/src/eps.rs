@@ -0,0 +1,151 @@
+// Encapsulated PostScript, for print shops and prepress tools that want vector artwork. Drawn from
+// the code's module matrix as one path in module units and scaled to the same dimensions as the PNG,
+// one point per pixel, so it can be placed and resized freely without losing edges
+
+use qrcode::Color;
+
+use crate::gradient::{Gradient, GradientKind};
+use crate::style::{finder_origins, in_finder, FinderShape, ModuleShape};
+use crate::symbol::Symbol;
+use crate::{module_px, parse_color, print, render, rendered_edge, RenderOptions};
+
+// Path procedures, in a dictionary of their own so the file leaves the host's dictionaries alone
+const PROLOG: &str = "/vcardqr 16 dict def
+vcardqr begin
+/re { /h exch def /w exch def /y exch def /x exch def
+  x y moveto w 0 rlineto 0 h rlineto w neg 0 rlineto closepath } bind def
+/rs { /r exch def /s exch def /y exch def /x exch def
+  x r add y moveto
+  x s add y x s add y s add r arct
+  x s add y s add x y s add r arct
+  x y s add x y r arct
+  x y x s add y r arct closepath } bind def
+/dm { /y exch def /x exch def
+  x 0.5 add y moveto 0.5 0.5 rlineto -0.5 0.5 rlineto -0.5 -0.5 rlineto closepath } bind def
+end
+";
+
+fn rgb((r, g, b): (u8, u8, u8)) -> String {
+    format!("{:.4} {:.4} {:.4}", r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+// A rounded square as a subpath; arct needs a radius, so sharp corners are a plain rectangle
+fn rounded_square(x: f32, y: f32, side: f32, radius: f32) -> String {
+    if radius <= 0.0 {
+        format!("{} {} {} {} re\n", x, y, side, side)
+    } else {
+        format!("{} {} {} {} rs\n", x, y, side, radius)
+    }
+}
+
+// The dark modules as one path, in module coordinates from the code's top-left corner. Square
+// modules are merged into runs along each row, which keeps files for large codes small
+fn module_path(code: &Symbol, modules: ModuleShape, finders: FinderShape) -> String {
+    let width = code.width();
+    let origins = finder_origins(code);
+    let colors = code.to_colors();
+    let dark = |x: usize, y: usize| colors[y * width + x] == Color::Dark && !in_finder(&origins, x, y);
+
+    let mut path = String::new();
+    for y in 0..width {
+        let mut x = 0;
+        while x < width {
+            if !dark(x, y) {
+                x += 1;
+                continue;
+            }
+            match modules {
+                ModuleShape::Square => {
+                    let start = x;
+                    while x < width && dark(x, y) {
+                        x += 1;
+                    }
+                    path.push_str(&format!("{} {} {} 1 re\n", start, y, x - start));
+                    continue;
+                }
+                ModuleShape::Diamond => path.push_str(&format!("{} {} dm\n", x, y)),
+                shape => path.push_str(&rounded_square(x as f32, y as f32, 1.0, shape.radius())),
+            }
+            x += 1;
+        }
+    }
+
+    // Ring and eye of each finder; the ring's inner edge is a hole under the even-odd rule
+    let [outer, inner, eye] = finders.radii();
+    for &(x, y) in &origins {
+        let (x, y) = (x as f32, y as f32);
+        path.push_str(&rounded_square(x, y, 7.0, outer));
+        path.push_str(&rounded_square(x + 1.0, y + 1.0, 5.0, inner));
+        path.push_str(&rounded_square(x + 2.0, y + 2.0, 3.0, eye));
+    }
+    path
+}
+
+// A PostScript 3 shading matching the PNG's gradient, in module coordinates
+fn shading(gradient: &Gradient, width: f32) -> String {
+    let (start, end) = (rgb(parse_color(&gradient.start)), rgb(parse_color(&gradient.end)));
+    let (kind, coords) = match gradient.kind {
+        GradientKind::Linear => (2, format!("0 0 {} 0", width)),
+        GradientKind::Radial => {
+            let center = width / 2.0;
+            (3, format!("{c} {c} 0 {c} {c} {:.3}", center * std::f32::consts::SQRT_2, c = center))
+        }
+    };
+    format!(
+        "<< /ShadingType {} /ColorSpace /DeviceRGB /Coords [{}] /Extend [true true]\n   /Function << /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >> >> shfill\n",
+        kind, coords, start, end,
+    )
+}
+
+// The code as an EPS file at the same colors, shapes, quiet zone and dimensions as the PNG. A logo
+// is raster artwork, so only its box is drawn, in the background color, for the logo to be placed on
+pub fn document(code: &Symbol, options: &RenderOptions) -> String {
+    let edge = rendered_edge(code, options);
+    let unit = module_px(code, options);
+    let quiet_zone = options.quiet_zone_modules();
+    let (modules, finders) = (options.module_shape.unwrap_or_default(), options.finder_shape.unwrap_or_default());
+    // A print size gives the artwork its real size in points instead of one point per pixel
+    let points = options.print_size_mm.map_or(edge as f32, print::mm_to_points);
+
+    let mut eps = format!(
+        "%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 {b} {b}\n%%HiResBoundingBox: 0 0 {p:.3} {p:.3}\n%%Creator: vcard-qr-generator\n%%LanguageLevel: {}\n%%EndComments\n",
+        if options.gradient.is_some() { 3 } else { 2 },
+        b = points.ceil() as u32,
+        p = points,
+    );
+    eps.push_str(PROLOG);
+    eps.push_str("vcardqr begin\ngsave\n");
+    if options.print_size_mm.is_some() {
+        eps.push_str(&format!("{s:.6} {s:.6} scale\n", s = points / edge as f32));
+    }
+    if !options.transparent {
+        eps.push_str(&format!("{} setrgbcolor\n0 0 {e} {e} rectfill\n", rgb(options.background_rgb()), e = edge));
+    }
+
+    // Module units with the origin at the code's top-left corner, y running down as in the PNG
+    eps.push_str(&format!("0 {} translate\n{u} {u} neg scale\n{q} {q} translate\n", edge, u = unit, q = quiet_zone));
+    eps.push_str("newpath\n");
+    eps.push_str(&module_path(code, modules, finders));
+    match &options.gradient {
+        Some(gradient) => {
+            eps.push_str("gsave\neoclip newpath\n");
+            eps.push_str(&shading(gradient, code.width() as f32));
+            eps.push_str("grestore\nnewpath\n");
+        }
+        None => {
+            let dark = options.color.as_deref().map(parse_color).unwrap_or((0, 0, 0));
+            eps.push_str(&format!("{} setrgbcolor\neofill\n", rgb(dark)));
+        }
+    }
+
+    if options.logo.is_some() {
+        let area = render::logo_box(code, 1, 0);
+        eps.push_str(&format!(
+            "{} setrgbcolor\n{} {} {s} {s} rectfill\n",
+            rgb(options.background_rgb()), area.x, area.y, s = area.side,
+        ));
+    }
+
+    eps.push_str("grestore\nend\nshowpage\n%%EOF\n");
+    eps
+}
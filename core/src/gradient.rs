@@ -0,0 +1,118 @@
+// Gradient fills for the dark modules. The code is drawn as a black-on-white mask first and each
+// pixel is then mapped to its color by position, so the fill can change across the code; the SVG
+// renderer gets the same gradient as a paint server
+
+#[cfg(feature = "image")]
+use image::{DynamicImage, GrayImage, ImageBuffer};
+use serde::{Deserialize, Serialize};
+
+use crate::{is_hex_color, parse_color};
+
+// Id of the gradient element inside the SVG
+const SVG_ID: &str = "qr-gradient";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+pub enum GradientKind {
+    // Left to right across the modules
+    Linear,
+    // From the center out to the corners
+    Radial,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct Gradient {
+    pub kind: GradientKind,
+    // Hex colors at the left edge (linear) or center (radial), and at the far end
+    pub start: String,
+    pub end: String,
+}
+
+impl Gradient {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, color) in [("start", &self.start), ("end", &self.end)] {
+            if !is_hex_color(color) {
+                return Err(format!("gradient {} must be a hex color such as #1a73e8", name));
+            }
+        }
+        Ok(())
+    }
+
+    // How far along the gradient a pixel center is, 0 to 1, on a code `edge` px wide whose
+    // modules start `inset` px in from each side
+    fn position(&self, x: f32, y: f32, edge: f32, inset: f32) -> f32 {
+        let span = (edge - 2.0 * inset).max(1.0);
+        let t = match self.kind {
+            GradientKind::Linear => (x - inset) / span,
+            GradientKind::Radial => {
+                let center = edge / 2.0;
+                (x - center).hypot(y - center) / (span / 2.0 * std::f32::consts::SQRT_2)
+            }
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+fn mix(start: (u8, u8, u8), end: (u8, u8, u8), t: f32) -> [u8; 3] {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    [channel(start.0, end.0), channel(start.1, end.1), channel(start.2, end.2)]
+}
+
+// Colors a mask: dark pixels from the gradient, light ones with the background, which is left
+// transparent (white underneath) in RGBA when asked. `inset` is the quiet zone in pixels
+#[cfg(feature = "image")]
+pub fn paint(mask: &GrayImage, gradient: &Gradient, inset: u32, background: (u8, u8, u8), transparent: bool) -> DynamicImage {
+    let (start, end) = (parse_color(&gradient.start), parse_color(&gradient.end));
+    let edge = mask.width();
+    let channels = if transparent { 4 } else { 3 };
+    let light = [background.0, background.1, background.2, if transparent { 0 } else { 255 }];
+
+    let mut raw = Vec::with_capacity((edge * edge) as usize * channels);
+    for (x, y, pixel) in mask.enumerate_pixels() {
+        if pixel[0] < 128 {
+            let t = gradient.position(x as f32 + 0.5, y as f32 + 0.5, edge as f32, inset as f32);
+            raw.extend_from_slice(&mix(start, end, t));
+            if transparent {
+                raw.push(255);
+            }
+        } else {
+            raw.extend_from_slice(&light[..channels]);
+        }
+    }
+
+    if transparent {
+        DynamicImage::ImageRgba8(ImageBuffer::from_raw(edge, edge, raw).expect("buffer matches its edge"))
+    } else {
+        DynamicImage::ImageRgb8(ImageBuffer::from_raw(edge, edge, raw).expect("buffer matches its edge"))
+    }
+}
+
+// What the SVG's dark modules are filled with
+pub fn svg_fill() -> String {
+    format!("url(#{})", SVG_ID)
+}
+
+// The matching <defs> element, in the same pixel coordinates as the PNG
+pub fn svg_defs(gradient: &Gradient, edge: u32, inset: u32) -> String {
+    let (start, end) = (parse_color(&gradient.start), parse_color(&gradient.end));
+    let stops = format!(
+        "<stop offset=\"0\" stop-color=\"#{:02x}{:02x}{:02x}\"/><stop offset=\"1\" stop-color=\"#{:02x}{:02x}{:02x}\"/>",
+        start.0, start.1, start.2, end.0, end.1, end.2,
+    );
+    let element = match gradient.kind {
+        GradientKind::Linear => format!(
+            "<linearGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" x1=\"{}\" y1=\"0\" x2=\"{}\" y2=\"0\">{}</linearGradient>",
+            SVG_ID, inset, edge - inset, stops,
+        ),
+        GradientKind::Radial => {
+            let radius = (edge - 2 * inset) as f32 / 2.0 * std::f32::consts::SQRT_2;
+            format!(
+                "<radialGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" cx=\"{c}\" cy=\"{c}\" r=\"{:.1}\">{}</radialGradient>",
+                SVG_ID, radius, stops, c = edge as f32 / 2.0,
+            )
+        }
+    };
+    format!("<defs>{}</defs>", element)
+}
@@ -0,0 +1,960 @@
+// vCard encoding and QR rendering shared by the server and the bindings (wasm/, ffi/, python/), so every
+// frontend escapes, encodes and draws a card exactly the way the server does
+
+#[cfg(feature = "image")]
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+use qrcode::render::{svg, unicode::Dense1x2};
+use qrcode::EcLevel;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub mod contrast;
+pub mod eps;
+pub mod gradient;
+pub mod print;
+pub mod render;
+pub mod style;
+pub mod symbol;
+pub mod symbology;
+
+use symbol::Symbol;
+use symbology::Symbology;
+
+// Bounds on the edge length a stored card may ask its code to be rendered at
+pub const MIN_RENDER_SIZE: u32 = 64;
+pub const MAX_RENDER_SIZE: u32 = 2048;
+// Pixels per module; even the largest code stays under 4000 px at the top scale
+pub const MAX_RENDER_SCALE: u32 = 20;
+
+// Modules of light border drawn around every code, on each side, unless quiet_zone says otherwise;
+// the QR standard asks for four. Up to the maximum leaves room for print bleed
+pub const DEFAULT_QUIET_ZONE: u32 = 4;
+pub const MAX_QUIET_ZONE: u32 = 16;
+// Micro QR, Data Matrix and Aztec codes get two
+pub const MICRO_QUIET_ZONE: u32 = 2;
+
+// Versions, i.e. symbol sizes, and mask patterns a card may ask for
+pub const MAX_VERSION: u8 = 40;
+pub const MAX_MICRO_VERSION: u8 = 4;
+const MAX_MASK: u8 = 7;
+const MAX_MICRO_MASK: u8 = 3;
+// qrcode's module size when neither size nor scale is set
+const DEFAULT_MODULE_PX: u32 = 8;
+
+// Bounds on the X- properties a card may carry
+pub const MAX_EXTENSIONS: usize = 20;
+pub const MAX_EXTENSION_VALUE_LEN: usize = 256;
+
+// Fields that can carry a custom label, and how long one may be
+pub const LABELED_FIELDS: &[&str] = &["mobile", "work", "website"];
+pub const MAX_LABEL_LEN: usize = 40;
+
+// How a card's QR code is drawn; unset fields keep the renderer's defaults
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct RenderOptions {
+    pub color: Option<String>,
+    // Two-color fill for the dark modules; takes the place of color when both are set
+    pub gradient: Option<gradient::Gradient>,
+    // Fill behind the modules instead of white, e.g. to match a tinted page
+    pub background_color: Option<String>,
+    // Leave the background out altogether: the PNG gets an alpha channel and the SVG no fill, for
+    // pages whose own background shows through
+    #[serde(default)]
+    pub transparent: bool,
+    // Shape of each dark module, and of the three finder patterns in the corners; square by default
+    pub module_shape: Option<style::ModuleShape>,
+    pub finder_shape: Option<style::FinderShape>,
+    // Minimum edge length in pixels
+    pub size: Option<u32>,
+    pub ec_level: Option<ErrorCorrection>,
+    // Pixels per module, for sharp print output at a known resolution; with size as well, whichever
+    // asks for bigger modules wins
+    pub scale: Option<u32>,
+    // Printed edge in millimetres, quiet zone included: the code is drawn at the pixel size that
+    // makes it exactly this wide at dpi (300 when unset). Takes the place of size and scale
+    pub print_size_mm: Option<f32>,
+    // Resolution written into the PNG, so it prints at the intended size
+    pub dpi: Option<u32>,
+    // Light margin around the code in modules. Less than the standard four relies on the page around
+    // the code being light, e.g. when it is embedded in a padded frame
+    pub quiet_zone: Option<u32>,
+    // Symbol size, from 1 (21 modules a side) to 40, or M1 to M4 given as 1 to 4 with micro; the
+    // smallest that holds the card when unset
+    pub version: Option<u8>,
+    // Micro QR: one corner marker and a two-module quiet zone, for tiny labels. Holds only short
+    // payloads (35 digits or 21 characters at most), no logo, and error correction up to Q
+    #[serde(default)]
+    pub micro: bool,
+    // Mask pattern, 0 to 7, or 0 to 3 with micro; the one that scores best for scanning when unset
+    pub mask: Option<u8>,
+    // QR by default; data_matrix or aztec for badge printers that read those better at small sizes.
+    // ec_level, version, micro, mask, finder_shape and logo only apply to QR
+    #[serde(default)]
+    pub symbology: Symbology,
+    // PNG data URL drawn in the center on a white box. Left out of GraphQL, where it would only be bulk
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub logo: Option<String>,
+}
+
+// Ordered weakest to strongest
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+pub enum ErrorCorrection {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl From<ErrorCorrection> for EcLevel {
+    fn from(level: ErrorCorrection) -> Self {
+        match level {
+            ErrorCorrection::L => EcLevel::L,
+            ErrorCorrection::M => EcLevel::M,
+            ErrorCorrection::Q => EcLevel::Q,
+            ErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size.is_some_and(|size| !(MIN_RENDER_SIZE..=MAX_RENDER_SIZE).contains(&size)) {
+            return Err(format!("size must be between {} and {}", MIN_RENDER_SIZE, MAX_RENDER_SIZE));
+        }
+        if self.scale.is_some_and(|scale| !(1..=MAX_RENDER_SCALE).contains(&scale)) {
+            return Err(format!("scale must be between 1 and {}", MAX_RENDER_SCALE));
+        }
+        if self.dpi.is_some_and(|dpi| !(print::MIN_DPI..=print::MAX_DPI).contains(&dpi)) {
+            return Err(format!("dpi must be between {} and {}", print::MIN_DPI, print::MAX_DPI));
+        }
+        if let Some(size_mm) = self.print_size_mm {
+            if !(print::MIN_PRINT_SIZE_MM..=print::MAX_PRINT_SIZE_MM).contains(&size_mm) {
+                return Err(format!("print_size_mm must be between {} and {}", print::MIN_PRINT_SIZE_MM, print::MAX_PRINT_SIZE_MM));
+            }
+            if self.size.is_some() || self.scale.is_some() {
+                return Err("print_size_mm sets the size; leave size and scale unset".to_string());
+            }
+            if self.print_edge_px().is_some_and(|edge| edge > print::MAX_PRINT_EDGE_PX) {
+                return Err(format!("print_size_mm at this dpi is more than {} pixels; lower one of them", print::MAX_PRINT_EDGE_PX));
+            }
+        }
+        if self.quiet_zone.is_some_and(|modules| modules > MAX_QUIET_ZONE) {
+            return Err(format!("quiet_zone must be between 0 and {} modules", MAX_QUIET_ZONE));
+        }
+        if let Some(gradient) = &self.gradient {
+            gradient.validate()?;
+        }
+        if self.background_color.as_deref().is_some_and(|color| !is_hex_color(color)) {
+            return Err("background_color must be a hex color such as #1e1e1e".to_string());
+        }
+        if self.transparent && self.background_color.is_some() {
+            return Err("background_color and transparent cannot both be set".to_string());
+        }
+        if let Some(logo) = &self.logo {
+            render::validate_logo(logo)?;
+        }
+        let (max_version, max_mask) = if self.micro { (MAX_MICRO_VERSION, MAX_MICRO_MASK) } else { (MAX_VERSION, MAX_MASK) };
+        if self.version.is_some_and(|version| !(1..=max_version).contains(&version)) {
+            return Err(format!("version must be between 1 and {}{}", max_version, if self.micro { " for Micro QR" } else { "" }));
+        }
+        if self.mask.is_some_and(|mask| mask > max_mask) {
+            return Err(format!("mask must be between 0 and {}{}", max_mask, if self.micro { " for Micro QR" } else { "" }));
+        }
+        if self.micro && self.logo.is_some() {
+            return Err("Micro QR codes are too small for a logo".to_string());
+        }
+        if self.micro && self.ec_level == Some(ErrorCorrection::H) {
+            return Err("Micro QR codes support error correction up to Q".to_string());
+        }
+        if self.symbology != Symbology::Qr {
+            let qr_only = [
+                ("ec_level", self.ec_level.is_some()),
+                ("version", self.version.is_some()),
+                ("micro", self.micro),
+                ("mask", self.mask.is_some()),
+                ("finder_shape", self.finder_shape.is_some()),
+                ("logo", self.logo.is_some()),
+            ];
+            if let Some((name, _)) = qr_only.iter().find(|(_, set)| *set) {
+                return Err(format!("{} only applies to QR codes, not to a {}", name, self.symbology.name()));
+            }
+        }
+        contrast::check(self)
+    }
+
+    // How far content overflows the largest code these options allow; None when it fits
+    pub fn overflow(&self, content: &str) -> Option<symbol::Overflow> {
+        if self.symbology != Symbology::Qr {
+            return symbology::overflow(content, self.symbology);
+        }
+        let level = self.effective_ec_level().unwrap_or(ErrorCorrection::M);
+        symbol::overflow(content.as_bytes(), level.into(), self.micro, self.version)
+    }
+
+    // A card's options: a vCard never fits in a Micro QR code, so micro is only for short text
+    pub fn validate_for_card(&self) -> Result<(), String> {
+        self.validate()?;
+        if self.micro {
+            return Err("micro is only for short text; a vCard does not fit in a Micro QR code".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn quiet_zone_modules(&self) -> u32 {
+        self.quiet_zone.unwrap_or(self.standard_quiet_zone())
+    }
+
+    // The quiet zone for this kind of code when quiet_zone is unset, as Symbol::standard_quiet_zone
+    fn standard_quiet_zone(&self) -> u32 {
+        if self.micro || self.symbology != Symbology::Qr { MICRO_QUIET_ZONE } else { DEFAULT_QUIET_ZONE }
+    }
+
+    // Edge of the image in pixels when print_size_mm is set
+    pub fn print_edge_px(&self) -> Option<u32> {
+        self.print_size_mm.map(|size_mm| print::edge_px(size_mm, self.dpi.unwrap_or(print::DEFAULT_DPI)))
+    }
+
+    // The color the light modules, quiet zone and logo box are drawn in
+    pub fn background_rgb(&self) -> (u8, u8, u8) {
+        self.background_color.as_deref().map(parse_color).unwrap_or((255, 255, 255))
+    }
+
+    // The level the code is encoded at. A logo hides the modules under it, so it raises the level
+    // to at least Q; error correction restores what the logo covers
+    pub fn effective_ec_level(&self) -> Option<ErrorCorrection> {
+        match self.logo {
+            Some(_) => Some(self.ec_level.unwrap_or(ErrorCorrection::M).max(ErrorCorrection::Q)),
+            None => self.ec_level,
+        }
+    }
+}
+
+// Messenger accounts, each written into the card as a typed deep-link URL
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct Messengers {
+    // Phone numbers in international format, e.g. +15551234567
+    pub whatsapp: Option<String>,
+    pub signal: Option<String>,
+    // Username, with or without the @
+    pub telegram: Option<String>,
+}
+
+// A messenger's deep link, as written into the card and shown as a button on landing pages
+#[derive(Serialize)]
+pub struct MessengerLink {
+    pub kind: &'static str,
+    pub label: &'static str,
+    pub url: String,
+}
+
+// The digits of a +-prefixed phone number, allowing the usual separators
+fn international_digits(number: &str) -> Option<String> {
+    let rest = number.strip_prefix('+')?;
+    if !rest.chars().all(|c| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')')) {
+        return None;
+    }
+    let digits: String = rest.chars().filter(char::is_ascii_digit).collect();
+    (8..=15).contains(&digits.len()).then_some(digits)
+}
+
+// Telegram usernames are 5-32 letters, digits and underscores, starting with a letter
+fn telegram_username(handle: &str) -> Option<&str> {
+    let name = handle.strip_prefix('@').unwrap_or(handle);
+    let valid = (5..=32).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(name)
+}
+
+impl Messengers {
+    // Blank handles become absent, the way cards store them
+    pub fn cleaned(&self) -> Messengers {
+        let clean = |value: &Option<String>| clean(value.as_deref()).map(str::to_string);
+        Messengers {
+            whatsapp: clean(&self.whatsapp),
+            signal: clean(&self.signal),
+            telegram: clean(&self.telegram),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (field, number) in [("whatsapp", &self.whatsapp), ("signal", &self.signal)] {
+            if clean(number.as_deref()).is_some_and(|number| international_digits(number).is_none()) {
+                return Err(format!("{} must be a phone number in international format, e.g. +15551234567", field));
+            }
+        }
+        if clean(self.telegram.as_deref()).is_some_and(|handle| telegram_username(handle).is_none()) {
+            return Err("telegram must be a username of 5-32 letters, digits or underscores, starting with a letter".to_string());
+        }
+        Ok(())
+    }
+
+    // Links for the handles that are set and valid, in a fixed order
+    pub fn links(&self) -> Vec<MessengerLink> {
+        let mut links = Vec::new();
+        if let Some(digits) = clean(self.whatsapp.as_deref()).and_then(international_digits) {
+            links.push(MessengerLink { kind: "whatsapp", label: "WhatsApp", url: format!("https://wa.me/{}", digits) });
+        }
+        if let Some(name) = clean(self.telegram.as_deref()).and_then(telegram_username) {
+            links.push(MessengerLink { kind: "telegram", label: "Telegram", url: format!("https://t.me/{}", name) });
+        }
+        if let Some(digits) = clean(self.signal.as_deref()).and_then(international_digits) {
+            links.push(MessengerLink { kind: "signal", label: "Signal", url: format!("https://signal.me/#p/+{}", digits) });
+        }
+        links
+    }
+}
+
+// Where a freelancer can be paid
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct Payment {
+    // PayPal.me username or link
+    pub paypal: Option<String>,
+    pub iban: Option<String>,
+}
+
+// The username of a PayPal.me link or bare username (letters and digits, up to 20)
+fn paypal_username(value: &str) -> Option<&str> {
+    let rest = value.strip_prefix("https://").or_else(|| value.strip_prefix("http://")).unwrap_or(value);
+    let rest = rest.strip_prefix("www.").unwrap_or(rest);
+    let name = rest.strip_prefix("paypal.me/").unwrap_or(rest).trim_end_matches('/');
+    let valid = (1..=20).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric());
+    valid.then_some(name)
+}
+
+// An IBAN without spaces, uppercased, if its format and ISO 13616 check digits are right
+pub fn normalize_iban(value: &str) -> Option<String> {
+    let iban: String = value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase();
+    let bytes = iban.as_bytes();
+    let well_formed = (15..=34).contains(&iban.len())
+        && bytes[..2].iter().all(u8::is_ascii_alphabetic)
+        && bytes[2..4].iter().all(u8::is_ascii_digit)
+        && bytes.iter().all(u8::is_ascii_alphanumeric);
+    if !well_formed {
+        return None;
+    }
+
+    // Country code and check digits move to the end, letters become 10-35, and the whole number
+    // must leave 1 modulo 97
+    let remainder = iban[4..].chars().chain(iban[..4].chars()).fold(0u32, |acc, c| {
+        let value = c.to_digit(36).unwrap_or(0);
+        if value < 10 { (acc * 10 + value) % 97 } else { (acc * 100 + value) % 97 }
+    });
+    (remainder == 1).then_some(iban)
+}
+
+impl Payment {
+    // Blank fields become absent, PayPal becomes its link and the IBAN loses its spaces, the way
+    // cards store them
+    pub fn cleaned(&self) -> Payment {
+        Payment {
+            paypal: clean(self.paypal.as_deref()).map(|paypal| self.paypal_url().unwrap_or_else(|| paypal.to_string())),
+            iban: clean(self.iban.as_deref()).map(|iban| normalize_iban(iban).unwrap_or_else(|| iban.to_string())),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if clean(self.paypal.as_deref()).is_some_and(|paypal| paypal_username(paypal).is_none()) {
+            return Err("paypal must be a PayPal.me username or link, e.g. https://paypal.me/janedoe".to_string());
+        }
+        if clean(self.iban.as_deref()).is_some_and(|iban| normalize_iban(iban).is_none()) {
+            return Err("iban is not a valid IBAN".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn paypal_url(&self) -> Option<String> {
+        clean(self.paypal.as_deref()).and_then(paypal_username).map(|name| format!("https://paypal.me/{}", name))
+    }
+
+    pub fn valid_iban(&self) -> Option<String> {
+        clean(self.iban.as_deref()).and_then(normalize_iban)
+    }
+}
+
+// The fields a vCard is made from, borrowed from whatever holds them
+#[derive(Default)]
+pub struct Contact<'a> {
+    pub first_name: &'a str,
+    pub last_name: &'a str,
+    pub mobile: Option<&'a str>,
+    pub work: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub company: Option<&'a str>,
+    pub role: Option<&'a str>,
+    pub street: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub state: Option<&'a str>,
+    pub postal_code: Option<&'a str>,
+    // ISO 3166-1 alpha-2 code, which picks the address layout
+    pub country: Option<&'a str>,
+    pub website: Option<&'a str>,
+    // X- properties, appended after the standard ones
+    pub extensions: Option<&'a BTreeMap<String, String>>,
+    // Custom labels by field name, e.g. "website" => "Booking"; shown by clients that read X-ABLabel
+    pub labels: Option<&'a BTreeMap<String, String>>,
+    pub messengers: Option<&'a Messengers>,
+    pub payment: Option<&'a Payment>,
+}
+
+// An owned contact with its render options, in the JSON shape of the server's /api/generate body
+#[derive(Deserialize, Default)]
+pub struct ContactRecord {
+    pub first_name: String,
+    pub last_name: String,
+    pub mobile: Option<String>,
+    pub work: Option<String>,
+    pub email: Option<String>,
+    pub company: Option<String>,
+    pub role: Option<String>,
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub website: Option<String>,
+    #[serde(default)]
+    pub extensions: BTreeMap<String, String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub messengers: Messengers,
+    #[serde(flatten)]
+    pub payment: Payment,
+    #[serde(flatten)]
+    pub render: RenderOptions,
+}
+
+impl ContactRecord {
+    pub fn contact(&self) -> Contact<'_> {
+        Contact {
+            first_name: &self.first_name,
+            last_name: &self.last_name,
+            mobile: self.mobile.as_deref(),
+            work: self.work.as_deref(),
+            email: self.email.as_deref(),
+            company: self.company.as_deref(),
+            role: self.role.as_deref(),
+            street: self.street.as_deref(),
+            city: self.city.as_deref(),
+            state: self.state.as_deref(),
+            postal_code: self.postal_code.as_deref(),
+            country: self.country.as_deref(),
+            website: self.website.as_deref(),
+            extensions: Some(&self.extensions),
+            labels: Some(&self.labels),
+            messengers: Some(&self.messengers),
+            payment: Some(&self.payment),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.render.validate_for_card()?;
+        validate_address(self.postal_code.as_deref(), self.country.as_deref())?;
+        validate_extensions(&self.extensions)?;
+        validate_labels(&self.labels)?;
+        self.messengers.validate()?;
+        self.payment.validate()
+    }
+}
+
+// X- followed by letters, digits and dashes, as vCard allows for extension property names
+pub fn is_extension_name(name: &str) -> bool {
+    name.len() > 2
+        && name[..2].eq_ignore_ascii_case("X-")
+        && name[2..].chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+pub fn validate_extensions(extensions: &BTreeMap<String, String>) -> Result<(), String> {
+    if extensions.len() > MAX_EXTENSIONS {
+        return Err(format!("at most {} extensions are allowed", MAX_EXTENSIONS));
+    }
+    let mut seen = std::collections::HashSet::new();
+    for (name, value) in extensions {
+        if !is_extension_name(name) {
+            return Err(format!("extension {:?} must be named X- followed by letters, digits or dashes", name));
+        }
+        // Property names are case-insensitive, so X-Foo and X-FOO would be the same property
+        if !seen.insert(name.to_ascii_uppercase()) {
+            return Err(format!("extension {} is given more than once", name));
+        }
+        if value.chars().count() > MAX_EXTENSION_VALUE_LEN {
+            return Err(format!("extension {} must be at most {} characters", name, MAX_EXTENSION_VALUE_LEN));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_labels(labels: &BTreeMap<String, String>) -> Result<(), String> {
+    for (field, label) in labels {
+        if !LABELED_FIELDS.contains(&field.as_str()) {
+            return Err(format!("labels may only be given for {}, not {:?}", LABELED_FIELDS.join(", "), field));
+        }
+        if label.trim().chars().count() > MAX_LABEL_LEN {
+            return Err(format!("label for {} must be at most {} characters", field, MAX_LABEL_LEN));
+        }
+    }
+    Ok(())
+}
+
+pub const MAX_POSTAL_CODE_LEN: usize = 16;
+
+pub fn validate_address(postal_code: Option<&str>, country: Option<&str>) -> Result<(), String> {
+    if clean(postal_code).is_some_and(|code| code.chars().count() > MAX_POSTAL_CODE_LEN) {
+        return Err(format!("postal_code must be at most {} characters", MAX_POSTAL_CODE_LEN));
+    }
+    if clean(country).is_some_and(|country| country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic())) {
+        return Err("country must be a two-letter ISO 3166 code, e.g. US or DE".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum AddressPart {
+    Street,
+    City,
+    State,
+    PostalCode,
+}
+
+// One line of an address layout: parts, each with the separator put before it when something
+// precedes it on the line
+type AddressLine = &'static [(&'static str, AddressPart)];
+
+use AddressPart::{City, PostalCode, State, Street};
+
+const US_LAYOUT: &[AddressLine] = &[&[("", Street)], &[("", City), (", ", State), (" ", PostalCode)]];
+const POSTAL_FIRST_LAYOUT: &[AddressLine] = &[&[("", Street)], &[("", PostalCode), (" ", City)], &[("", State)]];
+const UK_LAYOUT: &[AddressLine] = &[&[("", Street)], &[("", City)], &[("", State)], &[("", PostalCode)]];
+const CITY_POSTAL_LAYOUT: &[AddressLine] = &[&[("", Street)], &[("", City), (" ", PostalCode)], &[("", State)]];
+const IT_LAYOUT: &[AddressLine] = &[&[("", Street)], &[("", PostalCode), (" ", City), (" ", State)]];
+const EAST_ASIA_LAYOUT: &[AddressLine] = &[&[("", PostalCode)], &[("", State), (" ", City)], &[("", Street)]];
+
+// Address conventions by country: English name for the ADR country component and the line layout.
+// Countries not listed use the US layout and their code as the name
+const COUNTRY_FORMATS: &[(&str, &str, &[AddressLine])] = &[
+    ("AT", "Austria", POSTAL_FIRST_LAYOUT),
+    ("AU", "Australia", US_LAYOUT),
+    ("BE", "Belgium", POSTAL_FIRST_LAYOUT),
+    ("BR", "Brazil", US_LAYOUT),
+    ("CA", "Canada", US_LAYOUT),
+    ("CH", "Switzerland", POSTAL_FIRST_LAYOUT),
+    ("CN", "China", EAST_ASIA_LAYOUT),
+    ("DE", "Germany", POSTAL_FIRST_LAYOUT),
+    ("DK", "Denmark", POSTAL_FIRST_LAYOUT),
+    ("ES", "Spain", POSTAL_FIRST_LAYOUT),
+    ("FI", "Finland", POSTAL_FIRST_LAYOUT),
+    ("FR", "France", POSTAL_FIRST_LAYOUT),
+    ("GB", "United Kingdom", UK_LAYOUT),
+    ("IE", "Ireland", UK_LAYOUT),
+    ("IN", "India", CITY_POSTAL_LAYOUT),
+    ("IT", "Italy", IT_LAYOUT),
+    ("JP", "Japan", EAST_ASIA_LAYOUT),
+    ("KH", "Cambodia", CITY_POSTAL_LAYOUT),
+    ("KR", "South Korea", EAST_ASIA_LAYOUT),
+    ("MX", "Mexico", POSTAL_FIRST_LAYOUT),
+    ("NL", "Netherlands", POSTAL_FIRST_LAYOUT),
+    ("NO", "Norway", POSTAL_FIRST_LAYOUT),
+    ("NZ", "New Zealand", CITY_POSTAL_LAYOUT),
+    ("PL", "Poland", POSTAL_FIRST_LAYOUT),
+    ("PT", "Portugal", POSTAL_FIRST_LAYOUT),
+    ("SE", "Sweden", POSTAL_FIRST_LAYOUT),
+    ("SG", "Singapore", CITY_POSTAL_LAYOUT),
+    ("TH", "Thailand", CITY_POSTAL_LAYOUT),
+    ("US", "United States", US_LAYOUT),
+    ("VN", "Vietnam", CITY_POSTAL_LAYOUT),
+];
+
+fn country_format(code: &str) -> Option<&'static (&'static str, &'static str, &'static [AddressLine])> {
+    COUNTRY_FORMATS.iter().find(|(known, _, _)| known.eq_ignore_ascii_case(code))
+}
+
+// English name of a country code, or the code itself when it is not in the table
+pub fn country_name(code: &str) -> String {
+    country_format(code).map_or_else(|| code.to_ascii_uppercase(), |(_, name, _)| name.to_string())
+}
+
+// The contact's address as it is written in its country, one entry per line, ending with the
+// country name when one is set. Empty when there is no address
+pub fn address_lines(contact: &Contact) -> Vec<String> {
+    let country = clean(contact.country);
+    let layout = country.and_then(country_format).map_or(US_LAYOUT, |(_, _, layout)| layout);
+    let part = |part: AddressPart| match part {
+        Street => clean(contact.street),
+        City => clean(contact.city),
+        State => clean(contact.state),
+        PostalCode => clean(contact.postal_code),
+    };
+
+    let mut lines: Vec<String> = layout
+        .iter()
+        .map(|line| {
+            let mut text = String::new();
+            for (separator, value) in line.iter().filter_map(|(separator, p)| part(*p).map(|value| (separator, value))) {
+                if !text.is_empty() {
+                    text.push_str(separator);
+                }
+                text.push_str(value);
+            }
+            text
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    if !lines.is_empty() {
+        if let Some(country) = country {
+            lines.push(country_name(country));
+        }
+    }
+    lines
+}
+
+// vCard 3.0 text escaping, so a value cannot start a new property or split into components
+pub fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+// A contact field as it is stored and encoded: trimmed, with blanks treated as absent
+pub fn clean(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+// Writes properties, grouping each labeled one with its X-ABLabel as itemN (Apple's convention,
+// which other clients ignore, keeping the TYPE they already understand)
+struct Writer<'a> {
+    vcard: String,
+    labels: Option<&'a BTreeMap<String, String>>,
+    items: usize,
+}
+
+impl Writer<'_> {
+    fn push(&mut self, property: &str, value: &str) {
+        self.vcard.push_str(&format!("{}:{}\n", property, value));
+    }
+
+    fn push_grouped(&mut self, property: &str, value: &str, label: &str) {
+        self.items += 1;
+        self.vcard.push_str(&format!("item{0}.{1}:{2}\nitem{0}.X-ABLabel:{3}\n", self.items, property, value, escape_text(label)));
+    }
+
+    fn push_labeled(&mut self, field: &str, property: &str, value: &str) {
+        match self.labels.and_then(|labels| clean(labels.get(field).map(String::as_str))) {
+            Some(label) => self.push_grouped(property, value, label),
+            None => self.push(property, value),
+        }
+    }
+}
+
+pub fn generate_vcard(contact: &Contact) -> String {
+    let mut vcard = Writer {
+        vcard: String::from("BEGIN:VCARD\nVERSION:3.0\n"),
+        labels: contact.labels,
+        items: 0,
+    };
+    let (first_name, last_name) = (contact.first_name.trim(), contact.last_name.trim());
+
+    // Name
+    vcard.push("FN", &format!("{} {}", first_name, last_name));
+    vcard.push("N", &format!("{};{};;;", last_name, first_name));
+
+    // Phone numbers
+    if let Some(mobile) = clean(contact.mobile) {
+        vcard.push_labeled("mobile", "TEL;TYPE=CELL", mobile);
+    }
+    if let Some(work) = clean(contact.work) {
+        vcard.push_labeled("work", "TEL;TYPE=WORK", work);
+    }
+
+    // Email
+    if let Some(email) = clean(contact.email) {
+        vcard.push("EMAIL", email);
+    }
+
+    // Organization
+    if let Some(company) = clean(contact.company) {
+        vcard.push("ORG", company);
+    }
+    if let Some(role) = clean(contact.role) {
+        vcard.push("TITLE", role);
+    }
+
+    // Address, plus a LABEL laid out the way the card's country writes addresses when it has one
+    let (street, city, state) = (clean(contact.street), clean(contact.city), clean(contact.state));
+    let postal_code = clean(contact.postal_code);
+    if street.is_some() || city.is_some() || state.is_some() || postal_code.is_some() {
+        vcard.push("ADR;TYPE=WORK", &format!(";;{};{};{};{};{}",
+            street.unwrap_or_default(),
+            city.unwrap_or_default(),
+            state.unwrap_or_default(),
+            postal_code.unwrap_or_default(),
+            clean(contact.country).map(country_name).unwrap_or_default()
+        ));
+        if clean(contact.country).is_some() {
+            let label: Vec<String> = address_lines(contact).iter().map(|line| escape_text(line)).collect();
+            vcard.push("LABEL;TYPE=WORK", &label.join("\\n"));
+        }
+    }
+
+    // Website
+    if let Some(website) = clean(contact.website) {
+        vcard.push_labeled("website", "URL", website);
+    }
+
+    // Messengers
+    for link in contact.messengers.map(Messengers::links).unwrap_or_default() {
+        vcard.push_grouped(&format!("URL;TYPE={}", link.kind.to_ascii_uppercase()), &link.url, link.label);
+    }
+
+    // Payment
+    if let Some(payment) = contact.payment {
+        if let Some(url) = payment.paypal_url() {
+            vcard.push_grouped("URL;TYPE=PAYPAL", &url, "PayPal");
+        }
+        if let Some(iban) = payment.valid_iban() {
+            vcard.push("X-IBAN", &iban);
+        }
+    }
+
+    // Extensions
+    for (name, value) in contact.extensions.into_iter().flatten() {
+        if let Some(value) = clean(Some(value)) {
+            vcard.push(&name.to_ascii_uppercase(), &escape_text(value));
+        }
+    }
+
+    let mut vcard = vcard.vcard;
+    vcard.push_str("END:VCARD");
+    vcard
+}
+
+// Six hex digits with an optional leading #, the form parse_color reads
+pub fn is_hex_color(color: &str) -> bool {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn parse_color(color_str: &str) -> (u8, u8, u8) {
+    let hex = color_str.trim_start_matches('#');
+    if hex.len() == 6 {
+        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
+        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
+        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
+        (r, g, b)
+    } else {
+        (0, 0, 0)
+    }
+}
+
+// The code itself, before it is drawn in any format. Checks the options first, so no entry point
+// can ask for an edge past MAX_RENDER_SIZE
+pub fn build_qr_code(content: &str, options: &RenderOptions) -> Result<Symbol, String> {
+    options.validate()?;
+    if options.symbology != Symbology::Qr {
+        return symbology::encode(content, options.symbology);
+    }
+    let level = options.effective_ec_level().unwrap_or(ErrorCorrection::M);
+    Symbol::build(content.as_bytes(), level.into(), options.micro, options.version, options.mask)
+        .map_err(|e| e.to_string())
+}
+
+// Pixels per module for this code: the scale, or enough to reach the size, whichever is bigger. For
+// a print size, as many as fit its edge
+pub fn module_px(code: &Symbol, options: &RenderOptions) -> u32 {
+    let modules = code.width() as u32 + 2 * options.quiet_zone_modules();
+    if let Some(edge) = options.print_edge_px() {
+        return (edge / modules).max(1);
+    }
+    let for_size = options.size.map(|size| size.div_ceil(modules));
+    options.scale.into_iter().chain(for_size).max().unwrap_or(DEFAULT_MODULE_PX)
+}
+
+// Edge of the rendered code in pixels, quiet zone included, before the PNG is padded to a print size
+pub fn rendered_edge(code: &Symbol, options: &RenderOptions) -> u32 {
+    (code.width() as u32 + 2 * options.quiet_zone_modules()) * module_px(code, options)
+}
+
+// Draws the code straight into a raw pixel buffer of N channels: each module row becomes one pixel
+// row, copied unit times, which is far cheaper than setting pixels one by one. Returns the edge
+#[cfg(feature = "image")]
+fn draw_modules<const N: usize>(code: &Symbol, unit: u32, quiet_zone: u32, dark: [u8; N], light: [u8; N]) -> (u32, Vec<u8>) {
+    let modules = code.width();
+    let unit = unit as usize;
+    let border = quiet_zone as usize * unit;
+    let edge = modules * unit + 2 * border;
+
+    let light_row = light.repeat(edge);
+    let mut raw = Vec::with_capacity(edge * edge * N);
+    let mut row = Vec::with_capacity(edge * N);
+    for _ in 0..border {
+        raw.extend_from_slice(&light_row);
+    }
+    for module_row in code.to_colors().chunks(modules) {
+        row.clear();
+        row.extend_from_slice(&light_row[..border * N]);
+        for &color in module_row {
+            let pixel = if color == qrcode::Color::Dark { &dark } else { &light };
+            for _ in 0..unit {
+                row.extend_from_slice(pixel);
+            }
+        }
+        row.extend_from_slice(&light_row[..border * N]);
+        for _ in 0..unit {
+            raw.extend_from_slice(&row);
+        }
+    }
+    for _ in 0..border {
+        raw.extend_from_slice(&light_row);
+    }
+    (edge as u32, raw)
+}
+
+// The code's pixels in N channels: straight from the module rows when every shape is square, or
+// through the styling engine otherwise
+#[cfg(feature = "image")]
+fn draw_code<const N: usize>(code: &Symbol, unit: u32, options: &RenderOptions, dark: [u8; N], light: [u8; N]) -> (u32, Vec<u8>) {
+    if style::is_styled(options.module_shape, options.finder_shape) {
+        let (modules, finders) = (options.module_shape.unwrap_or_default(), options.finder_shape.unwrap_or_default());
+        style::draw(code, unit, options.quiet_zone_modules(), modules, finders, dark, light)
+    } else {
+        draw_modules(code, unit, options.quiet_zone_modules(), dark, light)
+    }
+}
+
+// Black on white in grey, RGB when a color, gradient or background is set, or RGBA when transparent.
+// Transparent pixels are white underneath, so anything that ignores alpha (scanning, PDFs) sees a
+// white background
+#[cfg(feature = "image")]
+pub fn render_qr_image(content: &str, options: &RenderOptions) -> Result<DynamicImage, String> {
+    let code = build_qr_code(content, options)?;
+    if let Some(edge) = options.print_edge_px() {
+        let modules = code.width() as u32 + 2 * options.quiet_zone_modules();
+        if edge < modules {
+            return Err(format!("This code needs at least {} pixels across; raise print_size_mm or dpi", modules));
+        }
+    }
+    let unit = module_px(&code, options);
+    let (r, g, b) = options.color.as_deref().map(parse_color).unwrap_or((0, 0, 0));
+
+    let image = if let Some(gradient) = &options.gradient {
+        let (edge, raw) = draw_code(&code, unit, options, [0], [255]);
+        let mask = ImageBuffer::from_raw(edge, edge, raw).expect("buffer matches its edge");
+        gradient::paint(&mask, gradient, options.quiet_zone_modules() * unit, options.background_rgb(), options.transparent)
+    } else if options.transparent {
+        let (edge, raw) = draw_code(&code, unit, options, [r, g, b, 255], [255, 255, 255, 0]);
+        DynamicImage::ImageRgba8(ImageBuffer::from_raw(edge, edge, raw).expect("buffer matches its edge"))
+    } else if options.color.is_some() || options.background_color.is_some() {
+        let (br, bg, bb) = options.background_rgb();
+        let (edge, raw) = draw_code(&code, unit, options, [r, g, b], [br, bg, bb]);
+        DynamicImage::ImageRgb8(ImageBuffer::from_raw(edge, edge, raw).expect("buffer matches its edge"))
+    } else {
+        let (edge, raw) = draw_code(&code, unit, options, [0], [255]);
+        DynamicImage::ImageLuma8(ImageBuffer::from_raw(edge, edge, raw).expect("buffer matches its edge"))
+    };
+
+    let image = match &options.logo {
+        Some(logo) => {
+            let logo = render::decode_logo(logo)?;
+            let area = render::logo_box(&code, unit, options.quiet_zone_modules());
+            render::overlay_logo(image, &logo, area, options.background_rgb())
+        }
+        None => image,
+    };
+    match options.print_edge_px() {
+        Some(edge) => Ok(print::pad(image, edge, options.background_rgb(), options.transparent)),
+        None => Ok(image),
+    }
+}
+
+#[cfg(feature = "image")]
+pub fn encode_png(img: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buffer, ImageFormat::Png)?;
+    Ok(buffer.into_inner())
+}
+
+// A code's PNG, carrying the options' dpi (the default print resolution for a print size) as its
+// physical resolution
+#[cfg(feature = "image")]
+pub fn encode_qr_png(img: &DynamicImage, options: &RenderOptions) -> Result<Vec<u8>, image::ImageError> {
+    let png = encode_png(img)?;
+    let dpi = options.dpi.or(options.print_size_mm.map(|_| print::DEFAULT_DPI));
+    Ok(match dpi {
+        Some(dpi) => print::set_png_dpi(png, dpi),
+        None => png,
+    })
+}
+
+// Same modules, colors and dimensions as the PNG, as standalone SVG markup
+pub fn render_qr_svg(content: &str, options: &RenderOptions) -> Result<String, String> {
+    let code = build_qr_code(content, options)?;
+    let (r, g, b) = options.color.as_deref().map(parse_color).unwrap_or((0, 0, 0));
+    let dark = match &options.gradient {
+        Some(_) => gradient::svg_fill(),
+        None => format!("#{:02x}{:02x}{:02x}", r, g, b),
+    };
+
+    let (br, bg, bb) = options.background_rgb();
+    let background = format!("#{:02x}{:02x}{:02x}", br, bg, bb);
+    let light = if options.transparent { "none" } else { &background };
+
+    let unit = module_px(&code, options);
+    // qrcode's own renderer only draws the standard quiet zone
+    let svg = if style::is_styled(options.module_shape, options.finder_shape) || options.quiet_zone_modules() != code.standard_quiet_zone() {
+        let (modules, finders) = (options.module_shape.unwrap_or_default(), options.finder_shape.unwrap_or_default());
+        style::svg(&code, unit, options.quiet_zone_modules(), modules, finders, &dark, light)
+    } else {
+        code.render::<svg::Color>()
+            .dark_color(svg::Color(&dark))
+            .light_color(svg::Color(light))
+            .module_dimensions(unit, unit)
+            .build()
+    };
+    let svg = match &options.gradient {
+        Some(gradient) => {
+            let defs = gradient::svg_defs(gradient, rendered_edge(&code, options), options.quiet_zone_modules() * unit);
+            svg.replacen("<path", &format!("{}<path", defs), 1)
+        }
+        None => svg,
+    };
+
+    let svg = match &options.logo {
+        Some(logo) => {
+            let overlay = render::logo_svg(logo, render::logo_box(&code, unit, options.quiet_zone_modules()), &background);
+            svg.replacen("</svg>", &format!("{}</svg>", overlay), 1)
+        }
+        None => svg,
+    };
+    // Vector output scales without loss, so the code simply fills the printed edge
+    match options.print_size_mm {
+        Some(size_mm) => Ok(svg.replacen(
+            &format!("width=\"{e}\" height=\"{e}\"", e = rendered_edge(&code, options)),
+            &format!("width=\"{s}mm\" height=\"{s}mm\"", s = size_mm),
+            1,
+        )),
+        None => Ok(svg),
+    }
+}
+
+// Vector artwork for print, as an EPS file of the same dimensions as the PNG in points
+pub fn render_qr_eps(content: &str, options: &RenderOptions) -> Result<String, String> {
+    let code = build_qr_code(content, options)?;
+    Ok(eps::document(&code, options))
+}
+
+// Two modules per character; drawn light on dark, which suits most terminals and CI logs.
+// Colors, gradient, background, shapes, size and logo do not apply
+pub fn render_qr_text(content: &str, options: &RenderOptions) -> Result<String, String> {
+    let code = build_qr_code(content, options)?;
+    Ok(code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
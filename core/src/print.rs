@@ -0,0 +1,84 @@
+// Physical sizing for print: a code asked for at print_size_mm and dpi is drawn at the pixel edge
+// that makes it exactly that wide at that resolution, and its PNG says so in a pHYs chunk, so layout
+// and RIP software place it at the right size without anyone converting units by hand
+
+#[cfg(feature = "image")]
+use image::{imageops, ColorType, DynamicImage, Rgba, RgbaImage};
+
+pub const MIN_DPI: u32 = 72;
+pub const MAX_DPI: u32 = 2400;
+// Resolution assumed for print_size_mm when dpi is unset; what most print shops ask for
+pub const DEFAULT_DPI: u32 = 300;
+pub const MIN_PRINT_SIZE_MM: f32 = 5.0;
+pub const MAX_PRINT_SIZE_MM: f32 = 1000.0;
+// Same order as the largest code the top scale draws
+pub const MAX_PRINT_EDGE_PX: u32 = 4096;
+
+const MM_PER_INCH: f32 = 25.4;
+const POINTS_PER_INCH: f32 = 72.0;
+
+// Whole pixels across size_mm at dpi
+pub fn edge_px(size_mm: f32, dpi: u32) -> u32 {
+    (size_mm / MM_PER_INCH * dpi as f32).round() as u32
+}
+
+pub fn mm_to_points(size_mm: f32) -> f32 {
+    size_mm / MM_PER_INCH * POINTS_PER_INCH
+}
+
+// Modules are whole pixels, so the code usually falls a few pixels short of the edge; the rest is
+// added to the quiet zone, split evenly, in the light color
+#[cfg(feature = "image")]
+pub fn pad(image: DynamicImage, edge: u32, (r, g, b): (u8, u8, u8), transparent: bool) -> DynamicImage {
+    if image.width() >= edge && image.height() >= edge {
+        return image;
+    }
+    let light = if transparent { Rgba([255, 255, 255, 0]) } else { Rgba([r, g, b, 255]) };
+    let mut canvas = RgbaImage::from_pixel(edge, edge, light);
+    let x = (edge - image.width().min(edge)) / 2;
+    let y = (edge - image.height().min(edge)) / 2;
+    imageops::replace(&mut canvas, &image.to_rgba8(), x as i64, y as i64);
+    let canvas = DynamicImage::ImageRgba8(canvas);
+    match image.color() {
+        ColorType::L8 => DynamicImage::ImageLuma8(canvas.to_luma8()),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(canvas.to_rgb8()),
+        _ => canvas,
+    }
+}
+
+// Adds a pHYs chunk right after IHDR, which always comes first and is always 13 bytes. Anything that
+// isn't a PNG is returned as it is
+pub fn set_png_dpi(png: Vec<u8>, dpi: u32) -> Vec<u8> {
+    const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+    if png.len() < IHDR_END || &png[12..16] != b"IHDR" {
+        return png;
+    }
+    let pixels_per_metre = (dpi as f32 / MM_PER_INCH * 1000.0).round() as u32;
+    let mut chunk = Vec::with_capacity(21);
+    chunk.extend_from_slice(&9u32.to_be_bytes());
+    chunk.extend_from_slice(b"pHYs");
+    chunk.extend_from_slice(&pixels_per_metre.to_be_bytes());
+    chunk.extend_from_slice(&pixels_per_metre.to_be_bytes());
+    // Unit: metres
+    chunk.push(1);
+    let crc = crc32(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+
+    let mut out = Vec::with_capacity(png.len() + chunk.len());
+    out.extend_from_slice(&png[..IHDR_END]);
+    out.extend_from_slice(&chunk);
+    out.extend_from_slice(&png[IHDR_END..]);
+    out
+}
+
+// The PNG chunk checksum (CRC-32, polynomial 0xEDB88320); one chunk of 13 bytes needs no table
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
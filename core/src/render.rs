@@ -0,0 +1,198 @@
+// Compositing on top of the drawn code: a logo in the center on a box of the background color. Placement is shared
+// by the PNG and SVG renderers so both come out the same
+
+#[cfg(feature = "image")]
+use image::{imageops, DynamicImage, ImageFormat, ImageReader, Limits, Rgba, RgbaImage};
+
+use crate::symbol::Symbol;
+
+// Logos travel with the card's render options, so they stay small
+pub const MAX_LOGO_BYTES: usize = 128 * 1024;
+// Larger logos are refused before they are decoded; the box they are drawn into is far smaller
+pub const MAX_LOGO_EDGE: u32 = 1024;
+
+const LOGO_PREFIX: &str = "data:image/png;base64,";
+
+// Share of the code's width, in modules, the logo box covers. About 5% of the modules, which
+// error correction level Q recovers with room to spare
+const LOGO_BOX_FRACTION: f32 = 0.2;
+
+// Where the logo goes, in pixels of the rendered code
+#[derive(Clone, Copy)]
+pub struct LogoBox {
+    // Top-left corner of the box
+    pub x: u32,
+    pub y: u32,
+    // Edge of the box
+    pub side: u32,
+    // Margin between the box edge and the logo
+    pub padding: u32,
+}
+
+// A box of whole modules centered on the middle module, with one module of margin around the logo
+pub fn logo_box(code: &Symbol, unit: u32, quiet_zone: u32) -> LogoBox {
+    let modules = code.width() as u32;
+    // Rounded up to odd, so the box is centered exactly like the code's middle module
+    let box_modules = ((modules as f32 * LOGO_BOX_FRACTION).round() as u32).max(3) | 1;
+    let start = quiet_zone + (modules - box_modules) / 2;
+    LogoBox { x: start * unit, y: start * unit, side: box_modules * unit, padding: unit }
+}
+
+// Only the envelope; whether it decodes is checked by check_logo, since this runs on every render.
+// The alphabet check also keeps the URL safe to write into an SVG attribute
+pub fn validate_logo(logo: &str) -> Result<(), String> {
+    let encoded = logo.strip_prefix(LOGO_PREFIX)
+        .filter(|encoded| encoded.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'='))
+        .ok_or("logo must be a PNG data URL (data:image/png;base64,...)".to_string())?;
+    if encoded.len() / 4 * 3 > MAX_LOGO_BYTES {
+        return Err(format!("logo must be at most {} KB", MAX_LOGO_BYTES / 1024));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+pub fn decode_logo(logo: &str) -> Result<DynamicImage, String> {
+    validate_logo(logo)?;
+    let encoded = &logo[LOGO_PREFIX.len()..];
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded)
+        .map_err(|_| "logo is not valid base64".to_string())?;
+
+    let mut reader = ImageReader::with_format(std::io::Cursor::new(bytes), ImageFormat::Png);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_LOGO_EDGE);
+    limits.max_image_height = Some(MAX_LOGO_EDGE);
+    reader.limits(limits);
+    reader.decode()
+        .map_err(|e| format!("logo is not a PNG of at most {0}x{0} pixels: {1}", MAX_LOGO_EDGE, e))
+}
+
+// Everything a submitted logo must pass, run once when a card is saved
+#[cfg(feature = "image")]
+pub fn check_logo(logo: &str) -> Result<(), String> {
+    decode_logo(logo).map(|_| ())
+}
+
+// Draws the box in the background color and the logo, scaled to fit inside it with its aspect ratio
+// kept. Transparent logo pixels show the box through, and the box stays opaque on transparent codes.
+// The result is RGB even for grey codes, so the logo keeps its colors, or RGBA if the code was
+#[cfg(feature = "image")]
+pub fn overlay_logo(code: DynamicImage, logo: &DynamicImage, area: LogoBox, background: (u8, u8, u8)) -> DynamicImage {
+    let keep_alpha = code.color().has_alpha();
+    let (br, bg, bb) = background;
+    let mut canvas: RgbaImage = code.into_rgba8();
+    for y in area.y..area.y + area.side {
+        for x in area.x..area.x + area.side {
+            canvas.put_pixel(x, y, Rgba([br, bg, bb, 255]));
+        }
+    }
+
+    let inner = area.side.saturating_sub(2 * area.padding).max(1);
+    let scale = (inner as f32 / logo.width() as f32).min(inner as f32 / logo.height() as f32);
+    let width = ((logo.width() as f32 * scale).round() as u32).clamp(1, inner);
+    let height = ((logo.height() as f32 * scale).round() as u32).clamp(1, inner);
+    let resized = imageops::resize(&logo.to_rgba8(), width, height, imageops::FilterType::Triangle);
+
+    let left = area.x + (area.side - width) / 2;
+    let top = area.y + (area.side - height) / 2;
+    for (x, y, pixel) in resized.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let alpha = a as f32 / 255.0;
+        let blend = |channel: u8, under: u8| (channel as f32 * alpha + under as f32 * (1.0 - alpha)).round() as u8;
+        canvas.put_pixel(left + x, top + y, Rgba([blend(r, br), blend(g, bg), blend(b, bb), 255]));
+    }
+    if keep_alpha {
+        DynamicImage::ImageRgba8(canvas)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).into_rgb8())
+    }
+}
+
+// The same box and logo as SVG elements, to go last inside the code's <svg>
+pub fn logo_svg(logo: &str, area: LogoBox, background: &str) -> String {
+    let inner = area.side.saturating_sub(2 * area.padding);
+    format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{side}\" height=\"{side}\" fill=\"{}\"/><image x=\"{}\" y=\"{}\" width=\"{inner}\" height=\"{inner}\" preserveAspectRatio=\"xMidYMid meet\" href=\"{}\"/>",
+        area.x, area.y, background, area.x + area.padding, area.y + area.padding, logo,
+        side = area.side, inner = inner,
+    )
+}
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use super::*;
+    use crate::{build_qr_code, encode_png, module_px, render_qr_image, ErrorCorrection, RenderOptions};
+
+    const CONTENT: &str = "BEGIN:VCARD\nVERSION:3.0\nN:Lovelace;Ada\nFN:Ada Lovelace\nEND:VCARD";
+    const RED: [u8; 3] = [255, 0, 0];
+
+    // A solid red square, as a submitted logo would arrive
+    fn logo() -> String {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(32, 32, Rgba([RED[0], RED[1], RED[2], 255])));
+        let png = encode_png(&image).unwrap();
+        format!("{}{}", LOGO_PREFIX, base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png))
+    }
+
+    fn with_logo(ec_level: Option<ErrorCorrection>) -> RenderOptions {
+        RenderOptions { logo: Some(logo()), ec_level, scale: Some(8), ..Default::default() }
+    }
+
+    // rqrr reports the level as its two format bits: M 0, L 1, H 2, Q 3
+    fn decoded_ec_bits(image: &DynamicImage) -> Vec<(u16, String)> {
+        let gray = image.to_luma8();
+        let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+            gray.width() as usize,
+            gray.height() as usize,
+            |x, y| gray.get_pixel(x as u32, y as u32)[0],
+        );
+        prepared.detect_grids().iter()
+            .filter_map(|grid| grid.decode().ok())
+            .map(|(meta, content)| (meta.ecc_level, content))
+            .collect()
+    }
+
+    #[test]
+    fn logo_raises_error_correction_to_q() {
+        for level in [None, Some(ErrorCorrection::L), Some(ErrorCorrection::M)] {
+            assert!(with_logo(level).effective_ec_level() == Some(ErrorCorrection::Q));
+            let decoded = decoded_ec_bits(&render_qr_image(CONTENT, &with_logo(level)).unwrap());
+            assert_eq!(decoded, vec![(3, CONTENT.to_string())]);
+        }
+        assert!(with_logo(Some(ErrorCorrection::H)).effective_ec_level() == Some(ErrorCorrection::H));
+    }
+
+    #[test]
+    fn logo_box_is_centered() {
+        let options = with_logo(None);
+        let code = build_qr_code(CONTENT, &options).unwrap();
+        let unit = module_px(&code, &options);
+        let quiet_zone = options.quiet_zone_modules();
+        let edge = (code.width() as u32 + 2 * quiet_zone) * unit;
+        let area = logo_box(&code, unit, quiet_zone);
+
+        assert_eq!(area.x, area.y);
+        assert_eq!(2 * area.x + area.side, edge);
+        assert_eq!(area.x % unit, 0);
+        assert_eq!(area.side % unit, 0);
+        assert_eq!(area.padding, unit);
+
+        let image = render_qr_image(CONTENT, &options).unwrap().to_rgb8();
+        assert_eq!(image.width(), edge);
+        assert_eq!(image.get_pixel(edge / 2, edge / 2).0, RED);
+        // The margin around the logo is the background color
+        assert_eq!(image.get_pixel(area.x, area.y).0, [255, 255, 255]);
+        assert_eq!(image.get_pixel(area.x + area.side - 1, area.y + area.side - 1).0, [255, 255, 255]);
+    }
+
+    #[test]
+    fn code_with_logo_still_decodes() {
+        for options in [
+            with_logo(None),
+            RenderOptions { color: Some("#1a73e8".to_string()), ..with_logo(None) },
+            RenderOptions { transparent: true, ..with_logo(Some(ErrorCorrection::H)) },
+        ] {
+            let image = render_qr_image(CONTENT, &options).unwrap();
+            let decoded: Vec<String> = decoded_ec_bits(&image).into_iter().map(|(_, content)| content).collect();
+            assert_eq!(decoded, vec![CONTENT.to_string()]);
+        }
+    }
+}
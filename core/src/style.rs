@@ -0,0 +1,213 @@
+// Module shapes. Instead of a square per dark module, each module gets its own shape inside its
+// cell, and the three finder patterns are drawn whole (outer ring and center eye) in a shape of
+// their own, since scanners locate the code by them. Works from the code's module matrix, so the
+// PNG and SVG renderers draw the same shapes
+
+use qrcode::{Color, Version};
+use serde::{Deserialize, Serialize};
+
+use crate::symbol::Symbol;
+
+// Modules on each side of a finder pattern
+const FINDER_MODULES: usize = 7;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+pub enum ModuleShape {
+    #[default]
+    Square,
+    // Square with its corners rounded off
+    Rounded,
+    // Dot filling the module's width
+    Circle,
+    // Square turned on its corner, touching the middle of each side
+    Diamond,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+pub enum FinderShape {
+    #[default]
+    Square,
+    Rounded,
+    // Concentric ring and dot
+    Circle,
+}
+
+// Whether any shape other than the plain square is asked for
+pub fn is_styled(modules: Option<ModuleShape>, finders: Option<FinderShape>) -> bool {
+    modules.is_some_and(|shape| shape != ModuleShape::Square) || finders.is_some_and(|shape| shape != FinderShape::Square)
+}
+
+// Top-left module of each finder pattern; Micro QR codes have only the one, and Data Matrix and
+// Aztec codes none that a finder shape applies to
+pub(crate) fn finder_origins(code: &Symbol) -> Vec<(usize, usize)> {
+    match code.version() {
+        Some(Version::Micro(_)) => vec![(0, 0)],
+        Some(Version::Normal(_)) => {
+            let far = code.width() - FINDER_MODULES;
+            vec![(0, 0), (far, 0), (0, far)]
+        }
+        None => Vec::new(),
+    }
+}
+
+pub(crate) fn in_finder(origins: &[(usize, usize)], x: usize, y: usize) -> bool {
+    origins.iter().any(|&(fx, fy)| (fx..fx + FINDER_MODULES).contains(&x) && (fy..fy + FINDER_MODULES).contains(&y))
+}
+
+// Inside the square from lo to hi on both axes with corners of the given radius
+#[cfg(feature = "image")]
+fn in_rounded_square(x: f32, y: f32, lo: f32, hi: f32, radius: f32) -> bool {
+    if x < lo || x > hi || y < lo || y > hi {
+        return false;
+    }
+    let (cx, cy) = (x.clamp(lo + radius, hi - radius), y.clamp(lo + radius, hi - radius));
+    (x - cx).hypot(y - cy) <= radius
+}
+
+impl ModuleShape {
+    // Corner radius as a share of the module, for the shapes drawn as rounded squares
+    pub(crate) fn radius(self) -> f32 {
+        match self {
+            ModuleShape::Square | ModuleShape::Diamond => 0.0,
+            ModuleShape::Rounded => 0.3,
+            ModuleShape::Circle => 0.5,
+        }
+    }
+
+    // Whether a point of the cell, from 0 to 1 on both axes, is dark
+    #[cfg(feature = "image")]
+    fn covers(self, x: f32, y: f32) -> bool {
+        match self {
+            ModuleShape::Diamond => (x - 0.5).abs() + (y - 0.5).abs() <= 0.5,
+            _ => in_rounded_square(x, y, 0.0, 1.0, self.radius()),
+        }
+    }
+}
+
+impl FinderShape {
+    // Corner radii of the ring's outer and inner edge and of the eye, in modules
+    pub(crate) fn radii(self) -> [f32; 3] {
+        match self {
+            FinderShape::Square => [0.0, 0.0, 0.0],
+            FinderShape::Rounded => [2.0, 1.2, 0.8],
+            FinderShape::Circle => [3.5, 2.5, 1.5],
+        }
+    }
+
+    // Whether a point of the pattern, from 0 to 7 modules on both axes, is dark
+    #[cfg(feature = "image")]
+    fn covers(self, x: f32, y: f32) -> bool {
+        let [outer, inner, eye] = self.radii();
+        (in_rounded_square(x, y, 0.0, 7.0, outer) && !in_rounded_square(x, y, 1.0, 6.0, inner))
+            || in_rounded_square(x, y, 2.0, 5.0, eye)
+    }
+}
+
+// Which pixels of a `side` px square are dark, sampled at pixel centers; scale maps pixels to the
+// units `covers` works in
+#[cfg(feature = "image")]
+fn stencil(side: usize, scale: f32, covers: impl Fn(f32, f32) -> bool) -> Vec<(usize, usize)> {
+    let mut dark = Vec::new();
+    for y in 0..side {
+        for x in 0..side {
+            if covers((x as f32 + 0.5) * scale, (y as f32 + 0.5) * scale) {
+                dark.push((x, y));
+            }
+        }
+    }
+    dark
+}
+
+// Draws the code into a raw pixel buffer of N channels with the given shapes; the stencils are
+// worked out once and stamped onto every module. Returns the edge, like draw_modules
+#[cfg(feature = "image")]
+pub fn draw<const N: usize>(
+    code: &Symbol,
+    unit: u32,
+    quiet_zone: u32,
+    modules: ModuleShape,
+    finders: FinderShape,
+    dark: [u8; N],
+    light: [u8; N],
+) -> (u32, Vec<u8>) {
+    let width = code.width();
+    let unit = unit as usize;
+    let border = quiet_zone as usize * unit;
+    let edge = width * unit + 2 * border;
+    let mut raw = light.repeat(edge * edge);
+    let mut stamp = |left: usize, top: usize, pixels: &[(usize, usize)]| {
+        for &(x, y) in pixels {
+            let at = ((top + y) * edge + left + x) * N;
+            raw[at..at + N].copy_from_slice(&dark);
+        }
+    };
+
+    let origins = finder_origins(code);
+    let module = stencil(unit, 1.0 / unit as f32, |x, y| modules.covers(x, y));
+    let finder = stencil(FINDER_MODULES * unit, 1.0 / unit as f32, |x, y| finders.covers(x, y));
+
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        if color == Color::Dark && !in_finder(&origins, x, y) {
+            stamp(border + x * unit, border + y * unit, &module);
+        }
+    }
+    for &(x, y) in &origins {
+        stamp(border + x * unit, border + y * unit, &finder);
+    }
+    (edge as u32, raw)
+}
+
+// SVG path data for a rounded square, clockwise from the top edge
+fn rounded_square_path(x: f32, y: f32, side: f32, radius: f32) -> String {
+    if radius <= 0.0 {
+        return format!("M{} {}h{}v{}h-{}z", x, y, side, side, side);
+    }
+    let straight = side - 2.0 * radius;
+    format!(
+        "M{x} {y}h{s}a{r} {r} 0 0 1 {r} {r}v{s}a{r} {r} 0 0 1 -{r} {r}h-{s}a{r} {r} 0 0 1 -{r} -{r}v-{s}a{r} {r} 0 0 1 {r} -{r}z",
+        x = x + radius, y = y, s = straight, r = radius,
+    )
+}
+
+// The same shapes as draw, as a standalone SVG in the layout qrcode's renderer uses: a background
+// path, then one path for every dark module. The finder's ring and eye are nested outlines, so
+// they are filled even-odd
+pub fn svg(code: &Symbol, unit: u32, quiet_zone: u32, modules: ModuleShape, finders: FinderShape, dark: &str, light: &str) -> String {
+    let width = code.width();
+    let u = unit as f32;
+    let border = (quiet_zone * unit) as f32;
+    let edge = width as u32 * unit + 2 * quiet_zone * unit;
+
+    let mut path = String::new();
+    let origins = finder_origins(code);
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        if color != Color::Dark || in_finder(&origins, x, y) {
+            continue;
+        }
+        let (left, top) = (border + x as f32 * u, border + y as f32 * u);
+        path.push_str(&match modules {
+            ModuleShape::Diamond => format!("M{} {}l{h} {h}l-{h} {h}l-{h} -{h}z", left + u / 2.0, top, h = u / 2.0),
+            shape => rounded_square_path(left, top, u, shape.radius() * u),
+        });
+    }
+    let [outer, inner, eye] = finders.radii();
+    for &(x, y) in &origins {
+        let (left, top) = (border + x as f32 * u, border + y as f32 * u);
+        path.push_str(&rounded_square_path(left, top, 7.0 * u, outer * u));
+        path.push_str(&rounded_square_path(left + u, top + u, 5.0 * u, inner * u));
+        path.push_str(&rounded_square_path(left + 2.0 * u, top + 2.0 * u, 3.0 * u, eye * u));
+    }
+
+    // Square modules only line up with the pixel grid when they are not anti-aliased
+    let crisp = if is_styled(Some(modules), Some(finders)) { "" } else { " shape-rendering=\"crispEdges\"" };
+    format!(
+        "<?xml version=\"1.0\" standalone=\"yes\"?><svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{e}\" height=\"{e}\" viewBox=\"0 0 {e} {e}\"{}><path fill=\"{}\" d=\"M0 0h{e}v{e}H0z\"/><path fill=\"{}\" fill-rule=\"evenodd\" d=\"{}\"/></svg>",
+        crisp, light, dark, path, e = edge,
+    )
+}
@@ -0,0 +1,145 @@
+// The grid of modules every renderer draws from. qrcode's QrCode always picks the smallest normal
+// version and the mask it scores best, so codes are built here from its parts instead, which lets a
+// card fix the version, ask for Micro QR or choose the mask. Data Matrix and Aztec codes come from
+// their own encoders (see symbology.rs) as the same grid
+
+use qrcode::bits::Bits;
+use qrcode::canvas::{Canvas, MaskPattern};
+use qrcode::render::{Pixel, Renderer};
+use qrcode::types::{QrError, QrResult};
+use qrcode::{ec, Color, EcLevel, Version};
+
+// The standard's quiet zone, in modules, which qrcode's own renderers draw
+const STANDARD_QUIET_ZONE: u32 = 4;
+const COMPACT_QUIET_ZONE: u32 = 2;
+
+// Masks by the number the standard gives them; Micro QR only has four, numbered on their own
+const MASKS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+const MICRO_MASKS: [MaskPattern; 4] = [
+    MaskPattern::HorizontalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+// How much a payload overflows the largest code it may go in, in bytes of encoded data
+pub struct Overflow {
+    pub needed_bytes: usize,
+    pub capacity_bytes: usize,
+}
+
+impl Overflow {
+    pub fn excess_bytes(&self) -> usize {
+        self.needed_bytes.saturating_sub(self.capacity_bytes)
+    }
+}
+
+// None when the data fits at this level in the given version, or else in the largest there is
+pub fn overflow(data: &[u8], ec_level: EcLevel, micro: bool, version: Option<u8>) -> Option<Overflow> {
+    let largest = match (micro, version) {
+        (true, Some(n)) => Version::Micro(n as i16),
+        (false, Some(n)) => Version::Normal(n as i16),
+        (true, None) => Version::Micro(4),
+        (false, None) => Version::Normal(40),
+    };
+    let mut bits = Bits::new(largest);
+    let capacity = bits.max_len(ec_level).ok()?;
+    // Only fails when a segment is longer than its length field can say, far past any capacity
+    let needed = match bits.push_optimal_data(data) {
+        Ok(()) => bits.len(),
+        Err(_) => data.len() * 8,
+    };
+    (needed > capacity).then(|| Overflow { needed_bytes: needed.div_ceil(8), capacity_bytes: capacity / 8 })
+}
+
+pub struct Symbol {
+    colors: Vec<Color>,
+    // None for the other symbologies
+    version: Option<Version>,
+    width: usize,
+}
+
+impl Symbol {
+    // `version` is 1 to 40, or M1 to M4 with micro; the smallest that holds the data when None.
+    // `mask` is the standard's number for it; the best scoring when None
+    pub fn build(data: &[u8], ec_level: EcLevel, micro: bool, version: Option<u8>, mask: Option<u8>) -> QrResult<Symbol> {
+        let candidates: Vec<Version> = match (micro, version) {
+            (true, Some(n)) => vec![Version::Micro(n as i16)],
+            (false, Some(n)) => vec![Version::Normal(n as i16)],
+            (true, None) => (1..=4).map(Version::Micro).collect(),
+            (false, None) => (1..=40).map(Version::Normal).collect(),
+        };
+
+        let mut last_error = QrError::DataTooLong;
+        for version in candidates {
+            match encode(data, version, ec_level) {
+                Ok((encoded, ec_data)) => {
+                    let mut canvas = Canvas::new(version, ec_level);
+                    canvas.draw_all_functional_patterns();
+                    canvas.draw_data(&encoded, &ec_data);
+                    let masks: &[MaskPattern] = if micro { &MICRO_MASKS } else { &MASKS };
+                    let canvas = match mask.and_then(|n| masks.get(n as usize)) {
+                        Some(&pattern) => {
+                            canvas.apply_mask(pattern);
+                            canvas
+                        }
+                        None => canvas.apply_best_mask(),
+                    };
+                    return Ok(Symbol { colors: canvas.into_colors(), version: Some(version), width: version.width() as usize });
+                }
+                // Too small, or no such level at this Micro version; try the next size up
+                Err(e @ (QrError::DataTooLong | QrError::InvalidVersion)) => last_error = e,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error)
+    }
+
+    // A square grid of another symbology, row by row
+    pub(crate) fn from_modules(dark: Vec<bool>, width: usize) -> Symbol {
+        let colors = dark.into_iter().map(|dark| if dark { Color::Dark } else { Color::Light }).collect();
+        Symbol { colors, version: None, width }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    // Four modules for QR; two for Micro QR, and for Data Matrix and Aztec, which ask for one and
+    // none but scan more reliably with a margin
+    pub fn standard_quiet_zone(&self) -> u32 {
+        match self.version {
+            Some(Version::Normal(_)) => STANDARD_QUIET_ZONE,
+            _ => COMPACT_QUIET_ZONE,
+        }
+    }
+
+    pub fn to_colors(&self) -> Vec<Color> {
+        self.colors.clone()
+    }
+
+    // qrcode's renderers over these modules, with the standard quiet zone for the kind of code
+    pub fn render<P: Pixel>(&self) -> Renderer<'_, P> {
+        Renderer::new(&self.colors, self.width, self.standard_quiet_zone())
+    }
+}
+
+fn encode(data: &[u8], version: Version, ec_level: EcLevel) -> QrResult<(Vec<u8>, Vec<u8>)> {
+    let mut bits = Bits::new(version);
+    bits.push_optimal_data(data)?;
+    bits.push_terminator(ec_level)?;
+    ec::construct_codewords(&bits.into_bytes(), version, ec_level)
+}
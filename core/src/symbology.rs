@@ -0,0 +1,86 @@
+// The 2D barcodes a card can be drawn as. QR is what phones read; Data Matrix and Aztec are for
+// badge printers and handheld scanners, which often read them better at small sizes. All three are
+// square grids of modules, so every renderer draws them alike from a Symbol
+
+use serde::{Deserialize, Serialize};
+
+use crate::symbol::{Overflow, Symbol};
+
+// Bytes the largest symbol holds: Data Matrix 144x144, and Aztec with all 32 layers at the
+// encoder's default error correction
+#[cfg(feature = "symbologies")]
+const DATA_MATRIX_CAPACITY: usize = 1556;
+#[cfg(feature = "symbologies")]
+const AZTEC_CAPACITY: usize = 1914;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[serde(rename_all = "snake_case")]
+pub enum Symbology {
+    #[default]
+    Qr,
+    DataMatrix,
+    Aztec,
+}
+
+impl Symbology {
+    pub fn name(self) -> &'static str {
+        match self {
+            Symbology::Qr => "QR code",
+            Symbology::DataMatrix => "Data Matrix code",
+            Symbology::Aztec => "Aztec code",
+        }
+    }
+}
+
+// Data Matrix is kept to square symbols, which is all the renderers draw
+#[cfg(feature = "symbologies")]
+pub fn encode(data: &str, symbology: Symbology) -> Result<Symbol, String> {
+    use datamatrix::{DataMatrix, SymbolList};
+    use rxing::{aztec::AztecWriter, BarcodeFormat, Writer};
+
+    match symbology {
+        Symbology::Qr => Err("QR codes are built by Symbol::build".to_string()),
+        Symbology::DataMatrix => {
+            let code = DataMatrix::encode(data.as_bytes(), SymbolList::default().enforce_square())
+                .map_err(|_| format!("Data too long for a {}", symbology.name()))?;
+            let bitmap = code.bitmap();
+            let mut dark = vec![false; bitmap.width() * bitmap.height()];
+            for (x, y) in bitmap.pixels() {
+                dark[y * bitmap.width() + x] = true;
+            }
+            Ok(Symbol::from_modules(dark, bitmap.width()))
+        }
+        Symbology::Aztec => {
+            // Zero for width and height draws one pixel per module and no margin
+            let matrix = AztecWriter
+                .encode(data, &BarcodeFormat::AZTEC, 0, 0)
+                .map_err(|_| format!("Data too long for an {}", symbology.name()))?;
+            let width = matrix.width() as usize;
+            let dark = (0..width * width).map(|i| matrix.get((i % width) as u32, (i / width) as u32)).collect();
+            Ok(Symbol::from_modules(dark, width))
+        }
+    }
+}
+
+#[cfg(not(feature = "symbologies"))]
+pub fn encode(_data: &str, symbology: Symbology) -> Result<Symbol, String> {
+    Err(format!("This build cannot draw a {}", symbology.name()))
+}
+
+// None when the data fits. Their encoders pick how to compact the data, so the size it would take
+// isn't known in advance; the raw length stands in for it when it doesn't fit
+#[cfg(feature = "symbologies")]
+pub fn overflow(data: &str, symbology: Symbology) -> Option<Overflow> {
+    let capacity_bytes = match symbology {
+        Symbology::Qr => return None,
+        Symbology::DataMatrix => DATA_MATRIX_CAPACITY,
+        Symbology::Aztec => AZTEC_CAPACITY,
+    };
+    encode(data, symbology).is_err().then_some(Overflow { needed_bytes: data.len(), capacity_bytes })
+}
+
+#[cfg(not(feature = "symbologies"))]
+pub fn overflow(_data: &str, _symbology: Symbology) -> Option<Overflow> {
+    None
+}
@@ -0,0 +1,313 @@
+// Load test for a running server: drives /api/generate (plain and colored) and the card listing
+// with concurrent clients, reports latency percentiles against each scenario's SLO, and can save
+// the results as a baseline to compare a later run against.
+//
+//   cargo run --release --example loadtest -- [--url http://127.0.0.1:3000] [--duration 30]
+//       [--concurrency 16] [--scenario all|generate|generate-color|list]
+//       --username <name> --password <password> [--save <path>] [--compare <path>] [--tolerance 10]
+//
+// Exits non-zero when a scenario misses its SLO or, with --compare, is slower than the baseline
+// by more than the tolerance. Every generate request stores a card, so point it at a server with
+// a scratch DATABASE_PATH.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: loadtest [--url <url>] [--duration <secs>] [--concurrency <n>] \
+[--scenario all|generate|generate-color|list] --username <name> --password <password> \
+[--save <path>] [--compare <path>] [--tolerance <percent>]";
+
+struct Scenario {
+    name: &'static str,
+    // What a run must stay within to pass
+    p95_ms: f64,
+    max_error_pct: f64,
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario { name: "generate", p95_ms: 250.0, max_error_pct: 1.0 },
+    // The RGB path: color and background are swapped in per pixel after the Luma render
+    Scenario { name: "generate-color", p95_ms: 300.0, max_error_pct: 1.0 },
+    Scenario { name: "list", p95_ms: 50.0, max_error_pct: 0.5 },
+];
+
+struct Options {
+    url: String,
+    duration: Duration,
+    concurrency: usize,
+    scenario: String,
+    username: String,
+    password: String,
+    save: Option<String>,
+    compare: Option<String>,
+    tolerance_pct: f64,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        url: "http://127.0.0.1:3000".to_string(),
+        duration: Duration::from_secs(30),
+        concurrency: 16,
+        scenario: "all".to_string(),
+        username: String::new(),
+        password: String::new(),
+        save: None,
+        compare: None,
+        tolerance_pct: 10.0,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value\n\n{}", arg, USAGE));
+        match arg.as_str() {
+            "--url" => options.url = value()?.trim_end_matches('/').to_string(),
+            "--duration" => options.duration = Duration::from_secs(value()?.parse().map_err(|_| "--duration must be whole seconds")?),
+            "--concurrency" => options.concurrency = value()?.parse().map_err(|_| "--concurrency must be a number")?,
+            "--scenario" => options.scenario = value()?,
+            "--username" => options.username = value()?,
+            "--password" => options.password = value()?,
+            "--save" => options.save = Some(value()?),
+            "--compare" => options.compare = Some(value()?),
+            "--tolerance" => options.tolerance_pct = value()?.parse().map_err(|_| "--tolerance must be a percentage")?,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unexpected argument: {}\n\n{}", other, USAGE)),
+        }
+    }
+
+    if options.scenario != "all" && !SCENARIOS.iter().any(|s| s.name == options.scenario) {
+        return Err(format!("Unknown scenario: {}\n\n{}", options.scenario, USAGE));
+    }
+    // There are no default credentials to fall back on
+    if options.username.is_empty() || options.password.is_empty() {
+        return Err(format!("--username and --password are required\n\n{}", USAGE));
+    }
+    if options.concurrency == 0 {
+        return Err("--concurrency must be at least 1".to_string());
+    }
+    Ok(options)
+}
+
+// One scenario's outcome, in the shape --save writes and --compare reads
+#[derive(Serialize, Deserialize, Clone)]
+struct Summary {
+    requests: u64,
+    errors: u64,
+    requests_per_sec: f64,
+    p50_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+impl Summary {
+    fn of(mut latencies: Vec<Duration>, errors: u64, elapsed: Duration) -> Self {
+        latencies.sort_unstable();
+        let percentile = |p: f64| {
+            if latencies.is_empty() {
+                return 0.0;
+            }
+            let index = ((latencies.len() as f64 * p).ceil() as usize).clamp(1, latencies.len()) - 1;
+            latencies[index].as_secs_f64() * 1000.0
+        };
+        let requests = latencies.len() as u64 + errors;
+        Summary {
+            requests,
+            errors,
+            requests_per_sec: requests as f64 / elapsed.as_secs_f64(),
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: latencies.last().map_or(0.0, |d| d.as_secs_f64() * 1000.0),
+        }
+    }
+
+    fn error_pct(&self) -> f64 {
+        self.errors as f64 * 100.0 / self.requests.max(1) as f64
+    }
+}
+
+// A distinct card per request, so nothing downstream can serve a repeat
+fn contact(worker: usize, n: u64, colored: bool) -> serde_json::Value {
+    let mut body = json!({
+        "first_name": format!("Load{}", worker),
+        "last_name": format!("Test{}", n),
+        "mobile": "+1 555 0100",
+        "email": format!("load{}.{}@example.com", worker, n),
+        "company": "Load Test Inc",
+        "role": "Benchmark",
+        "website": "https://example.com",
+    });
+    if colored {
+        body["color"] = json!("#1a73e8");
+        body["background_color"] = json!("#fffbe6");
+    }
+    body
+}
+
+async fn request(client: &reqwest::Client, url: &str, scenario: &str, worker: usize, n: u64) -> Result<(), String> {
+    let response = match scenario {
+        "generate" | "generate-color" => {
+            client.post(format!("{}/api/generate", url))
+                .json(&contact(worker, n, scenario == "generate-color"))
+                .send().await
+        }
+        _ => client.get(format!("{}/api/vcards?limit=50", url)).send().await,
+    }.map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    // Read the whole body, so the time covers the transfer as well as the first byte
+    response.bytes().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+    Ok(())
+}
+
+async fn run_scenario(client: &reqwest::Client, options: &Options, scenario: &str) -> Summary {
+    let started = Instant::now();
+    let deadline = started + options.duration;
+
+    let workers: Vec<_> = (0..options.concurrency).map(|worker| {
+        let client = client.clone();
+        let url = options.url.clone();
+        let scenario = scenario.to_string();
+        tokio::spawn(async move {
+            let mut latencies = Vec::new();
+            let mut errors = 0u64;
+            let mut first_error = None;
+            let mut n = 0u64;
+            while Instant::now() < deadline {
+                let sent = Instant::now();
+                match request(&client, &url, &scenario, worker, n).await {
+                    Ok(()) => latencies.push(sent.elapsed()),
+                    Err(e) => {
+                        errors += 1;
+                        first_error.get_or_insert(e);
+                    }
+                }
+                n += 1;
+            }
+            (latencies, errors, first_error)
+        })
+    }).collect();
+
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+    for worker in workers {
+        let (worker_latencies, worker_errors, first_error) = worker.await.expect("load test worker panicked");
+        if let Some(e) = first_error {
+            eprintln!("  {} error: {}", scenario, e);
+        }
+        latencies.extend(worker_latencies);
+        errors += worker_errors;
+    }
+    Summary::of(latencies, errors, started.elapsed())
+}
+
+async fn login(client: &reqwest::Client, options: &Options) -> Result<(), String> {
+    let response = client.post(format!("{}/api/login", options.url))
+        .json(&json!({ "username": options.username, "password": options.password }))
+        .send().await
+        .map_err(|e| format!("Could not reach {}: {}", options.url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Login as {} failed: HTTP {}", options.username, response.status()));
+    }
+    Ok(())
+}
+
+// Checks every scenario against its SLO and the baseline, printing one line per breach
+fn failures(results: &BTreeMap<String, Summary>, baseline: Option<&BTreeMap<String, Summary>>, tolerance_pct: f64) -> Vec<String> {
+    let mut failures = Vec::new();
+    for (name, summary) in results {
+        let slo = SCENARIOS.iter().find(|s| s.name == name).expect("results only hold known scenarios");
+        if summary.p95_ms > slo.p95_ms {
+            failures.push(format!("{}: p95 {:.1} ms is over its SLO of {:.0} ms", name, summary.p95_ms, slo.p95_ms));
+        }
+        if summary.error_pct() > slo.max_error_pct {
+            failures.push(format!("{}: {:.2}% errors is over its SLO of {}%", name, summary.error_pct(), slo.max_error_pct));
+        }
+        if let Some(before) = baseline.and_then(|b| b.get(name)) {
+            let limit = before.p95_ms * (1.0 + tolerance_pct / 100.0);
+            if summary.p95_ms > limit {
+                failures.push(format!(
+                    "{}: p95 {:.1} ms regressed from {:.1} ms (over the {}% tolerance)",
+                    name, summary.p95_ms, before.p95_ms, tolerance_pct,
+                ));
+            }
+        }
+    }
+    failures
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let baseline: Option<BTreeMap<String, Summary>> = options.compare.as_ref().map(|path| {
+        let contents = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Could not read baseline {}: {}", path, e);
+            std::process::exit(2);
+        });
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Baseline {} is not a load test result: {}", path, e);
+            std::process::exit(2);
+        })
+    });
+
+    let client = reqwest::Client::builder()
+        .cookie_store(true)
+        .pool_max_idle_per_host(options.concurrency)
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("HTTP client");
+    if let Err(e) = login(&client, &options).await {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+
+    println!(
+        "{} for {}s each with {} concurrent clients",
+        options.url, options.duration.as_secs(), options.concurrency,
+    );
+    println!(
+        "{:<16} {:>9} {:>8} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "scenario", "requests", "errors", "req/s", "p50 ms", "p95 ms", "p99 ms", "max ms",
+    );
+
+    let mut results = BTreeMap::new();
+    for scenario in SCENARIOS.iter().filter(|s| options.scenario == "all" || options.scenario == s.name) {
+        let summary = run_scenario(&client, &options, scenario.name).await;
+        println!(
+            "{:<16} {:>9} {:>8} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+            scenario.name, summary.requests, summary.errors, summary.requests_per_sec,
+            summary.p50_ms, summary.p95_ms, summary.p99_ms, summary.max_ms,
+        );
+        results.insert(scenario.name.to_string(), summary);
+    }
+
+    if let Some(path) = &options.save {
+        let contents = serde_json::to_string_pretty(&results).expect("results serialize");
+        match std::fs::write(path, contents) {
+            Ok(()) => println!("Saved results to {}", path),
+            Err(e) => eprintln!("Could not write {}: {}", path, e),
+        }
+    }
+
+    let failures = failures(&results, baseline.as_ref(), options.tolerance_pct);
+    if failures.is_empty() {
+        println!("✓ All scenarios within their SLOs{}", if baseline.is_some() { " and the baseline" } else { "" });
+    } else {
+        for failure in &failures {
+            eprintln!("✗ {}", failure);
+        }
+        std::process::exit(1);
+    }
+}
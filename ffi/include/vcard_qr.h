@@ -0,0 +1,46 @@
+/*
+ * C interface to the vCard QR generation core (ffi/ in the vcard-qr-generator repository).
+ *
+ * Contacts are UTF-8 JSON in the shape of the server's POST /api/generate body, e.g.
+ *   {"first_name":"Ada","last_name":"Lovelace","email":"ada@example.com","color":"#1a73e8"}
+ * and produce exactly what the server produces.
+ *
+ * Every function returns a VCQR_* status. On failure, vcqr_last_error() describes the problem.
+ * Output buffers belong to the caller and must be released with the matching free function.
+ * Functions may be called from any thread.
+ */
+#ifndef VCARD_QR_H
+#define VCARD_QR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define VCQR_OK 0
+/* A null pointer, invalid UTF-8, a malformed contact or out-of-range render options */
+#define VCQR_INVALID_ARGUMENT 1
+#define VCQR_RENDER_FAILED 2
+
+/* The vCard text the QR code encodes; free *out with vcqr_free_string. */
+int vcqr_generate_vcard(const char *contact_json, char **out);
+
+/* The QR code as SVG markup; free *out with vcqr_free_string. */
+int vcqr_render_svg(const char *contact_json, char **out);
+
+/* The QR code as PNG bytes; free *out with vcqr_free_bytes(*out, *out_len). */
+int vcqr_render_png(const char *contact_json, uint8_t **out, size_t *out_len);
+
+/* Why the last call on this thread failed, or "". Valid until the next call on that thread. */
+const char *vcqr_last_error(void);
+
+void vcqr_free_string(char *value);
+void vcqr_free_bytes(uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VCARD_QR_H */
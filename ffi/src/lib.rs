@@ -0,0 +1,142 @@
+// C ABI over vcard-core for native callers (the .NET badge printer, via P/Invoke), declared in
+// include/vcard_qr.h. Contacts go in as UTF-8 JSON in the shape of the server's /api/generate
+// body. Every function returns a VCQR_* status; on failure vcqr_last_error() says why
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, UnwindSafe};
+use std::ptr;
+
+use vcard_core::{encode_qr_png, generate_vcard, render_qr_image, render_qr_svg, ContactRecord};
+
+pub const VCQR_OK: c_int = 0;
+// A null pointer, invalid UTF-8, a malformed contact or out-of-range render options
+pub const VCQR_INVALID_ARGUMENT: c_int = 1;
+pub const VCQR_RENDER_FAILED: c_int = 2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+// Runs one call, recording its error and keeping panics from unwinding into C
+fn guard(call: impl FnOnce() -> Result<(), (c_int, String)> + UnwindSafe) -> c_int {
+    match catch_unwind(call) {
+        Ok(Ok(())) => {
+            set_last_error("");
+            VCQR_OK
+        }
+        Ok(Err((status, message))) => {
+            set_last_error(&message);
+            status
+        }
+        Err(_) => {
+            set_last_error("internal error");
+            VCQR_RENDER_FAILED
+        }
+    }
+}
+
+unsafe fn read_contact(contact_json: *const c_char) -> Result<ContactRecord, (c_int, String)> {
+    if contact_json.is_null() {
+        return Err((VCQR_INVALID_ARGUMENT, "contact_json is null".to_string()));
+    }
+    let json = CStr::from_ptr(contact_json)
+        .to_str()
+        .map_err(|_| (VCQR_INVALID_ARGUMENT, "contact_json is not UTF-8".to_string()))?;
+    let record: ContactRecord = serde_json::from_str(json)
+        .map_err(|e| (VCQR_INVALID_ARGUMENT, format!("Invalid contact: {}", e)))?;
+    record.validate().map_err(|e| (VCQR_INVALID_ARGUMENT, e))?;
+    Ok(record)
+}
+
+unsafe fn write_string(out: *mut *mut c_char, value: String) -> Result<(), (c_int, String)> {
+    if out.is_null() {
+        return Err((VCQR_INVALID_ARGUMENT, "out is null".to_string()));
+    }
+    let value = CString::new(value).map_err(|_| (VCQR_RENDER_FAILED, "output contains a NUL byte".to_string()))?;
+    *out = value.into_raw();
+    Ok(())
+}
+
+/// Writes the vCard text the QR code encodes to `*out`; free it with `vcqr_free_string`.
+///
+/// # Safety
+/// `contact_json` must be a NUL-terminated string and `out` a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn vcqr_generate_vcard(contact_json: *const c_char, out: *mut *mut c_char) -> c_int {
+    guard(|| {
+        let record = read_contact(contact_json)?;
+        write_string(out, generate_vcard(&record.contact()))
+    })
+}
+
+/// Writes the contact's QR code as SVG markup to `*out`; free it with `vcqr_free_string`.
+///
+/// # Safety
+/// `contact_json` must be a NUL-terminated string and `out` a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn vcqr_render_svg(contact_json: *const c_char, out: *mut *mut c_char) -> c_int {
+    guard(|| {
+        let record = read_contact(contact_json)?;
+        let svg = render_qr_svg(&generate_vcard(&record.contact()), &record.render)
+            .map_err(|e| (VCQR_RENDER_FAILED, e))?;
+        write_string(out, svg)
+    })
+}
+
+/// Writes the contact's QR code as PNG bytes to `*out` and their count to `*out_len`; free them
+/// with `vcqr_free_bytes`.
+///
+/// # Safety
+/// `contact_json` must be a NUL-terminated string; `out` and `out_len` valid pointers to write to.
+#[no_mangle]
+pub unsafe extern "C" fn vcqr_render_png(contact_json: *const c_char, out: *mut *mut u8, out_len: *mut usize) -> c_int {
+    guard(|| {
+        if out.is_null() || out_len.is_null() {
+            return Err((VCQR_INVALID_ARGUMENT, "out or out_len is null".to_string()));
+        }
+        let record = read_contact(contact_json)?;
+        let image = render_qr_image(&generate_vcard(&record.contact()), &record.render)
+            .map_err(|e| (VCQR_RENDER_FAILED, e))?;
+        let png = encode_qr_png(&image, &record.render).map_err(|e| (VCQR_RENDER_FAILED, e.to_string()))?;
+
+        let png = png.into_boxed_slice();
+        *out_len = png.len();
+        *out = Box::into_raw(png) as *mut u8;
+        Ok(())
+    })
+}
+
+/// Why the last call on this thread failed, or an empty string. Owned by the library and only
+/// valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn vcqr_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// Frees a string returned by this library. Null is ignored.
+///
+/// # Safety
+/// `value` must come from this library and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn vcqr_free_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Frees PNG bytes returned by `vcqr_render_png`. Null is ignored.
+///
+/// # Safety
+/// `data` and `len` must be exactly what `vcqr_render_png` returned, not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn vcqr_free_bytes(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
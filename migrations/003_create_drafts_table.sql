@@ -0,0 +1,11 @@
+-- Create drafts table for partially filled vCard forms
+CREATE TABLE IF NOT EXISTS drafts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    data TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on user_id for listing a user's drafts
+CREATE INDEX IF NOT EXISTS idx_drafts_user_id ON drafts(user_id);
@@ -0,0 +1,16 @@
+-- Create tags table for organizing vcards
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create join table between vcards and tags
+CREATE TABLE IF NOT EXISTS vcard_tags (
+    vcard_id INTEGER NOT NULL REFERENCES vcards(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (vcard_id, tag_id)
+);
+
+-- Create index on tag_id for filtering vcards by tag
+CREATE INDEX IF NOT EXISTS idx_vcard_tags_tag_id ON vcard_tags(tag_id);
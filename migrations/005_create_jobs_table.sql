@@ -0,0 +1,19 @@
+-- Create jobs table for background work
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued',
+    payload TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+-- Create index on status for the worker's queue lookups
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status);
+
+-- Create index on created_by for listing a user's jobs
+CREATE INDEX IF NOT EXISTS idx_jobs_created_by ON jobs(created_by);
@@ -0,0 +1,22 @@
+-- Create uploads table for resumable chunked uploads
+CREATE TABLE IF NOT EXISTS uploads (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    filename TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    size INTEGER,
+    data BLOB,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP
+);
+
+-- Create table holding parts of uploads that are still in progress
+CREATE TABLE IF NOT EXISTS upload_parts (
+    upload_id TEXT NOT NULL REFERENCES uploads(id) ON DELETE CASCADE,
+    part_number INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (upload_id, part_number)
+);
+
+-- Create index on created_at for expiring abandoned uploads
+CREATE INDEX IF NOT EXISTS idx_uploads_created_at ON uploads(created_at);
@@ -0,0 +1,17 @@
+-- Allow jobs to be queued for a future time
+ALTER TABLE jobs ADD COLUMN run_at TIMESTAMP;
+
+-- Create table of recurring job schedules
+CREATE TABLE IF NOT EXISTS job_schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    cron TEXT NOT NULL,
+    next_run_at TIMESTAMP,
+    last_job_id INTEGER REFERENCES jobs(id) ON DELETE SET NULL,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on next_run_at for finding due schedules
+CREATE INDEX IF NOT EXISTS idx_job_schedules_next_run_at ON job_schedules(next_run_at);
@@ -0,0 +1,7 @@
+-- Track which worker holds a job and until when
+ALTER TABLE jobs ADD COLUMN locked_by TEXT;
+ALTER TABLE jobs ADD COLUMN lease_expires_at TIMESTAMP;
+ALTER TABLE jobs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+
+-- Jobs running before leases existed have no lease to expire; retry them
+UPDATE jobs SET status = 'queued', started_at = NULL WHERE status = 'running';
@@ -0,0 +1,22 @@
+-- Create events table for conferences and meetups with attendee badges
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    badge_mode TEXT NOT NULL DEFAULT 'vcard',
+    starts_at TIMESTAMP,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create table of attendees, each backed by a vcard and holding a check-in token
+CREATE TABLE IF NOT EXISTS event_attendees (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_id INTEGER NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    vcard_id INTEGER NOT NULL REFERENCES vcards(id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    checked_in_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create index on event_id for listing an event's attendees
+CREATE INDEX IF NOT EXISTS idx_event_attendees_event_id ON event_attendees(event_id);
@@ -0,0 +1,7 @@
+-- Create table of consumed check-in tokens so each one is accepted only once
+CREATE TABLE IF NOT EXISTS checkin_tokens (
+    nonce TEXT PRIMARY KEY,
+    label TEXT NOT NULL,
+    consumed_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    consumed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,11 @@
+-- Create kiosks table for self-service generation devices
+CREATE TABLE IF NOT EXISTS kiosks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    color TEXT,
+    event_id INTEGER REFERENCES events(id) ON DELETE CASCADE,
+    last_used_at TIMESTAMP,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,22 @@
+-- Create playlists table for digital signage screens
+CREATE TABLE IF NOT EXISTS playlists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    interval_seconds INTEGER NOT NULL DEFAULT 10,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create table of slides, each showing a stored vcard or a URL
+CREATE TABLE IF NOT EXISTS playlist_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    playlist_id INTEGER NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    vcard_id INTEGER REFERENCES vcards(id) ON DELETE CASCADE,
+    url TEXT,
+    caption TEXT
+);
+
+-- Create index on playlist_id for loading a playlist's slides in order
+CREATE INDEX IF NOT EXISTS idx_playlist_items_playlist_id ON playlist_items(playlist_id, position);
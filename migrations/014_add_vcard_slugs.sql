@@ -0,0 +1,8 @@
+-- Add a public slug to vcards for embeds and shareable links
+ALTER TABLE vcards ADD COLUMN slug TEXT;
+
+-- Backfill existing vcards with random slugs
+UPDATE vcards SET slug = lower(hex(randomblob(8))) WHERE slug IS NULL;
+
+-- Create unique index on slug for public lookups
+CREATE UNIQUE INDEX IF NOT EXISTS idx_vcards_slug ON vcards(slug);
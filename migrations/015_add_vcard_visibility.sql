@@ -0,0 +1,2 @@
+-- Add the list of contact fields withheld from public views (JSON array of field names)
+ALTER TABLE vcards ADD COLUMN hidden_fields TEXT NOT NULL DEFAULT '[]';
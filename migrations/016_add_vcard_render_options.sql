@@ -0,0 +1,8 @@
+-- Add the full render options for each vcard (JSON object: color, size, ec_level)
+ALTER TABLE vcards ADD COLUMN render_options TEXT NOT NULL DEFAULT '{}';
+
+-- Carry over the color, previously the only option that was kept
+UPDATE vcards SET render_options = json_object('color', color) WHERE color IS NOT NULL;
+
+-- The color now lives in render_options
+ALTER TABLE vcards DROP COLUMN color;
@@ -0,0 +1,29 @@
+-- Record who created each vcard; rows from before this migration stay unowned
+ALTER TABLE vcards ADD COLUMN created_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+-- Trim contact fields and store blank optional values as NULL, matching how new cards are written
+UPDATE vcards SET
+    first_name = trim(first_name),
+    last_name = trim(last_name),
+    mobile = NULLIF(trim(mobile), ''),
+    work = NULLIF(trim(work), ''),
+    email = NULLIF(trim(email), ''),
+    company = NULLIF(trim(company), ''),
+    role = NULLIF(trim(role), ''),
+    street = NULLIF(trim(street), ''),
+    city = NULLIF(trim(city), ''),
+    state = NULLIF(trim(state), ''),
+    website = NULLIF(trim(website), '');
+
+-- Backfill timestamps on rows inserted without them
+UPDATE vcards SET created_at = CURRENT_TIMESTAMP WHERE created_at IS NULL;
+UPDATE vcards SET updated_at = created_at WHERE updated_at IS NULL;
+
+-- Reset render options that are not valid JSON
+UPDATE vcards SET render_options = '{}' WHERE json_valid(render_options) = 0;
+
+-- Create index on name for directory listings and lookups
+CREATE INDEX IF NOT EXISTS idx_vcards_name ON vcards(last_name COLLATE NOCASE, first_name COLLATE NOCASE);
+
+-- Create index on created_by for per-user listings
+CREATE INDEX IF NOT EXISTS idx_vcards_created_by ON vcards(created_by);
@@ -0,0 +1,2 @@
+-- Add X- extension properties for each vcard (JSON object of property name -> value)
+ALTER TABLE vcards ADD COLUMN extensions TEXT NOT NULL DEFAULT '{}';
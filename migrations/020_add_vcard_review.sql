@@ -0,0 +1,8 @@
+-- Add the review state of each vcard; existing cards are already live
+ALTER TABLE vcards ADD COLUMN status TEXT NOT NULL DEFAULT 'approved';
+ALTER TABLE vcards ADD COLUMN reviewed_by INTEGER REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE vcards ADD COLUMN reviewed_at TIMESTAMP;
+ALTER TABLE vcards ADD COLUMN review_note TEXT;
+
+-- Create index on status for the reviewers' queue
+CREATE INDEX IF NOT EXISTS idx_vcards_status ON vcards(status);
@@ -0,0 +1,10 @@
+-- Create table of vcard revisions, each a full JSON snapshot of the card's fields after an edit
+CREATE TABLE IF NOT EXISTS vcard_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vcard_id INTEGER NOT NULL REFERENCES vcards(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    edited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (vcard_id, revision)
+);
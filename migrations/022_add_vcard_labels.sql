@@ -0,0 +1,2 @@
+-- Add custom field labels (e.g. "Booking" for the website) to vcards, as a JSON object
+ALTER TABLE vcards ADD COLUMN labels TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+-- Add messenger handles (whatsapp, telegram, signal) to vcards, as a JSON object
+ALTER TABLE vcards ADD COLUMN messengers TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+-- Add payment details (paypal, iban) to vcards, as a JSON object
+ALTER TABLE vcards ADD COLUMN payment TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+-- Add postal_code and country to vcards; the country (ISO 3166-1 alpha-2) picks the address layout
+ALTER TABLE vcards ADD COLUMN postal_code TEXT;
+ALTER TABLE vcards ADD COLUMN country TEXT;
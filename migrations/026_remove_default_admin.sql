@@ -0,0 +1,8 @@
+-- New installs create their first admin through /api/setup. Drop the admin/admin account 002 seeds,
+-- but only where nothing was ever done with it: still the only user, still the seeded password hash
+-- and no cards yet. An instance already in use keeps it and warns at startup until it is changed
+DELETE FROM users
+WHERE username = 'admin'
+  AND password_hash = '$2b$12$21yrV/a7WOeMgVekvZMgB.VaT/2HyYU3OBnfFpyFDaHH3ewoIlHKi'
+  AND (SELECT COUNT(*) FROM users) = 1
+  AND NOT EXISTS (SELECT 1 FROM vcards);
@@ -0,0 +1,21 @@
+-- Where account activity notifications go; NULL until the user sets an address
+ALTER TABLE users ADD COLUMN email TEXT;
+
+-- Which notifications each user wants; a user without a row gets all of them
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    password_changed BOOLEAN NOT NULL DEFAULT 1,
+    api_key_created BOOLEAN NOT NULL DEFAULT 1,
+    new_login BOOLEAN NOT NULL DEFAULT 1,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Every IP and device (user agent) each user has signed in from, so a new one can be told apart
+CREATE TABLE IF NOT EXISTS login_sightings (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    ip TEXT NOT NULL,
+    user_agent TEXT NOT NULL,
+    first_seen TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    last_seen TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, ip, user_agent)
+);
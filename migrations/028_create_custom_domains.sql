@@ -0,0 +1,9 @@
+-- The organization's own hostnames, e.g. qr.acme.com, which serve its public pages; the primary one
+-- is used in links the app generates
+CREATE TABLE IF NOT EXISTS custom_domains (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    hostname TEXT NOT NULL UNIQUE,
+    is_primary BOOLEAN NOT NULL DEFAULT 0,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+-- A card's previous slugs, so printed short links keep working after it is renamed
+CREATE TABLE IF NOT EXISTS slug_redirects (
+    slug TEXT PRIMARY KEY,
+    vcard_id INTEGER NOT NULL REFERENCES vcards(id) ON DELETE CASCADE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_slug_redirects_vcard_id ON slug_redirects(vcard_id);
@@ -0,0 +1,20 @@
+-- Fields of each vcard whose links were broken at the last check (JSON array of field names)
+ALTER TABLE vcards ADD COLUMN broken_links TEXT NOT NULL DEFAULT '[]';
+
+-- The last check of every link on every vcard, for the admin report
+CREATE TABLE IF NOT EXISTS link_checks (
+    vcard_id INTEGER NOT NULL REFERENCES vcards(id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    url TEXT NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    broken BOOLEAN NOT NULL,
+    checked_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (vcard_id, field)
+);
+
+CREATE INDEX IF NOT EXISTS idx_link_checks_broken ON link_checks(broken);
+
+-- Check every night at 03:00 UTC; admins can drop or replace this like any other schedule
+INSERT INTO job_schedules (kind, payload, cron, next_run_at)
+VALUES ('link_check', '{}', '0 0 3 * * *', datetime('now', 'start of day', '+1 day', '+3 hours'));
@@ -0,0 +1,10 @@
+-- Named styling presets admins define and users pick with style_id; options is a JSON object of
+-- render options (colors, shapes, ec_level, quiet_zone, logo)
+CREATE TABLE IF NOT EXISTS qr_styles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    options TEXT NOT NULL DEFAULT '{}',
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
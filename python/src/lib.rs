@@ -0,0 +1,203 @@
+// Python bindings for data pipelines. A contact is a dict with the keys of the server's
+// /api/generate body (a pandas row via `row.to_dict()` works as is), and goes through the same
+// vcard-core code the server uses, so escaping and rendering match it exactly
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::collections::BTreeMap;
+
+use vcard_core::gradient::{Gradient, GradientKind};
+use vcard_core::style::{FinderShape, ModuleShape};
+use vcard_core::symbology::Symbology;
+use vcard_core::{encode_qr_png, generate_vcard, render_qr_image, render_qr_svg, ContactRecord, ErrorCorrection, Messengers, Payment, RenderOptions};
+
+// An optional text field; pandas marks empty cells as None or NaN, so both count as absent
+fn text(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<String>> {
+    let Some(value) = contact.get_item(key)? else {
+        return Ok(None);
+    };
+    if value.is_none() || value.extract::<f64>().is_ok_and(f64::is_nan) {
+        return Ok(None);
+    }
+    value.extract().map(Some).map_err(|_| PyTypeError::new_err(format!("{} must be a string", key)))
+}
+
+fn required(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<String> {
+    text(contact, key)?.ok_or_else(|| PyValueError::new_err(format!("{} is required", key)))
+}
+
+fn string_map(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<BTreeMap<String, String>> {
+    match contact.get_item(key)? {
+        Some(value) if !value.is_none() => value.extract()
+            .map_err(|_| PyTypeError::new_err(format!("{} must be a dict of strings", key))),
+        _ => Ok(BTreeMap::new()),
+    }
+}
+
+fn integer(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<u32>> {
+    match contact.get_item(key)? {
+        Some(value) if !value.is_none() => {
+            value.extract::<u32>().map(Some).map_err(|_| PyTypeError::new_err(format!("{} must be an integer", key)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn number(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<f32>> {
+    match contact.get_item(key)? {
+        Some(value) if !value.is_none() => {
+            value.extract::<f32>().map(Some).map_err(|_| PyTypeError::new_err(format!("{} must be a number", key)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// An optional flag; absent or None is false
+fn flag(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<bool> {
+    match contact.get_item(key)? {
+        Some(value) if !value.is_none() => value.extract().map_err(|_| PyTypeError::new_err(format!("{} must be a bool", key))),
+        _ => Ok(false),
+    }
+}
+
+// {"kind": "linear" or "radial", "start": "#...", "end": "#..."}
+fn gradient(contact: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<Gradient>> {
+    let fields = string_map(contact, key)?;
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    let kind = match fields.get("kind").map(String::as_str) {
+        Some("linear") => GradientKind::Linear,
+        Some("radial") => GradientKind::Radial,
+        _ => return Err(PyValueError::new_err(format!("{} kind must be linear or radial", key))),
+    };
+    let color = |name: &str| fields.get(name).cloned()
+        .ok_or_else(|| PyValueError::new_err(format!("{} {} is required", key, name)));
+    Ok(Some(Gradient { kind, start: color("start")?, end: color("end")? }))
+}
+
+fn read_contact(contact: &Bound<'_, PyDict>) -> PyResult<ContactRecord> {
+    let size = integer(contact, "size")?;
+    let scale = integer(contact, "scale")?;
+    let quiet_zone = integer(contact, "quiet_zone")?;
+    let print_size_mm = number(contact, "print_size_mm")?;
+    let dpi = integer(contact, "dpi")?;
+    // Out-of-range numbers saturate and are then refused by validation
+    let version = integer(contact, "version")?.map(|n| n.min(u8::MAX as u32) as u8);
+    let mask = integer(contact, "mask")?.map(|n| n.min(u8::MAX as u32) as u8);
+    let ec_level = match text(contact, "ec_level")?.as_deref() {
+        None => None,
+        Some("L") => Some(ErrorCorrection::L),
+        Some("M") => Some(ErrorCorrection::M),
+        Some("Q") => Some(ErrorCorrection::Q),
+        Some("H") => Some(ErrorCorrection::H),
+        Some(_) => return Err(PyValueError::new_err("ec_level must be one of L, M, Q, H")),
+    };
+    let module_shape = match text(contact, "module_shape")?.as_deref() {
+        None => None,
+        Some("square") => Some(ModuleShape::Square),
+        Some("rounded") => Some(ModuleShape::Rounded),
+        Some("circle") => Some(ModuleShape::Circle),
+        Some("diamond") => Some(ModuleShape::Diamond),
+        Some(_) => return Err(PyValueError::new_err("module_shape must be one of square, rounded, circle, diamond")),
+    };
+    let finder_shape = match text(contact, "finder_shape")?.as_deref() {
+        None => None,
+        Some("square") => Some(FinderShape::Square),
+        Some("rounded") => Some(FinderShape::Rounded),
+        Some("circle") => Some(FinderShape::Circle),
+        Some(_) => return Err(PyValueError::new_err("finder_shape must be one of square, rounded, circle")),
+    };
+    let symbology = match text(contact, "symbology")?.as_deref() {
+        None | Some("qr") => Symbology::Qr,
+        Some("data_matrix") => Symbology::DataMatrix,
+        Some("aztec") => Symbology::Aztec,
+        Some(_) => return Err(PyValueError::new_err("symbology must be one of qr, data_matrix, aztec")),
+    };
+    let extensions = string_map(contact, "extensions")?;
+    let labels = string_map(contact, "labels")?;
+
+    let record = ContactRecord {
+        first_name: required(contact, "first_name")?,
+        last_name: required(contact, "last_name")?,
+        mobile: text(contact, "mobile")?,
+        work: text(contact, "work")?,
+        email: text(contact, "email")?,
+        company: text(contact, "company")?,
+        role: text(contact, "role")?,
+        street: text(contact, "street")?,
+        city: text(contact, "city")?,
+        state: text(contact, "state")?,
+        postal_code: text(contact, "postal_code")?,
+        country: text(contact, "country")?,
+        website: text(contact, "website")?,
+        extensions,
+        labels,
+        messengers: Messengers {
+            whatsapp: text(contact, "whatsapp")?,
+            signal: text(contact, "signal")?,
+            telegram: text(contact, "telegram")?,
+        },
+        payment: Payment {
+            paypal: text(contact, "paypal")?,
+            iban: text(contact, "iban")?,
+        },
+        render: RenderOptions {
+            color: text(contact, "color")?,
+            gradient: gradient(contact, "gradient")?,
+            background_color: text(contact, "background_color")?,
+            transparent: flag(contact, "transparent")?,
+            module_shape,
+            finder_shape,
+            size,
+            ec_level,
+            scale,
+            print_size_mm,
+            dpi,
+            quiet_zone,
+            version,
+            micro: false,
+            mask,
+            symbology,
+            logo: text(contact, "logo")?,
+        },
+    };
+    record.validate().map_err(PyValueError::new_err)?;
+    Ok(record)
+}
+
+/// The vCard text the QR code encodes.
+#[pyfunction]
+#[pyo3(name = "generate_vcard")]
+fn generate_vcard_py(contact: &Bound<'_, PyDict>) -> PyResult<String> {
+    Ok(generate_vcard(&read_contact(contact)?.contact()))
+}
+
+/// The contact's QR code as PNG bytes, honoring color, gradient, background_color, transparent, module_shape, finder_shape, size, scale, print_size_mm, dpi, quiet_zone, ec_level, version and mask.
+#[pyfunction]
+fn render_png<'py>(py: Python<'py>, contact: &Bound<'py, PyDict>) -> PyResult<Bound<'py, PyBytes>> {
+    let record = read_contact(contact)?;
+    // Rendering does not touch Python objects, so other threads can run meanwhile
+    let png = py.allow_threads(|| {
+        let image = render_qr_image(&generate_vcard(&record.contact()), &record.render)?;
+        encode_qr_png(&image, &record.render).map_err(|e| e.to_string())
+    });
+    png.map(|png| PyBytes::new(py, &png)).map_err(PyValueError::new_err)
+}
+
+/// The contact's QR code as SVG markup, honoring color, gradient, background_color, transparent, module_shape, finder_shape, size, scale, print_size_mm, quiet_zone, ec_level, version and mask.
+#[pyfunction]
+fn render_svg(contact: &Bound<'_, PyDict>) -> PyResult<String> {
+    let record = read_contact(contact)?;
+    render_qr_svg(&generate_vcard(&record.contact()), &record.render).map_err(PyValueError::new_err)
+}
+
+#[pymodule]
+#[pyo3(name = "vcard_qr")]
+fn vcard_qr(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(generate_vcard_py, module)?)?;
+    module.add_function(wrap_pyfunction!(render_png, module)?)?;
+    module.add_function(wrap_pyfunction!(render_svg, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+// Text alternatives for a card's code, for people who can't see or scan it: a short alt text naming
+// whose card it is, and a description of what scanning it saves. Returned with generated images,
+// written into SVGs as <title> and <desc>, and used by the embed page and the public card data
+
+use serde::Serialize;
+
+use crate::embed::escape_html;
+use crate::{clean_field, VCardData};
+
+#[derive(Serialize, Clone)]
+pub struct AltText {
+    pub alt_text: String,
+    pub description: String,
+}
+
+// What the code holds, from the same fields the vCard is written from
+pub fn describe(data: &VCardData) -> AltText {
+    let name = format!("{} {}", data.first_name.trim(), data.last_name.trim()).trim().to_string();
+    let alt_text = format!("{} with the contact card of {}", data.render.symbology.name(), name);
+
+    if data.encrypt {
+        let description = format!(
+            "An encrypted contact card for {}. Only readers holding this site's key can open it; other scanners show unreadable text.",
+            name,
+        );
+        return AltText { alt_text, description };
+    }
+
+    let headline = match (clean_field(&data.role), clean_field(&data.company)) {
+        (Some(role), Some(company)) => format!("{}, {} at {}", name, role, company),
+        (Some(detail), None) | (None, Some(detail)) => format!("{}, {}", name, detail),
+        (None, None) => name,
+    };
+    let mut details: Vec<String> = [
+        ("mobile", &data.mobile),
+        ("work phone", &data.work),
+        ("email", &data.email),
+        ("website", &data.website),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| clean_field(value).map(|value| format!("{} {}", label, value)))
+    .collect();
+    let address = vcard_core::address_lines(&data.contact());
+    if !address.is_empty() {
+        details.push(format!("address {}", address.join(", ")));
+    }
+    details.extend(data.messengers.links().iter().map(|link| link.label.to_string()));
+
+    let description = if details.is_empty() {
+        format!("Scanning it saves a contact for {}.", headline)
+    } else {
+        format!("Scanning it saves a contact for {}: {}.", headline, details.join("; "))
+    };
+    AltText { alt_text, description }
+}
+
+// For codes of arbitrary text, which say what they hold as they are
+pub fn describe_text(text: &str) -> AltText {
+    AltText { alt_text: "QR code".to_string(), description: format!("Scanning it gives the text: {}", text) }
+}
+
+// Adds <title> and <desc> as the first children of the root element, with role="img" and
+// aria-labelledby so screen readers announce them
+pub fn label_svg(svg: &str, alt: &AltText) -> String {
+    let Some(start) = svg.find("<svg") else {
+        return svg.to_string();
+    };
+    let Some(end) = svg[start..].find('>').map(|offset| start + offset) else {
+        return svg.to_string();
+    };
+    let (open, rest) = svg.split_at(end);
+    format!(
+        "{} role=\"img\" aria-labelledby=\"qr-title qr-desc\"><title id=\"qr-title\">{}</title><desc id=\"qr-desc\">{}</desc>{}",
+        open,
+        escape_html(&alt.alt_text),
+        escape_html(&alt.description),
+        &rest[1..],
+    )
+}
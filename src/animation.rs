@@ -0,0 +1,203 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    DynamicImage, Delay, Frame, Rgb, RgbImage,
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::filenames;
+use crate::memory::{qr_edge_px, qr_image_bytes, MemoryBudget};
+use crate::scan;
+use crate::approvals::ReviewStatus;
+use crate::vcards::fetch_vcard;
+use crate::{generate_vcard, parse_color, render_qr_image, ErrorResponse, VCardData};
+use vcard_core::DEFAULT_QUIET_ZONE;
+
+const FRAME_COUNT: u32 = 24;
+const FRAME_DELAY_MS: u16 = 80;
+
+// Width of the animated frame drawn outside the quiet zone, in pixels
+const BAND_PX: u32 = 16;
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Apng,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationEffect {
+    // The frame fades between the card color and white
+    #[default]
+    Pulse,
+    // The frame runs through the color wheel
+    Cycle,
+}
+
+#[derive(Deserialize)]
+pub struct AnimationQuery {
+    #[serde(default)]
+    format: AnimationFormat,
+    #[serde(default)]
+    effect: AnimationEffect,
+}
+
+fn rgb_to_hsv((r, g, b): (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> Rgb<u8> {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match (hue.rem_euclid(360.0) / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+    Rgb([channel(r), channel(g), channel(b)])
+}
+
+// Color of the frame band at a point in the loop, where phase runs from 0 to 1
+fn band_color(effect: AnimationEffect, base: (u8, u8, u8), phase: f32) -> Rgb<u8> {
+    match effect {
+        AnimationEffect::Pulse => {
+            let fade = (1.0 - (phase * std::f32::consts::TAU).cos()) / 2.0 * 0.8;
+            let mix = |c: u8| (c as f32 + (255.0 - c as f32) * fade).round() as u8;
+            Rgb([mix(base.0), mix(base.1), mix(base.2)])
+        }
+        AnimationEffect::Cycle => {
+            let (hue, saturation, value) = rgb_to_hsv(base);
+            // Black and greys have no hue to turn, so cycle a vivid one instead
+            let (saturation, value) = if saturation < 0.2 { (0.8, 0.9) } else { (saturation, value.max(0.5)) };
+            hsv_to_rgb(hue + phase * 360.0, saturation, value)
+        }
+    }
+}
+
+// Every frame holds the same code pixels; only the band outside the quiet zone changes
+fn render_frames(code: &RgbImage, effect: AnimationEffect, base: (u8, u8, u8)) -> Vec<DynamicImage> {
+    let width = code.width() + 2 * BAND_PX;
+    let height = code.height() + 2 * BAND_PX;
+
+    (0..FRAME_COUNT)
+        .map(|frame| {
+            let color = band_color(effect, base, frame as f32 / FRAME_COUNT as f32);
+            let mut canvas = RgbImage::from_pixel(width, height, color);
+            image::imageops::replace(&mut canvas, code, BAND_PX as i64, BAND_PX as i64);
+            DynamicImage::ImageRgb8(canvas)
+        })
+        .collect()
+}
+
+fn encode_gif(frames: Vec<DynamicImage>) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut out);
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+        let frames = frames.into_iter().map(|frame| {
+            Frame::from_parts(frame.to_rgba8(), 0, 0, Delay::from_numer_denom_ms(FRAME_DELAY_MS as u32, 1))
+        });
+        encoder.encode_frames(frames).map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+fn encode_apng(frames: Vec<DynamicImage>) -> Result<Vec<u8>, String> {
+    let (width, height) = (frames[0].width(), frames[0].height());
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames.len() as u32, 0).map_err(|e| e.to_string())?;
+        encoder.set_frame_delay(FRAME_DELAY_MS, 1000).map_err(|e| e.to_string())?;
+
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        for frame in &frames {
+            writer.write_image_data(frame.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        writer.finish().map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+// A looping animation of a stored card for screens and digital signage
+pub async fn animated_qr_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Query(query): Query<AnimationQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let vcard = fetch_vcard(&pool, vcard_id).await?;
+    if vcard.status != ReviewStatus::Approved {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "vCard is awaiting approval".to_string() })));
+    }
+    let mut data: VCardData = vcard.into();
+    // The band is drawn right outside the quiet zone, so keep at least the standard one between them
+    data.render.quiet_zone = Some(data.render.quiet_zone_modules().max(DEFAULT_QUIET_ZONE));
+    let content = generate_vcard(&data);
+
+    // Every frame is held until encoding finishes
+    let frame_edge = qr_edge_px(&data.render) + 2 * BAND_PX as u64;
+    let _reservation = budget.reserve(qr_image_bytes(&data.render) + FRAME_COUNT as u64 * frame_edge.pow(2) * 3).await?;
+
+    let code = render_qr_image(&content, &data.render)
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: "Contact cannot be encoded as a QR code".to_string() })))?;
+
+    let base = data.render.color.as_deref().map(parse_color).unwrap_or((0, 0, 0));
+    let frames = render_frames(&code.to_rgb8(), query.effect, base);
+
+    // The code pixels never change, so checking the loop's two extremes covers every frame
+    for frame in [&frames[0], &frames[FRAME_COUNT as usize / 2]] {
+        if scan::verify(frame, &content).is_err() {
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: "Animated code failed to decode".to_string() })));
+        }
+    }
+
+    let (content_type, extension, body) = match query.format {
+        AnimationFormat::Gif => ("image/gif", "gif", encode_gif(frames)),
+        AnimationFormat::Apng => ("image/apng", "png", encode_apng(frames)),
+    };
+    let body = body
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to encode animation".to_string() })))?;
+
+    let filename = filenames::download_name(&pool, &data, vcard_id, format!("vcard-{}", vcard_id)).await;
+    let disposition = format!("inline; filename=\"{}-animated.{}\"", filename, extension);
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    ).into_response())
+}
@@ -0,0 +1,223 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::{get_current_user, UserInfo};
+use crate::events::{self, EventBus};
+use crate::settings::{get_setting, put_setting};
+use crate::vcards::{fetch_vcard, VCard, VCARD_COLUMNS};
+use crate::ErrorResponse;
+
+const APPROVAL_KEY: &str = "approval";
+
+const MAX_NOTE_LEN: usize = 500;
+
+// Where a card is in review. Cards from before the workflow, and every card while it is off,
+// are approved
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type, async_graphql::Enum)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+// Whether cards from regular users wait for review, and who besides admins may review them
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ApprovalSettings {
+    pub enabled: bool,
+    // User IDs
+    pub approvers: Vec<i64>,
+}
+
+impl ApprovalSettings {
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(get_setting(pool, APPROVAL_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn save(&self, pool: &SqlitePool, updated_by: i64) -> Result<(), sqlx::Error> {
+        put_setting(pool, APPROVAL_KEY, self, updated_by).await
+    }
+
+    pub fn can_review(&self, user_id: i64, is_admin: bool) -> bool {
+        is_admin || self.approvers.contains(&user_id)
+    }
+
+    // Reviewers' own cards go live straight away
+    pub fn needs_review(&self, user_id: i64, is_admin: bool) -> bool {
+        self.enabled && !self.can_review(user_id, is_admin)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    // approved or rejected
+    status: ReviewStatus,
+    note: Option<String>,
+}
+
+async fn mark_pending(pool: &SqlitePool, vcard_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE vcards SET status = 'pending', reviewed_by = NULL, reviewed_at = NULL, review_note = NULL WHERE id = ?")
+        .bind(vcard_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Call after `user` creates or edits a card: puts it back in review when the workflow applies to
+// them and tells reviewers, returning the card's status either way
+pub async fn submit(pool: &SqlitePool, events: &EventBus, vcard_id: i64, user: &UserInfo) -> Result<ReviewStatus, sqlx::Error> {
+    let settings = ApprovalSettings::load(pool).await?;
+    if !settings.needs_review(user.id, user.is_admin) {
+        return sqlx::query_scalar("SELECT status FROM vcards WHERE id = ?")
+            .bind(vcard_id)
+            .fetch_one(pool)
+            .await;
+    }
+
+    mark_pending(pool, vcard_id).await?;
+    let name: String = sqlx::query_scalar("SELECT first_name || ' ' || last_name FROM vcards WHERE id = ?")
+        .bind(vcard_id)
+        .fetch_one(pool)
+        .await?;
+    events.publish(events::TOPIC_REVIEW, serde_json::json!({
+        "vcard_id": vcard_id,
+        "name": name,
+        "status": ReviewStatus::Pending,
+        "username": user.username,
+    }));
+    Ok(ReviewStatus::Pending)
+}
+
+// For background imports, which only know the submitting user's ID. No event per card: the
+// reviewers' queue shows them
+pub async fn submit_imported(pool: &SqlitePool, vcard_id: i64, settings: &ApprovalSettings, created_by: Option<i64>, is_admin: bool) -> Result<(), sqlx::Error> {
+    match created_by {
+        Some(user_id) if settings.needs_review(user_id, is_admin) => mark_pending(pool, vcard_id).await,
+        _ => Ok(()),
+    }
+}
+
+async fn require_reviewer(pool: &SqlitePool, session: &Session) -> Result<UserInfo, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let settings = ApprovalSettings::load(pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    if !settings.can_review(user.id, user.is_admin) {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Approver access required".to_string() })));
+    }
+    Ok(user)
+}
+
+// Reviewers only: cards waiting for a decision, oldest first
+pub async fn list_pending_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<VCard>>, (StatusCode, Json<ErrorResponse>)> {
+    require_reviewer(&pool, &session).await?;
+
+    let vcards = sqlx::query_as(&format!("SELECT {} FROM vcards WHERE status = 'pending' ORDER BY updated_at, id", VCARD_COLUMNS))
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(vcards))
+}
+
+pub async fn review_handler(
+    State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Json(req): Json<ReviewRequest>,
+) -> Result<Json<VCard>, (StatusCode, Json<ErrorResponse>)> {
+    let user = require_reviewer(&pool, &session).await?;
+
+    if req.status == ReviewStatus::Pending {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "status must be approved or rejected".to_string() })));
+    }
+    let note = req.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+    if note.is_some_and(|note| note.chars().count() > MAX_NOTE_LEN) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("note must be at most {} characters", MAX_NOTE_LEN) })));
+    }
+
+    let vcard = fetch_vcard(&pool, vcard_id).await?;
+    if vcard.status != ReviewStatus::Pending {
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: "vCard is not awaiting review".to_string() })));
+    }
+
+    sqlx::query("UPDATE vcards SET status = ?, reviewed_by = ?, reviewed_at = CURRENT_TIMESTAMP, review_note = ? WHERE id = ? AND status = 'pending'")
+        .bind(req.status)
+        .bind(user.id)
+        .bind(note)
+        .bind(vcard_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to record decision".to_string() })))?;
+
+    events.publish(events::TOPIC_REVIEW, serde_json::json!({
+        "vcard_id": vcard_id,
+        "name": format!("{} {}", vcard.first_name, vcard.last_name),
+        "status": req.status,
+        "username": user.username,
+    }));
+
+    Ok(Json(fetch_vcard(&pool, vcard_id).await?))
+}
+
+// Admin only
+pub async fn get_approval_settings_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<ApprovalSettings>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let settings = ApprovalSettings::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(settings))
+}
+
+pub async fn update_approval_settings_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(mut settings): Json<ApprovalSettings>,
+) -> Result<Json<ApprovalSettings>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    settings.approvers.sort_unstable();
+    settings.approvers.dedup();
+    for &approver in &settings.approvers {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = ?)")
+            .bind(approver)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+        if !exists {
+            return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("User {} not found", approver) })));
+        }
+    }
+
+    put_setting(&pool, APPROVAL_KEY, &settings, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save approval settings".to_string() })))?;
+
+    Ok(Json(settings))
+}
@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use tower_sessions::Session;
 
+use crate::metrics;
+use crate::repo::UserRepo;
+
 // Session key for storing user ID
 pub const USER_ID_KEY: &str = "user_id";
 pub const USERNAME_KEY: &str = "username";
@@ -58,19 +61,78 @@ pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
     bcrypt::hash(password, 12)
 }
 
+impl UserRepo for SqlitePool {
+    async fn find_user(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        metrics::timed("find_user", sqlx::query_as("SELECT id, username, password_hash, is_admin FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(self))
+            .await
+    }
+
+    async fn find_user_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        metrics::timed("find_user_by_username", sqlx::query_as("SELECT id, username, password_hash, is_admin FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_optional(self))
+            .await
+    }
+
+    async fn list_users(&self) -> Result<Vec<UserInfo>, sqlx::Error> {
+        let users: Vec<(i64, String, bool)> = metrics::timed("list_users", sqlx::query_as("SELECT id, username, is_admin FROM users ORDER BY id")
+            .fetch_all(self))
+            .await?;
+        Ok(users.into_iter().map(|(id, username, is_admin)| UserInfo { id, username, is_admin }).collect())
+    }
+
+    async fn create_user(&self, username: &str, password_hash: &str, is_admin: bool) -> Result<Option<i64>, sqlx::Error> {
+        let result = metrics::timed("create_user", sqlx::query("INSERT INTO users (username, password_hash, is_admin) VALUES (?, ?, ?)")
+            .bind(username)
+            .bind(password_hash)
+            .bind(is_admin)
+            .execute(self))
+            .await;
+        match result {
+            Ok(result) => Ok(Some(result.last_insert_rowid())),
+            Err(e) if e.as_database_error().is_some_and(|db| db.is_unique_violation()) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn update_user(&self, user_id: i64, username: &str, is_admin: bool) -> Result<bool, sqlx::Error> {
+        let result = metrics::timed("update_user", sqlx::query("UPDATE users SET username = ?, is_admin = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(username)
+            .bind(is_admin)
+            .bind(user_id)
+            .execute(self))
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_password_hash(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = metrics::timed("set_password_hash", sqlx::query("UPDATE users SET password_hash = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(password_hash)
+            .bind(user_id)
+            .execute(self))
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = metrics::timed("delete_user", sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(user_id)
+            .execute(self))
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
 // Authenticate user
 pub async fn authenticate_user(
-    pool: &SqlitePool,
+    repo: &impl UserRepo,
     username: &str,
     password: &str,
 ) -> Result<User, String> {
-    let user = sqlx::query_as::<_, User>(
-        "SELECT id, username, password_hash, is_admin FROM users WHERE username = ?"
-    )
-    .bind(username)
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| format!("Database error: {}", e))?;
+    let user = repo.find_user_by_username(username).await
+        .map_err(|e| format!("Database error: {}", e))?;
 
     match user {
         Some(user) => {
@@ -0,0 +1,377 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::approvals::ReviewStatus;
+use crate::auth::get_current_user;
+use crate::capacity;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::repo::VcardRepo;
+use crate::settings::FieldRules;
+use crate::vcards::fetch_vcard;
+use crate::plugins;
+use crate::{render_qr_png, ErrorResponse, VCardData};
+
+const MAX_EVENT_NAME_LEN: usize = 100;
+const MAX_ATTENDEES_PER_IMPORT: usize = 1000;
+
+// What an attendee's badge QR encodes
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum BadgeMode {
+    // The attendee's contact card, with the check-in token as its UID
+    Vcard,
+    // Only the check-in token
+    Checkin,
+}
+
+#[derive(Deserialize)]
+pub struct CreateEventRequest {
+    name: String,
+    badge_mode: Option<BadgeMode>,
+    starts_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Event {
+    id: i64,
+    name: String,
+    badge_mode: BadgeMode,
+    starts_at: Option<String>,
+    attendees: i64,
+    checked_in: i64,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImportAttendeesRequest {
+    // Same fields as /api/generate
+    attendees: Vec<VCardData>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Attendee {
+    id: i64,
+    vcard_id: i64,
+    first_name: String,
+    last_name: String,
+    company: Option<String>,
+    role: Option<String>,
+    checked_in_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CheckinRequest {
+    // Raw text read from the badge: a bare token or a vCard carrying it as UID
+    scan: String,
+}
+
+#[derive(Serialize)]
+pub struct CheckinResponse {
+    attendee: Attendee,
+    checked_in_at: String,
+}
+
+const EVENT_COLUMNS: &str = r#"
+    e.id, e.name, e.badge_mode, e.starts_at, e.created_at,
+    (SELECT COUNT(*) FROM event_attendees a WHERE a.event_id = e.id) AS attendees,
+    (SELECT COUNT(*) FROM event_attendees a WHERE a.event_id = e.id AND a.checked_in_at IS NOT NULL) AS checked_in
+"#;
+
+const ATTENDEE_COLUMNS: &str = "a.id, a.vcard_id, v.first_name, v.last_name, v.company, v.role, a.checked_in_at";
+
+// Store the attendee's contact as a vcard and give them a fresh check-in token
+pub async fn add_attendee(pool: &SqlitePool, event_id: i64, data: &VCardData, created_by: Option<i64>) -> Result<i64, sqlx::Error> {
+    let vcard_id = pool.insert_vcard(data, created_by).await?;
+
+    let result = sqlx::query("INSERT INTO event_attendees (event_id, vcard_id, token) VALUES (?, ?, ?)")
+        .bind(event_id)
+        .bind(vcard_id)
+        .bind(uuid::Uuid::new_v4().simple().to_string())
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn fetch_event(pool: &SqlitePool, event_id: i64) -> Result<Event, (StatusCode, Json<ErrorResponse>)> {
+    let event: Option<Event> = sqlx::query_as(&format!("SELECT {} FROM events e WHERE e.id = ?", EVENT_COLUMNS))
+        .bind(event_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    event.ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Event not found".to_string() })))
+}
+
+async fn fetch_attendee(pool: &SqlitePool, event_id: i64, attendee_id: i64) -> Result<Attendee, (StatusCode, Json<ErrorResponse>)> {
+    let attendee: Option<Attendee> = sqlx::query_as(&format!(
+        "SELECT {} FROM event_attendees a JOIN vcards v ON v.id = a.vcard_id WHERE a.event_id = ? AND a.id = ?",
+        ATTENDEE_COLUMNS
+    ))
+    .bind(event_id)
+    .bind(attendee_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    attendee.ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Attendee not found".to_string() })))
+}
+
+// Pull the check-in token out of whatever the scanner read
+fn extract_token(scan: &str) -> &str {
+    let scan = scan.trim();
+    if scan.starts_with("BEGIN:VCARD") {
+        scan.lines()
+            .find_map(|line| line.strip_prefix("UID:"))
+            .map(str::trim)
+            .unwrap_or_default()
+    } else {
+        scan
+    }
+}
+
+pub async fn create_event_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<CreateEventRequest>,
+) -> Result<(StatusCode, Json<Event>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_EVENT_NAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Event name must be between 1 and {} characters", MAX_EVENT_NAME_LEN),
+        })));
+    }
+
+    let result = sqlx::query("INSERT INTO events (name, badge_mode, starts_at, created_by) VALUES (?, ?, ?, ?)")
+        .bind(name)
+        .bind(req.badge_mode.unwrap_or(BadgeMode::Vcard))
+        .bind(req.starts_at.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()))
+        .bind(user.id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create event".to_string() })))?;
+
+    let event = fetch_event(&pool, result.last_insert_rowid()).await?;
+    Ok((StatusCode::CREATED, Json(event)))
+}
+
+pub async fn list_events_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Event>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let events: Vec<Event> = sqlx::query_as(&format!("SELECT {} FROM events e ORDER BY e.starts_at DESC, e.id DESC", EVENT_COLUMNS))
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(events))
+}
+
+pub async fn get_event_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(event_id): Path<i64>,
+) -> Result<Json<Event>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    Ok(Json(fetch_event(&pool, event_id).await?))
+}
+
+pub async fn import_attendees_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(event_id): Path<i64>,
+    Json(req): Json<ImportAttendeesRequest>,
+) -> Result<(StatusCode, Json<Vec<Attendee>>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    fetch_event(&pool, event_id).await?;
+
+    if req.attendees.is_empty() || req.attendees.len() > MAX_ATTENDEES_PER_IMPORT {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("attendees must contain between 1 and {} entries", MAX_ATTENDEES_PER_IMPORT),
+        })));
+    }
+    let rules = FieldRules::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    // All checked before any is stored, so a bad entry leaves the event as it was
+    for (index, attendee) in req.attendees.iter().enumerate() {
+        rules.check(attendee)
+            .and_then(|()| capacity::check_card(attendee))
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: format!("Attendee {}: {}", index, e) })))?;
+    }
+
+    let mut attendee_ids = Vec::with_capacity(req.attendees.len());
+    for data in &req.attendees {
+        let attendee_id = add_attendee(&pool, event_id, data, Some(user.id)).await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to store attendee".to_string() })))?;
+        attendee_ids.push(attendee_id);
+    }
+
+    let mut attendees = Vec::with_capacity(attendee_ids.len());
+    for attendee_id in attendee_ids {
+        attendees.push(fetch_attendee(&pool, event_id, attendee_id).await?);
+    }
+
+    Ok((StatusCode::CREATED, Json(attendees)))
+}
+
+pub async fn list_attendees_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(event_id): Path<i64>,
+) -> Result<Json<Vec<Attendee>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    fetch_event(&pool, event_id).await?;
+
+    let attendees: Vec<Attendee> = sqlx::query_as(&format!(
+        "SELECT {} FROM event_attendees a JOIN vcards v ON v.id = a.vcard_id WHERE a.event_id = ? ORDER BY v.last_name, v.first_name",
+        ATTENDEE_COLUMNS
+    ))
+    .bind(event_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(attendees))
+}
+
+// Badge QR as PNG, encoded according to the event's badge mode
+pub async fn badge_png(pool: &SqlitePool, budget: &MemoryBudget, event_id: i64, attendee_id: i64) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    let event = fetch_event(pool, event_id).await?;
+    let attendee = fetch_attendee(pool, event_id, attendee_id).await?;
+
+    let token: String = sqlx::query_scalar("SELECT token FROM event_attendees WHERE id = ?")
+        .bind(attendee.id)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let vcard = fetch_vcard(pool, attendee.vcard_id).await?;
+
+    let content = match event.badge_mode {
+        BadgeMode::Checkin => token,
+        // A check-in token carries nothing of the card, but a card on the badge must be approved first
+        BadgeMode::Vcard if vcard.status != ReviewStatus::Approved => {
+            return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "vCard is awaiting approval".to_string() })));
+        }
+        BadgeMode::Vcard => {
+            let data = VCardData {
+                first_name: vcard.first_name,
+                last_name: vcard.last_name,
+                mobile: vcard.mobile,
+                work: vcard.work,
+                email: vcard.email,
+                company: vcard.company,
+                role: vcard.role,
+                street: vcard.street,
+                city: vcard.city,
+                state: vcard.state,
+                postal_code: vcard.postal_code,
+                country: vcard.country,
+                website: vcard.website,
+                extensions: vcard.extensions,
+                labels: vcard.labels,
+                messengers: vcard.messengers,
+                payment: vcard.payment,
+                ..Default::default()
+            };
+            // The UID goes in before plugins run, so a signature covers it
+            let vcard = vcard_core::generate_vcard(&data.contact())
+                .replace("END:VCARD", &format!("UID:{}\nEND:VCARD", token));
+            plugins::transform_vcard(vcard, &data)
+        }
+    };
+
+    let _reservation = budget.reserve(qr_image_bytes(&vcard.render)).await?;
+    render_qr_png(&content, &vcard.render)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render badge".to_string() })))
+}
+
+pub async fn badge_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    session: Session,
+    Path((event_id, attendee_id)): Path<(i64, i64)>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let png = badge_png(&pool, &budget, event_id, attendee_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "image/png")],
+        png,
+    ).into_response())
+}
+
+// Validate a scanned badge and mark the attendee present; a second scan is rejected
+pub async fn checkin_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(event_id): Path<i64>,
+    Json(req): Json<CheckinRequest>,
+) -> Result<Json<CheckinResponse>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let token = extract_token(&req.scan);
+
+    let attendee_id: Option<i64> = sqlx::query_scalar("SELECT id FROM event_attendees WHERE event_id = ? AND token = ?")
+        .bind(event_id)
+        .bind(token)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let attendee_id = attendee_id
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Badge not recognised for this event".to_string() })))?;
+
+    // Conditional update so two doors scanning the same badge cannot both succeed
+    let checked_in_at: Option<String> = sqlx::query_scalar(
+        "UPDATE event_attendees SET checked_in_at = CURRENT_TIMESTAMP WHERE id = ? AND checked_in_at IS NULL RETURNING checked_in_at"
+    )
+    .bind(attendee_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to check in".to_string() })))?;
+
+    let attendee = fetch_attendee(&pool, event_id, attendee_id).await?;
+
+    match checked_in_at {
+        Some(checked_in_at) => Ok(Json(CheckinResponse { attendee, checked_in_at })),
+        None => Err((StatusCode::CONFLICT, Json(ErrorResponse {
+            error: format!("Already checked in at {}", attendee.checked_in_at.unwrap_or_default()),
+        }))),
+    }
+}
@@ -0,0 +1,226 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::cache::ResponseCache;
+use crate::capacity;
+use crate::downloads;
+use crate::jobs;
+use crate::render_pool::Renderer;
+use crate::repo::VcardRepo;
+use crate::revisions;
+use crate::styles;
+use crate::vcards::VCard;
+use crate::{ErrorResponse, VCardData};
+
+pub const JOB_KIND: &str = "bulk";
+
+const MAX_BULK_IDS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    Delete,
+    Tag,
+    Untag,
+    Export,
+    // Drop the cached images and draw the code again, e.g. after a renderer upgrade
+    Rerender,
+    // Give the cards the look of the style template_id names
+    AssignTemplate,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BulkRequest {
+    action: BulkAction,
+    ids: Vec<i64>,
+    // Required for tag and untag
+    tag_id: Option<i64>,
+    // A style ID (see styles.rs); required for assign_template
+    template_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct BulkQueuedResponse {
+    job_id: i64,
+}
+
+#[derive(Serialize)]
+struct BulkFailure {
+    id: i64,
+    error: String,
+}
+
+fn check(req: &BulkRequest) -> Result<(), String> {
+    if req.ids.is_empty() || req.ids.len() > MAX_BULK_IDS {
+        return Err(format!("ids must contain between 1 and {} entries", MAX_BULK_IDS));
+    }
+    if matches!(req.action, BulkAction::Tag | BulkAction::Untag) && req.tag_id.is_none() {
+        return Err("tag_id is required for this action".to_string());
+    }
+    if req.action == BulkAction::AssignTemplate && req.template_id.is_none() {
+        return Err("template_id is required for this action".to_string());
+    }
+    Ok(())
+}
+
+// Check a payload before it is queued from somewhere other than bulk_handler
+pub fn validate(payload: &Value) -> Result<(), String> {
+    let req: BulkRequest = serde_json::from_value(payload.clone())
+        .map_err(|e| format!("Invalid bulk payload: {}", e))?;
+    check(&req)
+}
+
+// Queue a bulk action over many vcards as a background job
+pub async fn bulk_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<BulkRequest>,
+) -> Result<(StatusCode, Json<BulkQueuedResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    check(&req)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    if let (BulkAction::Tag | BulkAction::Untag, Some(tag_id)) = (req.action, req.tag_id) {
+        let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tags WHERE id = ?")
+            .bind(tag_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+        if exists == 0 {
+            return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Tag not found".to_string() })));
+        }
+    }
+
+    if let (BulkAction::AssignTemplate, Some(template_id)) = (req.action, req.template_id) {
+        let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM qr_styles WHERE id = ?")
+            .bind(template_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+        if exists == 0 {
+            return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Style not found".to_string() })));
+        }
+    }
+
+    let payload = serde_json::to_value(&req)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to queue job".to_string() })))?;
+
+    let job_id = jobs::enqueue(&pool, JOB_KIND, &payload, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to queue job".to_string() })))?;
+
+    Ok((StatusCode::ACCEPTED, Json(BulkQueuedResponse { job_id })))
+}
+
+// Restyled cards are checked as an edit would be, since a new error correction level or logo can
+// leave a long card without a code that holds it
+async fn assign_template(
+    pool: &SqlitePool,
+    cache: &ResponseCache,
+    vcard: VCard,
+    template_id: i64,
+    edited_by: Option<i64>,
+) -> Result<(), String> {
+    let vcard_id = vcard.id;
+    let mut data = VCardData::from(vcard);
+    styles::assign_style(pool, template_id, &mut data).await
+        .map_err(|(_, Json(e))| e.error)?;
+    capacity::check_card(&data)?;
+    revisions::update_vcard(pool, vcard_id, &data, edited_by).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    cache.invalidate(vcard_id);
+    Ok(())
+}
+
+async fn apply(
+    pool: &SqlitePool,
+    renderer: &Renderer,
+    cache: &ResponseCache,
+    req: &BulkRequest,
+    vcard_id: i64,
+    created_by: Option<i64>,
+) -> Result<Option<VCard>, String> {
+    let vcard = pool.find_vcard(vcard_id).await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "vCard not found".to_string())?;
+
+    let query = match req.action {
+        BulkAction::Export => return Ok(Some(vcard)),
+        // Only this instance's cache is dropped; copies held by others expire with
+        // PUBLIC_CACHE_MAX_AGE_SECS. A card the renderer can no longer draw is reported as failed
+        BulkAction::Rerender => {
+            cache.invalidate(vcard_id);
+            downloads::prerender_png(&vcard, renderer, cache).await?;
+            return Ok(None);
+        }
+        BulkAction::AssignTemplate => {
+            let template_id = req.template_id.ok_or_else(|| "template_id is required for this action".to_string())?;
+            assign_template(pool, cache, vcard, template_id, created_by).await?;
+            return Ok(None);
+        }
+        BulkAction::Delete => {
+            pool.delete_vcard(vcard_id).await
+                .map_err(|e| format!("Database error: {}", e))?;
+            return Ok(None);
+        }
+        BulkAction::Tag => sqlx::query("INSERT OR IGNORE INTO vcard_tags (vcard_id, tag_id) VALUES (?, ?)")
+            .bind(vcard_id)
+            .bind(req.tag_id),
+        BulkAction::Untag => sqlx::query("DELETE FROM vcard_tags WHERE vcard_id = ? AND tag_id = ?")
+            .bind(vcard_id)
+            .bind(req.tag_id),
+    };
+
+    query.execute(pool).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(None)
+}
+
+// Job body: apply the action to each vcard and summarize the outcome
+pub async fn run(
+    pool: &SqlitePool,
+    renderer: &Renderer,
+    cache: &ResponseCache,
+    payload: Value,
+    created_by: Option<i64>,
+) -> Result<Value, String> {
+    let req: BulkRequest = serde_json::from_value(payload)
+        .map_err(|e| format!("Invalid bulk payload: {}", e))?;
+
+    let mut failed = Vec::new();
+    let mut exported = Vec::new();
+
+    for &vcard_id in &req.ids {
+        match apply(pool, renderer, cache, &req, vcard_id, created_by).await {
+            Ok(Some(vcard)) => exported.push(vcard),
+            Ok(None) => {}
+            Err(error) => failed.push(BulkFailure { id: vcard_id, error }),
+        }
+    }
+
+    let mut summary = json!({
+        "action": req.action,
+        "requested": req.ids.len(),
+        "succeeded": req.ids.len() - failed.len(),
+        "failed": failed,
+    });
+    if req.action == BulkAction::Export {
+        summary["vcards"] = json!(exported);
+    }
+
+    Ok(summary)
+}
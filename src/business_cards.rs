@@ -0,0 +1,220 @@
+// A ready-made business card image: the card's QR code on one side and the name, title, company and
+// contact details laid out beside it, in one of a few built-in templates. Drawn at 300 dpi on the
+// 3.5 x 2 inch card size, so it goes to a print shop or into an email signature as is
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::approvals::ReviewStatus;
+use crate::auth::get_current_user;
+use crate::compose::{self, draw_text, fit_text_px};
+use crate::filenames;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::plugins;
+use crate::render_pool::{Priority, RenderPool};
+use crate::scan;
+use crate::vcards::fetch_vcard;
+use crate::{clean_field, encode_png, generate_vcard, parse_color, render_qr_image, ErrorResponse, RenderOptions, VCardData};
+use vcard_core::contrast::contrast_ratio;
+use vcard_core::{build_qr_code, MAX_RENDER_SCALE};
+
+// In pixels at 300 dpi
+const CARD_WIDTH: u32 = 1050;
+const CARD_HEIGHT: u32 = 600;
+const MARGIN: u32 = 48;
+// The code's square fills the card's height inside the margins; the text gets what is left
+const QR_EDGE: u32 = CARD_HEIGHT - 2 * MARGIN;
+const TEXT_WIDTH: u32 = CARD_WIDTH - QR_EDGE - 4 * MARGIN;
+
+const NAME_PX: f32 = 56.0;
+const TITLE_PX: f32 = 30.0;
+const DETAIL_PX: f32 = 26.0;
+// Space taken by the accent rule under the name, and between the titles and the details
+const RULE_GAP_PX: f32 = 30.0;
+const SECTION_GAP_PX: f32 = 22.0;
+
+const WHITE: (u8, u8, u8) = (255, 255, 255);
+const INK: (u8, u8, u8) = (34, 34, 34);
+const MUTED: (u8, u8, u8) = (102, 102, 102);
+const NEAR_BLACK: (u8, u8, u8) = (30, 30, 30);
+// An accent color below this against white isn't used for the name
+const MIN_TEXT_CONTRAST: f32 = 3.0;
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CardTemplate {
+    // White card, text on the left with the name in the card's color, the code on the right
+    #[default]
+    Classic,
+    // The code on a panel of the card's color on the left, text on white to its right
+    Panel,
+    // Light text on a near-black card, the code on the right
+    Dark,
+}
+
+#[derive(Deserialize)]
+pub struct BusinessCardQuery {
+    #[serde(default)]
+    template: CardTemplate,
+}
+
+struct Layout {
+    background: (u8, u8, u8),
+    // Fill behind the code's side of the card, when it differs from the background
+    panel: Option<(u8, u8, u8)>,
+    code_left: bool,
+    name: (u8, u8, u8),
+    text: (u8, u8, u8),
+    muted: (u8, u8, u8),
+    rule: (u8, u8, u8),
+}
+
+impl CardTemplate {
+    fn layout(self, accent: (u8, u8, u8)) -> Layout {
+        match self {
+            CardTemplate::Classic => Layout {
+                background: WHITE,
+                panel: None,
+                code_left: false,
+                name: if contrast_ratio(accent, WHITE) >= MIN_TEXT_CONTRAST { accent } else { INK },
+                text: INK,
+                muted: MUTED,
+                rule: accent,
+            },
+            CardTemplate::Panel => Layout {
+                background: WHITE,
+                panel: Some(accent),
+                code_left: true,
+                name: INK,
+                text: INK,
+                muted: MUTED,
+                rule: accent,
+            },
+            CardTemplate::Dark => Layout {
+                background: NEAR_BLACK,
+                panel: None,
+                code_left: false,
+                name: WHITE,
+                text: (230, 230, 230),
+                muted: (170, 170, 170),
+                rule: accent,
+            },
+        }
+    }
+}
+
+// The card's dark module color: a gradient's start, the color, or dark grey
+fn accent(options: &RenderOptions) -> (u8, u8, u8) {
+    match (&options.gradient, &options.color) {
+        (Some(gradient), _) => parse_color(&gradient.start),
+        (None, Some(color)) => parse_color(color),
+        (None, None) => INK,
+    }
+}
+
+// The card's own render options, at the most whole pixels per module that fit the code's square
+fn sized_options(content: &str, render: &RenderOptions) -> Result<RenderOptions, String> {
+    let options = RenderOptions { size: None, scale: None, print_size_mm: None, dpi: None, ..render.clone() };
+    let code = build_qr_code(content, &options)?;
+    let modules = code.width() as u32 + 2 * options.quiet_zone_modules();
+    Ok(RenderOptions { scale: Some((QR_EDGE / modules).clamp(1, MAX_RENDER_SCALE)), ..options })
+}
+
+fn rgba((r, g, b): (u8, u8, u8)) -> Rgba<u8> {
+    Rgba([r, g, b, 255])
+}
+
+fn draw_card(data: &VCardData, code: &DynamicImage, template: CardTemplate) -> Result<DynamicImage, String> {
+    let font = compose::font()?;
+    let layout = template.layout(accent(&data.render));
+    let mut card = RgbaImage::from_pixel(CARD_WIDTH, CARD_HEIGHT, rgba(layout.background));
+
+    let (code_x, text_x) = if layout.code_left { (MARGIN, QR_EDGE + 3 * MARGIN) } else { (CARD_WIDTH - MARGIN - QR_EDGE, MARGIN) };
+    if let Some(panel) = layout.panel {
+        imageops::replace(&mut card, &RgbaImage::from_pixel(QR_EDGE + 2 * MARGIN, CARD_HEIGHT, rgba(panel)), 0, 0);
+    }
+    // Whole pixels per module leave the code a little short of its square; it sits in the middle
+    let offset = QR_EDGE.saturating_sub(code.width()) / 2;
+    imageops::overlay(&mut card, &code.to_rgba8(), (code_x + offset) as i64, (MARGIN + offset) as i64);
+
+    let name = format!("{} {}", data.first_name.trim(), data.last_name.trim());
+    let titles: Vec<&str> = [clean_field(&data.role), clean_field(&data.company)].into_iter().flatten().collect();
+    let details: Vec<&str> = [&data.mobile, &data.work, &data.email, &data.website].into_iter().filter_map(clean_field).collect();
+
+    // The text block is centered on the card's height
+    let mut height = NAME_PX * 1.2 + RULE_GAP_PX + titles.len() as f32 * TITLE_PX * 1.35;
+    if !details.is_empty() {
+        height += SECTION_GAP_PX + details.len() as f32 * DETAIL_PX * 1.45;
+    }
+    let text_x = text_x as f32;
+    let mut top = (CARD_HEIGHT as f32 - height) / 2.0;
+    let line = |card: &mut RgbaImage, top: &mut f32, text: &str, size_px: f32, color: (u8, u8, u8), spacing: f32| {
+        let text = fit_text_px(&font, text, size_px, TEXT_WIDTH as f32);
+        draw_text(card, &font, &text, text_x, *top + size_px, size_px, rgba(color));
+        *top += size_px * spacing;
+    };
+
+    line(&mut card, &mut top, name.trim(), NAME_PX, layout.name, 1.2);
+    let rule = RgbaImage::from_pixel(64, 5, rgba(layout.rule));
+    imageops::replace(&mut card, &rule, text_x as i64, (top + 8.0) as i64);
+    top += RULE_GAP_PX;
+    for title in &titles {
+        line(&mut card, &mut top, title, TITLE_PX, layout.text, 1.35);
+    }
+    if !details.is_empty() {
+        top += SECTION_GAP_PX;
+    }
+    for detail in &details {
+        line(&mut card, &mut top, detail, DETAIL_PX, layout.muted, 1.45);
+    }
+
+    Ok(DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(card).to_rgb8()))
+}
+
+// The card as a business card PNG, in the template the query names
+pub async fn business_card_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    State(render_pool): State<RenderPool>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Query(query): Query<BusinessCardQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let vcard = fetch_vcard(&pool, vcard_id).await?;
+    if vcard.status != ReviewStatus::Approved {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "vCard is awaiting approval".to_string() })));
+    }
+    let fallback = filenames::sanitize(&format!("{}-{}", vcard.first_name, vcard.last_name));
+    let data: VCardData = vcard.into();
+    let filename = filenames::download_name(&pool, &data, vcard_id, fallback).await;
+
+    let content = generate_vcard(&data);
+    let options = sized_options(&content, &data.render)
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: "Contact cannot be encoded as a QR code".to_string() })))?;
+    // The code and the card, both held until the card is encoded
+    let _reservation = budget.reserve(qr_image_bytes(&options) + (CARD_WIDTH * CARD_HEIGHT) as u64 * 4).await?;
+
+    let png = render_pool.run(Priority::Interactive, move || {
+        let code = plugins::transform_image(render_qr_image(&content, &options)?, &content);
+        scan::verify(&code, &content)?;
+        let card = draw_card(&data, &code, query.template)?;
+        encode_png(&card).map_err(|e| e.to_string())
+    }).await?
+    .map_err(|e| {
+        eprintln!("Failed to render business card for vCard {}: {}", vcard_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render business card".to_string() }))
+    })?;
+
+    let disposition = format!("inline; filename=\"{}-card.png\"", filename);
+    Ok(([(header::CONTENT_TYPE, "image/png".to_string()), (header::CONTENT_DISPOSITION, disposition)], png).into_response())
+}
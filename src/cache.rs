@@ -0,0 +1,83 @@
+use axum::body::Bytes;
+use moka::future::Cache;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::vcards::VCard;
+
+// Rendered public responses (embed pages, QR and payment images), which are read far more often
+// than the cards behind them change. Entries are keyed by the card's updated_at as well as its ID,
+// so an edit made elsewhere, by another instance or the gRPC API, is never served from here; edits
+// through the REST API also drop the card's entries at once rather than leaving them to expire
+#[derive(Clone)]
+pub struct ResponseCache {
+    entries: Cache<Key, Bytes>,
+    capacity_mib: u64,
+    max_age: Duration,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+struct Key {
+    vcard_id: i64,
+    updated_at: String,
+    // What was rendered and with which inputs, e.g. "qr.png" or the payment amount
+    variant: String,
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name).ok().and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+impl ResponseCache {
+    // RESPONSE_CACHE_MB=0 turns the cache off; Cache-Control headers are still sent
+    pub fn from_env() -> Self {
+        let capacity_mib = env_number("RESPONSE_CACHE_MB").unwrap_or(64);
+        let max_age = Duration::from_secs(env_number("PUBLIC_CACHE_MAX_AGE_SECS").unwrap_or(300));
+        let entries = Cache::builder()
+            .max_capacity(capacity_mib * 1024 * 1024)
+            .weigher(|_, body: &Bytes| body.len().try_into().unwrap_or(u32::MAX))
+            // Shared caches honor max-age too, so nothing here outlives what a CDN may keep
+            .time_to_live(max_age.max(Duration::from_secs(1)))
+            .support_invalidation_closures()
+            .build();
+        ResponseCache { entries, capacity_mib, max_age }
+    }
+
+    pub fn capacity_mib(&self) -> u64 {
+        self.capacity_mib
+    }
+
+    pub fn max_age_secs(&self) -> u64 {
+        self.max_age.as_secs()
+    }
+
+    // For responses that are the same for every visitor until the card is edited
+    pub fn cache_control(&self) -> String {
+        format!("public, max-age={}", self.max_age.as_secs())
+    }
+
+    // The cached body for this card and variant, rendering and storing it on a miss. Failures are
+    // not cached
+    pub async fn get_or_render<E, F, Fut>(&self, vcard: &VCard, variant: &str, render: F) -> Result<Bytes, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, E>>,
+    {
+        let key = Key { vcard_id: vcard.id, updated_at: vcard.updated_at.clone(), variant: variant.to_string() };
+        if let Some(body) = self.entries.get(&key).await {
+            return Ok(body);
+        }
+        let body = Bytes::from(render().await?);
+        if self.capacity_mib > 0 {
+            self.entries.insert(key, body.clone()).await;
+        }
+        Ok(body)
+    }
+
+    // Drops everything rendered for a card; call after changing anything its public views show
+    pub fn invalidate(&self, vcard_id: i64) {
+        if let Err(e) = self.entries.invalidate_entries_if(move |key, _| key.vcard_id == vcard_id) {
+            eprintln!("Failed to invalidate cached responses for vCard {}: {}", vcard_id, e);
+        }
+    }
+}
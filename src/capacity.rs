@@ -0,0 +1,139 @@
+// A card too long for any QR code used to fail deep in the renderer as a bare 500. It is measured
+// against the largest code its options allow before anything is drawn or stored, and refused with
+// 422 saying by how much it overflows and what would make it fit
+
+use axum::{
+    extract::Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use vcard_core::symbology::Symbology;
+use vcard_core::{ErrorCorrection, RenderOptions};
+
+use crate::{clean_field, VCardData};
+
+// How many of the longest fields a refusal names
+const LONGEST_FIELDS_SHOWN: usize = 3;
+
+#[derive(Serialize)]
+pub struct CapacityError {
+    error: String,
+    // Bytes the encoded card takes, the most the code can hold with these options, and the difference
+    needed_bytes: usize,
+    capacity_bytes: usize,
+    excess_bytes: usize,
+    ec_level: ErrorCorrection,
+    suggestions: Vec<String>,
+}
+
+impl IntoResponse for CapacityError {
+    fn into_response(self) -> Response {
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(self)).into_response()
+    }
+}
+
+// The refusal as one line, suggestions included, for callers that report plain messages
+impl std::fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if !self.suggestions.is_empty() {
+            write!(f, " ({})", self.suggestions.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+fn letter(level: ErrorCorrection) -> &'static str {
+    match level {
+        ErrorCorrection::L => "L",
+        ErrorCorrection::M => "M",
+        ErrorCorrection::Q => "Q",
+        ErrorCorrection::H => "H",
+    }
+}
+
+// The contact fields taking the most room, with their length in bytes; extensions count together
+fn longest_fields(data: &VCardData) -> Vec<(&'static str, usize)> {
+    let mut fields: Vec<(&'static str, usize)> = [
+        ("mobile", &data.mobile),
+        ("work", &data.work),
+        ("email", &data.email),
+        ("company", &data.company),
+        ("role", &data.role),
+        ("street", &data.street),
+        ("city", &data.city),
+        ("state", &data.state),
+        ("website", &data.website),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| clean_field(value).map(|value| (name, value.len())))
+    .collect();
+    let extensions: usize = data.extensions.iter().map(|(name, value)| name.len() + value.len()).sum();
+    if extensions > 0 {
+        fields.push(("extensions", extensions));
+    }
+    fields.sort_by_key(|f| std::cmp::Reverse(f.1));
+    fields.truncate(LONGEST_FIELDS_SHOWN);
+    fields
+}
+
+// The checks a card passes before it is stored, for paths without a session or a JSON refusal of
+// their own: imports, jobs, badges, integrations and gRPC
+pub fn check_card(data: &VCardData) -> Result<(), String> {
+    data.validate()?;
+    check(&crate::generate_vcard(data), data).map_err(|e| e.to_string())
+}
+
+// Err when `content`, the card as it will be encoded, is too long for any code its options allow
+pub fn check(content: &str, data: &VCardData) -> Result<(), CapacityError> {
+    let options = &data.render;
+    let Some(overflow) = options.overflow(content) else {
+        return Ok(());
+    };
+    let level = options.effective_ec_level().unwrap_or(ErrorCorrection::M);
+    let fits = |options: &RenderOptions| options.overflow(content).is_none();
+
+    let mut suggestions = Vec::new();
+    // The strongest level below the current one that holds the card; a logo keeps it at Q or above
+    let lower = [ErrorCorrection::Q, ErrorCorrection::M, ErrorCorrection::L]
+        .into_iter()
+        .filter(|&candidate| candidate < level)
+        .find(|&candidate| fits(&RenderOptions { ec_level: Some(candidate), logo: None, ..options.clone() }));
+    match lower {
+        Some(candidate) if options.logo.is_some() && candidate < ErrorCorrection::Q => suggestions.push(format!(
+            "Remove the logo and set ec_level to {}; a logo keeps error correction at Q or above",
+            letter(candidate),
+        )),
+        Some(candidate) => suggestions.push(format!("Lower ec_level to {}, which holds the card", letter(candidate))),
+        None => {}
+    }
+    if options.version.is_some() && fits(&RenderOptions { version: None, ..options.clone() }) {
+        suggestions.push("Leave version unset, so the card can use a larger code".to_string());
+    }
+    if options.symbology != Symbology::Qr && fits(&RenderOptions { symbology: Symbology::Qr, ..options.clone() }) {
+        suggestions.push("Set symbology to qr; a QR code holds more".to_string());
+    }
+    if data.encrypt {
+        suggestions.push("Turn off encrypt; sealing the card makes it about a third longer".to_string());
+    }
+    let longest = longest_fields(data);
+    if !longest.is_empty() {
+        let listed: Vec<String> = longest.iter().map(|(name, len)| format!("{} ({} bytes)", name, len)).collect();
+        suggestions.push(format!("Shorten or drop fields; the longest are {}", listed.join(", ")));
+    }
+
+    Err(CapacityError {
+        error: format!(
+            "The card is {} bytes too long for a {}{}",
+            overflow.excess_bytes(),
+            options.symbology.name(),
+            if options.symbology == Symbology::Qr { format!(" at error correction {}", letter(level)) } else { String::new() },
+        ),
+        needed_bytes: overflow.needed_bytes,
+        capacity_bytes: overflow.capacity_bytes,
+        excess_bytes: overflow.excess_bytes(),
+        ec_level: level,
+        suggestions,
+    })
+}
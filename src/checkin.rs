@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::signing::SigningKey;
+use crate::{render_qr_png, ErrorResponse, RenderOptions};
+
+const MAX_LABEL_LEN: usize = 100;
+const MAX_TOKENS_PER_REQUEST: usize = 200;
+
+#[derive(Deserialize)]
+pub struct IssueTokensRequest {
+    // Shown to door staff when the token is scanned, e.g. "VIP day pass"
+    label: String,
+    count: Option<usize>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+// Signed into the token; short keys keep the QR small
+#[derive(Serialize, Deserialize)]
+struct TokenClaims {
+    n: String,
+    l: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct IssuedToken {
+    token: String,
+    image: String, // base64 encoded
+}
+
+#[derive(Serialize)]
+pub struct IssueTokensResponse {
+    label: String,
+    expires_at: Option<DateTime<Utc>>,
+    tokens: Vec<IssuedToken>,
+}
+
+#[derive(Deserialize)]
+pub struct CheckinRequest {
+    token: String,
+}
+
+#[derive(Serialize)]
+pub struct CheckinResponse {
+    label: String,
+    consumed_at: String,
+}
+
+// Tokens are not stored when issued; the signature alone proves they are ours
+pub async fn issue_tokens_handler(
+    State(key): State<SigningKey>,
+    State(budget): State<MemoryBudget>,
+    session: Session,
+    Json(req): Json<IssueTokensRequest>,
+) -> Result<(StatusCode, Json<IssueTokensResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let label = req.label.trim();
+    if label.is_empty() || label.len() > MAX_LABEL_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Label must be between 1 and {} characters", MAX_LABEL_LEN),
+        })));
+    }
+
+    let count = req.count.unwrap_or(1);
+    if count == 0 || count > MAX_TOKENS_PER_REQUEST {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("count must be between 1 and {}", MAX_TOKENS_PER_REQUEST),
+        })));
+    }
+
+    if req.expires_at.is_some_and(|t| t <= Utc::now()) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "expires_at must be in the future".to_string() })));
+    }
+
+    // Codes are rendered one at a time, so one code's worth covers the batch
+    let _reservation = budget.reserve(qr_image_bytes(&RenderOptions::default())).await?;
+    let mut tokens = Vec::with_capacity(count);
+    for _ in 0..count {
+        let claims = TokenClaims {
+            n: uuid::Uuid::new_v4().simple().to_string(),
+            l: label.to_string(),
+            x: req.expires_at.map(|t| t.timestamp()),
+        };
+        let payload = serde_json::to_vec(&claims)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to issue token".to_string() })))?;
+        let token = key.sign_token(&payload);
+
+        let png = render_qr_png(&token, &RenderOptions::default())
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render token".to_string() })))?;
+        let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+
+        tokens.push(IssuedToken {
+            token,
+            image: format!("data:image/png;base64,{}", base64_img),
+        });
+    }
+
+    Ok((StatusCode::CREATED, Json(IssueTokensResponse {
+        label: label.to_string(),
+        expires_at: req.expires_at,
+        tokens,
+    })))
+}
+
+// Validate a scanned token and consume it; any later scan of the same token is a replay
+pub async fn checkin_handler(
+    State(pool): State<SqlitePool>,
+    State(key): State<SigningKey>,
+    session: Session,
+    Json(req): Json<CheckinRequest>,
+) -> Result<Json<CheckinResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let claims: TokenClaims = key.verify_token(&req.token)
+        .and_then(|payload| serde_json::from_slice(&payload).ok())
+        .ok_or((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Invalid token".to_string() })))?;
+
+    if claims.x.is_some_and(|expires| expires <= Utc::now().timestamp()) {
+        return Err((StatusCode::GONE, Json(ErrorResponse { error: "Token has expired".to_string() })));
+    }
+
+    // The primary key on nonce makes consumption atomic across concurrent scans
+    let consumed_at: Option<String> = sqlx::query_scalar(
+        "INSERT INTO checkin_tokens (nonce, label, consumed_by) VALUES (?, ?, ?) ON CONFLICT(nonce) DO NOTHING RETURNING consumed_at"
+    )
+    .bind(&claims.n)
+    .bind(&claims.l)
+    .bind(user.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to check in".to_string() })))?;
+
+    match consumed_at {
+        Some(consumed_at) => Ok(Json(CheckinResponse { label: claims.l, consumed_at })),
+        None => {
+            let first_use: Option<String> = sqlx::query_scalar("SELECT consumed_at FROM checkin_tokens WHERE nonce = ?")
+                .bind(&claims.n)
+                .fetch_optional(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+            Err((StatusCode::CONFLICT, Json(ErrorResponse {
+                error: format!("Token already used at {}", first_use.unwrap_or_default()),
+            })))
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use serde::Serialize;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use crate::auth::hash_password;
+use crate::notifications;
+use crate::passwords;
+use crate::repo::{UserRepo, VcardRepo};
+use crate::vcards::VCard;
+use crate::{db, generate_vcard, render_qr_eps, render_qr_png, render_qr_svg, render_qr_text, render_vcard_png, RenderOptions, VCardData};
+
+const USAGE: &str = "\
+Usage:
+  vcard-qr-generator                                  Start the server
+  vcard-qr-generator user list                        List accounts
+  vcard-qr-generator user create <username> [--admin] [--password <password>]
+  vcard-qr-generator user reset-password <username> [--password <password>]
+  vcard-qr-generator db migrate                       Apply pending migrations; for deploys that
+                                                      start instances with MIGRATE_ON_START=false
+  vcard-qr-generator db backup <path>                 Write a consistent copy to a new file
+  vcard-qr-generator db vacuum                        Reclaim free space
+  vcard-qr-generator export <path>                    Write every vCard to <path>; .vcf for
+                                                      vCard format, anything else for JSON
+  vcard-qr-generator qr <text> [--format terminal|png|svg|eps]
+                                                      Print a QR code for <text>: block characters
+                                                      (default), or PNG bytes for redirecting to a file
+  vcard-qr-generator stream [--output-dir <dir>]      Read one JSON contact per line on stdin and
+                                                      write one JSON result per line to stdout:
+                                                      a base64 PNG, or the file written to <dir>
+  vcard-qr-generator tui                              Make a one-off card in the terminal
+                                                      (builds with --features tui)
+
+Without --password the password is read from standard input. Commands work on
+DATABASE_PATH directly, so they also work while the web UI is unreachable, and
+are safe to run while the server is up.";
+
+// Admin subcommands given on the command line; returns the process exit code
+pub async fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["user", "list"] => list_users().await,
+        ["user", "create", username, rest @ ..] => create_user(username, rest).await,
+        ["user", "reset-password", username, rest @ ..] => reset_password(username, rest).await,
+        ["db", "migrate"] => migrate().await,
+        ["db", "backup", path] => backup(path).await,
+        ["db", "vacuum"] => vacuum().await,
+        ["export", path] => export(path).await,
+        ["tui"] => tui(),
+        ["qr", text, rest @ ..] => qr(text, rest),
+        ["stream"] => stream(None),
+        ["stream", "--output-dir", dir] => stream(Some(dir)),
+        ["help" | "--help" | "-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("Unknown command: {}\n\n{}", args.join(" "), USAGE)),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+struct Flags<'a> {
+    admin: bool,
+    password: Option<&'a str>,
+}
+
+fn parse_flags<'a>(rest: &[&'a str], allow_admin: bool) -> Result<Flags<'a>, String> {
+    let mut flags = Flags { admin: false, password: None };
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match *arg {
+            "--admin" if allow_admin => flags.admin = true,
+            "--password" => {
+                flags.password = Some(rest.next().ok_or("--password needs a value")?);
+            }
+            other => return Err(format!("Unexpected argument: {}\n\n{}", other, USAGE)),
+        }
+    }
+    Ok(flags)
+}
+
+// From --password, or one line of standard input (prompting when it is a terminal)
+fn read_password(flag: Option<&str>) -> Result<String, String> {
+    let password = match flag {
+        Some(password) => password.to_string(),
+        None => {
+            let stdin = std::io::stdin();
+            if stdin.is_terminal() {
+                print!("Password: ");
+                std::io::stdout().flush().ok();
+            }
+            let mut line = String::new();
+            stdin.lock().read_line(&mut line).map_err(|e| format!("Could not read password: {}", e))?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    if password.is_empty() {
+        return Err("Password must not be empty".to_string());
+    }
+    Ok(password)
+}
+
+// Migrates like the server would on start, so with MIGRATE_ON_START=false commands also refuse a
+// database that `db migrate` has not caught up yet
+async fn open_database() -> Result<sqlx::SqlitePool, String> {
+    let (pool, _) = crate::init_database(crate::migrate_on_start()).await
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    Ok(pool)
+}
+
+// Always applies pending migrations, taking the same lock as starting instances do
+async fn migrate() -> Result<(), String> {
+    crate::init_database(true).await
+        .map_err(|e| format!("Failed to migrate database: {}", e))?;
+    println!("✓ Database is up to date");
+    Ok(())
+}
+
+async fn list_users() -> Result<(), String> {
+    let pool = open_database().await?;
+    let users = pool.list_users().await.map_err(|e| format!("Database error: {}", e))?;
+
+    println!("{:>5}  {:<32}  role", "id", "username");
+    for user in users {
+        println!("{:>5}  {:<32}  {}", user.id, user.username, if user.is_admin { "admin" } else { "user" });
+    }
+    Ok(())
+}
+
+async fn create_user(username: &str, rest: &[&str]) -> Result<(), String> {
+    let flags = parse_flags(rest, true)?;
+    let username = username.trim();
+    if username.is_empty() {
+        return Err("Username must not be empty".to_string());
+    }
+    let password = read_password(flags.password)?;
+    passwords::check(&password, &[username])?;
+    let password_hash = hash_password(&password).map_err(|e| format!("Failed to hash password: {}", e))?;
+
+    let pool = open_database().await?;
+    let id = pool.create_user(username, &password_hash, flags.admin).await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("Username {} already exists; use `user reset-password` to change its password", username))?;
+
+    println!("✓ Created {} {} (id {})", if flags.admin { "admin" } else { "user" }, username, id);
+    Ok(())
+}
+
+async fn reset_password(username: &str, rest: &[&str]) -> Result<(), String> {
+    let flags = parse_flags(rest, false)?;
+    let password = read_password(flags.password)?;
+    passwords::check(&password, &[username])?;
+    let password_hash = hash_password(&password).map_err(|e| format!("Failed to hash password: {}", e))?;
+
+    let pool = open_database().await?;
+    let user = pool.find_user_by_username(username).await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("No user named {}; see `user list`", username))?;
+    pool.set_password_hash(user.id, &password_hash).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    notifications::notify(&pool, user.id, notifications::Activity::PasswordChanged { by_admin: true }).await;
+
+    println!("✓ Password reset for {}", username);
+    Ok(())
+}
+
+async fn backup(path: &str) -> Result<(), String> {
+    let pool = open_database().await?;
+    let bytes = db::backup(&pool, path).await?;
+    println!("✓ Backed up to {} ({} bytes)", path, bytes);
+    Ok(())
+}
+
+async fn vacuum() -> Result<(), String> {
+    let pool = open_database().await?;
+    let (before, after) = db::vacuum(&pool).await.map_err(|e| format!("Vacuum failed: {}", e))?;
+    println!("✓ Vacuumed: {} bytes -> {} bytes", before, after);
+    Ok(())
+}
+
+// Every card, oldest first, through the same listing the API uses
+async fn export(path: &str) -> Result<(), String> {
+    let pool = open_database().await?;
+    let mut vcards: Vec<VCard> = Vec::new();
+    loop {
+        let page = pool.list_vcards(None, Some(i64::MAX), Some(vcards.len() as i64)).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if page.is_empty() {
+            break;
+        }
+        vcards.extend(page);
+    }
+    vcards.reverse();
+
+    let contents = if path.ends_with(".vcf") {
+        vcards.iter().map(|vcard| generate_vcard(&VCardData::from(vcard.clone()))).collect::<Vec<_>>().join("\r\n")
+    } else {
+        serde_json::to_string_pretty(&vcards).map_err(|e| e.to_string())?
+    };
+    std::fs::write(path, contents).map_err(|e| format!("Could not write {}: {}", path, e))?;
+
+    println!("✓ Exported {} vCards to {}", vcards.len(), path);
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn tui() -> Result<(), String> {
+    crate::tui::run()
+}
+
+#[cfg(not(feature = "tui"))]
+fn tui() -> Result<(), String> {
+    Err("This build has no terminal UI; rebuild with `cargo build --features tui`".to_string())
+}
+
+// Scannable straight from the terminal, or PNG bytes for `> code.png`; needs no database
+fn qr(text: &str, rest: &[&str]) -> Result<(), String> {
+    let format = match rest {
+        [] | ["--format", "terminal"] => "terminal",
+        ["--format", "png"] => "png",
+        ["--format", "svg"] => "svg",
+        ["--format", "eps"] => "eps",
+        _ => return Err(format!("Unexpected arguments: {}\n\n{}", rest.join(" "), USAGE)),
+    };
+
+    if format == "png" {
+        let mut stdout = std::io::stdout();
+        if stdout.is_terminal() {
+            return Err("Refusing to write PNG bytes to a terminal; redirect to a file".to_string());
+        }
+        let png = render_qr_png(text, &RenderOptions::default())?;
+        return stdout.write_all(&png).map_err(|e| e.to_string());
+    }
+
+    if format == "svg" {
+        println!("{}", render_qr_svg(text, &RenderOptions::default())?);
+        return Ok(());
+    }
+
+    if format == "eps" {
+        print!("{}", render_qr_eps(text, &RenderOptions::default())?);
+        return Ok(());
+    }
+
+    print!("{}", render_qr_text(text, &RenderOptions::default())?);
+    println!();
+    Ok(())
+}
+
+// One line of `stream` output, in input order
+#[derive(Serialize)]
+struct StreamResult {
+    // 1-based input line number
+    line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn stream_record(line: &str, number: usize, output_dir: Option<&str>) -> Result<StreamResult, String> {
+    let data: VCardData = serde_json::from_str(line).map_err(|e| format!("Invalid record: {}", e))?;
+    data.render.validate_for_card()?;
+    let png = render_vcard_png(&data)?;
+
+    let mut result = StreamResult { line: number, image: None, path: None, error: None };
+    match output_dir {
+        Some(dir) => {
+            // Line numbers keep names unique when two contacts share a name
+            let stem: String = format!("{}-{}", data.first_name, data.last_name)
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+                .collect();
+            let path = Path::new(dir).join(format!("{:06}-{}.png", number, stem));
+            std::fs::write(&path, png).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+            result.path = Some(path.display().to_string());
+        }
+        None => {
+            let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+            result.image = Some(format!("data:image/png;base64,{}", encoded));
+        }
+    }
+    Ok(result)
+}
+
+// Newline-delimited JSON in, one result per record out; a bad record gets an error line and the
+// rest carry on. Nothing is stored, so no database is needed. Exits non-zero if any record failed
+fn stream(output_dir: Option<&str>) -> Result<(), String> {
+    if let Some(dir) = output_dir {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir, e))?;
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let mut failed = 0;
+    for (index, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line.map_err(|e| format!("Could not read input: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let number = index + 1;
+        let result = stream_record(&line, number, output_dir).unwrap_or_else(|error| {
+            failed += 1;
+            StreamResult { line: number, image: None, path: None, error: Some(error) }
+        });
+        let json = serde_json::to_string(&result).map_err(|e| e.to_string())?;
+        writeln!(stdout, "{}", json).map_err(|e| format!("Could not write output: {}", e))?;
+    }
+
+    if failed > 0 {
+        return Err(format!("{} records failed", failed));
+    }
+    Ok(())
+}
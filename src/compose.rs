@@ -0,0 +1,186 @@
+// Text laid out and drawn onto raster images with the bundled font: the labels on proof sheets,
+// the caption printed under a code, such as "Scan me", the text side of business cards, and the
+// band across previews of cards still in review
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::{DynamicImage, ImageBuffer, Pixel, Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::{parse_color, RenderOptions};
+
+pub const FONT_DATA: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+
+pub const DEFAULT_CAPTION: &str = "Scan me";
+pub const MAX_CAPTION_LEN: usize = 40;
+
+// Caption text height as a share of the image width, and the smallest that stays legible
+const CAPTION_SCALE: f32 = 0.08;
+const MIN_CAPTION_PX: f32 = 12.0;
+
+// Share of the code's height the preview band covers: more than error correction level H restores,
+// so a preview never scans
+const PREVIEW_BAND_SCALE: f32 = 0.36;
+const PREVIEW_TEXT: &str = "PREVIEW";
+const PREVIEW_FILL: Rgba<u8> = Rgba([210, 210, 210, 255]);
+const PREVIEW_INK: Rgba<u8> = Rgba([34, 34, 34, 255]);
+
+// `true` for the default caption, or the text itself
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Caption {
+    Enabled(bool),
+    Text(String),
+}
+
+impl Caption {
+    // The text to draw, or None when the caption is turned off
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Caption::Enabled(true) => Some(DEFAULT_CAPTION),
+            Caption::Enabled(false) => None,
+            Caption::Text(text) => Some(text.trim()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.text() {
+            Some("") => Err("caption must not be blank; use true for the default".to_string()),
+            Some(text) if text.chars().count() > MAX_CAPTION_LEN => {
+                Err(format!("caption must be at most {} characters", MAX_CAPTION_LEN))
+            }
+            Some(text) if text.chars().any(char::is_control) => Err("caption must be a single line".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn font() -> Result<FontRef<'static>, String> {
+    FontRef::try_from_slice(FONT_DATA).map_err(|e| e.to_string())
+}
+
+pub fn text_width_px(font: &FontRef, text: &str, size_px: f32) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size_px));
+    text.chars().map(|c| scaled.h_advance(font.glyph_id(c))).sum()
+}
+
+// Shorten text with an ellipsis so it fits within max_px
+pub fn fit_text_px(font: &FontRef, text: &str, size_px: f32, max_px: f32) -> String {
+    if text_width_px(font, text, size_px) <= max_px {
+        return text.to_string();
+    }
+    let mut fitted = text.to_string();
+    while !fitted.is_empty() && text_width_px(font, &format!("{}...", fitted), size_px) > max_px {
+        fitted.pop();
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+// Draw text centered on center_x with its baseline at baseline_y, blended over what is there
+pub fn draw_text_centered<P: Pixel<Subpixel = u8>>(
+    canvas: &mut ImageBuffer<P, Vec<u8>>,
+    font: &FontRef,
+    text: &str,
+    center_x: f32,
+    baseline_y: f32,
+    size_px: f32,
+    color: P,
+) {
+    let left_x = center_x - text_width_px(font, text, size_px) / 2.0;
+    draw_text(canvas, font, text, left_x, baseline_y, size_px, color);
+}
+
+// Draw text starting at left_x with its baseline at baseline_y, blended over what is there
+pub fn draw_text<P: Pixel<Subpixel = u8>>(
+    canvas: &mut ImageBuffer<P, Vec<u8>>,
+    font: &FontRef,
+    text: &str,
+    left_x: f32,
+    baseline_y: f32,
+    size_px: f32,
+    color: P,
+) {
+    let scale = PxScale::from(size_px);
+    let scaled = font.as_scaled(scale);
+    let mut x = left_x;
+
+    for c in text.chars() {
+        let glyph_id = font.glyph_id(c);
+        let glyph = glyph_id.with_scale_and_position(scale, point(x, baseline_y));
+        x += scaled.h_advance(glyph_id);
+
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= canvas.width() as i32 || py >= canvas.height() as i32 {
+                    return;
+                }
+                let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                for (channel, target) in pixel.channels_mut().iter_mut().zip(color.channels()) {
+                    let value = *channel as f32 * (1.0 - coverage) + *target as f32 * coverage;
+                    *channel = value.round() as u8;
+                }
+            });
+        }
+    }
+}
+
+// The code with a band below it holding the caption, centered and shortened to fit the width. The
+// band takes the code's background, or stays clear on a transparent code, and the text its dark
+// color (a gradient's start)
+pub fn caption(image: DynamicImage, text: &str, options: &RenderOptions) -> Result<DynamicImage, String> {
+    let font = font()?;
+    let width = image.width();
+    let size_px = (width as f32 * CAPTION_SCALE).max(MIN_CAPTION_PX);
+    let band = (size_px * 1.6).round() as u32;
+
+    let dark = match (&options.gradient, &options.color) {
+        (Some(gradient), _) => parse_color(&gradient.start),
+        (None, Some(color)) => parse_color(color),
+        (None, None) => (0, 0, 0),
+    };
+    let (r, g, b) = options.background_rgb();
+    // A clear band keeps the text's color in its edges, so it blends onto any page
+    let fill = if options.transparent { Rgba([dark.0, dark.1, dark.2, 0]) } else { Rgba([r, g, b, 255]) };
+
+    let mut canvas = RgbaImage::from_pixel(width, image.height() + band, fill);
+    image::imageops::replace(&mut canvas, &image.to_rgba8(), 0, 0);
+
+    let text = fit_text_px(&font, text, size_px, width as f32 * 0.9);
+    let baseline = image.height() as f32 + size_px * 1.05;
+    draw_text_centered(&mut canvas, &font, &text, width as f32 / 2.0, baseline, size_px, Rgba([dark.0, dark.1, dark.2, 255]));
+
+    Ok(if options.transparent {
+        DynamicImage::ImageRgba8(canvas)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+    })
+}
+
+// Crosses the middle of a code with an opaque band reading PREVIEW. The code keeps its look around
+// it but can't be scanned, so it can't go to print before the card is approved
+pub fn preview(image: DynamicImage) -> Result<DynamicImage, String> {
+    let font = font()?;
+    let (width, height) = (image.width(), image.height());
+    let band = ((height as f32 * PREVIEW_BAND_SCALE).round() as u32).min(height);
+    let top = (height - band) / 2;
+
+    let mut canvas = image.to_rgba8();
+    for y in top..top + band {
+        for x in 0..width {
+            canvas.put_pixel(x, y, PREVIEW_FILL);
+        }
+    }
+    let size_px = (band as f32 * 0.4).max(MIN_CAPTION_PX);
+    let text = fit_text_px(&font, PREVIEW_TEXT, size_px, width as f32 * 0.9);
+    let baseline = top as f32 + band as f32 / 2.0 + size_px * 0.35;
+    draw_text_centered(&mut canvas, &font, &text, width as f32 / 2.0, baseline, size_px, PREVIEW_INK);
+
+    let canvas = DynamicImage::ImageRgba8(canvas);
+    Ok(match image {
+        DynamicImage::ImageLuma8(_) => DynamicImage::ImageLuma8(canvas.to_luma8()),
+        DynamicImage::ImageRgb8(_) => DynamicImage::ImageRgb8(canvas.to_rgb8()),
+        _ => canvas,
+    })
+}
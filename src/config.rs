@@ -0,0 +1,115 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use std::sync::OnceLock;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::ErrorResponse;
+
+const REDACTED: &str = "[redacted]";
+
+static INSTANCE_ID: OnceLock<String> = OnceLock::new();
+
+// Names this process among the instances sharing a database: INSTANCE_ID, else the host name (the
+// container ID under Docker), else a random one. Shows in the config, job leases and migration lock
+pub fn instance_id() -> &'static str {
+    INSTANCE_ID.get_or_init(|| {
+        ["INSTANCE_ID", "HOSTNAME"].iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .map(|value| value.trim().to_string())
+            .find(|value| !value.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()[..8].to_string())
+    })
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Env,
+    Default,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Setting {
+    name: &'static str,
+    // The value the server is actually using, after parsing and defaults
+    value: String,
+    source: Source,
+}
+
+// What this process is actually running with, for the startup banner and /api/admin/config.
+// Built from the parsed values rather than re-read from the environment, so a setting that
+// failed to parse shows the default it fell back to
+#[derive(Serialize, Clone)]
+pub struct EffectiveConfig {
+    version: &'static str,
+    // Which instance answered, when several run behind a load balancer
+    instance: &'static str,
+    features: Vec<&'static str>,
+    plugins: Vec<&'static str>,
+    settings: Vec<Setting>,
+}
+
+fn is_set(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| !value.trim().is_empty())
+}
+
+impl EffectiveConfig {
+    // Version, compiled-in features and installed plugins; settings are added by main as it reads them
+    pub fn for_build() -> Self {
+        let mut features = Vec::new();
+        if cfg!(feature = "grpc") {
+            features.push("grpc");
+        }
+        EffectiveConfig {
+            version: env!("CARGO_PKG_VERSION"),
+            instance: instance_id(),
+            features,
+            plugins: crate::plugins::names(),
+            settings: Vec::new(),
+        }
+    }
+
+    pub fn set(&mut self, name: &'static str, value: impl ToString) {
+        let source = if is_set(name) { Source::Env } else { Source::Default };
+        self.settings.push(Setting { name, value: value.to_string(), source });
+    }
+
+    // Credentials only ever show whether they were provided; `fallback` describes what happens when not
+    pub fn secret(&mut self, name: &'static str, fallback: &str) {
+        let value = if is_set(name) { REDACTED } else { fallback };
+        self.set(name, value);
+    }
+
+    pub fn log(&self) {
+        let features = if self.features.is_empty() { "none".to_string() } else { self.features.join(", ") };
+        let plugins = if self.plugins.is_empty() { "none".to_string() } else { self.plugins.join(", ") };
+        println!("vCard QR Generator {} on {} (features: {}, plugins: {})", self.version, self.instance, features, plugins);
+        let width = self.settings.iter().map(|s| s.name.len()).max().unwrap_or(0);
+        for setting in &self.settings {
+            let source = match setting.source {
+                Source::Env => "env",
+                Source::Default => "default",
+            };
+            println!("  {:width$}  {} ({})", setting.name, setting.value, source, width = width);
+        }
+    }
+}
+
+// Admin only: the effective configuration, with secrets redacted
+pub async fn config_handler(
+    State(config): State<EffectiveConfig>,
+    session: Session,
+) -> Result<Json<EffectiveConfig>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    Ok(Json(config))
+}
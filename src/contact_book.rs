@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use image::DynamicImage;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::db::DbRouter;
+use crate::memory::{embedded_qr_bytes, qr_image_bytes, MemoryBudget};
+use crate::pdf::{self, A4_HEIGHT_MM, A4_WIDTH_MM};
+use crate::render_pool::{Priority, RenderPool};
+use crate::scan;
+use crate::vcards::{self, VCard};
+use crate::{generate_vcard, render_qr_image, ErrorResponse, RenderOptions, VCardData};
+
+const MAX_ENTRIES: i64 = 500;
+const MAX_TITLE_LEN: usize = 100;
+
+// A4 portrait grid, in millimetres
+const COLUMNS: usize = 3;
+const ROWS: usize = 4;
+const MARGIN_MM: f32 = 15.0;
+const HEADER_MM: f32 = 15.0;
+const FOOTER_MM: f32 = 10.0;
+const CELL_WIDTH_MM: f32 = (A4_WIDTH_MM - 2.0 * MARGIN_MM) / COLUMNS as f32;
+const CELL_HEIGHT_MM: f32 = (A4_HEIGHT_MM - 2.0 * MARGIN_MM - HEADER_MM - FOOTER_MM) / ROWS as f32;
+const QR_SIZE_MM: f32 = 40.0;
+
+const TITLE_PT: f32 = 14.0;
+const NAME_PT: f32 = 10.0;
+const DETAIL_PT: f32 = 8.0;
+
+#[derive(Deserialize)]
+pub struct ContactBookQuery {
+    tag: Option<String>,
+    // Matches the card's company field, ignoring case
+    company: Option<String>,
+    title: Option<String>,
+}
+
+// Role and company on one line, whichever are set
+fn detail_line(vcard: &VCard) -> String {
+    [vcard.role.as_deref(), vcard.company.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Cards stored before the scan check may carry colors or shapes that do not read; print those as
+// plain squares, black on white
+pub fn printable_qr(content: &str, render: &RenderOptions) -> Result<DynamicImage, String> {
+    let plain = || RenderOptions { color: None, gradient: None, background_color: None, module_shape: None, finder_shape: None, ..render.clone() };
+    render_qr_image(content, render)
+        .ok()
+        .filter(|image| scan::verify(image, content).is_ok())
+        .map_or_else(|| render_qr_image(content, &plain()), Ok)
+}
+
+fn book_title(query: &ContactBookQuery) -> String {
+    if let Some(title) = query.title.as_deref().map(str::trim).filter(|title| !title.is_empty()) {
+        return title.to_string();
+    }
+    match (query.tag.as_deref(), query.company.as_deref()) {
+        (Some(tag), Some(company)) => format!("Contact book - {} ({})", company, tag),
+        (Some(tag), None) => format!("Contact book - {}", tag),
+        (None, Some(company)) => format!("Contact book - {}", company),
+        (None, None) => "Contact book".to_string(),
+    }
+}
+
+// A paginated PDF directory: one QR with name and role per person, twelve to a page
+pub async fn contact_book_handler(
+    State(db): State<DbRouter>,
+    State(budget): State<MemoryBudget>,
+    State(render_pool): State<RenderPool>,
+    session: Session,
+    Query(query): Query<ContactBookQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if query.title.as_ref().is_some_and(|title| title.len() > MAX_TITLE_LEN) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Title must be at most {} characters", MAX_TITLE_LEN),
+        })));
+    }
+
+    let tag = query.tag.as_deref().filter(|tag| !tag.is_empty());
+    let company = query.company.as_deref().filter(|company| !company.is_empty());
+
+    // One over the cap tells us the book would be truncated
+    let entries = vcards::list_vcards_by_name(db.reader(), tag, company, MAX_ENTRIES + 1).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    if entries.is_empty() {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "No vCards match".to_string() })));
+    }
+    if entries.len() as i64 > MAX_ENTRIES {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("More than {} vCards match; narrow the tag or company", MAX_ENTRIES),
+        })));
+    }
+
+    let title = pdf::fit_text(&book_title(&query), TITLE_PT, A4_WIDTH_MM - 2.0 * MARGIN_MM);
+    let per_page = COLUMNS * ROWS;
+    let page_count = entries.len().div_ceil(per_page);
+
+    // The document keeps every code until it is written out, next to the one being rendered
+    let entries: Vec<(String, String, VCardData, String)> = entries.into_iter()
+        .map(|vcard| {
+            let name = pdf::fit_text(&format!("{} {}", vcard.first_name, vcard.last_name), NAME_PT, CELL_WIDTH_MM - 4.0);
+            let detail = pdf::fit_text(&detail_line(&vcard), DETAIL_PT, CELL_WIDTH_MM - 4.0);
+            let data: VCardData = vcard.into();
+            let content = generate_vcard(&data);
+            (name, detail, data, content)
+        })
+        .collect();
+    let bytes = entries.iter().map(|(_, _, data, content)| embedded_qr_bytes(content, &data.render)).sum::<u64>()
+        + entries.iter().map(|(_, _, data, _)| qr_image_bytes(&data.render)).max().unwrap_or_default();
+    let _reservation = budget.reserve(bytes).await?;
+
+    let mut doc = pdf::Document::new();
+    for (index, (name, detail, data, content)) in entries.into_iter().enumerate() {
+        let slot = index % per_page;
+        if slot == 0 {
+            let page = index / per_page + 1;
+            doc.add_page(A4_WIDTH_MM, A4_HEIGHT_MM);
+            doc.text_centered(&title, A4_WIDTH_MM / 2.0, MARGIN_MM + 6.0, TITLE_PT);
+            doc.text_centered(&format!("Page {} of {}", page, page_count), A4_WIDTH_MM / 2.0, A4_HEIGHT_MM - MARGIN_MM, DETAIL_PT);
+        }
+
+        let x = MARGIN_MM + (slot % COLUMNS) as f32 * CELL_WIDTH_MM;
+        let y = MARGIN_MM + HEADER_MM + (slot / COLUMNS) as f32 * CELL_HEIGHT_MM;
+        let center_x = x + CELL_WIDTH_MM / 2.0;
+
+        // One code at a time, so interactive renders can go in between
+        let render = data.render.clone();
+        let image = render_pool.run(Priority::Batch, move || printable_qr(&content, &render)).await?
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render contact book".to_string() })))?;
+
+        doc.image(image.to_rgb8(), center_x - QR_SIZE_MM / 2.0, y, QR_SIZE_MM, QR_SIZE_MM);
+        doc.text_centered(&name, center_x, y + QR_SIZE_MM + 5.0, NAME_PT);
+        doc.text_centered(&detail, center_x, y + QR_SIZE_MM + 9.0, DETAIL_PT);
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/pdf".to_string()), (header::CONTENT_DISPOSITION, "attachment; filename=\"contact-book.pdf\"".to_string())],
+        doc.finish(),
+    ).into_response())
+}
@@ -0,0 +1,265 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::{sqlite::SqliteRow, Column, Row, SqlitePool, TypeInfo, ValueRef};
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::ErrorResponse;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+// Session payloads are never shown, not even to admins
+const HIDDEN_TABLES: &[&str] = &["tower_sessions"];
+
+// Credentials and their hashes come back as "[redacted]"
+const REDACTED_COLUMNS: &[(&str, &str)] = &[
+    ("users", "password_hash"),
+    ("kiosks", "token_hash"),
+    ("integrations", "token"),
+    ("event_attendees", "token"),
+    ("playlists", "token"),
+];
+
+// Query parameters of the rows endpoint that are not column filters
+const RESERVED_PARAMS: &[&str] = &["limit", "offset", "columns"];
+
+#[derive(Serialize)]
+pub struct TableSummary {
+    name: String,
+    columns: Vec<String>,
+    rows: i64,
+}
+
+#[derive(Serialize)]
+pub struct TableRows {
+    table: String,
+    columns: Vec<String>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    rows: Vec<Map<String, Value>>,
+}
+
+#[derive(Serialize)]
+pub struct ObjectUsage {
+    name: String,
+    bytes: i64,
+}
+
+#[derive(Serialize)]
+pub struct StorageReport {
+    database_path: String,
+    file_bytes: Option<u64>,
+    wal_bytes: Option<u64>,
+    page_size: i64,
+    page_count: i64,
+    free_pages: i64,
+    // Tables and indexes, largest first
+    objects: Vec<ObjectUsage>,
+}
+
+fn database_error(e: sqlx::Error) -> (StatusCode, Json<ErrorResponse>) {
+    eprintln!("Database error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() }))
+}
+
+async fn require_admin(session: &Session) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+    Ok(())
+}
+
+// Identifiers only ever come from sqlite_master or table_info, but are quoted anyway
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+async fn table_names(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(names.into_iter().filter(|name| !HIDDEN_TABLES.contains(&name.as_str())).collect())
+}
+
+async fn table_columns(pool: &SqlitePool, table: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+        .bind(table)
+        .fetch_all(pool)
+        .await
+}
+
+async fn count_rows(pool: &SqlitePool, table: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", quote(table)))
+        .fetch_one(pool)
+        .await
+}
+
+// A cell as JSON, by the type SQLite actually stored; blobs are summarized rather than dumped
+fn cell(row: &SqliteRow, index: usize) -> Value {
+    let Ok(raw) = row.try_get_raw(index) else {
+        return Value::Null;
+    };
+    if raw.is_null() {
+        return Value::Null;
+    }
+    let kind = raw.type_info().name().to_string();
+    match kind.as_str() {
+        "INTEGER" => row.try_get::<i64, _>(index).map(Value::from).unwrap_or(Value::Null),
+        "REAL" => row.try_get::<f64, _>(index).map(Value::from).unwrap_or(Value::Null),
+        "BLOB" => row.try_get::<Vec<u8>, _>(index)
+            .map(|bytes| Value::from(format!("<{} bytes>", bytes.len())))
+            .unwrap_or(Value::Null),
+        _ => row.try_get::<String, _>(index).map(Value::from).unwrap_or(Value::Null),
+    }
+}
+
+// Every browsable table with its columns and row count
+pub async fn list_tables_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<TableSummary>>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let mut tables = Vec::new();
+    for name in table_names(&pool).await.map_err(database_error)? {
+        let columns = table_columns(&pool, &name).await.map_err(database_error)?;
+        let rows = count_rows(&pool, &name).await.map_err(database_error)?;
+        tables.push(TableSummary { name, columns, rows });
+    }
+
+    Ok(Json(tables))
+}
+
+// Read-only page of a table: `columns` picks columns, any other parameter is an equality filter
+pub async fn table_rows_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(table): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<TableRows>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    if !table_names(&pool).await.map_err(database_error)?.contains(&table) {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Table not found".to_string() })));
+    }
+    let all_columns = table_columns(&pool, &table).await.map_err(database_error)?;
+
+    let unknown_column = |column: &str| (StatusCode::BAD_REQUEST, Json(ErrorResponse {
+        error: format!("Unknown column '{}' in {}", column, table),
+    }));
+
+    let columns = match params.get("columns").map(|list| list.trim()).filter(|list| !list.is_empty()) {
+        Some(list) => {
+            let mut picked = Vec::new();
+            for column in list.split(',').map(str::trim) {
+                if !all_columns.iter().any(|c| c == column) {
+                    return Err(unknown_column(column));
+                }
+                picked.push(column.to_string());
+            }
+            picked
+        }
+        None => all_columns.clone(),
+    };
+
+    let mut filters: Vec<(&String, &String)> = params.iter()
+        .filter(|(key, _)| !RESERVED_PARAMS.contains(&key.as_str()))
+        .collect();
+    filters.sort();
+    if let Some((column, _)) = filters.iter().find(|(column, _)| !all_columns.contains(column)) {
+        return Err(unknown_column(column));
+    }
+
+    let parse_param = |name: &str, default: i64| match params.get(name) {
+        Some(value) => value.parse::<i64>().map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("{} must be a number", name),
+        }))),
+        None => Ok(default),
+    };
+    let limit = parse_param("limit", DEFAULT_PAGE_SIZE)?.clamp(1, MAX_PAGE_SIZE);
+    let offset = parse_param("offset", 0)?.max(0);
+
+    let where_clause = if filters.is_empty() {
+        String::new()
+    } else {
+        let conditions: Vec<String> = filters.iter().map(|(column, _)| format!("{} = ?", quote(column))).collect();
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+
+    let count_sql = format!("SELECT COUNT(*) FROM {}{}", quote(&table), where_clause);
+    let mut count_query = sqlx::query_scalar::<_, i64>(&count_sql);
+    for (_, value) in &filters {
+        count_query = count_query.bind(value.as_str());
+    }
+    let total = count_query.fetch_one(&pool).await.map_err(database_error)?;
+
+    let select_list: Vec<String> = columns.iter().map(|column| quote(column)).collect();
+    let rows_sql = format!(
+        "SELECT {} FROM {}{} ORDER BY rowid LIMIT ? OFFSET ?",
+        select_list.join(", "), quote(&table), where_clause
+    );
+    let mut rows_query = sqlx::query(&rows_sql);
+    for (_, value) in &filters {
+        rows_query = rows_query.bind(value.as_str());
+    }
+    let fetched = rows_query.bind(limit).bind(offset).fetch_all(&pool).await.map_err(database_error)?;
+
+    let rows = fetched.iter().map(|row| {
+        row.columns().iter().enumerate().map(|(index, column)| {
+            let name = column.name().to_string();
+            let value = if REDACTED_COLUMNS.contains(&(table.as_str(), name.as_str())) {
+                Value::from("[redacted]")
+            } else {
+                cell(row, index)
+            };
+            (name, value)
+        }).collect()
+    }).collect();
+
+    Ok(Json(TableRows { table, columns, total, limit, offset, rows }))
+}
+
+// Database size on disk, the reclaimable part, and what each table and index takes up
+pub async fn storage_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<StorageReport>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(&pool).await.map_err(database_error)?;
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(&pool).await.map_err(database_error)?;
+    let free_pages: i64 = sqlx::query_scalar("PRAGMA freelist_count").fetch_one(&pool).await.map_err(database_error)?;
+
+    let objects: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT name, SUM(pgsize) AS bytes FROM dbstat GROUP BY name ORDER BY bytes DESC, name"
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(database_error)?;
+
+    let database_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "vcards.db".to_string());
+    let file_bytes = std::fs::metadata(&database_path).ok().map(|m| m.len());
+    let wal_bytes = std::fs::metadata(format!("{}-wal", database_path)).ok().map(|m| m.len());
+
+    Ok(Json(StorageReport {
+        database_path,
+        file_bytes,
+        wal_bytes,
+        page_size,
+        page_count,
+        free_pages,
+        objects: objects.into_iter().map(|(name, bytes)| ObjectUsage { name, bytes }).collect(),
+    }))
+}
@@ -0,0 +1,167 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+
+// Sends reads that can tolerate replication lag (listings, exports, reporting) to a read-only
+// replica when DATABASE_REPLICA_PATH is set; everything else, and every write, keeps using the
+// primary pool from the app state
+#[derive(Clone)]
+pub struct DbRouter {
+    primary: SqlitePool,
+    replica: Option<SqlitePool>,
+    replica_path: Option<String>,
+}
+
+impl DbRouter {
+    pub async fn connect(primary: SqlitePool) -> Result<Self, sqlx::Error> {
+        let replica_path = std::env::var("DATABASE_REPLICA_PATH")
+            .ok()
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty());
+
+        let replica = match &replica_path {
+            Some(path) => {
+                // The replica is kept up to date by something else (e.g. Litestream or LiteFS), so never create or write it
+                let options = SqliteConnectOptions::new()
+                    .filename(path)
+                    .read_only(true)
+                    .create_if_missing(false);
+                let pool = SqlitePool::connect_with(options).await?;
+                println!("✓ Connected to read replica at {}", path);
+                Some(pool)
+            }
+            None => None,
+        };
+
+        Ok(DbRouter { primary, replica, replica_path })
+    }
+
+    pub fn replica_path(&self) -> Option<&str> {
+        self.replica_path.as_deref()
+    }
+
+    pub fn primary(&self) -> &SqlitePool {
+        &self.primary
+    }
+
+    pub fn replica(&self) -> Option<&SqlitePool> {
+        self.replica.as_ref()
+    }
+
+    // Only for queries where slightly stale results are acceptable
+    pub fn reader(&self) -> &SqlitePool {
+        self.replica.as_ref().unwrap_or(&self.primary)
+    }
+}
+
+// Writes a consistent copy of the database to `path` with VACUUM INTO, which is safe while the
+// server keeps writing. The file must not already exist
+pub async fn backup(pool: &SqlitePool, path: &str) -> Result<u64, String> {
+    if std::path::Path::new(path).exists() {
+        return Err(format!("{} already exists; backups never overwrite a file", path));
+    }
+    sqlx::query("VACUUM INTO ?")
+        .bind(path)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Backup failed: {}", e))?;
+    std::fs::metadata(path).map(|m| m.len()).map_err(|e| format!("Backup written but unreadable: {}", e))
+}
+
+async fn database_bytes(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(pool).await?;
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(pool).await?;
+    Ok(page_size * page_count)
+}
+
+// Rebuilds the file to hand free pages back to the filesystem; returns the size before and after
+pub async fn vacuum(pool: &SqlitePool) -> Result<(i64, i64), sqlx::Error> {
+    let before = database_bytes(pool).await?;
+    sqlx::query("VACUUM").execute(pool).await?;
+    let after = database_bytes(pool).await?;
+    Ok((before, after))
+}
+
+// How long a migration lock is held without being renewed before another instance may take it
+// over; renewed after every migration, so only a crashed holder ever lets it lapse
+const MIGRATION_LOCK_LEASE: &str = "+10 minutes";
+const MIGRATION_LOCK_POLL: std::time::Duration = std::time::Duration::from_millis(500);
+
+// Advisory lock that lets one instance at a time apply migrations when several start against the
+// same database during a rolling deploy. SQLite has no advisory locks, so it is a single row with a
+// lease; the others wait for it and then find every migration already applied
+pub struct MigrationLock {
+    pool: SqlitePool,
+    owner: String,
+}
+
+impl MigrationLock {
+    // Waits up to `timeout` for the lock
+    pub async fn acquire(pool: &SqlitePool, timeout: std::time::Duration) -> Result<Self, String> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS migration_lock (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                owner TEXT NOT NULL,
+                expires_at TIMESTAMP NOT NULL
+            )
+            "#,
+        )
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Could not create the migration lock: {}", e))?;
+
+        let owner = format!("{} pid {} ({})", crate::config::instance_id(), std::process::id(), &uuid::Uuid::new_v4().to_string()[..8]);
+        let deadline = std::time::Instant::now() + timeout;
+        let mut announced = false;
+        loop {
+            let taken = sqlx::query(
+                "INSERT INTO migration_lock (id, owner, expires_at) VALUES (1, ?1, datetime('now', ?2))
+                 ON CONFLICT(id) DO UPDATE SET owner = excluded.owner, expires_at = excluded.expires_at
+                 WHERE migration_lock.expires_at < CURRENT_TIMESTAMP",
+            )
+            .bind(&owner)
+            .bind(MIGRATION_LOCK_LEASE)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Could not take the migration lock: {}", e))?
+            .rows_affected() == 1;
+            if taken {
+                return Ok(MigrationLock { pool: pool.clone(), owner });
+            }
+
+            if !announced {
+                let holder: Option<String> = sqlx::query_scalar("SELECT owner FROM migration_lock WHERE id = 1")
+                    .fetch_optional(pool)
+                    .await
+                    .ok()
+                    .flatten();
+                println!("→ Waiting for {} to finish migrating", holder.as_deref().unwrap_or("another instance"));
+                announced = true;
+            }
+            if std::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "Timed out after {}s waiting for the migration lock; if no other instance is migrating, it frees itself once its lease runs out",
+                    timeout.as_secs(),
+                ));
+            }
+            tokio::time::sleep(MIGRATION_LOCK_POLL).await;
+        }
+    }
+
+    // Extends the lease, between migrations that may each take a while
+    pub async fn renew(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE migration_lock SET expires_at = datetime('now', ?) WHERE id = 1 AND owner = ?")
+            .bind(MIGRATION_LOCK_LEASE)
+            .bind(&self.owner)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn release(self) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM migration_lock WHERE id = 1 AND owner = ?")
+            .bind(&self.owner)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
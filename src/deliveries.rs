@@ -0,0 +1,190 @@
+// Outbound deliveries (for now the notification emails) are retried rather than dropped when they
+// fail: each failure is kept with its error, the job goes back in the queue after an exponentially
+// growing wait, and after MAX_ATTEMPTS it is parked as 'dead' until an admin retries it by hand
+
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::notifications;
+use crate::ErrorResponse;
+
+// Job kinds that send something out of the server
+pub const DELIVERY_KINDS: &[&str] = &[notifications::JOB_KIND];
+
+// Attempts before a delivery is dead: retried after 1, 2, 4 ... 64 minutes, about two hours in all
+pub const MAX_ATTEMPTS: i64 = 8;
+const FIRST_RETRY_SECONDS: i64 = 60;
+
+pub fn is_delivery(kind: &str) -> bool {
+    DELIVERY_KINDS.contains(&kind)
+}
+
+// SQLite datetime modifier for when to try again after the given failed attempt
+pub fn retry_modifier(attempt: i64) -> String {
+    format!("+{} seconds", FIRST_RETRY_SECONDS << (attempt - 1).clamp(0, 16))
+}
+
+pub async fn record_failure(pool: &SqlitePool, job_id: i64, attempt: i64, error: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO delivery_failures (job_id, attempt, error) VALUES (?, ?, ?)")
+        .bind(job_id)
+        .bind(attempt)
+        .bind(error)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+fn kinds_json() -> String {
+    serde_json::to_string(DELIVERY_KINDS).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct DeliveryFailure {
+    attempt: i64,
+    error: String,
+    failed_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct DeliveryRow {
+    id: i64,
+    kind: String,
+    status: String,
+    payload: String,
+    error: Option<String>,
+    attempts: i64,
+    run_at: Option<String>,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct Delivery {
+    job_id: i64,
+    kind: String,
+    // 'dead', or 'queued' while waiting for its next automatic attempt
+    status: String,
+    payload: Value,
+    error: Option<String>,
+    attempts: i64,
+    // When the next automatic attempt is due; null once dead
+    next_attempt_at: Option<String>,
+    created_at: String,
+    failures: Vec<DeliveryFailure>,
+}
+
+#[derive(Serialize)]
+pub struct RetryResponse {
+    retried: u64,
+}
+
+async fn require_admin(session: &Session) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+    Ok(())
+}
+
+// Admin: deliveries that have failed at least once and not gone through since, dead ones first,
+// each with its failed attempts
+pub async fn list_deliveries_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Delivery>>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let db_error = |_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() }));
+    let rows: Vec<DeliveryRow> = sqlx::query_as(
+        r#"
+        SELECT id, kind, status, payload, error, attempts, run_at, created_at FROM jobs
+        WHERE kind IN (SELECT value FROM json_each(?))
+          AND (status = 'dead' OR (status = 'queued' AND error IS NOT NULL))
+        ORDER BY status = 'dead' DESC, id DESC
+        LIMIT 500
+        "#
+    )
+    .bind(kinds_json())
+    .fetch_all(&pool)
+    .await
+    .map_err(db_error)?;
+
+    let mut deliveries = Vec::with_capacity(rows.len());
+    for row in rows {
+        let failures: Vec<DeliveryFailure> = sqlx::query_as(
+            "SELECT attempt, error, failed_at FROM delivery_failures WHERE job_id = ? ORDER BY id"
+        )
+        .bind(row.id)
+        .fetch_all(&pool)
+        .await
+        .map_err(db_error)?;
+        let dead = row.status == "dead";
+        deliveries.push(Delivery {
+            job_id: row.id,
+            kind: row.kind,
+            status: row.status,
+            payload: serde_json::from_str(&row.payload).unwrap_or(Value::Null),
+            error: row.error,
+            attempts: row.attempts,
+            next_attempt_at: if dead { None } else { row.run_at },
+            created_at: row.created_at,
+            failures,
+        });
+    }
+
+    Ok(Json(deliveries))
+}
+
+// Puts failed deliveries back in the queue to run straight away, with a fresh set of attempts.
+// The failures already recorded stay
+async fn requeue(pool: &SqlitePool, job_id: Option<i64>) -> Result<u64, sqlx::Error> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE jobs SET status = 'queued', attempts = 0, run_at = NULL, finished_at = NULL
+        WHERE kind IN (SELECT value FROM json_each(?1))
+          AND (status = 'dead' OR (status = 'queued' AND error IS NOT NULL))
+          AND (?2 IS NULL OR id = ?2)
+        "#
+    )
+    .bind(kinds_json())
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(updated.rows_affected())
+}
+
+// Admin: retries one failed delivery now, whether it is dead or waiting for its next attempt
+pub async fn retry_delivery_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(job_id): Path<i64>,
+) -> Result<Json<RetryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let retried = requeue(&pool, Some(job_id)).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if retried == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "No failed delivery with that job ID".to_string() })));
+    }
+    Ok(Json(RetryResponse { retried }))
+}
+
+// Admin: retries every failed delivery now, e.g. once the mail server is back
+pub async fn retry_all_deliveries_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<RetryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let retried = requeue(&pool, None).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    Ok(Json(RetryResponse { retried }))
+}
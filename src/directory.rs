@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::embed::{encode_query_value, escape_html, fill_template, EmbedConfig};
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::public::redact_hidden_fields;
+use crate::settings::{get_setting, put_setting};
+use crate::vcards::{self, VCard};
+use crate::{render_qr_png, ErrorResponse, RenderOptions};
+
+const DIRECTORY_KEY: &str = "directory";
+
+const MAX_ENTRIES: i64 = 500;
+const MAX_TITLE_LEN: usize = 100;
+
+pub const DIRECTORY_TEMPLATE: &str = include_str!("../static/directory.html");
+// Every placeholder directory_handler fills in; the startup self-check makes sure the template has them all
+pub const DIRECTORY_PLACEHOLDERS: &[&str] = &["title", "q", "filters", "summary", "entries", "qr_url"];
+
+// The public team directory is off until an admin turns it on
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct DirectorySettings {
+    pub enabled: bool,
+    // Page heading; "Team directory" when unset
+    pub title: Option<String>,
+}
+
+impl DirectorySettings {
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(get_setting(pool, DIRECTORY_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn save(&self, pool: &SqlitePool, updated_by: i64) -> Result<(), sqlx::Error> {
+        put_setting(pool, DIRECTORY_KEY, self, updated_by).await
+    }
+
+    // Trims the title, dropping a blank one, and checks its length
+    pub fn normalized(mut self) -> Result<Self, String> {
+        self.title = self.title.map(|title| title.trim().to_string()).filter(|title| !title.is_empty());
+        if self.title.as_ref().is_some_and(|title| title.chars().count() > MAX_TITLE_LEN) {
+            return Err(format!("Title must be at most {} characters", MAX_TITLE_LEN));
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DirectoryQuery {
+    // Matches the card's company field, ignoring case, so each company or department gets its own page
+    company: Option<String>,
+    tag: Option<String>,
+    // Search over name, role and company
+    q: Option<String>,
+}
+
+impl DirectoryQuery {
+    fn company(&self) -> Option<&str> {
+        self.company.as_deref().map(str::trim).filter(|company| !company.is_empty())
+    }
+
+    fn tag(&self) -> Option<&str> {
+        self.tag.as_deref().map(str::trim).filter(|tag| !tag.is_empty())
+    }
+
+    fn search(&self) -> Option<&str> {
+        self.q.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
+
+    // The company and tag filters as a query string, kept by search and by the directory's QR
+    fn filters(&self) -> String {
+        [("company", self.company()), ("tag", self.tag())]
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| format!("{}={}", name, encode_query_value(value))))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+// Every search word must appear in the name, role or company
+fn matches(vcard: &VCard, search: &str) -> bool {
+    let haystack = [Some(vcard.first_name.as_str()), Some(vcard.last_name.as_str()), vcard.role.as_deref(), vcard.company.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    search.to_lowercase().split_whitespace().all(|word| haystack.contains(word))
+}
+
+fn entry_html(vcard: &VCard) -> String {
+    let detail = [vcard.role.as_deref(), vcard.company.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let slug = encode_query_value(&vcard.slug);
+    format!(
+        "<li><a class=\"card\" href=\"/embed/{slug}\"><img src=\"/embed/{slug}/qr.png\" alt=\"\" loading=\"lazy\"><span class=\"name\">{}</span><span class=\"detail\">{}</span></a><a class=\"qr\" href=\"/embed/{slug}/qr.png\" target=\"_blank\" rel=\"noopener\">QR code</a></li>",
+        escape_html(&format!("{} {}", vcard.first_name, vcard.last_name)),
+        escape_html(&detail),
+    )
+}
+
+// The settings, or a 404 while the directory is off
+async fn enabled_settings(pool: &SqlitePool) -> Result<DirectorySettings, (StatusCode, Json<ErrorResponse>)> {
+    let settings = DirectorySettings::load(pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if !settings.enabled {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })));
+    }
+    Ok(settings)
+}
+
+// Approved cards for the directory's filters, with hidden fields removed
+async fn directory_entries(pool: &SqlitePool, query: &DirectoryQuery) -> Result<Vec<VCard>, (StatusCode, Json<ErrorResponse>)> {
+    let mut entries = vcards::list_vcards_by_name(pool, query.tag(), query.company(), MAX_ENTRIES).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    for vcard in &mut entries {
+        redact_hidden_fields(pool, vcard).await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    }
+    Ok(entries)
+}
+
+// Public: every published card on one searchable page, each linking to its landing page and QR
+pub async fn directory_handler(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<DirectoryQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let settings = enabled_settings(&pool).await?;
+    let entries = directory_entries(&pool, &query).await?;
+
+    let total = entries.len();
+    let shown: Vec<VCard> = match query.search() {
+        Some(search) => entries.into_iter().filter(|vcard| matches(vcard, search)).collect(),
+        None => entries,
+    };
+
+    let mut title = settings.title.unwrap_or_else(|| "Team directory".to_string());
+    if let Some(company) = query.company() {
+        title = format!("{} - {}", title, company);
+    }
+    let people = if total == 1 { "person" } else { "people" };
+    let summary = match query.search() {
+        Some(_) => format!("{} of {} {}", shown.len(), total, people),
+        None => format!("{} {}", total, people),
+    };
+    let filters: String = [("company", query.company()), ("tag", query.tag())]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| format!("<input type=\"hidden\" name=\"{}\" value=\"{}\">", name, escape_html(value))))
+        .collect();
+    let qr_url = match query.filters().as_str() {
+        "" => "/directory/qr.png".to_string(),
+        filters => format!("/directory/qr.png?{}", filters),
+    };
+
+    let html = fill_template(DIRECTORY_TEMPLATE, &[
+        ("title", escape_html(&title)),
+        ("q", escape_html(query.search().unwrap_or_default())),
+        ("filters", filters),
+        ("summary", summary),
+        ("entries", shown.iter().map(entry_html).collect()),
+        ("qr_url", escape_html(&qr_url)),
+    ]);
+
+    Ok(([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response())
+}
+
+// Public: a QR code for the directory page itself, with the same company and tag, to print once
+// for a whole department
+pub async fn directory_qr_handler(
+    State(pool): State<SqlitePool>,
+    State(config): State<EmbedConfig>,
+    State(budget): State<MemoryBudget>,
+    headers: HeaderMap,
+    Query(query): Query<DirectoryQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    enabled_settings(&pool).await?;
+
+    let url = match query.filters().as_str() {
+        "" => format!("{}/directory", config.base_url(&headers)),
+        filters => format!("{}/directory?{}", config.base_url(&headers), filters),
+    };
+    let _reservation = budget.reserve(qr_image_bytes(&RenderOptions::default())).await?;
+    let png = render_qr_png(&url, &RenderOptions::default())
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() })))?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+// Admin only
+pub async fn get_directory_settings_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<DirectorySettings>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let settings = DirectorySettings::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(settings))
+}
+
+pub async fn update_directory_settings_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(settings): Json<DirectorySettings>,
+) -> Result<Json<DirectorySettings>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let settings = settings.normalized()
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+
+    settings.save(&pool, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save directory settings".to_string() })))?;
+
+    Ok(Json(settings))
+}
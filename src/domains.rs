@@ -0,0 +1,307 @@
+// Custom domains: an organization's own hostnames, e.g. qr.acme.com, pointed at this server so its
+// public pages and the links in printed codes carry its name instead of ours. Requests are routed by
+// Host header; on a custom domain only the public pages answer. TLS is left to the reverse proxy,
+// which gets a certificate per domain over ACME on first use and asks /api/domains/check before it
+// does, so it never requests one for a hostname nobody registered
+
+use axum::{
+    extract::{Json, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::embed::EmbedConfig;
+use crate::{ErrorResponse, MessageResponse};
+
+// How often each instance picks up domains added or removed through another one
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+const MAX_HOSTNAME_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+
+// Path prefixes a custom domain serves: public card pages, the directory and their assets
+const LANDING_PREFIXES: &[&str] = &["/v/", "/embed/", "/directory", "/oembed", "/api/public/", "/static/"];
+
+// The registered hostnames, held in memory so routing a request never waits on the database
+#[derive(Clone, Default)]
+pub struct DomainSet {
+    snapshot: Arc<RwLock<Snapshot>>,
+}
+
+#[derive(Default)]
+struct Snapshot {
+    hosts: HashSet<String>,
+    primary: Option<String>,
+}
+
+impl DomainSet {
+    pub async fn reload(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let rows: Vec<(String, bool)> = sqlx::query_as("SELECT hostname, is_primary FROM custom_domains")
+            .fetch_all(pool)
+            .await?;
+        let primary = rows.iter().find(|(_, is_primary)| *is_primary).map(|(hostname, _)| hostname.clone());
+        let hosts = rows.into_iter().map(|(hostname, _)| hostname).collect();
+        *self.snapshot.write().unwrap() = Snapshot { hosts, primary };
+        Ok(())
+    }
+
+    // Reloads every REFRESH_INTERVAL until the process exits
+    pub async fn keep_fresh(self, pool: SqlitePool) {
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            if let Err(e) = self.reload(&pool).await {
+                eprintln!("Failed to reload custom domains: {}", e);
+            }
+        }
+    }
+
+    pub fn contains(&self, host: &str) -> bool {
+        self.snapshot.read().unwrap().hosts.contains(host)
+    }
+
+    // The domain links generated on the shared hostname should use
+    pub fn primary(&self) -> Option<String> {
+        self.snapshot.read().unwrap().primary.clone()
+    }
+}
+
+// The Host header's hostname, lowercased and without the port
+pub fn request_host(headers: &HeaderMap) -> Option<String> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+    let host = host.rsplit_once(':').map_or(host, |(name, port)| if port.chars().all(|c| c.is_ascii_digit()) { name } else { host });
+    Some(host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+fn is_landing_path(path: &str) -> bool {
+    LANDING_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+        // Signed image links; without a token these answer 401, since sessions stay on our hostname
+        || (path.starts_with("/api/vcards/") && (path.ends_with("/qr.png") || path.ends_with("/qr.svg")))
+}
+
+// Middleware: on a custom domain, / goes to the directory and anything but the public pages is 404
+pub async fn route_by_host(State(domains): State<DomainSet>, request: Request, next: Next) -> Response {
+    let on_custom_domain = request_host(request.headers()).is_some_and(|host| domains.contains(&host));
+    if !on_custom_domain {
+        return next.run(request).await;
+    }
+    match request.uri().path() {
+        "/" => Redirect::to("/directory").into_response(),
+        path if is_landing_path(path) => next.run(request).await,
+        _ => (StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })).into_response(),
+    }
+}
+
+// Lowercased, without a trailing dot; refuses URLs, IP addresses and anything that is not a
+// dotted DNS name
+fn normalize_hostname(input: &str) -> Result<String, String> {
+    let hostname = input.trim().trim_end_matches('.').to_ascii_lowercase();
+    let invalid = || format!("{} is not a domain name such as qr.example.com", input.trim());
+    if hostname.is_empty() || hostname.len() > MAX_HOSTNAME_LEN || !hostname.contains('.') {
+        return Err(invalid());
+    }
+    let labels_ok = hostname.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= MAX_LABEL_LEN
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    if !labels_ok || hostname.parse::<std::net::Ipv4Addr>().is_ok() {
+        return Err(invalid());
+    }
+    Ok(hostname)
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct CustomDomain {
+    id: i64,
+    hostname: String,
+    is_primary: bool,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateDomainRequest {
+    hostname: String,
+    // Use this domain in generated links; takes over from the current primary
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateDomainRequest {
+    primary: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CheckDomainQuery {
+    domain: String,
+}
+
+async fn require_admin(session: &Session) -> Result<i64, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+    Ok(user.id)
+}
+
+async fn set_primary(pool: &SqlitePool, domain_id: i64, primary: bool) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if primary {
+        sqlx::query("UPDATE custom_domains SET is_primary = 0 WHERE id != ?")
+            .bind(domain_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    let updated = sqlx::query("UPDATE custom_domains SET is_primary = ? WHERE id = ?")
+        .bind(primary)
+        .bind(domain_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(updated.rows_affected() > 0)
+}
+
+async fn fetch_domain(pool: &SqlitePool, domain_id: i64) -> Result<CustomDomain, (StatusCode, Json<ErrorResponse>)> {
+    sqlx::query_as("SELECT id, hostname, is_primary, created_at FROM custom_domains WHERE id = ?")
+        .bind(domain_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Domain not found".to_string() })))
+}
+
+// Changes made here take effect on this instance at once and on the others within REFRESH_INTERVAL
+async fn reload(pool: &SqlitePool, config: &EmbedConfig) {
+    if let Err(e) = config.domains.reload(pool).await {
+        eprintln!("Failed to reload custom domains: {}", e);
+    }
+}
+
+pub async fn list_domains_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<CustomDomain>>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let domains = sqlx::query_as("SELECT id, hostname, is_primary, created_at FROM custom_domains ORDER BY hostname")
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(domains))
+}
+
+pub async fn create_domain_handler(
+    State(pool): State<SqlitePool>,
+    State(config): State<EmbedConfig>,
+    session: Session,
+    Json(req): Json<CreateDomainRequest>,
+) -> Result<(StatusCode, Json<CustomDomain>), (StatusCode, Json<ErrorResponse>)> {
+    let user_id = require_admin(&session).await?;
+
+    let hostname = normalize_hostname(&req.hostname)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    let shared = config.public_url.as_deref().and_then(|url| url.split("://").nth(1)).map(|host| host.split([':', '/']).next().unwrap_or(host));
+    if shared.is_some_and(|host| host.eq_ignore_ascii_case(&hostname)) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("{} is this server's own PUBLIC_URL", hostname) })));
+    }
+
+    let result = sqlx::query("INSERT INTO custom_domains (hostname, created_by) VALUES (?, ?) ON CONFLICT(hostname) DO NOTHING")
+        .bind(&hostname)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to add domain".to_string() })))?;
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: format!("{} is already registered", hostname) })));
+    }
+    let domain_id = result.last_insert_rowid();
+
+    if req.primary {
+        set_primary(&pool, domain_id, true).await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to add domain".to_string() })))?;
+    }
+    reload(&pool, &config).await;
+
+    println!("✓ Custom domain {} added", hostname);
+    Ok((StatusCode::CREATED, Json(fetch_domain(&pool, domain_id).await?)))
+}
+
+pub async fn update_domain_handler(
+    State(pool): State<SqlitePool>,
+    State(config): State<EmbedConfig>,
+    session: Session,
+    Path(domain_id): Path<i64>,
+    Json(req): Json<UpdateDomainRequest>,
+) -> Result<Json<CustomDomain>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let updated = set_primary(&pool, domain_id, req.primary).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update domain".to_string() })))?;
+    if !updated {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Domain not found".to_string() })));
+    }
+    reload(&pool, &config).await;
+
+    Ok(Json(fetch_domain(&pool, domain_id).await?))
+}
+
+pub async fn delete_domain_handler(
+    State(pool): State<SqlitePool>,
+    State(config): State<EmbedConfig>,
+    session: Session,
+    Path(domain_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let result = sqlx::query("DELETE FROM custom_domains WHERE id = ?")
+        .bind(domain_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete domain".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Domain not found".to_string() })));
+    }
+    reload(&pool, &config).await;
+
+    Ok(Json(MessageResponse {
+        message: "Domain deleted successfully".to_string(),
+    }))
+}
+
+// Public, for the reverse proxy's on-demand TLS: 200 for a registered domain, 404 otherwise.
+// Reads the database rather than the in-memory set, so a domain added on another instance gets
+// its certificate straight away
+pub async fn check_domain_handler(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<CheckDomainQuery>,
+) -> StatusCode {
+    let Ok(hostname) = normalize_hostname(&query.domain) else {
+        return StatusCode::NOT_FOUND;
+    };
+    let registered: Result<bool, _> = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM custom_domains WHERE hostname = ?)")
+        .bind(&hostname)
+        .fetch_one(&pool)
+        .await;
+    match registered {
+        Ok(true) => StatusCode::OK,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            eprintln!("Failed to check custom domain {}: {}", hostname, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
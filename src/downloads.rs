@@ -0,0 +1,279 @@
+// A stored card's code as a plain image file, for scripts and curl rather than the JSON API, where
+// base64 adds a third to the size: curl -OJ .../api/vcards/42/qr.png saves it under its own name.
+// /api/vcards/:id/qr serves every representation from one URL, picked by the Accept header. The
+// .png and .svg routes also take a signed ?token= instead of a session, for embedding elsewhere;
+// .txt prints the code in a terminal, for a quick check over SSH
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::alt_text;
+use crate::approvals::ReviewStatus;
+use crate::auth::get_current_user;
+use crate::cache::ResponseCache;
+use crate::filenames;
+use crate::image_links::{self, LinkFormat};
+use crate::memory::qr_image_bytes;
+use crate::negotiation::{self, GenerateMedia};
+use crate::plugins;
+use crate::render_pool::{Priority, Renderer};
+use crate::scan;
+use crate::signing::SigningKey;
+use crate::vcards::{fetch_vcard, VCard};
+use crate::{eps_response, generate_vcard, render_qr_image, render_qr_pdf, render_qr_svg, text_response, ErrorResponse, VCardData, PDF_DEFAULT_SIZE_MM};
+
+#[derive(Serialize)]
+pub struct QrImage {
+    // The PNG as a data URL, as /api/generate returns it
+    image: String,
+    filename: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImageQuery {
+    // A signed image link, in place of a session
+    token: Option<String>,
+}
+
+impl ImageQuery {
+    fn disposition(&self) -> &'static str {
+        if self.token.is_some() { "inline" } else { "attachment" }
+    }
+}
+
+// A signed-in user, or a valid image link for this card and format
+async fn authorize(
+    pool: &SqlitePool,
+    key: &SigningKey,
+    session: &Session,
+    query: &ImageQuery,
+    vcard_id: i64,
+    format: LinkFormat,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    match &query.token {
+        Some(token) => image_links::check(pool, key, token, vcard_id, format).await,
+        None => get_current_user(session).await
+            .map(|_| ())
+            .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() }))),
+    }
+}
+
+// An approved card, with its download name: the admin's filename pattern, or else the contact's
+// name, e.g. "Jane_Doe". Callers check the requester first
+pub async fn downloadable(pool: &SqlitePool, vcard_id: i64) -> Result<(VCard, String), (StatusCode, Json<ErrorResponse>)> {
+    let vcard = fetch_vcard(pool, vcard_id).await?;
+    if vcard.status != ReviewStatus::Approved {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "vCard is awaiting approval".to_string() })));
+    }
+
+    let data: VCardData = vcard.clone().into();
+    let name = filenames::sanitize(&format!("{} {}", data.first_name, data.last_name));
+    let fallback = if name.is_empty() { format!("vcard-{}", vcard_id) } else { name };
+    let filename = filenames::download_name(pool, &data, vcard_id, fallback).await;
+    Ok((vcard, filename))
+}
+
+// `disposition` is attachment for downloads, inline for images shown on other pages
+fn file_response(disposition: &str, content_type: &str, filename: &str, extension: &str, body: impl IntoResponse) -> Response {
+    let disposition = format!("{}; filename=\"{}.{}\"", disposition, filename, extension);
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    ).into_response()
+}
+
+// The whole card, for its owner; public views draw the redacted card under their own variant, so the
+// two never share a cache entry
+async fn png(
+    vcard: &VCard,
+    renderer: &Renderer,
+    cache: &ResponseCache,
+    priority: Priority,
+) -> Result<axum::body::Bytes, (StatusCode, Json<ErrorResponse>)> {
+    cache.get_or_render(vcard, "download qr.png", || async {
+        let data: VCardData = vcard.clone().into();
+        let _reservation = renderer.budget.reserve(qr_image_bytes(&data.render)).await?;
+        renderer.pool.png(priority, generate_vcard(&data), data.render.clone()).await
+    }).await
+}
+
+// Draws a card's PNG into the cache behind interactive renders, so the next download is a hit
+pub async fn prerender_png(vcard: &VCard, renderer: &Renderer, cache: &ResponseCache) -> Result<(), String> {
+    png(vcard, renderer, cache, Priority::Batch).await
+        .map(drop)
+        .map_err(|(_, Json(e))| e.error)
+}
+
+// Drawn from the code itself, so image plugins do not apply
+async fn svg(vcard: &VCard, cache: &ResponseCache) -> Result<axum::body::Bytes, (StatusCode, Json<ErrorResponse>)> {
+    cache.get_or_render(vcard, "download qr.svg", || async {
+        let data: VCardData = vcard.clone().into();
+        render_qr_svg(&generate_vcard(&data), &data.render)
+            .map(|svg| alt_text::label_svg(&svg, &alt_text::describe(&data)).into_bytes())
+            .map_err(|e| {
+                eprintln!("Failed to render QR code as SVG: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+            })
+    }).await
+}
+
+// The A4 page /api/generate makes for "format": "pdf", at the default print size
+async fn pdf(
+    vcard: &VCard,
+    renderer: &Renderer,
+) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    let data: VCardData = vcard.clone().into();
+    let _reservation = renderer.budget.reserve(qr_image_bytes(&data.render)).await?;
+    let name = format!("{} {}", data.first_name, data.last_name).trim().to_string();
+    let content = generate_vcard(&data);
+    renderer.pool.run(Priority::Interactive, move || {
+        let image = plugins::transform_image(render_qr_image(&content, &data.render)?, &content);
+        scan::verify(&image, &content)?;
+        Ok::<_, String>(render_qr_pdf(image.to_rgb8(), &name, PDF_DEFAULT_SIZE_MM))
+    }).await?
+        .map_err(|e| {
+            eprintln!("Failed to render QR code as PDF: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+        })
+}
+
+pub async fn qr_png_handler(
+    State(pool): State<SqlitePool>,
+    State(key): State<SigningKey>,
+    State(renderer): State<Renderer>,
+    State(cache): State<ResponseCache>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Query(query): Query<ImageQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    authorize(&pool, &key, &session, &query, vcard_id, LinkFormat::Png).await?;
+    let (vcard, filename) = downloadable(&pool, vcard_id).await?;
+    let png = png(&vcard, &renderer, &cache, Priority::Interactive).await?;
+    Ok(file_response(query.disposition(), "image/png", &filename, "png", png))
+}
+
+pub async fn qr_svg_handler(
+    State(pool): State<SqlitePool>,
+    State(key): State<SigningKey>,
+    State(cache): State<ResponseCache>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Query(query): Query<ImageQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    authorize(&pool, &key, &session, &query, vcard_id, LinkFormat::Svg).await?;
+    let (vcard, filename) = downloadable(&pool, vcard_id).await?;
+    let svg = svg(&vcard, &cache).await?;
+    Ok(file_response(query.disposition(), "image/svg+xml", &filename, "svg", svg))
+}
+
+// Shown rather than saved, so curl prints it: curl -b cookies .../api/vcards/42/qr.txt
+pub async fn qr_text_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+    let (vcard, filename) = downloadable(&pool, vcard_id).await?;
+    let data: VCardData = vcard.into();
+    text_response(&generate_vcard(&data), &data.render, &format!("inline; filename=\"{}.txt\"", filename))
+}
+
+// One URL for every representation, chosen by Accept as on /api/generate; JSON without one
+pub async fn qr_handler(
+    State(pool): State<SqlitePool>,
+    State(renderer): State<Renderer>,
+    State(cache): State<ResponseCache>,
+    session: Session,
+    headers: HeaderMap,
+    Path(vcard_id): Path<i64>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let media = negotiation::negotiate(&headers)
+        .ok_or((StatusCode::NOT_ACCEPTABLE, Json(ErrorResponse {
+            error: format!("Can only respond with {}", GenerateMedia::SUPPORTED),
+        })))?;
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+    let (vcard, filename) = downloadable(&pool, vcard_id).await?;
+
+    let response = match media {
+        GenerateMedia::Json => {
+            let png = png(&vcard, &renderer, &cache, Priority::Interactive).await?;
+            let image = format!("data:image/png;base64,{}", base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png));
+            Json(QrImage { image, filename: format!("{}.png", filename) }).into_response()
+        }
+        GenerateMedia::Png => file_response("attachment", "image/png", &filename, "png", png(&vcard, &renderer, &cache, Priority::Interactive).await?),
+        GenerateMedia::Svg => file_response("attachment", "image/svg+xml", &filename, "svg", svg(&vcard, &cache).await?),
+        GenerateMedia::Pdf => file_response("attachment", "application/pdf", &filename, "pdf", pdf(&vcard, &renderer).await?),
+        GenerateMedia::Eps => {
+            let data: VCardData = vcard.into();
+            eps_response(&generate_vcard(&data), &data.render, &format!("attachment; filename=\"{}.eps\"", filename))?
+        }
+        GenerateMedia::VCard => {
+            let data: VCardData = vcard.into();
+            file_response("attachment", "text/vcard; charset=utf-8", &filename, "vcf", generate_vcard(&data))
+        }
+    };
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Path, State};
+
+    use super::png;
+    use crate::cache::ResponseCache;
+    use crate::memory::MemoryBudget;
+    use crate::render_pool::{Priority, RenderPool, Renderer};
+    use crate::repo::VcardRepo;
+    use crate::VCardData;
+
+    fn decode(png: &[u8]) -> String {
+        let gray = image::load_from_memory(png).unwrap().to_luma8();
+        let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+            gray.width() as usize,
+            gray.height() as usize,
+            |x, y| gray.get_pixel(x as u32, y as u32)[0],
+        );
+        let grids = prepared.detect_grids();
+        grids[0].decode().unwrap().1
+    }
+
+    #[tokio::test]
+    async fn downloads_do_not_leak_hidden_fields_into_the_embed_image() {
+        let pool = crate::test_pool().await;
+        let data = VCardData {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            email: Some("ada@example.com".to_string()),
+            ..Default::default()
+        };
+        let id = pool.insert_vcard(&data, None).await.unwrap();
+        sqlx::query("UPDATE vcards SET hidden_fields = '[\"email\"]' WHERE id = ?").bind(id).execute(&pool).await.unwrap();
+
+        let renderer = Renderer { budget: MemoryBudget::new(64), pool: RenderPool::new(1) };
+        let cache = ResponseCache::from_env();
+        let (vcard, _) = super::downloadable(&pool, id).await.unwrap();
+        let download = png(&vcard, &renderer, &cache, Priority::Interactive).await.unwrap();
+        assert!(decode(&download).contains("ada@example.com"));
+
+        let embed = crate::embed::embed_qr_handler(
+            State(pool.clone()),
+            State(renderer.budget.clone()),
+            State(renderer.pool.clone()),
+            State(cache.clone()),
+            Path(vcard.slug.clone()),
+        ).await.unwrap();
+        let embed = axum::body::to_bytes(embed.into_body(), usize::MAX).await.unwrap();
+        let content = decode(&embed);
+        assert!(content.contains("FN:Ada Lovelace"));
+        assert!(!content.contains("ada@example.com"));
+    }
+}
@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::{ErrorResponse, MessageResponse};
+
+// Upper bound on stored draft JSON so autosave can't be used as free storage
+const MAX_DRAFT_BYTES: usize = 16 * 1024;
+// Likewise for the number of drafts: enough for a few forms left open, not an address book
+const MAX_DRAFTS_PER_USER: i64 = 20;
+// Drafts nobody has saved for this long are abandoned
+const DRAFT_EXPIRY_DAYS: i64 = 30;
+
+#[derive(sqlx::FromRow)]
+struct DraftRow {
+    id: i64,
+    data: String,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct Draft {
+    id: i64,
+    data: Value,
+    created_at: String,
+    updated_at: String,
+}
+
+impl From<DraftRow> for Draft {
+    fn from(row: DraftRow) -> Self {
+        Draft {
+            id: row.id,
+            data: serde_json::from_str(&row.data).unwrap_or(Value::Null),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+// Drafts hold partially filled VCardData, so any JSON object is accepted
+fn validate_draft(data: &Value) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    if !data.is_object() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Draft must be a JSON object".to_string() })));
+    }
+
+    let serialized = data.to_string();
+    if serialized.len() > MAX_DRAFT_BYTES {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, Json(ErrorResponse { error: "Draft is too large".to_string() })));
+    }
+
+    Ok(serialized)
+}
+
+async fn fetch_draft(pool: &SqlitePool, user_id: i64, draft_id: i64) -> Result<Draft, (StatusCode, Json<ErrorResponse>)> {
+    let row: Option<DraftRow> = sqlx::query_as("SELECT id, data, created_at, updated_at FROM drafts WHERE id = ? AND user_id = ?")
+        .bind(draft_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    row.map(Draft::from)
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Draft not found".to_string() })))
+}
+
+// Remove a draft once it has been promoted to a real vcard
+pub async fn discard_draft(pool: &SqlitePool, user_id: i64, draft_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM drafts WHERE id = ? AND user_id = ?")
+        .bind(draft_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Opportunistically drops abandoned drafts, everyone's, as uploads.rs does for uploads
+async fn expire_drafts(pool: &SqlitePool) {
+    if let Err(e) = sqlx::query("DELETE FROM drafts WHERE updated_at < datetime('now', ?)")
+        .bind(format!("-{} days", DRAFT_EXPIRY_DAYS))
+        .execute(pool)
+        .await
+    {
+        eprintln!("Failed to expire abandoned drafts: {}", e);
+    }
+}
+
+// None once the user holds MAX_DRAFTS_PER_USER; counted in the insert, so parallel saves can't overshoot
+async fn insert_draft(pool: &SqlitePool, user_id: i64, serialized: &str) -> Result<Option<i64>, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO drafts (user_id, data) SELECT ?1, ?2 WHERE (SELECT COUNT(*) FROM drafts WHERE user_id = ?1) < ?3")
+        .bind(user_id)
+        .bind(serialized)
+        .bind(MAX_DRAFTS_PER_USER)
+        .execute(pool)
+        .await?;
+    Ok((result.rows_affected() > 0).then_some(result.last_insert_rowid()))
+}
+
+pub async fn list_drafts_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Draft>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    expire_drafts(&pool).await;
+
+    let rows: Vec<DraftRow> = sqlx::query_as("SELECT id, data, created_at, updated_at FROM drafts WHERE user_id = ? ORDER BY updated_at DESC, id DESC")
+        .bind(user.id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(rows.into_iter().map(Draft::from).collect()))
+}
+
+pub async fn get_draft_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(draft_id): Path<i64>,
+) -> Result<Json<Draft>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    Ok(Json(fetch_draft(&pool, user.id, draft_id).await?))
+}
+
+pub async fn create_draft_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(data): Json<Value>,
+) -> Result<Json<Draft>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let serialized = validate_draft(&data)?;
+
+    expire_drafts(&pool).await;
+
+    let draft_id = insert_draft(&pool, user.id, &serialized).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save draft".to_string() })))?
+        .ok_or((StatusCode::CONFLICT, Json(ErrorResponse {
+            error: format!("At most {} drafts can be kept; delete one first", MAX_DRAFTS_PER_USER),
+        })))?;
+
+    Ok(Json(fetch_draft(&pool, user.id, draft_id).await?))
+}
+
+// Autosave: overwrite the draft with the latest form state
+pub async fn update_draft_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(draft_id): Path<i64>,
+    Json(data): Json<Value>,
+) -> Result<Json<Draft>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let serialized = validate_draft(&data)?;
+
+    let result = sqlx::query("UPDATE drafts SET data = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND user_id = ?")
+        .bind(&serialized)
+        .bind(draft_id)
+        .bind(user.id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save draft".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Draft not found".to_string() })));
+    }
+
+    Ok(Json(fetch_draft(&pool, user.id, draft_id).await?))
+}
+
+pub async fn delete_draft_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(draft_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    discard_draft(&pool, user.id, draft_id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete draft".to_string() })))?;
+
+    Ok(Json(MessageResponse {
+        message: "Draft deleted successfully".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expire_drafts, insert_draft, MAX_DRAFTS_PER_USER};
+    use crate::repo::UserRepo;
+
+    async fn draft_count(pool: &sqlx::SqlitePool, user_id: i64) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM drafts WHERE user_id = ?").bind(user_id).fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn each_user_keeps_a_limited_number_of_drafts() {
+        let pool = crate::test_pool().await;
+        let ada = pool.create_user("ada", "unused", false).await.unwrap().unwrap();
+        let grace = pool.create_user("grace", "unused", false).await.unwrap().unwrap();
+
+        for _ in 0..MAX_DRAFTS_PER_USER {
+            assert!(insert_draft(&pool, ada, "{}").await.unwrap().is_some());
+        }
+        assert!(insert_draft(&pool, ada, "{}").await.unwrap().is_none());
+        assert_eq!(draft_count(&pool, ada).await, MAX_DRAFTS_PER_USER);
+
+        // The limit is per user
+        assert!(insert_draft(&pool, grace, "{}").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn abandoned_drafts_expire() {
+        let pool = crate::test_pool().await;
+        let ada = pool.create_user("ada", "unused", false).await.unwrap().unwrap();
+        let stale = insert_draft(&pool, ada, "{}").await.unwrap().unwrap();
+        insert_draft(&pool, ada, "{}").await.unwrap().unwrap();
+        sqlx::query("UPDATE drafts SET updated_at = datetime('now', '-31 days') WHERE id = ?")
+            .bind(stale)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        expire_drafts(&pool).await;
+        assert_eq!(draft_count(&pool, ada).await, 1);
+    }
+}
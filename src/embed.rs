@@ -0,0 +1,313 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::alt_text;
+use crate::cache::ResponseCache;
+use crate::domains::{self, DomainSet};
+use crate::filenames;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::public::{address_lines, find_public_vcard};
+use crate::render_pool::{Priority, RenderPool};
+use crate::vcards::VCard;
+use crate::{generate_vcard, ErrorResponse, VCardData};
+
+const DEFAULT_EMBED_WIDTH: u32 = 320;
+const DEFAULT_EMBED_HEIGHT: u32 = 440;
+
+pub const EMBED_TEMPLATE: &str = include_str!("../static/embed.html");
+// Every placeholder embed_handler fills in; the startup self-check makes sure the template has them all
+pub const EMBED_PLACEHOLDERS: &[&str] = &["name", "detail", "address", "image", "alt_text", "description", "messengers", "vcf_url", "oembed_url"];
+
+#[derive(Clone)]
+pub struct EmbedConfig {
+    // Origin embeds and oEmbed responses link back to; derived from the Host header when unset
+    pub public_url: Option<String>,
+    // Value of the frame-ancestors directive on embed pages
+    pub frame_ancestors: String,
+    // The organization's own domains, which take precedence over public_url
+    pub domains: DomainSet,
+}
+
+impl EmbedConfig {
+    pub fn from_env() -> Self {
+        let public_url = std::env::var("PUBLIC_URL")
+            .ok()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+        let frame_ancestors = std::env::var("EMBED_FRAME_ANCESTORS")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| "*".to_string());
+        EmbedConfig { public_url, frame_ancestors, domains: DomainSet::default() }
+    }
+
+    // The request's own custom domain, else the primary one, else PUBLIC_URL or the Host header.
+    // Custom domains are served over HTTPS by the proxy in front
+    pub fn base_url(&self, headers: &HeaderMap) -> String {
+        if let Some(host) = domains::request_host(headers).filter(|host| self.domains.contains(host)) {
+            return format!("https://{}", host);
+        }
+        if let Some(primary) = self.domains.primary() {
+            return format!("https://{}", primary);
+        }
+        if let Some(url) = &self.public_url {
+            return url.clone();
+        }
+        let host = headers
+            .get(header::HOST)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("localhost");
+        format!("http://{}", host)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OEmbedQuery {
+    url: String,
+    maxwidth: Option<u32>,
+    maxheight: Option<u32>,
+    format: Option<String>,
+}
+
+// https://oembed.com/#section2.3, "rich" type
+#[derive(Serialize)]
+pub struct OEmbedResponse {
+    version: &'static str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: String,
+    provider_name: &'static str,
+    provider_url: String,
+    html: String,
+    width: u32,
+    height: u32,
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Substitute {{key}} placeholders in one pass, so values are never themselves expanded
+pub fn fill_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let key = &after[..end];
+                match values.iter().find(|(name, _)| *name == key) {
+                    Some((_, value)) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn detail_line(vcard: &VCard) -> String {
+    [vcard.role.as_deref(), vcard.company.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn fetch_public_vcard(pool: &SqlitePool, slug: &str) -> Result<VCard, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = find_public_vcard(pool, slug).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    vcard.ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))
+}
+
+// Public: a minimal card for iframes, with the QR code and a save button
+pub async fn embed_handler(
+    State(pool): State<SqlitePool>,
+    State(config): State<EmbedConfig>,
+    State(budget): State<MemoryBudget>,
+    State(render_pool): State<RenderPool>,
+    State(cache): State<ResponseCache>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = fetch_public_vcard(&pool, &slug).await?;
+    let base_url = config.base_url(&headers);
+    // The page links back to itself, so it differs by the host it was requested on
+    let html = cache.get_or_render(&vcard, &format!("embed {}", base_url), || {
+        render_embed_page(vcard.clone(), &slug, &base_url, &budget, &render_pool)
+    }).await?;
+
+    let csp = format!("frame-ancestors {}", config.frame_ancestors);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (header::CONTENT_SECURITY_POLICY, csp),
+            (header::CACHE_CONTROL, cache.cache_control()),
+        ],
+        html,
+    ).into_response())
+}
+
+// The page's HTML, with the QR code inlined
+async fn render_embed_page(
+    vcard: VCard,
+    slug: &str,
+    base_url: &str,
+    budget: &MemoryBudget,
+    render_pool: &RenderPool,
+) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    let name = format!("{} {}", vcard.first_name, vcard.last_name);
+    let detail = detail_line(&vcard);
+    let address = address_lines(&vcard);
+    let address = address.iter().map(|line| escape_html(line)).collect::<Vec<_>>().join("<br>");
+    let messengers: String = vcard.messengers.links()
+        .iter()
+        .map(|link| format!(
+            "<a class=\"messenger {}\" href=\"{}\" target=\"_blank\" rel=\"noopener\">{}</a>",
+            link.kind, escape_html(&link.url), link.label
+        ))
+        .collect();
+
+    let data: VCardData = vcard.into();
+    let alt = alt_text::describe(&data);
+    let reservation = budget.reserve(qr_image_bytes(&data.render)).await?;
+    let png = render_pool.png(Priority::Interactive, generate_vcard(&data), data.render.clone()).await?;
+    drop(reservation);
+    let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+
+    let embed_url = format!("{}/embed/{}", base_url, slug);
+    let oembed_url = format!("{}/oembed?url={}&format=json", base_url, encode_query_value(&embed_url));
+
+    let html = fill_template(EMBED_TEMPLATE, &[
+        ("name", escape_html(&name)),
+        ("detail", escape_html(&detail)),
+        ("address", address),
+        ("image", format!("data:image/png;base64,{}", base64_img)),
+        ("alt_text", escape_html(&alt.alt_text)),
+        ("description", escape_html(&alt.description)),
+        ("messengers", messengers),
+        ("vcf_url", escape_html(&format!("/embed/{}/contact.vcf", slug))),
+        ("oembed_url", escape_html(&oembed_url)),
+    ]);
+    Ok(html.into_bytes())
+}
+
+// Public: the card as a .vcf download, behind the embed's save button
+pub async fn embed_vcf_handler(
+    State(pool): State<SqlitePool>,
+    State(cache): State<ResponseCache>,
+    Path(slug): Path<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = fetch_public_vcard(&pool, &slug).await?;
+    let vcard_id = vcard.id;
+    let fallback = filenames::sanitize(&format!("{}-{}", vcard.first_name, vcard.last_name));
+
+    let data: VCardData = vcard.into();
+    let filename = filenames::download_name(&pool, &data, vcard_id, fallback).await;
+    let disposition = format!("attachment; filename=\"{}.vcf\"", filename);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/vcard; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::CACHE_CONTROL, cache.cache_control()),
+        ],
+        generate_vcard(&data),
+    ).into_response())
+}
+
+// Public: the card's QR code on its own, for directories and print
+pub async fn embed_qr_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    State(render_pool): State<RenderPool>,
+    State(cache): State<ResponseCache>,
+    Path(slug): Path<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = fetch_public_vcard(&pool, &slug).await?;
+    let png = cache.get_or_render(&vcard, "qr.png", || async {
+        let data: VCardData = vcard.clone().into();
+        let _reservation = budget.reserve(qr_image_bytes(&data.render)).await?;
+        render_pool.png(Priority::Interactive, generate_vcard(&data), data.render.clone()).await
+    }).await?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png".to_string()), (header::CACHE_CONTROL, cache.cache_control())], png).into_response())
+}
+
+// Public oEmbed endpoint: turns an embed URL into iframe markup for blogs and intranets
+pub async fn oembed_handler(
+    State(pool): State<SqlitePool>,
+    State(config): State<EmbedConfig>,
+    headers: HeaderMap,
+    Query(query): Query<OEmbedQuery>,
+) -> Result<Json<OEmbedResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if query.format.as_deref().is_some_and(|format| format != "json") {
+        return Err((StatusCode::NOT_IMPLEMENTED, Json(ErrorResponse { error: "Only the json format is supported".to_string() })));
+    }
+
+    let base_url = config.base_url(&headers);
+    let slug = query.url
+        .strip_prefix(&format!("{}/embed/", base_url))
+        .map(|rest| rest.split(['/', '?', '#']).next().unwrap_or_default())
+        .filter(|slug| !slug.is_empty())
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "URL is not an embeddable vCard".to_string() })))?;
+
+    let vcard = fetch_public_vcard(&pool, slug).await?;
+
+    let width = query.maxwidth.map_or(DEFAULT_EMBED_WIDTH, |max| max.min(DEFAULT_EMBED_WIDTH));
+    let height = query.maxheight.map_or(DEFAULT_EMBED_HEIGHT, |max| max.min(DEFAULT_EMBED_HEIGHT));
+    let title = format!("{} {}", vcard.first_name, vcard.last_name);
+
+    let html = format!(
+        "<iframe src=\"{}\" width=\"{}\" height=\"{}\" title=\"{}\" style=\"border:0\" loading=\"lazy\"></iframe>",
+        escape_html(&format!("{}/embed/{}", base_url, slug)), width, height, escape_html(&title)
+    );
+
+    Ok(Json(OEmbedResponse {
+        version: "1.0",
+        kind: "rich",
+        title,
+        provider_name: "vCard QR Generator",
+        provider_url: base_url,
+        html,
+        width,
+        height,
+    }))
+}
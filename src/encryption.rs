@@ -0,0 +1,111 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::ErrorResponse;
+
+// Marks a QR payload as "VCQR1:<key id>:<base64url nonce and ciphertext>"
+const PREFIX: &str = "VCQR1";
+const NONCE_LEN: usize = 12;
+
+// Shared keys for internal codes that outside scanners must not be able to read
+#[derive(Clone, Default)]
+pub struct PayloadKeys {
+    // The first key encrypts; all of them decrypt, so older codes keep working after a rotation
+    keys: Arc<Vec<(String, Aes256Gcm)>>,
+}
+
+impl PayloadKeys {
+    // QR_ENCRYPTION_KEYS="2026:<base64 of 32 bytes>,2025:<...>"; bad entries are skipped with a warning
+    pub fn from_env() -> Self {
+        let spec = std::env::var("QR_ENCRYPTION_KEYS").unwrap_or_default();
+        let mut keys = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let parsed = entry.split_once(':').and_then(|(id, key)| {
+                let id = id.trim();
+                let cipher = Aes256Gcm::new_from_slice(&STANDARD.decode(key.trim()).ok()?).ok()?;
+                let valid_id = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                valid_id.then(|| (id.to_string(), cipher))
+            });
+            match parsed {
+                Some(key) => keys.push(key),
+                None => eprintln!("Ignoring QR_ENCRYPTION_KEYS entry: expected <id>:<base64 of 32 bytes>"),
+            }
+        }
+        PayloadKeys { keys: Arc::new(keys) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    // The key ID is bound to the ciphertext, so a payload cannot be relabeled to another key
+    pub fn encrypt(&self, plaintext: &str) -> Option<String> {
+        let (id, cipher) = self.keys.first()?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: id.as_bytes() }).ok()?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Some(format!("{}:{}:{}", PREFIX, id, URL_SAFE_NO_PAD.encode(sealed)))
+    }
+
+    // The key ID and plaintext of a payload one of our keys sealed
+    pub fn decrypt(&self, payload: &str) -> Result<(String, String), String> {
+        let mut parts = payload.trim().splitn(3, ':');
+        let (Some(PREFIX), Some(id), Some(sealed)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err("Not an encrypted QR payload".to_string());
+        };
+        let (_, cipher) = self.keys.iter().find(|(key_id, _)| key_id == id)
+            .ok_or_else(|| format!("Unknown key ID {:?}", id))?;
+
+        let sealed = URL_SAFE_NO_PAD.decode(sealed).map_err(|_| "Malformed payload".to_string())?;
+        if sealed.len() <= NONCE_LEN {
+            return Err("Malformed payload".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| "Malformed payload".to_string())?;
+        let plaintext = cipher.decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: id.as_bytes() })
+            .map_err(|_| "Payload was tampered with or sealed with a different key".to_string())?;
+        let plaintext = String::from_utf8(plaintext).map_err(|_| "Malformed payload".to_string())?;
+        Ok((id.to_string(), plaintext))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DecryptRequest {
+    payload: String,
+}
+
+#[derive(Serialize)]
+pub struct DecryptResponse {
+    key_id: String,
+    content: String,
+}
+
+// Internal scanners post what they read; only signed-in users can see the contents
+pub async fn decrypt_handler(
+    State(keys): State<PayloadKeys>,
+    session: Session,
+    Json(req): Json<DecryptRequest>,
+) -> Result<Json<DecryptResponse>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !keys.is_enabled() {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Encrypted QR codes are not configured".to_string() })));
+    }
+
+    let (key_id, content) = keys.decrypt(&req.payload)
+        .map_err(|error| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error })))?;
+
+    Ok(Json(DecryptResponse { key_id, content }))
+}
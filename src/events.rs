@@ -0,0 +1,142 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use sqlx::SqlitePool;
+use tokio::sync::broadcast;
+use tower_sessions::Session;
+
+use crate::approvals::ApprovalSettings;
+use crate::auth::get_current_user;
+
+pub const TOPIC_GENERATION: &str = "generation";
+pub const TOPIC_FAILED_LOGIN: &str = "failed_login";
+pub const TOPIC_JOB: &str = "job";
+pub const TOPIC_REVIEW: &str = "review";
+
+const TOPICS: &[&str] = &[TOPIC_GENERATION, TOPIC_FAILED_LOGIN, TOPIC_JOB, TOPIC_REVIEW];
+
+// Approvers who are not admins only hear about cards to review
+const APPROVER_TOPICS: &[&str] = &[TOPIC_REVIEW];
+
+// Events buffered per subscriber before a slow dashboard starts missing them
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Clone, Serialize)]
+pub struct Event {
+    topic: &'static str,
+    data: Value,
+    at: String,
+}
+
+// In-process fan-out of live events to connected admin dashboards
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        EventBus { sender }
+    }
+
+    pub fn publish(&self, topic: &'static str, data: Value) {
+        // Sending only fails when nobody is listening, which is fine
+        let _ = self.sender.send(Event {
+            topic,
+            data,
+            at: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe { topics: Vec<String> },
+    Unsubscribe { topics: Vec<String> },
+}
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    session: Session,
+    State(events): State<EventBus>,
+    State(pool): State<SqlitePool>,
+) -> Response {
+    let Some(user) = get_current_user(&session).await else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if user.is_admin {
+        return ws.on_upgrade(move |socket| handle_socket(socket, events, TOPICS));
+    }
+
+    match ApprovalSettings::load(&pool).await {
+        Ok(settings) if settings.can_review(user.id, false) => ws.on_upgrade(move |socket| handle_socket(socket, events, APPROVER_TOPICS)),
+        Ok(_) => (StatusCode::FORBIDDEN, "Admin access required").into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+// Apply a subscription change, returning the reply for the client
+fn handle_client_message(text: &str, topics: &mut HashSet<&'static str>, allowed: &'static [&'static str]) -> Value {
+    let message: ClientMessage = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => return json!({ "type": "error", "error": format!("Invalid message: {}", e) }),
+    };
+
+    let (requested, subscribe) = match message {
+        ClientMessage::Subscribe { topics } => (topics, true),
+        ClientMessage::Unsubscribe { topics } => (topics, false),
+    };
+
+    for name in &requested {
+        match allowed.iter().find(|t| *t == name) {
+            Some(topic) if subscribe => { topics.insert(topic); }
+            Some(topic) => { topics.remove(topic); }
+            None => return json!({ "type": "error", "error": format!("Unknown topic: {}", name) }),
+        }
+    }
+
+    let mut current: Vec<&str> = topics.iter().copied().collect();
+    current.sort();
+    json!({ "type": "subscriptions", "topics": current })
+}
+
+// Nothing is sent until the client subscribes to at least one topic
+async fn handle_socket(mut socket: WebSocket, events: EventBus, allowed: &'static [&'static str]) {
+    let mut rx = events.sender.subscribe();
+    let mut topics: HashSet<&'static str> = HashSet::new();
+
+    loop {
+        let outgoing = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => handle_client_message(&text, &mut topics, allowed),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            event = rx.recv() => match event {
+                Ok(event) if topics.contains(event.topic) => json!({
+                    "type": "event",
+                    "topic": event.topic,
+                    "data": event.data,
+                    "at": event.at,
+                }),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => json!({ "type": "lagged", "missed": missed }),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        if socket.send(Message::Text(outgoing.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
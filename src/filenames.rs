@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::settings::{get_setting, put_setting};
+use crate::{clean_field, ErrorResponse, VCardData};
+
+const FILENAME_PATTERN_KEY: &str = "filename_pattern";
+
+const MAX_PATTERN_LEN: usize = 100;
+const MAX_NAME_LEN: usize = 120;
+
+// What {placeholder}s a pattern may use
+pub const PLACEHOLDERS: &[&str] = &["first_name", "last_name", "company", "role", "email", "id"];
+
+// How downloaded files are named, e.g. "{last_name}_{first_name}_{company}", set by admins. Each
+// download adds its own extension; without a pattern every endpoint keeps its built-in name
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct FilenamePattern {
+    pub pattern: Option<String>,
+}
+
+// Header- and filesystem-safe: anything but ASCII letters, digits, '-', '_' and '.' becomes '_',
+// runs of '_' collapse, and separators left by empty fields are trimmed
+pub fn sanitize(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' };
+        if !(c == '_' && out.ends_with('_')) {
+            out.push(c);
+        }
+    }
+    out.trim_matches(['_', '-', '.']).chars().take(MAX_NAME_LEN).collect()
+}
+
+// Checks placeholders and braces, and drops a trailing extension such as ".png"
+fn validate(pattern: &str) -> Result<String, String> {
+    let mut pattern = pattern.trim();
+    if let Some((base, extension)) = pattern.rsplit_once('.') {
+        if !extension.is_empty() && extension.len() <= 4 && extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            pattern = base;
+        }
+    }
+    if pattern.chars().count() > MAX_PATTERN_LEN {
+        return Err(format!("Pattern must be at most {} characters", MAX_PATTERN_LEN));
+    }
+
+    let mut rest = pattern;
+    let mut placeholders = 0;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err("Unmatched } in pattern".to_string());
+        }
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or("Unmatched { in pattern".to_string())?;
+        let name = &after[..end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!("Unknown placeholder {{{}}}; expected one of {}", name, PLACEHOLDERS.join(", ")));
+        }
+        placeholders += 1;
+        rest = &after[end + 1..];
+    }
+    if placeholders == 0 {
+        return Err("Pattern must use at least one placeholder, or every download would share a name".to_string());
+    }
+    Ok(pattern.to_string())
+}
+
+impl FilenamePattern {
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(get_setting(pool, FILENAME_PATTERN_KEY).await?.unwrap_or_default())
+    }
+
+    // The sanitized base name for one card, or None without a pattern or when it comes out empty
+    pub fn apply(&self, data: &VCardData, vcard_id: i64) -> Option<String> {
+        let mut name = self.pattern.clone()?;
+        for placeholder in PLACEHOLDERS {
+            let value = match *placeholder {
+                "first_name" => data.first_name.trim().to_string(),
+                "last_name" => data.last_name.trim().to_string(),
+                "company" => clean_field(&data.company).unwrap_or_default().to_string(),
+                "role" => clean_field(&data.role).unwrap_or_default().to_string(),
+                "email" => clean_field(&data.email).unwrap_or_default().to_string(),
+                "id" => vcard_id.to_string(),
+                _ => continue,
+            };
+            name = name.replace(&format!("{{{}}}", placeholder), &value);
+        }
+        Some(sanitize(&name)).filter(|name| !name.is_empty())
+    }
+}
+
+// A download's name without extension, or `fallback` without a pattern. A settings read failure
+// falls back too rather than failing the download
+pub async fn download_name(pool: &SqlitePool, data: &VCardData, vcard_id: i64, fallback: String) -> String {
+    let pattern = FilenamePattern::load(pool).await.unwrap_or_else(|e| {
+        eprintln!("Failed to load filename pattern: {}", e);
+        FilenamePattern::default()
+    });
+    pattern.apply(data, vcard_id).unwrap_or(fallback)
+}
+
+// Readable by every user, so the generator can show the name a download will get
+pub async fn get_filename_pattern_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<FilenamePattern>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let pattern = FilenamePattern::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(pattern))
+}
+
+pub async fn update_filename_pattern_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(pattern): Json<FilenamePattern>,
+) -> Result<Json<FilenamePattern>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    // An empty pattern goes back to the built-in names
+    let pattern = match pattern.pattern.as_deref().map(str::trim).filter(|pattern| !pattern.is_empty()) {
+        Some(pattern) => Some(validate(pattern).map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?),
+        None => None,
+    };
+    let pattern = FilenamePattern { pattern };
+
+    put_setting(&pool, FILENAME_PATTERN_KEY, &pattern, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save filename pattern".to_string() })))?;
+
+    Ok(Json(pattern))
+}
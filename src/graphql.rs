@@ -0,0 +1,113 @@
+use async_graphql::{ComplexObject, Context, EmptyMutation, EmptySubscription, Guard, Object, Schema, SimpleObject};
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::{get_current_user, UserInfo};
+use crate::repo::VcardRepo;
+use crate::tags::{self, Tag};
+use crate::vcards::VCard;
+use crate::ErrorResponse;
+
+// Bounds on how much work a single query can ask for
+const MAX_DEPTH: usize = 8;
+const MAX_COMPLEXITY: usize = 500;
+
+pub type VCardSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+#[derive(SimpleObject, sqlx::FromRow)]
+struct UserNode {
+    id: i64,
+    username: String,
+    is_admin: bool,
+    created_at: String,
+}
+
+// Restricts a field to admins; the signed-in user is attached per request
+struct AdminGuard;
+
+impl Guard for AdminGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if ctx.data::<UserInfo>()?.is_admin {
+            Ok(())
+        } else {
+            Err("Admin access required".into())
+        }
+    }
+}
+
+fn database_error(e: sqlx::Error) -> async_graphql::Error {
+    eprintln!("Database error: {}", e);
+    "Database error".into()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn me(&self, ctx: &Context<'_>) -> async_graphql::Result<UserNode> {
+        let user = ctx.data::<UserInfo>()?;
+        sqlx::query_as("SELECT id, username, is_admin, created_at FROM users WHERE id = ?")
+            .bind(user.id)
+            .fetch_one(ctx.data::<SqlitePool>()?)
+            .await
+            .map_err(database_error)
+    }
+
+    async fn vcard(&self, ctx: &Context<'_>, id: i64) -> async_graphql::Result<Option<VCard>> {
+        ctx.data::<SqlitePool>()?.find_vcard(id).await.map_err(database_error)
+    }
+
+    // Same filtering and paging as GET /api/vcards
+    async fn vcards(
+        &self,
+        ctx: &Context<'_>,
+        tag: Option<String>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> async_graphql::Result<Vec<VCard>> {
+        ctx.data::<SqlitePool>()?.list_vcards(tag.as_deref(), limit, offset).await
+            .map_err(database_error)
+    }
+
+    async fn tags(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Tag>> {
+        tags::list_tags(ctx.data::<SqlitePool>()?).await.map_err(database_error)
+    }
+
+    #[graphql(guard = "AdminGuard")]
+    async fn users(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<UserNode>> {
+        sqlx::query_as("SELECT id, username, is_admin, created_at FROM users ORDER BY id")
+            .fetch_all(ctx.data::<SqlitePool>()?)
+            .await
+            .map_err(database_error)
+    }
+}
+
+#[ComplexObject]
+impl VCard {
+    async fn tags(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Tag>> {
+        tags::vcard_tags(ctx.data::<SqlitePool>()?, self.id).await.map_err(database_error)
+    }
+}
+
+pub fn build_schema(pool: SqlitePool) -> VCardSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(pool)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+pub async fn graphql_handler(
+    State(schema): State<VCardSchema>,
+    session: Session,
+    Json(request): Json<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    Ok(Json(schema.execute(request.data(user)).await))
+}
@@ -0,0 +1,284 @@
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use crate::cache::ResponseCache;
+use crate::capacity;
+use crate::events::{self, EventBus};
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::repo::VcardRepo;
+use crate::revisions;
+use crate::settings::FieldRules;
+use crate::vcards;
+use crate::{render_vcard_png, RenderOptions, VCardData};
+
+pub mod proto {
+    tonic::include_proto!("vcards.v1");
+}
+
+use proto::v_card_service_server::{VCardService, VCardServiceServer};
+use proto::{
+    batch_result, BatchResult, Contact, DeleteVCardResponse, GenerateResponse, ListVCardsRequest,
+    ListVCardsResponse, UpdateVCardRequest, VCardId,
+};
+
+// Results buffered for a batch client before generation waits on it
+const BATCH_BUFFER: usize = 16;
+
+impl From<Contact> for VCardData {
+    fn from(contact: Contact) -> Self {
+        VCardData {
+            first_name: contact.first_name,
+            last_name: contact.last_name,
+            mobile: contact.mobile,
+            work: contact.work,
+            email: contact.email,
+            company: contact.company,
+            role: contact.role,
+            street: contact.street,
+            city: contact.city,
+            state: contact.state,
+            postal_code: contact.postal_code,
+            country: contact.country,
+            website: contact.website,
+            extensions: Default::default(),
+            labels: Default::default(),
+            messengers: Default::default(),
+            payment: Default::default(),
+            render: RenderOptions { color: contact.color, ..Default::default() },
+            draft_id: None,
+            logo_upload_id: None,
+            style_id: None,
+            format: Default::default(),
+            pdf_size_mm: None,
+            caption: None,
+            encrypt: false,
+        }
+    }
+}
+
+// An update carries only the contact fields and color; the rest of the stored card is kept
+fn merge_contact(stored: vcards::VCard, contact: Contact) -> VCardData {
+    let color = contact.color.clone();
+    VCardData {
+        extensions: stored.extensions,
+        labels: stored.labels,
+        messengers: stored.messengers,
+        payment: stored.payment,
+        render: RenderOptions { color, ..stored.render },
+        ..VCardData::from(contact)
+    }
+}
+
+impl From<vcards::VCard> for proto::VCard {
+    fn from(vcard: vcards::VCard) -> Self {
+        proto::VCard {
+            id: vcard.id,
+            contact: Some(Contact {
+                first_name: vcard.first_name,
+                last_name: vcard.last_name,
+                mobile: vcard.mobile,
+                work: vcard.work,
+                email: vcard.email,
+                company: vcard.company,
+                role: vcard.role,
+                street: vcard.street,
+                city: vcard.city,
+                state: vcard.state,
+                postal_code: vcard.postal_code,
+                country: vcard.country,
+                website: vcard.website,
+                color: vcard.render.color,
+            }),
+            created_at: vcard.created_at,
+            updated_at: vcard.updated_at,
+        }
+    }
+}
+
+fn database_error(e: sqlx::Error) -> Status {
+    eprintln!("Database error: {}", e);
+    Status::internal("Database error")
+}
+
+#[derive(Clone)]
+struct VCardGrpc {
+    pool: SqlitePool,
+    events: EventBus,
+    memory: MemoryBudget,
+    cache: ResponseCache,
+}
+
+impl VCardGrpc {
+    // The same checks as REST submissions: field formats, the organization's required fields, and
+    // whether any code can hold the card
+    async fn check_card(&self, data: &VCardData) -> Result<(), Status> {
+        data.validate().map_err(Status::invalid_argument)?;
+        FieldRules::load(&self.pool).await
+            .map_err(database_error)?
+            .check(data)
+            .map_err(Status::invalid_argument)?;
+        capacity::check_card(data).map_err(Status::invalid_argument)
+    }
+
+    // Same steps as the REST generate handler, minus session and drafts
+    async fn generate_one(&self, contact: Contact) -> Result<GenerateResponse, Status> {
+        let data = VCardData::from(contact);
+        self.check_card(&data).await?;
+
+        let reservation = self.memory.reserve(qr_image_bytes(&data.render)).await
+            .map_err(|(_, error)| Status::resource_exhausted(error.0.error))?;
+        let png = render_vcard_png(&data).map_err(Status::internal)?;
+        drop(reservation);
+
+        let vcard_id = self.pool.insert_vcard(&data, None).await
+            .map_err(database_error)?;
+
+        self.events.publish(events::TOPIC_GENERATION, serde_json::json!({
+            "vcard_id": vcard_id,
+            "name": format!("{} {}", data.first_name, data.last_name),
+            "username": "grpc",
+        }));
+
+        Ok(GenerateResponse { vcard_id, png })
+    }
+}
+
+#[tonic::async_trait]
+impl VCardService for VCardGrpc {
+    type GenerateBatchStream = ReceiverStream<Result<BatchResult, Status>>;
+
+    async fn generate(&self, request: Request<Contact>) -> Result<Response<GenerateResponse>, Status> {
+        Ok(Response::new(self.generate_one(request.into_inner()).await?))
+    }
+
+    // A failed contact is reported in its result and does not end the stream
+    async fn generate_batch(
+        &self,
+        request: Request<Streaming<Contact>>,
+    ) -> Result<Response<Self::GenerateBatchStream>, Status> {
+        let mut contacts = request.into_inner();
+        let (tx, rx) = mpsc::channel(BATCH_BUFFER);
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut index = 0;
+            loop {
+                let contact = match contacts.message().await {
+                    Ok(Some(contact)) => contact,
+                    Ok(None) => break,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                };
+
+                let result = match service.generate_one(contact).await {
+                    Ok(generated) => batch_result::Result::Generated(generated),
+                    Err(status) => batch_result::Result::Error(status.message().to_string()),
+                };
+
+                if tx.send(Ok(BatchResult { index, result: Some(result) })).await.is_err() {
+                    break;
+                }
+                index += 1;
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_v_card(&self, request: Request<VCardId>) -> Result<Response<proto::VCard>, Status> {
+        let vcard = self.pool.find_vcard(request.into_inner().id).await
+            .map_err(database_error)?
+            .ok_or_else(|| Status::not_found("vCard not found"))?;
+
+        Ok(Response::new(vcard.into()))
+    }
+
+    async fn list_v_cards(&self, request: Request<ListVCardsRequest>) -> Result<Response<ListVCardsResponse>, Status> {
+        let req = request.into_inner();
+        let vcards = self.pool.list_vcards(req.tag.as_deref(), req.limit, req.offset).await
+            .map_err(database_error)?;
+
+        Ok(Response::new(ListVCardsResponse {
+            vcards: vcards.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn update_v_card(&self, request: Request<UpdateVCardRequest>) -> Result<Response<proto::VCard>, Status> {
+        let req = request.into_inner();
+        let contact = req.contact
+            .ok_or_else(|| Status::invalid_argument("contact is required"))?;
+
+        let stored = self.pool.find_vcard(req.id).await
+            .map_err(database_error)?
+            .ok_or_else(|| Status::not_found("vCard not found"))?;
+        let data = merge_contact(stored, contact);
+        self.check_card(&data).await?;
+
+        if !revisions::update_vcard(&self.pool, req.id, &data, None).await.map_err(database_error)? {
+            return Err(Status::not_found("vCard not found"));
+        }
+        self.cache.invalidate(req.id);
+
+        let vcard = self.pool.find_vcard(req.id).await
+            .map_err(database_error)?
+            .ok_or_else(|| Status::not_found("vCard not found"))?;
+
+        Ok(Response::new(vcard.into()))
+    }
+
+    async fn delete_v_card(&self, request: Request<VCardId>) -> Result<Response<DeleteVCardResponse>, Status> {
+        if !self.pool.delete_vcard(request.into_inner().id).await.map_err(database_error)? {
+            return Err(Status::not_found("vCard not found"));
+        }
+
+        Ok(Response::new(DeleteVCardResponse {}))
+    }
+}
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:50051";
+
+// Serve the gRPC API until the process exits; callers present GRPC_TOKEN as a bearer token when one is set
+pub async fn serve(pool: SqlitePool, events: EventBus, memory: MemoryBudget, cache: ResponseCache) {
+    let addr = std::env::var("GRPC_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    let addr: SocketAddr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("Invalid GRPC_ADDR {}: {}", addr, e);
+            return;
+        }
+    };
+
+    let expected = std::env::var("GRPC_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(|token| format!("Bearer {}", token));
+
+    // tonic fixes the interceptor signature, Status and all
+    #[allow(clippy::result_large_err)]
+    let check_token = move |request: Request<()>| -> Result<Request<()>, Status> {
+        match &expected {
+            Some(expected) => {
+                let presented = request.metadata().get("authorization").and_then(|v| v.to_str().ok());
+                if presented == Some(expected.as_str()) {
+                    Ok(request)
+                } else {
+                    Err(Status::unauthenticated("Invalid or missing token"))
+                }
+            }
+            None => Ok(request),
+        }
+    };
+
+    let service = VCardServiceServer::with_interceptor(VCardGrpc { pool, events, memory, cache }, check_token);
+
+    println!("gRPC server running on {}", addr);
+
+    if let Err(e) = Server::builder().add_service(service).serve(addr).await {
+        eprintln!("gRPC server error: {}", e);
+    }
+}
@@ -0,0 +1,208 @@
+// Signed links to a card's QR image for third-party pages, newsletters and partner sites, which
+// load /api/vcards/:id/qr.png without a session. The link carries a token signed with SECRET_KEY
+// naming the card, the format and when it expires, so nothing is stored per link. Admins decide
+// whether such links work at all and how long they may live; both are checked on every request,
+// so turning hotlinking off or shortening the limit also cuts off links already handed out
+
+use axum::{
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::embed::EmbedConfig;
+use crate::settings::{get_setting, put_setting};
+use crate::signing::SigningKey;
+use crate::ErrorResponse;
+
+const IMAGE_LINKS_KEY: &str = "image_links";
+
+const MAX_LIFETIME_HOURS: u32 = 24 * 365;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl LinkFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            LinkFormat::Png => "png",
+            LinkFormat::Svg => "svg",
+        }
+    }
+}
+
+// Off until an admin allows it, so no card can be embedded elsewhere by accident
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ImageLinkPolicy {
+    pub allow_hotlinking: bool,
+    // Lifetime of a link when the requester doesn't ask for one
+    pub default_hours: u32,
+    // Longest lifetime a link may have, from when it was issued
+    pub max_hours: u32,
+}
+
+impl Default for ImageLinkPolicy {
+    fn default() -> Self {
+        ImageLinkPolicy { allow_hotlinking: false, default_hours: 24 * 7, max_hours: 24 * 30 }
+    }
+}
+
+impl ImageLinkPolicy {
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(get_setting(pool, IMAGE_LINKS_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn save(&self, pool: &SqlitePool, updated_by: i64) -> Result<(), sqlx::Error> {
+        put_setting(pool, IMAGE_LINKS_KEY, self, updated_by).await
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_LIFETIME_HOURS).contains(&self.max_hours) {
+            return Err(format!("max_hours must be between 1 and {}", MAX_LIFETIME_HOURS));
+        }
+        if !(1..=self.max_hours).contains(&self.default_hours) {
+            return Err("default_hours must be between 1 and max_hours".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Signed into the token; short keys keep the URL short
+#[derive(Serialize, Deserialize)]
+struct LinkClaims {
+    // vCard ID
+    v: i64,
+    f: LinkFormat,
+    // Issued and expires, in Unix seconds
+    i: i64,
+    x: i64,
+}
+
+// Whether `token` grants the image of this card in this format right now. Errors are what the
+// requester sees: 403 for a link that is not valid here, 410 once it has expired
+pub async fn check(
+    pool: &SqlitePool,
+    key: &SigningKey,
+    token: &str,
+    vcard_id: i64,
+    format: LinkFormat,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let claims: LinkClaims = key.verify_token(token)
+        .and_then(|payload| serde_json::from_slice(&payload).ok())
+        .filter(|claims: &LinkClaims| claims.v == vcard_id && claims.f == format)
+        .ok_or((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Invalid image link".to_string() })))?;
+
+    let policy = ImageLinkPolicy::load(pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if !policy.allow_hotlinking {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Image links are turned off".to_string() })));
+    }
+    if claims.x - claims.i > policy.max_hours as i64 * 3600 {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Image link outlives the current lifetime limit".to_string() })));
+    }
+    if claims.x <= Utc::now().timestamp() {
+        return Err((StatusCode::GONE, Json(ErrorResponse { error: "Image link has expired".to_string() })));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct IssueLinkRequest {
+    #[serde(default)]
+    format: LinkFormat,
+    // Within the admin's limit; their default when omitted
+    expires_in_hours: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct IssuedLink {
+    url: String,
+    expires_at: DateTime<Utc>,
+}
+
+pub async fn issue_link_handler(
+    State(pool): State<SqlitePool>,
+    State(key): State<SigningKey>,
+    State(config): State<EmbedConfig>,
+    session: Session,
+    headers: HeaderMap,
+    Path(vcard_id): Path<i64>,
+    Json(req): Json<IssueLinkRequest>,
+) -> Result<(StatusCode, Json<IssuedLink>), (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let policy = ImageLinkPolicy::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if !policy.allow_hotlinking {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Image links are turned off".to_string() })));
+    }
+    let hours = req.expires_in_hours.unwrap_or(policy.default_hours);
+    if !(1..=policy.max_hours).contains(&hours) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("expires_in_hours must be between 1 and {}", policy.max_hours),
+        })));
+    }
+
+    // Only cards that can be downloaded get a link
+    crate::downloads::downloadable(&pool, vcard_id).await?;
+
+    let issued = Utc::now();
+    let expires_at = issued + chrono::Duration::hours(hours as i64);
+    let claims = LinkClaims { v: vcard_id, f: req.format, i: issued.timestamp(), x: expires_at.timestamp() };
+    let payload = serde_json::to_vec(&claims)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to issue link".to_string() })))?;
+
+    let url = format!(
+        "{}/api/vcards/{}/qr.{}?token={}",
+        config.base_url(&headers), vcard_id, req.format.extension(), key.sign_token(&payload)
+    );
+    Ok((StatusCode::CREATED, Json(IssuedLink { url, expires_at })))
+}
+
+pub async fn get_image_link_policy_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<ImageLinkPolicy>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let policy = ImageLinkPolicy::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(policy))
+}
+
+pub async fn update_image_link_policy_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(policy): Json<ImageLinkPolicy>,
+) -> Result<Json<ImageLinkPolicy>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    policy.validate()
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    policy.save(&pool, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save image link policy".to_string() })))?;
+
+    Ok(Json(policy))
+}
@@ -0,0 +1,396 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+use crate::approvals::{self, ApprovalSettings};
+use crate::auth::get_current_user;
+use crate::capacity;
+use crate::jobs;
+use crate::repo::{UserRepo, VcardRepo};
+use crate::uploads::read_upload;
+use crate::settings::FieldRules;
+use crate::{ErrorResponse, VCardData};
+
+pub const JOB_KIND: &str = "import";
+
+const MAX_IMPORT_ROWS: usize = 10_000;
+
+// VCardData fields a CSV column can be mapped to, with common header spellings
+const FIELD_ALIASES: &[(&str, &[&str])] = &[
+    ("first_name", &["firstname", "first", "givenname", "forename"]),
+    ("last_name", &["lastname", "last", "surname", "familyname"]),
+    ("mobile", &["mobile", "mobilephone", "cell", "cellphone"]),
+    ("work", &["work", "workphone", "phone", "officephone", "telephone", "businessphone"]),
+    ("email", &["email", "emailaddress", "mail"]),
+    ("company", &["company", "organization", "organisation", "org", "employer"]),
+    ("role", &["role", "title", "jobtitle", "position"]),
+    ("street", &["street", "address", "streetaddress", "address1"]),
+    ("city", &["city", "town"]),
+    ("state", &["state", "province", "region", "stateprovince"]),
+    ("postal_code", &["postalcode", "zip", "zipcode", "postcode"]),
+    ("country", &["country", "countrycode"]),
+    ("website", &["website", "url", "web", "homepage"]),
+    ("color", &["color", "colour"]),
+];
+
+#[derive(Deserialize)]
+pub struct DetectColumnsRequest {
+    header: String,
+}
+
+#[derive(Serialize)]
+pub struct DetectedColumn {
+    name: String,
+    suggested_field: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct DetectColumnsResponse {
+    columns: Vec<DetectedColumn>,
+    fields: Vec<&'static str>,
+}
+
+// The CSV comes either inline or from a completed chunked upload
+#[derive(Serialize, Deserialize)]
+pub struct ImportRequest {
+    csv: Option<String>,
+    upload_id: Option<String>,
+    // CSV column name -> VCardData field name
+    mapping: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportQueuedResponse {
+    job_id: i64,
+}
+
+// What happened to one CSV row, kept for the downloadable report
+#[derive(Serialize, Deserialize)]
+struct RowOutcome {
+    row: usize,
+    status: String,
+    vcard_id: Option<i64>,
+    error: Option<String>,
+}
+
+impl RowOutcome {
+    fn imported(row: usize, vcard_id: i64) -> Self {
+        RowOutcome { row, status: "imported".to_string(), vcard_id: Some(vcard_id), error: None }
+    }
+
+    fn failed(row: usize, error: String) -> Self {
+        RowOutcome { row, status: "failed".to_string(), vcard_id: None, error: Some(error) }
+    }
+}
+
+fn is_known_field(field: &str) -> bool {
+    FIELD_ALIASES.iter().any(|(name, _)| *name == field)
+}
+
+fn suggest_field(column: &str) -> Option<&'static str> {
+    let normalized: String = column
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    FIELD_ALIASES
+        .iter()
+        .find(|(name, aliases)| name.replace('_', "") == normalized || aliases.contains(&normalized.as_str()))
+        .map(|(name, _)| *name)
+}
+
+fn parse_header(header: &str) -> Result<Vec<String>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(header.as_bytes());
+
+    match reader.records().next() {
+        Some(Ok(record)) => Ok(record.iter().map(|c| c.trim().to_string()).collect()),
+        Some(Err(e)) => Err(format!("Invalid CSV header: {}", e)),
+        None => Err("CSV header is empty".to_string()),
+    }
+}
+
+async fn load_csv(pool: &SqlitePool, req: &ImportRequest, user_id: Option<i64>) -> Result<String, String> {
+    let csv = match (&req.csv, &req.upload_id) {
+        (Some(csv), None) => csv.clone(),
+        (None, Some(upload_id)) => {
+            let data = read_upload(pool, upload_id, user_id).await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| "Upload not found or not complete".to_string())?;
+            String::from_utf8(data).map_err(|_| "Upload is not valid UTF-8 text".to_string())?
+        }
+        _ => return Err("Provide exactly one of csv or upload_id".to_string()),
+    };
+
+    // Counted up front, so an oversized file is refused whole instead of failing after storing rows
+    if csv_reader(&csv).records().count() > MAX_IMPORT_ROWS {
+        return Err(format!("CSV has more than {} rows", MAX_IMPORT_ROWS));
+    }
+    Ok(csv)
+}
+
+// Rows may be ragged; short rows simply leave fields unset
+fn csv_reader(csv: &str) -> csv::Reader<&[u8]> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .flexible(true)
+        .from_reader(csv.as_bytes())
+}
+
+// Set a mapped VCardData field from a CSV cell
+pub fn set_field(data: &mut VCardData, field: &str, value: &str) {
+    let value = value.trim().to_string();
+    match field {
+        "first_name" => data.first_name = value,
+        "last_name" => data.last_name = value,
+        "mobile" => data.mobile = Some(value),
+        "work" => data.work = Some(value),
+        "email" => data.email = Some(value),
+        "company" => data.company = Some(value),
+        "role" => data.role = Some(value),
+        "street" => data.street = Some(value),
+        "city" => data.city = Some(value),
+        "state" => data.state = Some(value),
+        "postal_code" => data.postal_code = Some(value),
+        "country" => data.country = Some(value),
+        "website" => data.website = Some(value),
+        "color" => data.render.color = Some(value),
+        _ => {}
+    }
+}
+
+// Mapping targets must be known fields and cover both name fields
+pub fn check_mapping(mapping: &HashMap<String, String>) -> Result<(), String> {
+    if let Some(field) = mapping.values().find(|f| !is_known_field(f)) {
+        return Err(format!("Unknown field in mapping: {}", field));
+    }
+    for required in ["first_name", "last_name"] {
+        if !mapping.values().any(|f| f == required) {
+            return Err(format!("Mapping must include {}", required));
+        }
+    }
+    Ok(())
+}
+
+// Check a payload before it is queued from somewhere other than import_handler
+pub fn validate(payload: &Value) -> Result<(), String> {
+    let req: ImportRequest = serde_json::from_value(payload.clone())
+        .map_err(|e| format!("Invalid import payload: {}", e))?;
+    if req.csv.is_some() == req.upload_id.is_some() {
+        return Err("Provide exactly one of csv or upload_id".to_string());
+    }
+    check_mapping(&req.mapping)
+}
+
+// Step one: report the columns found in a header row and a suggested mapping
+pub async fn detect_columns_handler(
+    session: Session,
+    Json(req): Json<DetectColumnsRequest>,
+) -> Result<Json<DetectColumnsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let columns = parse_header(&req.header)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    Ok(Json(DetectColumnsResponse {
+        columns: columns
+            .into_iter()
+            .map(|name| DetectedColumn { suggested_field: suggest_field(&name), name })
+            .collect(),
+        fields: FIELD_ALIASES.iter().map(|(name, _)| *name).collect(),
+    }))
+}
+
+// Step two: queue the import with the confirmed mapping
+pub async fn import_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<ImportRequest>,
+) -> Result<(StatusCode, Json<ImportQueuedResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    check_mapping(&req.mapping)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    let csv = load_csv(&pool, &req, Some(user.id)).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    let header_line = csv.lines().next().unwrap_or_default();
+    let columns = parse_header(header_line)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    if let Some(column) = req.mapping.keys().find(|c| !columns.contains(c)) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("Column not found in CSV: {}", column) })));
+    }
+
+    let payload = serde_json::to_value(&req)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to queue job".to_string() })))?;
+
+    let job_id = jobs::enqueue(&pool, JOB_KIND, &payload, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to queue job".to_string() })))?;
+
+    Ok((StatusCode::ACCEPTED, Json(ImportQueuedResponse { job_id })))
+}
+
+// Job body: apply the mapping to every CSV row and store the valid ones, owned by whoever queued the import
+pub async fn run(pool: &SqlitePool, payload: Value, created_by: Option<i64>) -> Result<Value, String> {
+    let req: ImportRequest = serde_json::from_value(payload)
+        .map_err(|e| format!("Invalid import payload: {}", e))?;
+
+    let csv = load_csv(pool, &req, None).await?;
+    let mut reader = csv_reader(&csv);
+
+    let headers = reader.headers()
+        .map_err(|e| format!("Invalid CSV header: {}", e))?
+        .clone();
+
+    // Resolve the mapping to column positions once
+    let mapped: Vec<(usize, &str)> = headers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, column)| req.mapping.get(column).map(|field| (i, field.as_str())))
+        .collect();
+
+    let rules = FieldRules::load(pool).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let approval = ApprovalSettings::load(pool).await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let importer_is_admin = match created_by {
+        Some(user_id) => pool.find_user(user_id).await
+            .map_err(|e| format!("Database error: {}", e))?
+            .is_some_and(|user| user.is_admin),
+        None => false,
+    };
+
+    let mut outcomes = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        // Row numbers as seen in a spreadsheet: header is row 1
+        let row = index + 2;
+
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                outcomes.push(RowOutcome::failed(row, format!("Invalid CSV row: {}", e)));
+                continue;
+            }
+        };
+
+        let mut data = VCardData::default();
+        for &(i, field) in &mapped {
+            if let Some(value) = record.get(i) {
+                set_field(&mut data, field, value);
+            }
+        }
+
+        // The checks an edit through the API gets, so a bad row is reported rather than stored
+        if let Err(e) = rules.check(&data).and_then(|()| capacity::check_card(&data)) {
+            outcomes.push(RowOutcome::failed(row, e));
+            continue;
+        }
+
+        let inserted = match pool.insert_vcard(&data, created_by).await {
+            Ok(vcard_id) => approvals::submit_imported(pool, vcard_id, &approval, created_by, importer_is_admin).await.map(|_| vcard_id),
+            Err(e) => Err(e),
+        };
+        match inserted {
+            Ok(vcard_id) => outcomes.push(RowOutcome::imported(row, vcard_id)),
+            Err(e) => outcomes.push(RowOutcome::failed(row, format!("Database error: {}", e))),
+        }
+    }
+
+    let imported = outcomes.iter().filter(|o| o.vcard_id.is_some()).count();
+
+    Ok(json!({
+        "rows": outcomes.len(),
+        "imported": imported,
+        "failed": outcomes.len() - imported,
+        "outcomes": outcomes,
+    }))
+}
+
+// CSV of every input row with its import status appended
+pub async fn report(pool: &SqlitePool, payload: Value, result: Value) -> Result<Vec<u8>, String> {
+    let req: ImportRequest = serde_json::from_value(payload)
+        .map_err(|e| format!("Invalid import payload: {}", e))?;
+    let outcomes: Vec<RowOutcome> = serde_json::from_value(result["outcomes"].clone())
+        .map_err(|e| format!("Invalid import result: {}", e))?;
+
+    let csv = load_csv(pool, &req, None).await?;
+    let mut reader = csv_reader(&csv);
+
+    let headers = reader.headers()
+        .map_err(|e| format!("Invalid CSV header: {}", e))?
+        .clone();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let mut header_row: Vec<&str> = headers.iter().collect();
+    header_row.extend(["status", "vcard_id", "error"]);
+    writer.write_record(&header_row).map_err(|e| e.to_string())?;
+
+    for (record, outcome) in reader.records().zip(&outcomes) {
+        // Pad or truncate so every report row lines up with the header
+        let mut cells: Vec<String> = match record {
+            Ok(record) => record.iter().map(str::to_string).collect(),
+            Err(_) => Vec::new(),
+        };
+        cells.resize(headers.len(), String::new());
+
+        cells.push(outcome.status.clone());
+        cells.push(outcome.vcard_id.map(|id| id.to_string()).unwrap_or_default());
+        cells.push(outcome.error.clone().unwrap_or_default());
+        writer.write_record(&cells).map_err(|e| e.to_string())?;
+    }
+
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{run, MAX_IMPORT_ROWS};
+
+    async fn card_count(pool: &sqlx::SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM vcards").fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn oversized_csv_is_refused_before_any_row_is_stored() {
+        let pool = crate::test_pool().await;
+        let mut csv = String::from("First,Last\n");
+        for i in 0..=MAX_IMPORT_ROWS {
+            csv.push_str(&format!("Ada{},Lovelace\n", i));
+        }
+        let payload = json!({ "csv": csv, "mapping": { "First": "first_name", "Last": "last_name" } });
+
+        let error = run(&pool, payload, None).await.unwrap_err();
+        assert!(error.contains("more than"), "{}", error);
+        assert_eq!(card_count(&pool).await, 0);
+    }
+
+    #[tokio::test]
+    async fn csv_at_the_limit_is_imported() {
+        let pool = crate::test_pool().await;
+        let mut csv = String::from("First,Last\n");
+        for i in 0..MAX_IMPORT_ROWS {
+            csv.push_str(&format!("Ada{},Lovelace\n", i));
+        }
+        let payload = json!({ "csv": csv, "mapping": { "First": "first_name", "Last": "last_name" } });
+
+        let result = run(&pool, payload, None).await.unwrap();
+        assert_eq!(result["imported"], MAX_IMPORT_ROWS);
+        assert_eq!(card_count(&pool).await, MAX_IMPORT_ROWS as i64);
+    }
+}
@@ -0,0 +1,231 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::capacity;
+use crate::events::{self, EventBus};
+use crate::imports::{check_mapping, set_field};
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::repo::VcardRepo;
+use crate::settings;
+use crate::{generate_vcard, render_vcard_png, ErrorResponse, MessageResponse, VCardData};
+
+const MAX_INTEGRATION_NAME_LEN: usize = 100;
+
+#[derive(Deserialize)]
+pub struct IntegrationRequest {
+    name: String,
+    // Source key in the inbound JSON -> VCardData field name.
+    // Nested values are addressed with dots, e.g. "contact.email".
+    mapping: HashMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct Integration {
+    id: i64,
+    name: String,
+    token: String,
+    mapping: HashMap<String, String>,
+    last_received_at: Option<String>,
+    created_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct IntegrationRow {
+    id: i64,
+    name: String,
+    token: String,
+    mapping: String,
+    last_received_at: Option<String>,
+    created_at: String,
+}
+
+impl From<IntegrationRow> for Integration {
+    fn from(row: IntegrationRow) -> Self {
+        Integration {
+            id: row.id,
+            name: row.name,
+            token: row.token,
+            mapping: serde_json::from_str(&row.mapping).unwrap_or_default(),
+            last_received_at: row.last_received_at,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct InboundResponse {
+    vcard_id: i64,
+    image: String, // base64 encoded
+}
+
+// Look up a dotted path in the inbound body; numbers and booleans are taken as text
+fn lookup(body: &Value, path: &str) -> Option<String> {
+    let value = path.split('.').try_fold(body, |value, key| value.get(key))?;
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+pub async fn create_integration_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<IntegrationRequest>,
+) -> Result<(StatusCode, Json<Integration>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_INTEGRATION_NAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Name must be between 1 and {} characters", MAX_INTEGRATION_NAME_LEN),
+        })));
+    }
+
+    check_mapping(&req.mapping)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    // The token is the only credential an inbound caller presents
+    let token = uuid::Uuid::new_v4().simple().to_string();
+
+    let mapping = serde_json::to_string(&req.mapping)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create integration".to_string() })))?;
+
+    let row: IntegrationRow = sqlx::query_as(
+        "INSERT INTO integrations (name, token, mapping, created_by) VALUES (?, ?, ?, ?) RETURNING id, name, token, mapping, last_received_at, created_at"
+    )
+    .bind(name)
+    .bind(&token)
+    .bind(&mapping)
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create integration".to_string() })))?;
+
+    Ok((StatusCode::CREATED, Json(row.into())))
+}
+
+pub async fn list_integrations_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Integration>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let rows: Vec<IntegrationRow> = sqlx::query_as("SELECT id, name, token, mapping, last_received_at, created_at FROM integrations ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(rows.into_iter().map(Into::into).collect()))
+}
+
+pub async fn delete_integration_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(integration_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let result = sqlx::query("DELETE FROM integrations WHERE id = ?")
+        .bind(integration_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete integration".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Integration not found".to_string() })));
+    }
+
+    Ok(Json(MessageResponse {
+        message: "Integration deleted successfully".to_string(),
+    }))
+}
+
+// Public: external systems post a contact here, authenticated by the token in the path
+pub async fn inbound_handler(
+    State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    State(budget): State<MemoryBudget>,
+    Path(token): Path<String>,
+    Json(body): Json<Value>,
+) -> Result<(StatusCode, Json<InboundResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let row: Option<IntegrationRow> = sqlx::query_as("SELECT id, name, token, mapping, last_received_at, created_at FROM integrations WHERE token = ?")
+        .bind(&token)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let integration: Integration = row
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))?
+        .into();
+
+    if !body.is_object() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Body must be a JSON object".to_string() })));
+    }
+
+    let mut data = VCardData::default();
+    for (source, field) in &integration.mapping {
+        if let Some(value) = lookup(&body, source) {
+            set_field(&mut data, field, &value);
+        }
+    }
+
+    data.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    settings::check_required_fields(&pool, &data).await?;
+    capacity::check(&generate_vcard(&data), &data)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: e.to_string() })))?;
+
+    let reservation = budget.reserve(qr_image_bytes(&data.render)).await?;
+    let png = render_vcard_png(&data)
+        .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: "Contact cannot be encoded as a QR code".to_string() })))?;
+    drop(reservation);
+
+    let vcard_id = pool.insert_vcard(&data, None).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to store vCard".to_string() })))?;
+
+    if let Err(e) = sqlx::query("UPDATE integrations SET last_received_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(integration.id)
+        .execute(&pool)
+        .await
+    {
+        eprintln!("Failed to record delivery for integration {}: {}", integration.id, e);
+    }
+
+    events.publish(events::TOPIC_GENERATION, serde_json::json!({
+        "vcard_id": vcard_id,
+        "name": format!("{} {}", data.first_name, data.last_name),
+        "username": integration.name,
+    }));
+
+    let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+
+    Ok((StatusCode::CREATED, Json(InboundResponse {
+        vcard_id,
+        image: format!("data:image/png;base64,{}", base64_img),
+    })))
+}
@@ -0,0 +1,160 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+// Reported foreign key violations are capped; the count is always exact
+const MAX_REPORTED_VIOLATIONS: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CheckMode {
+    Full,
+    Quick,
+    Off,
+}
+
+impl CheckMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckMode::Full => "full",
+            CheckMode::Quick => "quick",
+            CheckMode::Off => "off",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IntegrityConfig {
+    pub mode: CheckMode,
+    // Refuse to start on a corrupted file instead of starting degraded
+    pub required: bool,
+}
+
+impl IntegrityConfig {
+    pub fn from_env() -> Self {
+        let mode = match std::env::var("INTEGRITY_CHECK").ok().as_deref().map(str::trim) {
+            Some("quick") => CheckMode::Quick,
+            Some("off") => CheckMode::Off,
+            _ => CheckMode::Full,
+        };
+        let required = std::env::var("INTEGRITY_CHECK_REQUIRED")
+            .map(|v| v != "false" && v != "0")
+            .unwrap_or(true);
+        IntegrityConfig { mode, required }
+    }
+}
+
+#[derive(Clone, sqlx::FromRow)]
+pub struct ForeignKeyViolation {
+    table: String,
+    rowid: Option<i64>,
+    parent: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct IntegrityReport {
+    // Whether the file itself is sound; foreign key violations do not affect this
+    pub ok: bool,
+    // Problems from PRAGMA integrity_check (or quick_check); empty when ok
+    pub problems: Vec<String>,
+    pub foreign_key_violations: usize,
+    // Details go to the log only, since readiness is public
+    #[serde(skip)]
+    pub violations: Vec<ForeignKeyViolation>,
+    pub checked_at: String,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    status: &'static str,
+    database: &'static str,
+    // Absent when the startup check is turned off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<IntegrityReport>,
+}
+
+pub async fn check(pool: &SqlitePool, mode: CheckMode) -> Result<IntegrityReport, sqlx::Error> {
+    let pragma = if mode == CheckMode::Quick { "PRAGMA quick_check" } else { "PRAGMA integrity_check" };
+    let mut problems: Vec<String> = match sqlx::query_scalar::<_, String>(pragma).fetch_all(pool).await {
+        Ok(results) => results.into_iter().filter(|line| line != "ok").collect(),
+        // A badly damaged file makes the check itself fail
+        Err(sqlx::Error::Database(e)) => vec![e.message().to_string()],
+        Err(e) => return Err(e),
+    };
+
+    let fk_check = sqlx::query_as::<_, ForeignKeyViolation>("SELECT \"table\", rowid, parent FROM pragma_foreign_key_check")
+        .fetch_all(pool)
+        .await;
+    let mut violations = match fk_check {
+        Ok(violations) => violations,
+        Err(sqlx::Error::Database(e)) => {
+            problems.push(e.message().to_string());
+            Vec::new()
+        }
+        Err(e) => return Err(e),
+    };
+    let foreign_key_violations = violations.len();
+    violations.truncate(MAX_REPORTED_VIOLATIONS);
+
+    Ok(IntegrityReport {
+        ok: problems.is_empty(),
+        problems,
+        foreign_key_violations,
+        violations,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+// Run the configured startup check and log it; Err means startup should stop
+pub async fn check_on_startup(pool: &SqlitePool, config: &IntegrityConfig) -> Result<Option<IntegrityReport>, String> {
+    if config.mode == CheckMode::Off {
+        println!("→ Database integrity check disabled");
+        return Ok(None);
+    }
+
+    let report = check(pool, config.mode).await
+        .map_err(|e| format!("Integrity check could not run: {}", e))?;
+
+    if report.ok {
+        println!("✓ Database integrity check passed");
+    } else {
+        for problem in &report.problems {
+            eprintln!("Database integrity problem: {}", problem);
+        }
+        if config.required {
+            return Err(format!(
+                "Database failed its integrity check ({} problems); restore a good copy or set INTEGRITY_CHECK_REQUIRED=false to start anyway",
+                report.problems.len()
+            ));
+        }
+        eprintln!("Starting with a database that failed its integrity check");
+    }
+
+    if report.foreign_key_violations > 0 {
+        eprintln!("Database has {} foreign key violations", report.foreign_key_violations);
+        for violation in &report.violations {
+            eprintln!("  {} row {} references a missing {} row", violation.table, violation.rowid.unwrap_or_default(), violation.parent);
+        }
+    }
+
+    Ok(Some(report))
+}
+
+// Public: ready once the database answers and the startup check found the file sound
+pub async fn readiness_handler(
+    State(pool): State<SqlitePool>,
+    State(report): State<Option<IntegrityReport>>,
+) -> Response {
+    let reachable = sqlx::query("SELECT 1").execute(&pool).await.is_ok();
+    let sound = report.as_ref().is_none_or(|report| report.ok);
+
+    let status = if reachable && sound { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(ReadinessResponse {
+        status: if status == StatusCode::OK { "ready" } else { "not_ready" },
+        database: if !reachable { "unreachable" } else if sound { "ok" } else { "corrupt" },
+        integrity: report,
+    })).into_response()
+}
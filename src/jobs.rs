@@ -0,0 +1,362 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::bulk;
+use crate::cache::ResponseCache;
+use crate::deliveries;
+use crate::events::{self, EventBus};
+use crate::imports;
+use crate::linkcheck;
+use crate::notifications;
+use crate::render_pool::Renderer;
+use crate::schedules;
+use crate::ErrorResponse;
+
+// How often the worker checks for queued jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// A claimed job belongs to its worker until the lease runs out. The worker
+// renews it while the job runs; if the worker dies, another instance can
+// reclaim the job once the lease expires.
+const LEASE_SECONDS: u64 = 60;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(LEASE_SECONDS / 3);
+
+// Jobs that keep losing their worker (e.g. crash the process) are given up on
+const MAX_ATTEMPTS: i64 = 3;
+
+// Deliveries also count their own failures against this, so they get more
+fn max_attempts(kind: &str) -> i64 {
+    if deliveries::is_delivery(kind) { deliveries::MAX_ATTEMPTS } else { MAX_ATTEMPTS }
+}
+
+#[derive(sqlx::FromRow)]
+struct JobRow {
+    id: i64,
+    kind: String,
+    status: String,
+    result: Option<String>,
+    error: Option<String>,
+    attempts: i64,
+    created_by: Option<i64>,
+    run_at: Option<String>,
+    created_at: String,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Job {
+    id: i64,
+    kind: String,
+    status: String,
+    result: Option<Value>,
+    error: Option<String>,
+    attempts: i64,
+    run_at: Option<String>,
+    created_at: String,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+}
+
+impl From<JobRow> for Job {
+    fn from(row: JobRow) -> Self {
+        Job {
+            id: row.id,
+            kind: row.kind,
+            status: row.status,
+            result: row.result.and_then(|r| serde_json::from_str(&r).ok()),
+            error: row.error,
+            attempts: row.attempts,
+            run_at: row.run_at,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ClaimedJob {
+    id: i64,
+    kind: String,
+    payload: String,
+    attempts: i64,
+    created_by: Option<i64>,
+}
+
+const JOB_COLUMNS: &str = "id, kind, status, result, error, attempts, created_by, run_at, created_at, started_at, finished_at";
+
+// Queue a job for the background worker, returning its ID
+pub async fn enqueue(pool: &SqlitePool, kind: &str, payload: &Value, created_by: i64) -> Result<i64, sqlx::Error> {
+    enqueue_at(pool, kind, payload, Some(created_by), None).await
+}
+
+// Queue a job that won't be picked up before run_at (UTC, SQLite datetime format)
+pub async fn enqueue_at(
+    pool: &SqlitePool,
+    kind: &str,
+    payload: &Value,
+    created_by: Option<i64>,
+    run_at: Option<&str>,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO jobs (kind, payload, created_by, run_at) VALUES (?, ?, ?, ?)")
+        .bind(kind)
+        .bind(payload.to_string())
+        .bind(created_by)
+        .bind(run_at)
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+// Check a payload for a job that is queued without going through its handler
+pub fn validate_payload(kind: &str, payload: &Value) -> Result<(), String> {
+    match kind {
+        bulk::JOB_KIND => bulk::validate(payload),
+        imports::JOB_KIND => imports::validate(payload),
+        // Takes no options
+        linkcheck::JOB_KIND => Ok(()),
+        _ => Err(format!("Unknown job kind: {}", kind)),
+    }
+}
+
+// Dispatch a job to the code that knows how to run its kind
+async fn run_job(
+    pool: &SqlitePool,
+    renderer: &Renderer,
+    cache: &ResponseCache,
+    kind: &str,
+    payload: Value,
+    created_by: Option<i64>,
+) -> Result<Value, String> {
+    match kind {
+        bulk::JOB_KIND => bulk::run(pool, renderer, cache, payload, created_by).await,
+        imports::JOB_KIND => imports::run(pool, payload, created_by).await,
+        notifications::JOB_KIND => notifications::run(payload).await,
+        linkcheck::JOB_KIND => linkcheck::run(pool).await,
+        _ => Err(format!("Unknown job kind: {}", kind)),
+    }
+}
+
+// Build the downloadable per-row report for kinds that support one
+async fn job_report(pool: &SqlitePool, kind: &str, payload: Value, result: Value) -> Option<Result<Vec<u8>, String>> {
+    match kind {
+        imports::JOB_KIND => Some(imports::report(pool, payload, result).await),
+        _ => None,
+    }
+}
+
+fn lease_modifier() -> String {
+    format!("+{} seconds", LEASE_SECONDS)
+}
+
+// Atomically take the next due job, or one whose worker's lease has lapsed
+async fn claim_next(pool: &SqlitePool, worker_id: &str) -> Result<Option<ClaimedJob>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        UPDATE jobs SET
+            status = 'running',
+            locked_by = ?1,
+            lease_expires_at = datetime('now', ?2),
+            attempts = attempts + 1,
+            started_at = CURRENT_TIMESTAMP
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE (status = 'queued' AND (run_at IS NULL OR run_at <= CURRENT_TIMESTAMP))
+               OR (status = 'running' AND lease_expires_at < CURRENT_TIMESTAMP)
+            ORDER BY id LIMIT 1
+        )
+        RETURNING id, kind, payload, attempts, created_by
+        "#,
+    )
+    .bind(worker_id)
+    .bind(lease_modifier())
+    .fetch_optional(pool)
+    .await
+}
+
+// Keep extending the lease for as long as the job is running
+async fn heartbeat(pool: SqlitePool, job_id: i64, worker_id: String) {
+    loop {
+        tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+        if let Err(e) = sqlx::query("UPDATE jobs SET lease_expires_at = datetime('now', ?) WHERE id = ? AND locked_by = ?")
+            .bind(lease_modifier())
+            .bind(job_id)
+            .bind(&worker_id)
+            .execute(&pool)
+            .await
+        {
+            eprintln!("Failed to renew lease on job {}: {}", job_id, e);
+        }
+    }
+}
+
+// Record the outcome, unless the lease was lost and another worker took over, and return the
+// job's new status. A failed delivery goes back in the queue after a backoff until it runs out of
+// attempts, then it is 'dead' rather than 'failed'
+async fn finish(pool: &SqlitePool, job: &ClaimedJob, worker_id: &str, outcome: Result<Value, String>) -> Result<&'static str, sqlx::Error> {
+    let delivery = deliveries::is_delivery(&job.kind);
+    let (status, result, error) = match outcome {
+        Ok(result) => ("completed", Some(result.to_string()), None),
+        Err(error) if delivery && job.attempts < deliveries::MAX_ATTEMPTS => ("queued", None, Some(error)),
+        Err(error) if delivery => ("dead", None, Some(error)),
+        Err(error) => ("failed", None, Some(error)),
+    };
+    let retry_at = (status == "queued").then(|| deliveries::retry_modifier(job.attempts));
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = ?1, result = ?2, error = ?3,
+            run_at = CASE WHEN ?4 IS NULL THEN run_at ELSE datetime('now', ?4) END,
+            finished_at = CASE WHEN ?4 IS NULL THEN CURRENT_TIMESTAMP END,
+            locked_by = NULL, lease_expires_at = NULL
+        WHERE id = ?5 AND locked_by = ?6
+        "#,
+    )
+    .bind(status)
+    .bind(result)
+    .bind(&error)
+    .bind(retry_at)
+    .bind(job.id)
+    .bind(worker_id)
+    .execute(pool)
+    .await?;
+
+    if updated.rows_affected() == 0 {
+        eprintln!("Lost lease on job {}; result discarded", job.id);
+    } else if let (true, Some(error)) = (delivery, &error) {
+        deliveries::record_failure(pool, job.id, job.attempts, error).await?;
+    }
+    Ok(status)
+}
+
+// Background worker: runs queued jobs one at a time. Safe to run on every
+// instance sharing the database, since jobs are claimed under a lease.
+pub async fn run_worker(pool: SqlitePool, events: EventBus, renderer: Renderer, cache: ResponseCache) {
+    let worker_id = format!("{}:{}", crate::config::instance_id(), uuid::Uuid::new_v4());
+
+    loop {
+        schedules::enqueue_due(&pool).await;
+
+        match claim_next(&pool, &worker_id).await {
+            Ok(Some(job)) => {
+                let outcome = if job.attempts > max_attempts(&job.kind) {
+                    Err(format!("Job abandoned after {} attempts", max_attempts(&job.kind)))
+                } else {
+                    let beat = tokio::spawn(heartbeat(pool.clone(), job.id, worker_id.clone()));
+                    let payload = serde_json::from_str(&job.payload).unwrap_or(Value::Null);
+                    let outcome = run_job(&pool, &renderer, &cache, &job.kind, payload, job.created_by).await;
+                    beat.abort();
+                    outcome
+                };
+
+                match finish(&pool, &job, &worker_id, outcome).await {
+                    Ok(status) => events.publish(events::TOPIC_JOB, serde_json::json!({
+                        "job_id": job.id,
+                        "kind": job.kind,
+                        "status": status,
+                    })),
+                    Err(e) => eprintln!("Failed to record result of job {}: {}", job.id, e),
+                }
+            }
+            Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+            Err(e) => {
+                eprintln!("Failed to claim job: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+pub async fn list_jobs_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Job>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let rows: Vec<JobRow> = sqlx::query_as(&format!("SELECT {} FROM jobs WHERE created_by = ? ORDER BY id DESC LIMIT 100", JOB_COLUMNS))
+        .bind(user.id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(rows.into_iter().map(Job::from).collect()))
+}
+
+pub async fn get_job_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(job_id): Path<i64>,
+) -> Result<Json<Job>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let row: Option<JobRow> = sqlx::query_as(&format!("SELECT {} FROM jobs WHERE id = ?", JOB_COLUMNS))
+        .bind(job_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    // Users only see their own jobs; admins see everything
+    match row {
+        Some(row) if user.is_admin || row.created_by == Some(user.id) => Ok(Json(Job::from(row))),
+        _ => Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Job not found".to_string() }))),
+    }
+}
+
+pub async fn job_report_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(job_id): Path<i64>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    #[derive(sqlx::FromRow)]
+    struct ReportSource {
+        kind: String,
+        status: String,
+        payload: String,
+        result: Option<String>,
+        created_by: Option<i64>,
+    }
+
+    let job: Option<ReportSource> = sqlx::query_as("SELECT kind, status, payload, result, created_by FROM jobs WHERE id = ?")
+        .bind(job_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let job = match job {
+        Some(job) if user.is_admin || job.created_by == Some(user.id) => job,
+        _ => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Job not found".to_string() }))),
+    };
+
+    let result = match (job.status.as_str(), job.result) {
+        ("completed", Some(result)) => serde_json::from_str(&result).unwrap_or(Value::Null),
+        _ => return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: "Job has not completed".to_string() }))),
+    };
+    let payload = serde_json::from_str(&job.payload).unwrap_or(Value::Null);
+
+    let csv = job_report(&pool, &job.kind, payload, result).await
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "No report available for this job".to_string() })))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: e })))?;
+
+    let disposition = format!("attachment; filename=\"job-{}-report.csv\"", job_id);
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/csv".to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        csv,
+    ).into_response())
+}
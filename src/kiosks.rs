@@ -0,0 +1,242 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::{header, HeaderMap, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::badges;
+use crate::capacity;
+use crate::events::{self, EventBus};
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::repo::VcardRepo;
+use crate::render_pool::{Priority, RenderPool};
+use crate::{generate_vcard, ErrorResponse, MessageResponse, RenderOptions, VCardData};
+
+const MAX_KIOSK_NAME_LEN: usize = 100;
+
+#[derive(Deserialize)]
+pub struct CreateKioskRequest {
+    name: String,
+    // Render settings every code from this kiosk uses
+    color: Option<String>,
+    // When set, each submission registers an attendee and returns their badge
+    event_id: Option<i64>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Kiosk {
+    id: i64,
+    name: String,
+    color: Option<String>,
+    event_id: Option<i64>,
+    last_used_at: Option<String>,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct CreatedKiosk {
+    #[serde(flatten)]
+    kiosk: Kiosk,
+    // Only returned here; the server keeps a hash
+    token: String,
+    kiosk_url: String,
+}
+
+// What a visitor can fill in at the kiosk; styling comes from the kiosk itself
+#[derive(Deserialize)]
+pub struct KioskSubmission {
+    first_name: String,
+    last_name: String,
+    mobile: Option<String>,
+    work: Option<String>,
+    email: Option<String>,
+    company: Option<String>,
+    role: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct KioskQrResponse {
+    image: String, // base64 encoded
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// The kiosk presents its device token as a bearer token, never a session
+async fn authenticate_kiosk(pool: &SqlitePool, headers: &HeaderMap) -> Result<Kiosk, (StatusCode, Json<ErrorResponse>)> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Kiosk token required".to_string() })))?;
+
+    let kiosk: Option<Kiosk> = sqlx::query_as(
+        "UPDATE kiosks SET last_used_at = CURRENT_TIMESTAMP WHERE token_hash = ? RETURNING id, name, color, event_id, last_used_at, created_at"
+    )
+    .bind(hash_token(token.trim()))
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    kiosk.ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Invalid kiosk token".to_string() })))
+}
+
+pub async fn create_kiosk_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<CreateKioskRequest>,
+) -> Result<(StatusCode, Json<CreatedKiosk>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_KIOSK_NAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Name must be between 1 and {} characters", MAX_KIOSK_NAME_LEN),
+        })));
+    }
+
+    if let Some(event_id) = req.event_id {
+        badges::fetch_event(&pool, event_id).await?;
+    }
+
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+
+    let kiosk: Kiosk = sqlx::query_as(
+        "INSERT INTO kiosks (name, token_hash, color, event_id, created_by) VALUES (?, ?, ?, ?, ?) RETURNING id, name, color, event_id, last_used_at, created_at"
+    )
+    .bind(name)
+    .bind(hash_token(&token))
+    .bind(&req.color)
+    .bind(req.event_id)
+    .bind(user.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create kiosk".to_string() })))?;
+
+    Ok((StatusCode::CREATED, Json(CreatedKiosk {
+        kiosk,
+        kiosk_url: format!("/kiosk#{}", token),
+        token,
+    })))
+}
+
+pub async fn list_kiosks_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Kiosk>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let kiosks: Vec<Kiosk> = sqlx::query_as("SELECT id, name, color, event_id, last_used_at, created_at FROM kiosks ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(kiosks))
+}
+
+// Deleting a kiosk revokes its token immediately
+pub async fn delete_kiosk_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(kiosk_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let result = sqlx::query("DELETE FROM kiosks WHERE id = ?")
+        .bind(kiosk_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete kiosk".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Kiosk not found".to_string() })));
+    }
+
+    Ok(Json(MessageResponse {
+        message: "Kiosk deleted successfully".to_string(),
+    }))
+}
+
+// The only thing a kiosk token can do
+pub async fn kiosk_generate_handler(
+    State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    State(budget): State<MemoryBudget>,
+    State(render_pool): State<RenderPool>,
+    headers: HeaderMap,
+    Json(submission): Json<KioskSubmission>,
+) -> Result<Json<KioskQrResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let kiosk = authenticate_kiosk(&pool, &headers).await?;
+
+    if submission.first_name.trim().is_empty() || submission.last_name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "First and last name are required".to_string() })));
+    }
+
+    let data = VCardData {
+        first_name: submission.first_name,
+        last_name: submission.last_name,
+        mobile: submission.mobile,
+        work: submission.work,
+        email: submission.email,
+        company: submission.company,
+        role: submission.role,
+        render: RenderOptions { color: kiosk.color.clone(), ..Default::default() },
+        ..Default::default()
+    };
+    // Anyone at the kiosk can type here, so the submission gets the same checks as a signed-in one
+    capacity::check_card(&data)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: e })))?;
+
+    let (vcard_id, png) = match kiosk.event_id {
+        Some(event_id) => {
+            let attendee_id = badges::add_attendee(&pool, event_id, &data, None).await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to register attendee".to_string() })))?;
+            let png = badges::badge_png(&pool, &budget, event_id, attendee_id).await?;
+            let vcard_id: i64 = sqlx::query_scalar("SELECT vcard_id FROM event_attendees WHERE id = ?")
+                .bind(attendee_id)
+                .fetch_one(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+            (vcard_id, png)
+        }
+        None => {
+            let vcard_id = pool.insert_vcard(&data, None).await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to store vCard".to_string() })))?;
+            let _reservation = budget.reserve(qr_image_bytes(&data.render)).await?;
+            let png = render_pool.png(Priority::Interactive, generate_vcard(&data), data.render.clone()).await?;
+            (vcard_id, png)
+        }
+    };
+
+    events.publish(events::TOPIC_GENERATION, serde_json::json!({
+        "vcard_id": vcard_id,
+        "name": format!("{} {}", data.first_name, data.last_name),
+        "username": kiosk.name,
+    }));
+
+    let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+
+    Ok(Json(KioskQrResponse {
+        image: format!("data:image/png;base64,{}", base64_img),
+    }))
+}
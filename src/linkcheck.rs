@@ -0,0 +1,253 @@
+// Link rot checker: a scheduled job that requests every link stored on the cards (the website and
+// any extension holding a URL) and flags the ones that no longer answer, on the card itself and in
+// an admin report, so a printed code doesn't send people to a dead page for months. The migration
+// schedules it nightly; it can also be queued by hand like any other job
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use tokio::task::JoinSet;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::outbound;
+use crate::{clean_field, ErrorResponse, VCardData};
+
+pub const JOB_KIND: &str = "link_check";
+
+const USER_AGENT: &str = concat!("vcard-qr-generator/", env!("CARGO_PKG_VERSION"), " link checker");
+// Links requested at once
+const CONCURRENCY: usize = 8;
+
+// The links on a card by field: "website", or "extensions.X-..." for an extension holding a URL.
+// A website without a scheme is requested over https, as phones open it
+pub fn links(website: Option<&str>, extensions: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut links = Vec::new();
+    if let Some(website) = website {
+        let url = if website.contains("://") { website.to_string() } else { format!("https://{}", website) };
+        links.push(("website".to_string(), url));
+    }
+    for (name, value) in extensions {
+        let value = value.trim();
+        if value.starts_with("http://") || value.starts_with("https://") {
+            links.push((format!("extensions.{}", name), value.to_string()));
+        }
+    }
+    links
+}
+
+// Links into this network are skipped, so a card can't use the checker to probe internal hosts
+fn is_internal(url: &reqwest::Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return true;
+    };
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => is_internal_ip(ip),
+        Err(_) => host == "localhost" || host.ends_with(".localhost") || host.ends_with(".internal"),
+    }
+}
+
+fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified(),
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
+
+#[derive(Clone)]
+struct Outcome {
+    status_code: Option<u16>,
+    error: Option<String>,
+    broken: bool,
+}
+
+// HEAD first, then GET for servers that don't take HEAD. Only answers that mean the page is gone
+// (404, 410, server errors) or no answer at all count as broken; 401, 403 and 429 are sites that
+// turn away robots, and the page may well be there
+async fn check(url: &str) -> Outcome {
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && !is_internal(&parsed) => parsed,
+        Ok(_) => return Outcome { status_code: None, error: Some("Not checked: not a public http(s) link".to_string()), broken: false },
+        Err(e) => return Outcome { status_code: None, error: Some(format!("Invalid URL: {}", e)), broken: true },
+    };
+    let client = outbound::client();
+    let mut response = client.head(parsed.clone()).header(reqwest::header::USER_AGENT, USER_AGENT).send().await;
+    if let Ok(head) = &response {
+        if matches!(head.status().as_u16(), 405 | 501) {
+            response = client.get(parsed).header(reqwest::header::USER_AGENT, USER_AGENT).send().await;
+        }
+    }
+    match response {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            Outcome { status_code: Some(status), error: None, broken: matches!(status, 404 | 410) || status >= 500 }
+        }
+        Err(e) => Outcome { status_code: None, error: Some(e.to_string()), broken: true },
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CardLinks {
+    id: i64,
+    website: Option<String>,
+    #[sqlx(json)]
+    extensions: BTreeMap<String, String>,
+}
+
+// Job handler: checks every card's links, each distinct URL once, and records the results
+pub async fn run(pool: &SqlitePool) -> Result<Value, String> {
+    let cards: Vec<CardLinks> = sqlx::query_as("SELECT id, website, extensions FROM vcards")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let cards: Vec<(i64, Vec<(String, String)>)> = cards
+        .into_iter()
+        .map(|card| (card.id, links(card.website.as_deref(), &card.extensions)))
+        .collect();
+
+    let mut urls: Vec<String> = cards.iter().flat_map(|(_, links)| links.iter().map(|(_, url)| url.clone())).collect();
+    urls.sort();
+    urls.dedup();
+
+    let mut outcomes: HashMap<String, Outcome> = HashMap::with_capacity(urls.len());
+    for batch in urls.chunks(CONCURRENCY) {
+        let mut checks = JoinSet::new();
+        for url in batch {
+            let url = url.clone();
+            checks.spawn(async move {
+                let outcome = check(&url).await;
+                (url, outcome)
+            });
+        }
+        while let Some(checked) = checks.join_next().await {
+            if let Ok((url, outcome)) = checked {
+                outcomes.insert(url, outcome);
+            }
+        }
+    }
+
+    let mut broken_cards = 0;
+    for (vcard_id, links) in &cards {
+        let results: Vec<(&str, &str, Option<&Outcome>)> = links.iter()
+            .map(|(field, url)| (field.as_str(), url.as_str(), outcomes.get(url)))
+            .collect();
+        if results.iter().any(|(_, _, outcome)| outcome.is_some_and(|outcome| outcome.broken)) {
+            broken_cards += 1;
+        }
+        record(pool, *vcard_id, &results).await.map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    let broken = outcomes.values().filter(|outcome| outcome.broken).count();
+    Ok(json!({ "checked": outcomes.len(), "broken": broken, "cards_with_broken_links": broken_cards }))
+}
+
+// Replaces a card's check results and its broken_links flag
+async fn record(pool: &SqlitePool, vcard_id: i64, results: &[(&str, &str, Option<&Outcome>)]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM link_checks WHERE vcard_id = ?")
+        .bind(vcard_id)
+        .execute(&mut *tx)
+        .await?;
+    let mut broken_fields = Vec::new();
+    for (field, url, outcome) in results {
+        let Some(outcome) = outcome else { continue };
+        sqlx::query("INSERT INTO link_checks (vcard_id, field, url, status_code, error, broken) VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(vcard_id)
+            .bind(*field)
+            .bind(*url)
+            .bind(outcome.status_code)
+            .bind(&outcome.error)
+            .bind(outcome.broken)
+            .execute(&mut *tx)
+            .await?;
+        if outcome.broken {
+            broken_fields.push(field.to_string());
+        }
+    }
+    sqlx::query("UPDATE vcards SET broken_links = ? WHERE id = ?")
+        .bind(serde_json::to_string(&broken_fields).unwrap_or_else(|_| "[]".to_string()))
+        .bind(vcard_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+// After an edit, drops the results for links the card no longer has, so a fixed website stops
+// being flagged straight away rather than at the next check
+pub async fn forget_changed(pool: &SqlitePool, vcard_id: i64, data: &VCardData) {
+    let current = links(clean_field(&data.website), &data.extensions);
+    let result = async {
+        let mut tx = pool.begin().await?;
+        let checked: Vec<(String, String)> = sqlx::query_as("SELECT field, url FROM link_checks WHERE vcard_id = ?")
+            .bind(vcard_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        for (field, url) in checked.into_iter().filter(|link| !current.contains(link)) {
+            sqlx::query("DELETE FROM link_checks WHERE vcard_id = ? AND field = ? AND url = ?")
+                .bind(vcard_id)
+                .bind(&field)
+                .bind(&url)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query(
+            r#"
+            UPDATE vcards SET broken_links = (
+                SELECT json_group_array(field) FROM (SELECT field FROM link_checks WHERE vcard_id = ?1 AND broken ORDER BY field)
+            ) WHERE id = ?1
+            "#
+        )
+        .bind(vcard_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    };
+    if let Err(e) = result.await {
+        eprintln!("Failed to clear link checks for vCard {}: {}", vcard_id, e);
+    }
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct BrokenLink {
+    vcard_id: i64,
+    first_name: String,
+    last_name: String,
+    field: String,
+    url: String,
+    status_code: Option<i64>,
+    error: Option<String>,
+    checked_at: String,
+}
+
+// Admin: every link that was broken at its last check, grouped by card
+pub async fn link_report_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<BrokenLink>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let report: Vec<BrokenLink> = sqlx::query_as(
+        r#"
+        SELECT c.vcard_id, v.first_name, v.last_name, c.field, c.url, c.status_code, c.error, c.checked_at
+        FROM link_checks c JOIN vcards v ON v.id = c.vcard_id
+        WHERE c.broken
+        ORDER BY v.last_name COLLATE NOCASE, v.first_name COLLATE NOCASE, c.vcard_id, c.field
+        "#
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(report))
+}
@@ -1,25 +1,210 @@
+mod alt_text;
+mod animation;
+mod approvals;
 mod auth;
+mod badges;
+mod bulk;
+mod business_cards;
+mod cache;
+mod capacity;
+mod checkin;
+mod cli;
+mod compose;
+mod config;
+mod contact_book;
+mod data_browser;
+mod deliveries;
+mod db;
+mod directory;
+mod domains;
+mod downloads;
+mod drafts;
+mod embed;
+mod encryption;
+mod events;
+mod filenames;
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod image_links;
+mod imports;
+mod integrations;
+mod integrity;
+mod jobs;
+mod kiosks;
+mod linkcheck;
+mod mailer;
+mod memory;
+mod metrics;
+mod negotiation;
+mod notifications;
+mod outbound;
+mod passwords;
+mod payments;
+mod pdf;
+mod plugins;
+mod posters;
+mod proofs;
+mod public;
+mod rate_limit;
+mod render_pool;
+mod repo;
+mod revisions;
+mod scan;
+mod schedules;
+mod selfcheck;
+mod server;
+mod settings;
+mod setup;
+mod signage;
+mod signing;
+mod slugs;
+mod styles;
+mod tags;
+#[cfg(feature = "tui")]
+mod tui;
+mod uploads;
+mod vcards;
+mod verify;
 
 use axum::{
-    extract::{Json, Path, State},
-    http::{StatusCode, header},
+    extract::{ConnectInfo, DefaultBodyLimit, FromRef, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Redirect, Response},
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Router,
 };
-use image::{ImageBuffer, Luma, DynamicImage, ImageFormat};
-use qrcode::QrCode;
+use vcard_core::{contrast, encode_png, encode_qr_png, parse_color, render_qr_eps, render_qr_image, render_qr_svg, render_qr_text, Messengers, Payment, RenderOptions, MIN_RENDER_SIZE};
 use serde::{Deserialize, Serialize};
 use sqlx::{SqlitePool, migrate::MigrateDatabase, Sqlite};
-use std::io::Cursor;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tower_http::services::ServeDir;
 use tower_sessions::{Expiry, SessionManagerLayer};
 use tower_sessions_sqlx_store::SqliteStore;
 use tower_sessions::Session;
 
 use auth::{User, UserInfo, authenticate_user, set_user_session, clear_session, get_current_user, hash_password};
+use events::EventBus;
+use rate_limit::RateLimiter;
+use repo::{UserRepo, VcardRepo};
+use signing::SigningKey;
+
+// Limits for the public instant QR endpoint
+const INSTANT_QR_MAX_TEXT_LEN: usize = 512;
+const INSTANT_QR_DEFAULT_SIZE: u32 = 256;
+const INSTANT_QR_MAX_SIZE: u32 = 1024;
+
+// Printed edge of the code with format=pdf, in millimetres. The scan check passes codes down to
+// 12 mm, and the largest still fits an A4 page with margins
+const PDF_DEFAULT_SIZE_MM: f32 = 50.0;
+const PDF_MIN_SIZE_MM: f32 = 15.0;
+const PDF_MAX_SIZE_MM: f32 = 180.0;
+const PDF_TOP_MM: f32 = 30.0;
+const PDF_NAME_PT: f32 = 14.0;
+
+
+#[derive(Clone)]
+struct AppState {
+    pool: SqlitePool,
+    db: db::DbRouter,
+    events: EventBus,
+    graphql: graphql::VCardSchema,
+    signing_key: SigningKey,
+    // Empty unless QR_ENCRYPTION_KEYS is set
+    payload_keys: encryption::PayloadKeys,
+    embed: embed::EmbedConfig,
+    config: config::EffectiveConfig,
+    // Outcome of the startup integrity check, if it ran
+    integrity: Option<integrity::IntegrityReport>,
+    // Present only when INSTANT_QR_ENABLED is set
+    instant_qr_limiter: Option<Arc<RateLimiter>>,
+    memory: memory::MemoryBudget,
+    render_pool: render_pool::RenderPool,
+    response_cache: cache::ResponseCache,
+}
 
-#[derive(Deserialize)]
+impl FromRef<AppState> for SqlitePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for db::DbRouter {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for EventBus {
+    fn from_ref(state: &AppState) -> Self {
+        state.events.clone()
+    }
+}
+
+impl FromRef<AppState> for SigningKey {
+    fn from_ref(state: &AppState) -> Self {
+        state.signing_key.clone()
+    }
+}
+
+impl FromRef<AppState> for encryption::PayloadKeys {
+    fn from_ref(state: &AppState) -> Self {
+        state.payload_keys.clone()
+    }
+}
+
+impl FromRef<AppState> for embed::EmbedConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.embed.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<integrity::IntegrityReport> {
+    fn from_ref(state: &AppState) -> Self {
+        state.integrity.clone()
+    }
+}
+
+impl FromRef<AppState> for config::EffectiveConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for memory::MemoryBudget {
+    fn from_ref(state: &AppState) -> Self {
+        state.memory.clone()
+    }
+}
+
+impl FromRef<AppState> for render_pool::RenderPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.render_pool.clone()
+    }
+}
+
+impl FromRef<AppState> for render_pool::Renderer {
+    fn from_ref(state: &AppState) -> Self {
+        render_pool::Renderer { budget: state.memory.clone(), pool: state.render_pool.clone() }
+    }
+}
+
+impl FromRef<AppState> for cache::ResponseCache {
+    fn from_ref(state: &AppState) -> Self {
+        state.response_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for graphql::VCardSchema {
+    fn from_ref(state: &AppState) -> Self {
+        state.graphql.clone()
+    }
+}
+
+#[derive(Deserialize, Default)]
 struct VCardData {
     first_name: String,
     last_name: String,
@@ -31,13 +216,141 @@ struct VCardData {
     street: Option<String>,
     city: Option<String>,
     state: Option<String>,
+    postal_code: Option<String>,
+    // ISO 3166-1 alpha-2 code; sets the address layout
+    country: Option<String>,
     website: Option<String>,
-    color: Option<String>,
+    // X- properties carried inside the card, e.g. a CRM ID or cost center
+    #[serde(default)]
+    extensions: BTreeMap<String, String>,
+    // Custom labels for mobile, work and website, e.g. {"website": "Booking"}
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    // whatsapp, telegram and signal, sent alongside the contact fields
+    #[serde(flatten)]
+    messengers: Messengers,
+    // paypal and iban
+    #[serde(flatten)]
+    payment: Payment,
+    // Sent alongside the contact fields and stored with the card, so re-renders match the original
+    #[serde(flatten)]
+    render: RenderOptions,
+    // Draft to discard once this card has been generated
+    draft_id: Option<i64>,
+    // Extra rendering to include in the response; not stored with the card
+    #[serde(default)]
+    format: OutputFormat,
+    // Edge of the code on the page with format=pdf, in millimetres
+    pdf_size_mm: Option<f32>,
+    // Text under the code in the PNG: true for "Scan me", or the text itself
+    caption: Option<compose::Caption>,
+    // Encode the card sealed with the instance's shared key instead of as plain text; only the
+    // returned image is affected
+    #[serde(default)]
+    encrypt: bool,
+    // A completed upload of the submitter's to use as the logo; copied into the render options
+    logo_upload_id: Option<String>,
+    // A styling preset whose options fill in those the submission leaves unset; copied like the logo
+    style_id: Option<i64>,
+}
+
+impl VCardData {
+    fn contact(&self) -> vcard_core::Contact<'_> {
+        vcard_core::Contact {
+            first_name: &self.first_name,
+            last_name: &self.last_name,
+            mobile: self.mobile.as_deref(),
+            work: self.work.as_deref(),
+            email: self.email.as_deref(),
+            company: self.company.as_deref(),
+            role: self.role.as_deref(),
+            street: self.street.as_deref(),
+            city: self.city.as_deref(),
+            state: self.state.as_deref(),
+            postal_code: self.postal_code.as_deref(),
+            country: self.country.as_deref(),
+            website: self.website.as_deref(),
+            extensions: Some(&self.extensions),
+            labels: Some(&self.labels),
+            messengers: Some(&self.messengers),
+            payment: Some(&self.payment),
+        }
+    }
+
+    // Everything about a submission that is checked before it is rendered or stored
+    fn validate(&self) -> Result<(), String> {
+        self.render.validate_for_card()?;
+        if let Some(logo) = &self.render.logo {
+            vcard_core::render::check_logo(logo)?;
+        }
+        vcard_core::validate_address(self.postal_code.as_deref(), self.country.as_deref())?;
+        vcard_core::validate_extensions(&self.extensions)?;
+        vcard_core::validate_labels(&self.labels)?;
+        self.messengers.validate()?;
+        self.payment.validate()?;
+        if self.pdf_size_mm.is_some_and(|size| !(PDF_MIN_SIZE_MM..=PDF_MAX_SIZE_MM).contains(&size)) {
+            return Err(format!("pdf_size_mm must be between {} and {}", PDF_MIN_SIZE_MM, PDF_MAX_SIZE_MM));
+        }
+        if let Some(caption) = &self.caption {
+            caption.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    #[default]
+    Png,
+    // Unicode block characters that scan when printed to a terminal or CI log
+    Terminal,
+    // Vector markup that stays crisp at any print size
+    Svg,
+    // A4 page with the code at a set physical size and the contact's name under it; replaces the
+    // JSON response
+    Pdf,
+    // Vector artwork for prepress, as an EPS file; replaces the JSON response
+    Eps,
+    // The terminal drawing on its own as text/plain, for curl over SSH; replaces the JSON response
+    Ascii,
 }
 
 #[derive(Serialize)]
 struct QrResponse {
     image: String, // base64 encoded
+    // Only with format=terminal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    // Only with format=svg
+    #[serde(skip_serializing_if = "Option::is_none")]
+    svg: Option<String>,
+    scannability: scan::ScanReport,
+    // pending while the card waits for an approver
+    status: approvals::ReviewStatus,
+    // What to save the image as, from the admin's filename pattern
+    filename: String,
+    // Colors that passed but may not scan everywhere, e.g. low contrast
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<contrast::ContrastWarning>,
+    // alt_text and description of what the code holds, for screen readers
+    #[serde(flatten)]
+    alt: alt_text::AltText,
+}
+
+#[derive(Deserialize)]
+struct InstantQrQuery {
+    text: String,
+    size: Option<u32>,
+    #[serde(default)]
+    format: OutputFormat,
+    // Text under the PNG; "Scan me" when given empty
+    caption: Option<String>,
+    // Encoding: the symbol size, Micro QR for short text on tiny labels, and the mask pattern
+    version: Option<u8>,
+    #[serde(default)]
+    micro: bool,
+    mask: Option<u8>,
 }
 
 #[derive(Deserialize)]
@@ -71,89 +384,49 @@ struct MessageResponse {
     message: String,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
 struct ErrorResponse {
     error: String,
 }
 
-fn generate_vcard(data: &VCardData) -> String {
-    let mut vcard = String::from("BEGIN:VCARD\nVERSION:3.0\n");
-
-    // Name
-    vcard.push_str(&format!("FN:{} {}\n", data.first_name, data.last_name));
-    vcard.push_str(&format!("N:{};{};;;\n", data.last_name, data.first_name));
-
-    // Phone numbers
-    if let Some(mobile) = &data.mobile {
-        if !mobile.is_empty() {
-            vcard.push_str(&format!("TEL;TYPE=CELL:{}\n", mobile));
-        }
-    }
-    if let Some(work) = &data.work {
-        if !work.is_empty() {
-            vcard.push_str(&format!("TEL;TYPE=WORK:{}\n", work));
-        }
-    }
-
-    // Email
-    if let Some(email) = &data.email {
-        if !email.is_empty() {
-            vcard.push_str(&format!("EMAIL:{}\n", email));
-        }
-    }
-
-    // Organization
-    if let Some(company) = &data.company {
-        if !company.is_empty() {
-            vcard.push_str(&format!("ORG:{}\n", company));
-        }
-    }
-    if let Some(role) = &data.role {
-        if !role.is_empty() {
-            vcard.push_str(&format!("TITLE:{}\n", role));
-        }
-    }
-
-    // Address
-    let has_address = data.street.as_ref().map_or(false, |s| !s.is_empty())
-        || data.city.as_ref().map_or(false, |s| !s.is_empty())
-        || data.state.as_ref().map_or(false, |s| !s.is_empty());
+// A contact field as it is stored and encoded: trimmed, with blanks treated as absent
+fn clean_field(value: &Option<String>) -> Option<&str> {
+    vcard_core::clean(value.as_deref())
+}
 
-    if has_address {
-        vcard.push_str(&format!("ADR;TYPE=WORK:;;{};{};{};;;\n",
-            data.street.as_ref().unwrap_or(&String::new()),
-            data.city.as_ref().unwrap_or(&String::new()),
-            data.state.as_ref().unwrap_or(&String::new())
-        ));
-    }
+fn generate_vcard(data: &VCardData) -> String {
+    plugins::transform_vcard(vcard_core::generate_vcard(&data.contact()), data)
+}
 
-    // Website
-    if let Some(website) = &data.website {
-        if !website.is_empty() {
-            vcard.push_str(&format!("URL:{}\n", website));
-        }
-    }
+// Render a card's QR code as PNG with its stored options
+fn render_vcard_png(data: &VCardData) -> Result<Vec<u8>, String> {
+    render_qr_png(&generate_vcard(data), &data.render)
+}
 
-    vcard.push_str("END:VCARD");
-    vcard
+// Every PNG handed out is decoded back first, so an unreadable code is never returned
+fn render_qr_png(content: &str, options: &RenderOptions) -> Result<Vec<u8>, String> {
+    let image = plugins::transform_image(render_qr_image(content, options)?, content);
+    scan::verify(&image, content)?;
+    encode_qr_png(&image, options).map_err(|e| e.to_string())
 }
 
-fn parse_color(color_str: &str) -> (u8, u8, u8) {
-    let hex = color_str.trim_start_matches('#');
-    if hex.len() == 6 {
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-        (r, g, b)
-    } else {
-        (0, 0, 0)
-    }
+// One A4 page with the code `size_mm` wide, centered near the top, and the name under it
+fn render_qr_pdf(image: image::RgbImage, name: &str, size_mm: f32) -> Vec<u8> {
+    let center = pdf::A4_WIDTH_MM / 2.0;
+    let mut doc = pdf::Document::new();
+    doc.add_page(pdf::A4_WIDTH_MM, pdf::A4_HEIGHT_MM);
+    doc.image(image, center - size_mm / 2.0, PDF_TOP_MM, size_mm, size_mm);
+    doc.text_centered(&pdf::fit_text(name, PDF_NAME_PT, pdf::A4_WIDTH_MM - 40.0), center, PDF_TOP_MM + size_mm + 10.0, PDF_NAME_PT);
+    doc.finish()
 }
 
 // Authentication handlers
 async fn login_handler(
     State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     session: Session,
+    headers: HeaderMap,
     Json(req): Json<LoginRequest>,
 ) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
     match authenticate_user(&pool, &req.username, &req.password).await {
@@ -161,11 +434,20 @@ async fn login_handler(
             set_user_session(&session, &user).await
                 .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Session error".to_string() })))?;
 
+            let user_agent = headers.get(header::USER_AGENT).and_then(|value| value.to_str().ok()).unwrap_or("");
+            notifications::record_login(&pool, user.id, &addr.ip().to_string(), user_agent).await;
+
             Ok(Json(MessageResponse {
                 message: "Login successful".to_string(),
             }))
         }
-        Err(e) => Err((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: e }))),
+        Err(e) => {
+            events.publish(events::TOPIC_FAILED_LOGIN, serde_json::json!({
+                "username": req.username,
+                "ip": addr.ip().to_string(),
+            }));
+            Err((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: e })))
+        }
     }
 }
 
@@ -192,28 +474,26 @@ async fn change_password_handler(
         .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
 
     // Get full user from database
-    let user: User = sqlx::query_as("SELECT id, username, password_hash, is_admin FROM users WHERE id = ?")
-        .bind(user_info.id)
-        .fetch_one(&pool)
-        .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    let user: User = pool.find_user(user_info.id).await
+        .ok()
+        .flatten()
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
 
     // Verify current password
     if !auth::verify_password(&req.current_password, &user.password_hash) {
         return Err((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Current password is incorrect".to_string() })));
     }
 
+    passwords::require_strong(&req.new_password, &[&user.username])?;
+
     // Hash new password
     let new_hash = hash_password(&req.new_password)
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to hash password".to_string() })))?;
 
     // Update password
-    sqlx::query("UPDATE users SET password_hash = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(&new_hash)
-        .bind(user.id)
-        .execute(&pool)
-        .await
+    pool.set_password_hash(user.id, &new_hash).await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update password".to_string() })))?;
+    notifications::notify(&pool, user.id, notifications::Activity::PasswordChanged { by_admin: false }).await;
 
     Ok(Json(MessageResponse {
         message: "Password updated successfully".to_string(),
@@ -232,22 +512,10 @@ async fn get_users_handler(
         return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
     }
 
-    // Fetch users with created_at for display
-
-    #[derive(Serialize, sqlx::FromRow)]
-    struct UserWithDate {
-        id: i64,
-        username: String,
-        is_admin: bool,
-        created_at: String,
-    }
-
-    let users_with_dates: Vec<UserWithDate> = sqlx::query_as("SELECT id, username, is_admin, created_at FROM users ORDER BY id")
-        .fetch_all(&pool)
-        .await
+    let users = pool.list_users().await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
 
-    Ok(Json(users_with_dates.into_iter().map(|u| UserInfo { id: u.id, username: u.username, is_admin: u.is_admin }).collect()))
+    Ok(Json(users))
 }
 
 async fn create_user_handler(
@@ -262,22 +530,14 @@ async fn create_user_handler(
         return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
     }
 
+    passwords::require_strong(&req.password, &[&req.username])?;
+
     let password_hash = hash_password(&req.password)
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to hash password".to_string() })))?;
 
-    sqlx::query("INSERT INTO users (username, password_hash, is_admin) VALUES (?, ?, ?)")
-        .bind(&req.username)
-        .bind(&password_hash)
-        .bind(req.is_admin)
-        .execute(&pool)
-        .await
-        .map_err(|e| {
-            if e.to_string().contains("UNIQUE") {
-                (StatusCode::CONFLICT, Json(ErrorResponse { error: "Username already exists".to_string() }))
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() }))
-            }
-        })?;
+    pool.create_user(&req.username, &password_hash, req.is_admin).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?
+        .ok_or((StatusCode::CONFLICT, Json(ErrorResponse { error: "Username already exists".to_string() })))?;
 
     Ok(Json(MessageResponse {
         message: "User created successfully".to_string(),
@@ -297,13 +557,13 @@ async fn update_user_handler(
         return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
     }
 
+    // Checked first, so a weak password leaves the rest of the user unchanged too
+    if let Some(password) = req.password.as_deref().filter(|password| !password.is_empty()) {
+        passwords::require_strong(password, &[&req.username])?;
+    }
+
     // Update username and admin status
-    sqlx::query("UPDATE users SET username = ?, is_admin = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(&req.username)
-        .bind(req.is_admin)
-        .bind(user_id)
-        .execute(&pool)
-        .await
+    pool.update_user(user_id, &req.username, req.is_admin).await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update user".to_string() })))?;
 
     // Update password if provided
@@ -312,12 +572,9 @@ async fn update_user_handler(
             let password_hash = hash_password(&password)
                 .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to hash password".to_string() })))?;
 
-            sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
-                .bind(&password_hash)
-                .bind(user_id)
-                .execute(&pool)
-                .await
+            pool.set_password_hash(user_id, &password_hash).await
                 .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update password".to_string() })))?;
+            notifications::notify(&pool, user_id, notifications::Activity::PasswordChanged { by_admin: user_id != user.id }).await;
         }
     }
 
@@ -343,10 +600,7 @@ async fn delete_user_handler(
         return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Cannot delete your own account".to_string() })));
     }
 
-    sqlx::query("DELETE FROM users WHERE id = ?")
-        .bind(user_id)
-        .execute(&pool)
-        .await
+    pool.delete_user(user_id).await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete user".to_string() })))?;
 
     Ok(Json(MessageResponse {
@@ -357,76 +611,310 @@ async fn delete_user_handler(
 // VCard generation handler (requires auth)
 async fn generate_qr(
     State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    State(payload_keys): State<encryption::PayloadKeys>,
+    State(renderer): State<render_pool::Renderer>,
     session: Session,
-    Json(data): Json<VCardData>,
-) -> Result<Json<QrResponse>, StatusCode> {
+    headers: HeaderMap,
+    Json(mut data): Json<VCardData>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
     // Check authentication
-    if get_current_user(&session).await.is_none() {
-        return Err(StatusCode::UNAUTHORIZED);
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    // Decided up front, so an unacceptable request stores nothing
+    let media = negotiation::negotiate(&headers)
+        .ok_or((StatusCode::NOT_ACCEPTABLE, Json(ErrorResponse {
+            error: format!("Can only respond with {}", negotiation::GenerateMedia::SUPPORTED),
+        })))?;
+    if media == negotiation::GenerateMedia::VCard && data.encrypt {
+        return Err((StatusCode::NOT_ACCEPTABLE, Json(ErrorResponse {
+            error: "Encrypted codes do not hold a vCard; ask for an image or JSON".to_string(),
+        })));
     }
 
-    // Save to database
-    let result = sqlx::query(
-        r#"
-        INSERT INTO vcards (first_name, last_name, mobile, work, email, company, role, street, city, state, website, color)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#
-    )
-    .bind(&data.first_name)
-    .bind(&data.last_name)
-    .bind(&data.mobile)
-    .bind(&data.work)
-    .bind(&data.email)
-    .bind(&data.company)
-    .bind(&data.role)
-    .bind(&data.street)
-    .bind(&data.city)
-    .bind(&data.state)
-    .bind(&data.website)
-    .bind(&data.color)
-    .execute(&pool)
-    .await;
-
-    if let Err(e) = result {
-        eprintln!("Database error: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
-
-    let vcard_content = generate_vcard(&data);
-
-    let code = QrCode::new(vcard_content.as_bytes())
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let qr_image = code.render::<Luma<u8>>().build();
-
-    // Convert to colored image if color is specified
-    let dynamic_img = if let Some(color_str) = &data.color {
-        let (r, g, b) = parse_color(color_str);
-        let width = qr_image.width();
-        let height = qr_image.height();
-        let rgb_img = ImageBuffer::from_fn(width, height, |x, y| {
-            let pixel = qr_image.get_pixel(x, y);
-            if pixel[0] == 0 {
-                image::Rgb([r, g, b])
-            } else {
-                image::Rgb([255, 255, 255])
+    styles::apply_style(&pool, &mut data).await?;
+    uploads::attach_logo(&pool, user.id, &mut data).await?;
+    data.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    settings::check_required_fields(&pool, &data).await?;
+
+    // A card that will wait for review only gets a preview back, the final code coming from its
+    // downloads once approved. Rasters are crossed out; formats that can't carry the mark are
+    // refused here, so such a request stores nothing
+    let preview = approvals::ApprovalSettings::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?
+        .needs_review(user.id, user.is_admin);
+    let previewable = match media {
+        negotiation::GenerateMedia::Png | negotiation::GenerateMedia::Pdf => true,
+        negotiation::GenerateMedia::Json => matches!(data.format, OutputFormat::Png | OutputFormat::Pdf),
+        negotiation::GenerateMedia::Svg | negotiation::GenerateMedia::Eps | negotiation::GenerateMedia::VCard => false,
+    };
+    if preview && !previewable {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse {
+            error: "Cards awaiting approval are previewed as PNG or PDF; other formats can be downloaded once approved".to_string(),
+        })));
+    }
+
+    let _reservation = renderer.budget.reserve(memory::qr_image_bytes(&data.render)).await?;
+
+    // Render and self-check before storing, so a code that fails to decode leaves nothing behind
+    let mut content = generate_vcard(&data);
+    if data.encrypt {
+        content = payload_keys.encrypt(&content)
+            .ok_or((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Encrypted QR codes are not configured".to_string() })))?;
+    }
+    if let Err(e) = capacity::check(&content, &data) {
+        return Ok(e.into_response());
+    }
+    let (scannability, png, page_image) = {
+        let content = content.clone();
+        let options = data.render.clone();
+        let for_pdf = data.format == OutputFormat::Pdf || media == negotiation::GenerateMedia::Pdf;
+        let caption = data.caption.as_ref().and_then(|caption| caption.text()).map(str::to_string);
+        renderer.pool.run(render_pool::Priority::Interactive, move || {
+            let image = render_qr_image(&content, &options)
+                .map_err(|e| {
+                    eprintln!("Failed to render QR code: {}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+                })?;
+            let image = plugins::transform_image(image, &content);
+
+            if let Err(e) = scan::verify(&image, &content) {
+                eprintln!("Refusing QR code: {}", e);
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse {
+                    error: "The code did not decode in its self-check; try a darker color".to_string(),
+                })));
             }
-        });
-        DynamicImage::ImageRgb8(rgb_img)
-    } else {
-        DynamicImage::ImageLuma8(qr_image)
+            let scannability = scan::score(&image, &content);
+            let image = if preview {
+                compose::preview(image).map_err(|e| {
+                    eprintln!("Failed to draw preview band: {}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+                })?
+            } else {
+                image
+            };
+            // The page has the name under the code already, so only the PNG gets the caption
+            let page_image = for_pdf.then(|| image.to_rgb8());
+            let image = match &caption {
+                Some(text) => compose::caption(image, text, &options).map_err(|e| {
+                    eprintln!("Failed to draw caption: {}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+                })?,
+                None => image,
+            };
+
+            let png = encode_qr_png(&image, &options)
+                .map_err(|e| {
+                    eprintln!("Failed to encode QR code: {}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to encode QR code".to_string() }))
+                })?;
+            // The PDF embeds the image itself; alpha is dropped, leaving transparent codes on white
+            Ok((scannability, png, page_image))
+        }).await??
+    };
+
+    // Save to database
+    let vcard_id = match pool.insert_vcard(&data, Some(user.id)).await {
+        Ok(vcard_id) => vcard_id,
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to store vCard".to_string() })));
+        }
     };
 
-    // Encode to PNG
-    let mut buffer = Cursor::new(Vec::new());
-    dynamic_img.write_to(&mut buffer, ImageFormat::Png)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    events.publish(events::TOPIC_GENERATION, serde_json::json!({
+        "vcard_id": vcard_id,
+        "name": format!("{} {}", data.first_name, data.last_name),
+        "username": user.username,
+    }));
+
+    let status = approvals::submit(&pool, &events, vcard_id, &user).await
+        .map_err(|e| {
+            eprintln!("Failed to submit vCard {} for review: {}", vcard_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to store vCard".to_string() }))
+        })?;
+
+    // Promote the draft: it now lives on as a real vcard
+    if let Some(draft_id) = data.draft_id {
+        if let Err(e) = drafts::discard_draft(&pool, user.id, draft_id).await {
+            eprintln!("Failed to discard draft {}: {}", draft_id, e);
+        }
+    }
+
+    let filename = filenames::download_name(&pool, &data, vcard_id, "vcard-qr-code".to_string()).await;
+    match media {
+        negotiation::GenerateMedia::Json => {
+            if let Some(page_image) = page_image {
+                let name = format!("{} {}", data.first_name, data.last_name);
+                let pdf = render_qr_pdf(page_image, name.trim(), data.pdf_size_mm.unwrap_or(PDF_DEFAULT_SIZE_MM));
+                let disposition = format!("attachment; filename=\"{}.pdf\"", filename);
+                return Ok(([(header::CONTENT_TYPE, "application/pdf".to_string()), (header::CONTENT_DISPOSITION, disposition)], pdf).into_response());
+            }
+            if data.format == OutputFormat::Eps {
+                return eps_response(&content, &data.render, &format!("attachment; filename=\"{}.eps\"", filename));
+            }
+            if data.format == OutputFormat::Ascii {
+                return text_response(&content, &data.render, &format!("inline; filename=\"{}.txt\"", filename));
+            }
+        }
+        negotiation::GenerateMedia::Png => {
+            let disposition = format!("inline; filename=\"{}.png\"", filename);
+            return Ok(([(header::CONTENT_TYPE, "image/png".to_string()), (header::CONTENT_DISPOSITION, disposition)], png).into_response());
+        }
+        negotiation::GenerateMedia::Svg => {
+            // Image plugins only apply to rasters; the code itself is the same
+            let svg = render_qr_svg(&content, &data.render).map_err(|e| {
+                eprintln!("Failed to render QR code as SVG: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+            })?;
+            let svg = alt_text::label_svg(&svg, &alt_text::describe(&data));
+            let disposition = format!("inline; filename=\"{}.svg\"", filename);
+            return Ok(([(header::CONTENT_TYPE, "image/svg+xml".to_string()), (header::CONTENT_DISPOSITION, disposition)], svg).into_response());
+        }
+        negotiation::GenerateMedia::Pdf => {
+            let name = format!("{} {}", data.first_name, data.last_name);
+            let page_image = page_image.expect("rendered for the pdf media type");
+            let pdf = render_qr_pdf(page_image, name.trim(), data.pdf_size_mm.unwrap_or(PDF_DEFAULT_SIZE_MM));
+            let disposition = format!("inline; filename=\"{}.pdf\"", filename);
+            return Ok(([(header::CONTENT_TYPE, "application/pdf".to_string()), (header::CONTENT_DISPOSITION, disposition)], pdf).into_response());
+        }
+        negotiation::GenerateMedia::Eps => {
+            return eps_response(&content, &data.render, &format!("inline; filename=\"{}.eps\"", filename));
+        }
+        negotiation::GenerateMedia::VCard => {
+            let disposition = format!("inline; filename=\"{}.vcf\"", filename);
+            return Ok(([(header::CONTENT_TYPE, "text/vcard; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposition)], content).into_response());
+        }
+    }
 
-    let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, buffer.into_inner());
+    let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+    let text = match data.format {
+        OutputFormat::Terminal => Some(render_qr_text(&content, &data.render).map_err(|e| {
+            eprintln!("Failed to render QR code as text: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+        })?),
+        OutputFormat::Png | OutputFormat::Svg | OutputFormat::Pdf | OutputFormat::Eps | OutputFormat::Ascii => None,
+    };
+    let svg = match data.format {
+        OutputFormat::Svg => Some(render_qr_svg(&content, &data.render).map_err(|e| {
+            eprintln!("Failed to render QR code as SVG: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+        })?),
+        OutputFormat::Png | OutputFormat::Terminal | OutputFormat::Pdf | OutputFormat::Eps | OutputFormat::Ascii => None,
+    };
+    let alt = alt_text::describe(&data);
+    let svg = svg.map(|svg| alt_text::label_svg(&svg, &alt));
 
     Ok(Json(QrResponse {
         image: format!("data:image/png;base64,{}", base64_img),
-    }))
+        text,
+        svg,
+        scannability,
+        status,
+        filename: format!("{}.png", filename),
+        warnings: contrast::warnings(&data.render),
+        alt,
+    }).into_response())
+}
+
+// Like the SVG, drawn from the code itself, so image plugins do not apply
+fn eps_response(content: &str, options: &RenderOptions, disposition: &str) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let eps = render_qr_eps(content, options).map_err(|e| {
+        eprintln!("Failed to render QR code as EPS: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+    })?;
+    Ok(([(header::CONTENT_TYPE, "application/postscript".to_string()), (header::CONTENT_DISPOSITION, disposition.to_string())], eps).into_response())
+}
+
+// The code in Unicode block characters, light on dark, which scans straight off a terminal
+fn text_response(content: &str, options: &RenderOptions, disposition: &str) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let text = render_qr_text(content, options).map_err(|e| {
+        eprintln!("Failed to render QR code as text: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+    })?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposition.to_string())], text + "\n").into_response())
+}
+
+// Public instant QR handler (disabled by default, nothing is persisted)
+async fn instant_qr(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(query): Query<InstantQrQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let limiter = state.instant_qr_limiter
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))?;
+
+    if !limiter.check(addr.ip()) {
+        return Err((StatusCode::TOO_MANY_REQUESTS, Json(ErrorResponse { error: "Rate limit exceeded".to_string() })));
+    }
+
+    if query.text.is_empty() || query.text.len() > INSTANT_QR_MAX_TEXT_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("text must be between 1 and {} bytes", INSTANT_QR_MAX_TEXT_LEN),
+        })));
+    }
+
+    let size = query.size.unwrap_or(INSTANT_QR_DEFAULT_SIZE).clamp(MIN_RENDER_SIZE, INSTANT_QR_MAX_SIZE);
+    let options = RenderOptions { size: Some(size), version: query.version, micro: query.micro, mask: query.mask, ..Default::default() };
+    options.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    let _reservation = state.memory.reserve(memory::qr_image_bytes(&options)).await?;
+
+    if matches!(query.format, OutputFormat::Terminal | OutputFormat::Ascii) {
+        let text = render_qr_text(&query.text, &options)
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Text cannot be encoded as a QR code".to_string() })))?;
+        return Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8"), (header::CACHE_CONTROL, "public, max-age=3600")],
+            text,
+        ).into_response());
+    }
+
+    if query.format == OutputFormat::Pdf {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "format pdf is only available from /api/generate".to_string() })));
+    }
+
+    if query.format == OutputFormat::Eps {
+        let eps = render_qr_eps(&query.text, &options)
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Text cannot be encoded as a QR code".to_string() })))?;
+        return Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/postscript"), (header::CACHE_CONTROL, "public, max-age=3600")],
+            eps,
+        ).into_response());
+    }
+
+    if query.format == OutputFormat::Svg {
+        let svg = render_qr_svg(&query.text, &options)
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Text cannot be encoded as a QR code".to_string() })))?;
+        let svg = alt_text::label_svg(&svg, &alt_text::describe_text(&query.text));
+        return Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "image/svg+xml"), (header::CACHE_CONTROL, "public, max-age=3600")],
+            svg,
+        ).into_response());
+    }
+
+    let mut image = render_qr_image(&query.text, &options)
+        .map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Text cannot be encoded as a QR code".to_string() })))?;
+    if let Some(text) = &query.caption {
+        let caption = if text.trim().is_empty() { compose::Caption::Enabled(true) } else { compose::Caption::Text(text.clone()) };
+        caption.validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+        image = compose::caption(image, caption.text().unwrap_or(compose::DEFAULT_CAPTION), &RenderOptions::default())
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to encode image".to_string() })))?;
+    }
+
+    let png = encode_png(&image)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to encode image".to_string() })))?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "image/png"), (header::CACHE_CONTROL, "public, max-age=3600")],
+        png,
+    ).into_response())
 }
 
 // Page handlers
@@ -443,6 +931,17 @@ async fn serve_login() -> Response {
     (StatusCode::OK, [(header::CONTENT_TYPE, "text/html")], html).into_response()
 }
 
+// Kiosk page is public; it authenticates with its device token, not a session
+async fn serve_kiosk() -> Response {
+    let html = include_str!("../static/kiosk.html");
+    (StatusCode::OK, [(header::CONTENT_TYPE, "text/html")], html).into_response()
+}
+
+async fn serve_signage() -> Response {
+    let html = include_str!("../static/signage.html");
+    (StatusCode::OK, [(header::CONTENT_TYPE, "text/html")], html).into_response()
+}
+
 async fn serve_profile(session: Session) -> Response {
     if get_current_user(&session).await.is_none() {
         return Redirect::to("/login").into_response();
@@ -462,25 +961,104 @@ async fn serve_admin(session: Session) -> Response {
     }
 }
 
+// Upgrades any older database in place: every migration not yet recorded runs in order, each
+// in its own transaction together with its bookkeeping row, so a failure leaves the database
+// at the previous step and the next start retries from there. Migrations are never edited
+// once shipped, and each one's checksum is recorded so an edit stops startup; the vcards table,
+// for example, reaches its current shape through 001, 014, 015, 016 and 017 in turn.
+fn migrations() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("001_create_vcards_table", include_str!("../migrations/001_create_vcards_table.sql")),
+        ("002_create_users_table", include_str!("../migrations/002_create_users_table.sql")),
+        ("003_create_drafts_table", include_str!("../migrations/003_create_drafts_table.sql")),
+        ("004_create_tags_tables", include_str!("../migrations/004_create_tags_tables.sql")),
+        ("005_create_jobs_table", include_str!("../migrations/005_create_jobs_table.sql")),
+        ("006_create_uploads_tables", include_str!("../migrations/006_create_uploads_tables.sql")),
+        ("007_add_job_scheduling", include_str!("../migrations/007_add_job_scheduling.sql")),
+        ("008_add_job_leases", include_str!("../migrations/008_add_job_leases.sql")),
+        ("009_create_integrations_table", include_str!("../migrations/009_create_integrations_table.sql")),
+        ("010_create_events_tables", include_str!("../migrations/010_create_events_tables.sql")),
+        ("011_create_checkin_tokens_table", include_str!("../migrations/011_create_checkin_tokens_table.sql")),
+        ("012_create_kiosks_table", include_str!("../migrations/012_create_kiosks_table.sql")),
+        ("013_create_playlists_tables", include_str!("../migrations/013_create_playlists_tables.sql")),
+        ("014_add_vcard_slugs", include_str!("../migrations/014_add_vcard_slugs.sql")),
+        ("015_add_vcard_visibility", include_str!("../migrations/015_add_vcard_visibility.sql")),
+        ("016_add_vcard_render_options", include_str!("../migrations/016_add_vcard_render_options.sql")),
+        ("017_normalize_vcards", include_str!("../migrations/017_normalize_vcards.sql")),
+        ("018_add_vcard_extensions", include_str!("../migrations/018_add_vcard_extensions.sql")),
+        ("019_create_settings_table", include_str!("../migrations/019_create_settings_table.sql")),
+        ("020_add_vcard_review", include_str!("../migrations/020_add_vcard_review.sql")),
+        ("021_create_vcard_revisions_table", include_str!("../migrations/021_create_vcard_revisions_table.sql")),
+        ("022_add_vcard_labels", include_str!("../migrations/022_add_vcard_labels.sql")),
+        ("023_add_vcard_messengers", include_str!("../migrations/023_add_vcard_messengers.sql")),
+        ("024_add_vcard_payment", include_str!("../migrations/024_add_vcard_payment.sql")),
+        ("025_add_vcard_postal_code_country", include_str!("../migrations/025_add_vcard_postal_code_country.sql")),
+        ("026_remove_default_admin", include_str!("../migrations/026_remove_default_admin.sql")),
+        ("027_create_notification_tables", include_str!("../migrations/027_create_notification_tables.sql")),
+        ("028_create_custom_domains", include_str!("../migrations/028_create_custom_domains.sql")),
+        ("029_create_slug_redirects", include_str!("../migrations/029_create_slug_redirects.sql")),
+        ("030_create_link_checks", include_str!("../migrations/030_create_link_checks.sql")),
+        ("031_create_delivery_failures", include_str!("../migrations/031_create_delivery_failures.sql")),
+        ("032_create_qr_styles", include_str!("../migrations/032_create_qr_styles.sql")),
+    ]
+}
+
+// MIGRATE_ON_START=false leaves migrations to `db migrate`, run once before a rolling deploy, and
+// has instances refuse to start while any are pending instead
+fn migrate_on_start() -> bool {
+    std::env::var("MIGRATE_ON_START")
+        .map(|v| v != "false" && v != "0")
+        .unwrap_or(true)
+}
+
+// How long an instance waits for another to finish migrating the same database
+fn migration_lock_timeout() -> Duration {
+    Duration::from_secs(std::env::var("MIGRATION_LOCK_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(300))
+}
+
+// Only one instance applies migrations at a time; the others wait on the lock and then find
+// nothing left to do
 async fn run_migrations(pool: &SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    let lock = db::MigrationLock::acquire(pool, migration_lock_timeout()).await?;
+    let result = apply_migrations(pool, &lock).await;
+    if let Err(e) = lock.release().await {
+        eprintln!("Failed to release the migration lock: {}", e);
+    }
+    result
+}
+
+// A fresh in-memory database with every migration applied
+#[cfg(test)]
+async fn test_pool() -> SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+    run_migrations(&pool).await.unwrap();
+    pool
+}
+
+async fn apply_migrations(pool: &SqlitePool, lock: &db::MigrationLock) -> Result<(), Box<dyn std::error::Error>> {
     // Create migrations table if it doesn't exist
     sqlx::query(
         r#"
         CREATE TABLE IF NOT EXISTS migrations (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             name TEXT NOT NULL UNIQUE,
-            applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            checksum TEXT
         )
         "#,
     )
     .execute(pool)
     .await?;
+    selfcheck::ensure_checksum_column(pool).await?;
 
     // List of migrations to apply
-    let migrations = vec![
-        ("001_create_vcards_table", include_str!("../migrations/001_create_vcards_table.sql")),
-        ("002_create_users_table", include_str!("../migrations/002_create_users_table.sql")),
-    ];
+    let migrations = migrations();
+
+    // Refuse to touch the schema if a migration that already ran has been edited since
+    selfcheck::verify_migrations(pool, &migrations).await?;
 
     for (name, sql) in migrations {
         // Check if migration already applied
@@ -492,15 +1070,21 @@ async fn run_migrations(pool: &SqlitePool) -> Result<(), Box<dyn std::error::Err
         if exists == 0 {
             println!("Running migration: {}", name);
 
+            let mut tx = pool.begin().await?;
+
             // Execute migration SQL
-            sqlx::raw_sql(sql).execute(pool).await?;
+            sqlx::raw_sql(sql).execute(&mut *tx).await?;
 
             // Record migration as applied
-            sqlx::query("INSERT INTO migrations (name) VALUES (?)")
+            sqlx::query("INSERT INTO migrations (name, checksum) VALUES (?, ?)")
                 .bind(name)
-                .execute(pool)
+                .bind(selfcheck::migration_checksum(sql))
+                .execute(&mut *tx)
                 .await?;
 
+            tx.commit().await?;
+            lock.renew().await?;
+
             println!("✓ Migration {} applied", name);
         } else {
             println!("→ Migration {} already applied", name);
@@ -510,7 +1094,34 @@ async fn run_migrations(pool: &SqlitePool) -> Result<(), Box<dyn std::error::Err
     Ok(())
 }
 
-async fn init_database() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+// For instances that leave migrating to `db migrate`: fail unless this build's migrations have all
+// been applied
+async fn check_migrations(pool: &SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    let tracked: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'migrations'")
+        .fetch_one(pool)
+        .await?;
+    let applied: Vec<String> = if tracked > 0 {
+        sqlx::query_scalar("SELECT name FROM migrations").fetch_all(pool).await?
+    } else {
+        Vec::new()
+    };
+
+    let pending: Vec<&str> = migrations().into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !applied.iter().any(|applied| applied == name))
+        .collect();
+    if !pending.is_empty() {
+        return Err(format!(
+            "{} migrations are pending ({}); run `vcard-qr-generator db migrate` first, or start with MIGRATE_ON_START=true",
+            pending.len(), pending.join(", "),
+        ).into());
+    }
+    println!("✓ Database schema is up to date");
+    Ok(())
+}
+
+// `migrate` applies pending migrations; otherwise the schema is only checked
+async fn init_database(migrate: bool) -> Result<(SqlitePool, Option<integrity::IntegrityReport>), Box<dyn std::error::Error>> {
     // Get database path from environment variable or use default
     let db_path = std::env::var("DATABASE_PATH").unwrap_or_else(|_| "vcards.db".to_string());
     let db_url = format!("sqlite://{}", db_path);
@@ -526,16 +1137,70 @@ async fn init_database() -> Result<SqlitePool, Box<dyn std::error::Error>> {
     let pool = SqlitePool::connect(&db_url).await?;
     println!("✓ Connected to database at {}", db_path);
 
-    // Run migrations
-    run_migrations(&pool).await?;
+    // Catch a corrupted file before migrations write to it
+    let integrity = integrity::check_on_startup(&pool, &integrity::IntegrityConfig::from_env()).await?;
+
+    if migrate {
+        run_migrations(&pool).await?;
+    } else {
+        check_migrations(&pool).await?;
+    }
 
-    Ok(pool)
+    Ok((pool, integrity))
 }
 
 #[tokio::main]
 async fn main() {
+    // Before anything generates a card, so the CLI and the server post-process alike
+    let signing_key = SigningKey::from_env().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    plugins::install(plugins::builtin(&signing_key));
+
+    // Admin subcommands run against the database and exit without starting the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args).await);
+    }
+
+    // Fail now on missing or broken assets rather than with a 500 the first time one is needed
+    if let Err(problems) = selfcheck::check_assets() {
+        for problem in &problems {
+            eprintln!("Asset check failed: {}", problem);
+        }
+        eprintln!("Refusing to start with {} asset problems", problems.len());
+        std::process::exit(1);
+    }
+
     // Initialize database
-    let pool = init_database().await.expect("Failed to initialize database");
+    let (pool, integrity) = init_database(migrate_on_start()).await.expect("Failed to initialize database");
+
+    // Live events for admin dashboards
+    let events = EventBus::new();
+
+    // Shared by every request that renders images, REST and gRPC alike
+    let memory = memory::MemoryBudget::new(std::env::var("IMAGE_MEMORY_BUDGET_MB")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|&mib| mib > 0)
+        .unwrap_or(512));
+
+    // Started now so the workers are warm before the first request
+    let render_pool = render_pool::RenderPool::new(std::env::var("RENDER_WORKERS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&workers| workers > 0)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(2, |n| n.get())));
+    let response_cache = cache::ResponseCache::from_env();
+
+    // Start background job worker; bulk re-renders draw on the same pool and cache as requests
+    let renderer = render_pool::Renderer { budget: memory.clone(), pool: render_pool.clone() };
+    tokio::spawn(jobs::run_worker(pool.clone(), events.clone(), renderer, response_cache.clone()));
+
+    // Internal gRPC API, only in builds with the grpc feature
+    #[cfg(feature = "grpc")]
+    tokio::spawn(grpc::serve(pool.clone(), events.clone(), memory.clone(), response_cache.clone()));
 
     // Create session store
     let session_store = SqliteStore::new(pool.clone());
@@ -547,12 +1212,134 @@ async fn main() {
         .and_then(|s| s.parse::<i64>().ok())
         .unwrap_or(24);
 
+    // Signed with the shared secret, so any instance accepts a cookie another one issued
     let session_layer = SessionManagerLayer::new(session_store)
+        .with_signed(signing_key.session_key())
         .with_expiry(Expiry::OnInactivity(tower_sessions::cookie::time::Duration::hours(session_hours)));
 
+    // Instant QR endpoint is off unless explicitly enabled
+    let instant_qr_enabled = std::env::var("INSTANT_QR_ENABLED")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    let instant_qr_rate_limit = std::env::var("INSTANT_QR_RATE_LIMIT")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(10);
+    let instant_qr_limiter = instant_qr_enabled
+        .then(|| Arc::new(RateLimiter::new(instant_qr_rate_limit, Duration::from_secs(60))));
+
+    // Optional read replica for listings and reporting
+    let db = db::DbRouter::connect(pool.clone()).await.expect("Failed to connect to read replica");
+
+    let embed = embed::EmbedConfig::from_env();
+    if let Err(e) = embed.domains.reload(&pool).await {
+        eprintln!("Failed to load custom domains: {}", e);
+    }
+    tokio::spawn(embed.domains.clone().keep_fresh(pool.clone()));
+    let payload_keys = encryption::PayloadKeys::from_env();
+    let integrity_config = integrity::IntegrityConfig::from_env();
+    let server_config = server::ServerConfig::from_env();
+    let outbound_config = outbound::OutboundConfig::from_env();
+    if let Err(e) = outbound::init(&outbound_config) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    // Get bind address from environment variable or use default
+    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let port = std::env::var("PORT")
+        .ok()
+        .and_then(|s| s.parse::<u16>().ok())
+        .unwrap_or(3000);
+    let bind_addr = format!("{}:{}", host, port);
+
+    // Effective configuration, logged below and served to admins
+    let mut config = config::EffectiveConfig::for_build();
+    config.set("HOST", &host);
+    config.set("PORT", port);
+    config.set("DATABASE_PATH", std::env::var("DATABASE_PATH").unwrap_or_else(|_| "vcards.db".to_string()));
+    config.set("DATABASE_REPLICA_PATH", db.replica_path().unwrap_or("(none)"));
+    config.set("MIGRATE_ON_START", migrate_on_start());
+    config.set("MIGRATION_LOCK_TIMEOUT_SECS", migration_lock_timeout().as_secs());
+    config.set("SESSION_EXPIRY_HOURS", session_hours);
+    config.set("PASSWORD_MIN_SCORE", passwords::min_score());
+    config.secret("SMTP_URL", "(none, email off)");
+    config.set("MAIL_FROM", std::env::var("MAIL_FROM").unwrap_or_else(|_| "(default)".to_string()));
+    config.set("HTTPS_PROXY", outbound_config.https_proxy.as_deref().map_or("(none, direct)".to_string(), outbound::redact));
+    config.set("HTTP_PROXY", outbound_config.http_proxy.as_deref().map_or("(none, direct)".to_string(), outbound::redact));
+    config.set("NO_PROXY", outbound_config.no_proxy.as_deref().unwrap_or("(none)"));
+    config.set("OUTBOUND_CA_BUNDLE", outbound_config.ca_bundle.as_deref().unwrap_or("(built-in roots only)"));
+    config.set("OUTBOUND_TIMEOUT_SECS", outbound_config.timeout.as_secs());
+    config.set("INSTANT_QR_ENABLED", instant_qr_enabled);
+    config.set("INSTANT_QR_RATE_LIMIT", instant_qr_rate_limit);
+    config.set("INSTANCE_ID", config::instance_id());
+    config.secret("SECRET_KEY", "(required)");
+    config.set("SIGN_VCARDS", verify::signing_enabled());
+    config.secret("QR_ENCRYPTION_KEYS", "(none, encryption off)");
+    config.set("PUBLIC_URL", embed.public_url.as_deref().unwrap_or("(from Host header)"));
+    config.set("EMBED_FRAME_ANCESTORS", &embed.frame_ancestors);
+    config.set("INTEGRITY_CHECK", integrity_config.mode.as_str());
+    config.set("INTEGRITY_CHECK_REQUIRED", integrity_config.required);
+    config.set("HTTP2_ENABLED", server_config.http2);
+    config.set("HTTP2_MAX_CONCURRENT_STREAMS", server_config.http2_max_concurrent_streams);
+    config.set("HTTP2_KEEPALIVE_INTERVAL_SECS", server_config.http2_keepalive_interval.map_or("(off)".to_string(), |interval| interval.as_secs().to_string()));
+    config.set("HTTP2_KEEPALIVE_TIMEOUT_SECS", server_config.http2_keepalive_timeout.as_secs());
+    config.set("HTTP_KEEPALIVE", server_config.http1_keepalive);
+    config.set("HTTP_KEEPALIVE_TIMEOUT_SECS", server_config.http1_keepalive_timeout.as_secs());
+    config.set("TCP_NODELAY", server_config.tcp_nodelay);
+    config.set("IMAGE_MEMORY_BUDGET_MB", memory.limit_mib());
+    config.set("RENDER_WORKERS", render_pool.workers());
+    config.set("SLOW_QUERY_MS", metrics::slow_query_threshold().as_millis());
+    config.set("RESPONSE_CACHE_MB", response_cache.capacity_mib());
+    config.set("PUBLIC_CACHE_MAX_AGE_SECS", response_cache.max_age_secs());
+    config.set("VCARD_EXTRA_PROPERTIES", std::env::var("VCARD_EXTRA_PROPERTIES").unwrap_or_else(|_| "(none)".to_string()));
+    #[cfg(feature = "grpc")]
+    {
+        config.set("GRPC_ADDR", std::env::var("GRPC_ADDR").unwrap_or_else(|_| grpc::DEFAULT_ADDR.to_string()));
+        config.secret("GRPC_TOKEN", "(none, unauthenticated)");
+    }
+
+    // Checked now, while the pool is still ours, and reported once the server is listening
+    let setup_required = setup::setup_required(&pool).await.unwrap_or(false);
+    let default_admin = setup::default_admin_present(&pool).await;
+
+    let state = AppState {
+        // The GraphQL API only reads, so all of it can use the replica
+        graphql: graphql::build_schema(db.reader().clone()),
+        db,
+        signing_key,
+        payload_keys,
+        embed,
+        config: config.clone(),
+        integrity,
+        pool,
+        events,
+        instant_qr_limiter,
+        memory,
+        render_pool,
+        response_cache,
+    };
+
     let app = Router::new()
         // Public routes
         .route("/login", get(serve_login))
+        .route("/qr", get(instant_qr))
+        .route("/kiosk", get(serve_kiosk))
+        .route("/ready", get(integrity::readiness_handler))
+        .route("/signage/:token", get(serve_signage))
+        .route("/v/:slug", get(slugs::short_link_handler))
+        .route("/embed/:slug", get(embed::embed_handler))
+        .route("/embed/:slug/contact.vcf", get(embed::embed_vcf_handler))
+        .route("/embed/:slug/qr.png", get(embed::embed_qr_handler))
+        .route("/embed/:slug/payment.png", get(payments::payment_qr_handler))
+        .route("/api/verify", post(verify::verify_handler))
+        .route("/api/setup", get(setup::setup_status_handler).post(setup::setup_handler))
+        .route("/api/password-strength", post(passwords::password_strength_handler))
+        .route("/api/domains/check", get(domains::check_domain_handler))
+        .route("/api/decrypt", post(encryption::decrypt_handler))
+        .route("/oembed", get(embed::oembed_handler))
+        .route("/directory", get(directory::directory_handler))
+        .route("/directory/qr.png", get(directory::directory_qr_handler))
         // Protected routes
         .route("/", get(serve_index))
         .route("/profile", get(serve_profile))
@@ -562,29 +1349,104 @@ async fn main() {
         .route("/api/logout", post(logout_handler))
         .route("/api/me", get(me_handler))
         .route("/api/change-password", post(change_password_handler))
+        .route("/api/notifications", get(notifications::get_notifications_handler).put(notifications::update_notifications_handler))
         .route("/api/generate", post(generate_qr))
+        .route("/api/vcards", get(vcards::list_vcards_handler))
+        .route("/api/vcards/bulk", post(bulk::bulk_handler))
+        .route("/api/vcards/:id", get(vcards::get_vcard_handler).put(vcards::update_vcard_handler))
+        .route("/api/vcards/:id/animated", get(animation::animated_qr_handler))
+        .route("/api/vcards/:id/business-card.png", get(business_cards::business_card_handler))
+        .route("/api/vcards/:id/qr", get(downloads::qr_handler))
+        .route("/api/vcards/:id/qr.png", get(downloads::qr_png_handler))
+        .route("/api/vcards/:id/qr.svg", get(downloads::qr_svg_handler))
+        .route("/api/vcards/:id/qr.txt", get(downloads::qr_text_handler))
+        .route("/api/vcards/:id/image-links", post(image_links::issue_link_handler))
+        .route("/api/vcards/:id/clone", post(vcards::clone_vcard_handler))
+        .route("/api/vcards/:id/proof", post(proofs::proof_sheet_handler))
+        .route("/api/vcards/:id/visibility", get(public::get_visibility_handler).put(public::update_visibility_handler))
+        .route("/api/vcards/:id/slug", put(slugs::update_slug_handler))
+        .route("/api/vcards/:id/tags", get(tags::list_vcard_tags_handler))
+        .route("/api/vcards/:id/tags/:tag_id", put(tags::add_vcard_tag_handler).delete(tags::remove_vcard_tag_handler))
+        .route("/api/contact-book", get(contact_book::contact_book_handler))
+        .route("/api/poster", get(posters::poster_handler))
+        .route("/api/tags", get(tags::list_tags_handler).post(tags::create_tag_handler))
+        .route("/api/tags/:id", put(tags::update_tag_handler).delete(tags::delete_tag_handler))
+        .route("/api/styles", get(styles::list_styles_handler).post(styles::create_style_handler))
+        .route("/api/styles/:id", get(styles::get_style_handler).put(styles::update_style_handler).delete(styles::delete_style_handler))
+        .route("/api/imports", post(imports::import_handler))
+        .route("/api/imports/detect", post(imports::detect_columns_handler))
+        .route("/api/uploads", post(uploads::initiate_upload_handler))
+        .route("/api/uploads/:id", get(uploads::get_upload_handler))
+        .route("/api/uploads/:id/parts/:part", put(uploads::upload_part_handler).layer(DefaultBodyLimit::max(uploads::MAX_PART_BYTES)))
+        .route("/api/uploads/:id/complete", post(uploads::complete_upload_handler))
+        .route("/api/jobs", get(jobs::list_jobs_handler))
+        .route("/api/jobs/:id", get(jobs::get_job_handler))
+        .route("/api/jobs/:id/report", get(jobs::job_report_handler))
+        .route("/api/schedules", get(schedules::list_schedules_handler).post(schedules::create_schedule_handler))
+        .route("/api/schedules/:id", delete(schedules::delete_schedule_handler))
+        .route("/api/kiosk/generate", post(kiosks::kiosk_generate_handler))
+        .route("/api/inbound/:token", post(integrations::inbound_handler))
+        .route("/api/signage/:token", get(signage::signage_handler))
+        .route("/api/public/vcards/:slug", get(public::public_vcard_handler))
+        .route("/api/graphql", post(graphql::graphql_handler))
+        .route("/api/events", get(badges::list_events_handler).post(badges::create_event_handler))
+        .route("/api/events/:id", get(badges::get_event_handler))
+        .route("/api/events/:id/attendees", get(badges::list_attendees_handler).post(badges::import_attendees_handler))
+        .route("/api/events/:id/attendees/:attendee_id/badge", get(badges::badge_handler))
+        .route("/api/events/:id/checkin", post(badges::checkin_handler))
+        .route("/api/checkin", post(checkin::checkin_handler))
+        .route("/api/drafts", get(drafts::list_drafts_handler).post(drafts::create_draft_handler))
+        .route("/api/drafts/:id", get(drafts::get_draft_handler).put(drafts::update_draft_handler).delete(drafts::delete_draft_handler))
+        .route("/api/approvals", get(approvals::list_pending_handler))
+        .route("/api/vcards/:id/review", post(approvals::review_handler))
+        .route("/api/vcards/:id/revisions", get(revisions::list_revisions_handler))
+        .route("/api/vcards/:id/revisions/:revision/rollback", post(revisions::rollback_handler))
+        .route("/api/field-rules", get(settings::get_field_rules_handler).put(settings::update_field_rules_handler))
         // Admin API routes
+        .route("/ws", get(events::ws_handler))
+        .route("/api/checkin/tokens", post(checkin::issue_tokens_handler))
+        .route("/api/kiosks", get(kiosks::list_kiosks_handler).post(kiosks::create_kiosk_handler))
+        .route("/api/kiosks/:id", delete(kiosks::delete_kiosk_handler))
+        .route("/api/playlists", get(signage::list_playlists_handler).post(signage::create_playlist_handler))
+        .route("/api/playlists/:id", delete(signage::delete_playlist_handler))
+        .route("/api/integrations", get(integrations::list_integrations_handler).post(integrations::create_integration_handler))
+        .route("/api/integrations/:id", delete(integrations::delete_integration_handler))
+        .route("/api/admin/tables", get(data_browser::list_tables_handler))
+        .route("/api/admin/tables/:name", get(data_browser::table_rows_handler))
+        .route("/api/admin/storage", get(data_browser::storage_handler))
+        .route("/api/admin/metrics", get(metrics::metrics_handler))
+        .route("/api/admin/config", get(config::config_handler))
+        .route("/api/admin/approval", get(approvals::get_approval_settings_handler).put(approvals::update_approval_settings_handler))
+        .route("/api/admin/filename-pattern", get(filenames::get_filename_pattern_handler).put(filenames::update_filename_pattern_handler))
+        .route("/api/admin/directory", get(directory::get_directory_settings_handler).put(directory::update_directory_settings_handler))
+        .route("/api/admin/domains", get(domains::list_domains_handler).post(domains::create_domain_handler))
+        .route("/api/admin/domains/:id", put(domains::update_domain_handler).delete(domains::delete_domain_handler))
+        .route("/api/admin/image-links", get(image_links::get_image_link_policy_handler).put(image_links::update_image_link_policy_handler))
+        .route("/api/admin/link-report", get(linkcheck::link_report_handler))
+        .route("/api/admin/test-email", post(mailer::test_email_handler))
+        .route("/api/admin/deliveries", get(deliveries::list_deliveries_handler))
+        .route("/api/admin/deliveries/retry", post(deliveries::retry_all_deliveries_handler))
+        .route("/api/admin/deliveries/:id/retry", post(deliveries::retry_delivery_handler))
         .route("/api/users", get(get_users_handler).post(create_user_handler))
         .route("/api/users/:id", put(update_user_handler).delete(delete_user_handler))
         .nest_service("/static", ServeDir::new("static"))
+        .layer(axum::middleware::from_fn(metrics::track_route))
         .layer(session_layer)
-        .with_state(pool);
-
-    // Get bind address from environment variable or use default
-    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|s| s.parse::<u16>().ok())
-        .unwrap_or(3000);
-    let bind_addr = format!("{}:{}", host, port);
+        .layer(axum::middleware::from_fn_with_state(state.embed.domains.clone(), domains::route_by_host))
+        .with_state(state);
 
     let listener = tokio::net::TcpListener::bind(&bind_addr)
         .await
         .unwrap();
 
     println!("Server running on http://{}", bind_addr);
-    println!("Default admin credentials: username=admin, password=admin");
-    println!("Database path: {}", std::env::var("DATABASE_PATH").unwrap_or_else(|_| "vcards.db".to_string()));
+    if setup_required {
+        println!("→ No users yet; open http://{}/login to create the first admin", bind_addr);
+    }
+    if default_admin {
+        eprintln!("Warning: the admin account still has the default password; change it from the profile page");
+    }
+    config.log();
 
-    axum::serve(listener, app).await.unwrap();
+    server::serve(listener, app, server_config).await;
 }
@@ -0,0 +1,96 @@
+use axum::{extract::Json, http::StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{ErrorResponse, RenderOptions};
+use vcard_core::render::MAX_LOGO_EDGE;
+use vcard_core::{build_qr_code, rendered_edge};
+
+const MIB: u64 = 1024 * 1024;
+
+// Edge of the largest code, version 40, in modules before its quiet zone; modules are 8 px unless a
+// size or scale is set
+const MAX_CODE_MODULES: u64 = 177;
+const DEFAULT_MODULE_PX: u64 = 8;
+// The grey render plus the RGB(A) copies plugins, scanning and encoding make of it
+const BYTES_PER_PIXEL: u64 = 8;
+
+// How long a render waits for others to finish before giving up with 503
+const RESERVE_WAIT: Duration = Duration::from_secs(10);
+
+// Caps the memory all in-flight image renders may hold together, so a burst of large requests is
+// queued instead of exhausting the host. Counted in MiB
+#[derive(Clone)]
+pub struct MemoryBudget {
+    permits: Arc<Semaphore>,
+    limit_mib: u32,
+}
+
+// Held for as long as the buffers it covers are alive; dropping it returns the memory to the budget
+pub type Reservation = OwnedSemaphorePermit;
+
+impl MemoryBudget {
+    pub fn new(limit_mib: u32) -> Self {
+        MemoryBudget {
+            permits: Arc::new(Semaphore::new(limit_mib as usize)),
+            limit_mib,
+        }
+    }
+
+    pub fn limit_mib(&self) -> u32 {
+        self.limit_mib
+    }
+
+    // What is not reserved by renders in flight right now
+    pub fn available_mib(&self) -> u32 {
+        self.permits.available_permits() as u32
+    }
+
+    // 413 for a request that could never fit, 503 when the budget stays full for too long
+    pub async fn reserve(&self, bytes: u64) -> Result<Reservation, (StatusCode, Json<ErrorResponse>)> {
+        let mib = bytes.div_ceil(MIB).max(1);
+        if mib > self.limit_mib as u64 {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, Json(ErrorResponse {
+                error: "Requested image is too large to render".to_string(),
+            })));
+        }
+
+        match tokio::time::timeout(RESERVE_WAIT, self.permits.clone().acquire_many_owned(mib as u32)).await {
+            Ok(Ok(reservation)) => Ok(reservation),
+            _ => Err((StatusCode::SERVICE_UNAVAILABLE, Json(ErrorResponse {
+                error: "Server is busy rendering images; try again shortly".to_string(),
+            }))),
+        }
+    }
+}
+
+// Upper bound on one rendered code's edge in pixels. A set size is rounded up to whole modules, so
+// the edge overshoots it by less than one pixel per module
+pub fn qr_edge_px(options: &RenderOptions) -> u64 {
+    let max_modules = MAX_CODE_MODULES + 2 * options.quiet_zone_modules() as u64;
+    let for_size = options.size.map(|size| size as u64 + max_modules);
+    let for_scale = options.scale.map(|scale| scale as u64 * max_modules);
+    let for_print = options.print_edge_px().map(|edge| (edge as u64).max(max_modules));
+    for_size.into_iter().chain(for_scale).chain(for_print).max().unwrap_or(max_modules * DEFAULT_MODULE_PX)
+}
+
+// Upper bound on the memory one rendered code takes, working copies and a decoded logo included
+pub fn qr_image_bytes(options: &RenderOptions) -> u64 {
+    let logo = match options.logo {
+        Some(_) => (MAX_LOGO_EDGE as u64).pow(2) * 4,
+        None => 0,
+    };
+    qr_edge_px(options).pow(2) * BYTES_PER_PIXEL + logo
+}
+
+// The edge this content will actually render at, for documents that keep many codes at once and
+// would be overcounted by the bound. Content that cannot be encoded costs nothing; rendering it fails
+pub fn rendered_edge_px(content: &str, options: &RenderOptions) -> u64 {
+    build_qr_code(content, options).map_or(0, |code| rendered_edge(&code, options) as u64)
+}
+
+// What a document holds for each code it embeds: the RGB copy kept until the file is written
+pub fn embedded_qr_bytes(content: &str, options: &RenderOptions) -> u64 {
+    rendered_edge_px(content, options).pow(2) * 3
+}
@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Json, MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::db::DbRouter;
+use crate::memory::MemoryBudget;
+use crate::render_pool::{QueueStats, RenderPool};
+use crate::ErrorResponse;
+
+// Timings of the data layer's queries and the load on the pools behind them, to tell whether
+// SQLite or rendering is what slows requests down under load
+
+const DEFAULT_SLOW_QUERY_MS: u64 = 200;
+
+tokio::task_local! {
+    // The route pattern of the request being handled, e.g. "/api/vcards/:id"
+    static ROUTE: String;
+}
+
+static SLOW_QUERY_THRESHOLD: OnceLock<Duration> = OnceLock::new();
+static QUERIES: Mutex<BTreeMap<&'static str, QueryStats>> = Mutex::new(BTreeMap::new());
+
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct QueryStats {
+    count: u64,
+    errors: u64,
+    slow: u64,
+    total_ms: u64,
+    max_ms: u64,
+}
+
+// SLOW_QUERY_MS, read once; 0 logs every query
+pub fn slow_query_threshold() -> Duration {
+    *SLOW_QUERY_THRESHOLD.get_or_init(|| {
+        Duration::from_millis(std::env::var("SLOW_QUERY_MS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SLOW_QUERY_MS))
+    })
+}
+
+// Middleware that makes the matched route known to the queries a request runs
+pub async fn track_route(request: Request, next: Next) -> Response {
+    let route = request.extensions().get::<MatchedPath>()
+        .map_or_else(|| request.uri().path().to_string(), |path| path.as_str().to_string());
+    ROUTE.scope(route, next.run(request)).await
+}
+
+// Runs one query, recording how long it took (waiting for a connection included) under `name`.
+// Queries over the threshold are logged with the route that ran them
+pub async fn timed<T>(name: &'static str, query: impl Future<Output = Result<T, sqlx::Error>>) -> Result<T, sqlx::Error> {
+    let started = Instant::now();
+    let result = query.await;
+    let elapsed = started.elapsed();
+
+    let slow = elapsed >= slow_query_threshold();
+    if slow {
+        let route = ROUTE.try_with(Clone::clone).unwrap_or_else(|_| "(background)".to_string());
+        eprintln!("Slow query {} took {} ms on {}", name, elapsed.as_millis(), route);
+    }
+
+    let ms = elapsed.as_millis() as u64;
+    let mut queries = QUERIES.lock().unwrap();
+    let stats = queries.entry(name).or_default();
+    stats.count += 1;
+    stats.errors += result.is_err() as u64;
+    stats.slow += slow as u64;
+    stats.total_ms += ms;
+    stats.max_ms = stats.max_ms.max(ms);
+    drop(queries);
+
+    result
+}
+
+#[derive(Serialize)]
+pub struct PoolStats {
+    // Open connections, and how many of them are handing out queries right now
+    size: u32,
+    in_use: u32,
+    max: u32,
+    // Share of the maximum in use; at 100 queries wait for a connection
+    saturation_pct: u32,
+}
+
+impl PoolStats {
+    fn of(pool: &SqlitePool) -> Self {
+        let size = pool.size();
+        let in_use = size.saturating_sub(pool.num_idle() as u32);
+        let max = pool.options().get_max_connections();
+        PoolStats { size, in_use, max, saturation_pct: in_use * 100 / max.max(1) }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MetricsReport {
+    slow_query_ms: u64,
+    primary_pool: PoolStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replica_pool: Option<PoolStats>,
+    queries: BTreeMap<&'static str, QueryStats>,
+    render: QueueStats,
+    image_memory_free_mib: u32,
+    image_memory_limit_mib: u32,
+}
+
+// Counters since the server started
+pub async fn metrics_handler(
+    State(db): State<DbRouter>,
+    State(render_pool): State<RenderPool>,
+    State(budget): State<MemoryBudget>,
+    session: Session,
+) -> Result<Json<MetricsReport>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    Ok(Json(MetricsReport {
+        slow_query_ms: slow_query_threshold().as_millis() as u64,
+        primary_pool: PoolStats::of(db.primary()),
+        replica_pool: db.replica().map(PoolStats::of),
+        queries: QUERIES.lock().unwrap().clone(),
+        render: render_pool.stats(),
+        image_memory_free_mib: budget.available_mib(),
+        image_memory_limit_mib: budget.limit_mib(),
+    }))
+}
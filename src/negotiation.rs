@@ -0,0 +1,60 @@
+use axum::http::{header, HeaderMap};
+
+// What /api/generate and /api/vcards/:id/qr can answer with
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GenerateMedia {
+    // Today's response: the image as a data URL plus scannability and review status
+    Json,
+    Png,
+    Svg,
+    // A printable page with the code and the contact's name
+    Pdf,
+    // Vector artwork for print
+    Eps,
+    // The text the code holds
+    VCard,
+}
+
+impl GenerateMedia {
+    pub const SUPPORTED: &'static str = "application/json, image/png, image/svg+xml, application/pdf, application/postscript, text/vcard";
+
+    fn from_media_range(range: &str) -> Option<Self> {
+        match range {
+            "application/json" | "application/*" | "*/*" => Some(GenerateMedia::Json),
+            "image/png" | "image/*" => Some(GenerateMedia::Png),
+            "image/svg+xml" => Some(GenerateMedia::Svg),
+            "application/pdf" => Some(GenerateMedia::Pdf),
+            "application/postscript" | "application/eps" | "image/x-eps" => Some(GenerateMedia::Eps),
+            "text/vcard" | "text/x-vcard" | "text/directory" => Some(GenerateMedia::VCard),
+            _ => None,
+        }
+    }
+}
+
+// The best supported type in the Accept header by quality, earlier entries winning ties. No header
+// means JSON, as before; None when the client accepts nothing we can produce
+pub fn negotiate(headers: &HeaderMap) -> Option<GenerateMedia> {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+        return Some(GenerateMedia::Json);
+    };
+    if accept.trim().is_empty() {
+        return Some(GenerateMedia::Json);
+    }
+
+    let mut candidates: Vec<(f32, GenerateMedia)> = accept
+        .split(',')
+        .filter_map(|entry| {
+            let mut params = entry.split(';').map(str::trim);
+            let range = params.next()?.to_ascii_lowercase();
+            let quality = params
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let media = GenerateMedia::from_media_range(&range)?;
+            (quality > 0.0).then_some((quality, media))
+        })
+        .collect();
+    // Stable, so equal qualities keep the client's order
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    candidates.first().map(|(_, media)| *media)
+}
@@ -0,0 +1,237 @@
+// Account activity notifications: an email to the user when their password changes or they sign in
+// from an IP address or device (user agent) they have not used before. Each user sets their address
+// and picks which notifications they want. Messages go out through the job queue, so a slow or
+// unreachable mail server never holds up the request, and nothing is queued while email is off
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::jobs;
+use crate::mailer::mailer;
+use crate::ErrorResponse;
+
+pub const JOB_KIND: &str = "email";
+
+// Longer user agents are cut, so one client can't fill the sightings table with huge rows
+const MAX_USER_AGENT_LEN: usize = 256;
+
+// A user without a row gets every notification
+#[derive(Serialize, Deserialize, Clone, Copy, sqlx::FromRow)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub password_changed: bool,
+    // API keys are not issued yet; the choice is kept for when they are
+    pub api_key_created: bool,
+    pub new_login: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        NotificationPreferences { password_changed: true, api_key_created: true, new_login: true }
+    }
+}
+
+impl NotificationPreferences {
+    pub async fn load(pool: &SqlitePool, user_id: i64) -> Result<Self, sqlx::Error> {
+        let preferences: Option<NotificationPreferences> = sqlx::query_as(
+            "SELECT password_changed, api_key_created, new_login FROM notification_preferences WHERE user_id = ?"
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+        Ok(preferences.unwrap_or_default())
+    }
+}
+
+pub enum Activity<'a> {
+    PasswordChanged {
+        // Set by an admin or from the command line rather than by the user
+        by_admin: bool,
+    },
+    NewLogin {
+        ip: &'a str,
+        user_agent: &'a str,
+    },
+}
+
+impl Activity<'_> {
+    fn wanted(&self, preferences: &NotificationPreferences) -> bool {
+        match self {
+            Activity::PasswordChanged { .. } => preferences.password_changed,
+            Activity::NewLogin { .. } => preferences.new_login,
+        }
+    }
+
+    // Subject and body
+    fn message(&self, username: &str) -> (String, String) {
+        let when = chrono::Utc::now().format("%Y-%m-%d %H:%M UTC");
+        let footer = "If this was you, there is nothing to do. If not, change your password and tell your administrator.\n\nYou can turn these emails off on your profile page.";
+        match self {
+            Activity::PasswordChanged { by_admin } => (
+                "Your password was changed".to_string(),
+                format!(
+                    "The password for your vCard QR Generator account {} was changed{} at {}.\n\n{}\n",
+                    username, if *by_admin { " by an administrator" } else { "" }, when, footer,
+                ),
+            ),
+            Activity::NewLogin { ip, user_agent } => (
+                "New sign-in to your account".to_string(),
+                format!(
+                    "Your vCard QR Generator account {} was signed in to at {} from a new IP address or device:\n\n  IP address: {}\n  Device: {}\n\n{}\n",
+                    username, when, ip, if user_agent.is_empty() { "unknown" } else { user_agent }, footer,
+                ),
+            ),
+        }
+    }
+}
+
+// Queues the email for this activity if the user has an address and wants it. Failures are logged,
+// never passed on, so the action that triggered the notification still succeeds
+pub async fn notify(pool: &SqlitePool, user_id: i64, activity: Activity<'_>) {
+    if mailer().is_none() {
+        return;
+    }
+    let result = async {
+        let user: Option<(String, Option<String>)> = sqlx::query_as("SELECT username, email FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+        let Some((username, Some(email))) = user else {
+            return Ok(());
+        };
+        if !activity.wanted(&NotificationPreferences::load(pool, user_id).await?) {
+            return Ok(());
+        }
+        let (subject, body) = activity.message(&username);
+        jobs::enqueue_at(pool, JOB_KIND, &json!({ "to": email, "subject": subject, "body": body }), Some(user_id), None).await?;
+        Ok::<(), sqlx::Error>(())
+    };
+    if let Err(e) = result.await {
+        eprintln!("Failed to queue notification for user {}: {}", user_id, e);
+    }
+}
+
+// Remembers where a user signed in from and notifies them when it is somewhere new. The very first
+// sign-in after this was introduced only starts the record
+pub async fn record_login(pool: &SqlitePool, user_id: i64, ip: &str, user_agent: &str) {
+    let user_agent: String = user_agent.chars().take(MAX_USER_AGENT_LEN).collect();
+    let result = async {
+        let known: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM login_sightings WHERE user_id = ?)")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+        let inserted = sqlx::query("INSERT OR IGNORE INTO login_sightings (user_id, ip, user_agent) VALUES (?, ?, ?)")
+            .bind(user_id)
+            .bind(ip)
+            .bind(&user_agent)
+            .execute(pool)
+            .await?;
+        if inserted.rows_affected() == 0 {
+            sqlx::query("UPDATE login_sightings SET last_seen = CURRENT_TIMESTAMP WHERE user_id = ? AND ip = ? AND user_agent = ?")
+                .bind(user_id)
+                .bind(ip)
+                .bind(&user_agent)
+                .execute(pool)
+                .await?;
+        }
+        Ok::<bool, sqlx::Error>(known && inserted.rows_affected() > 0)
+    };
+    match result.await {
+        Ok(true) => notify(pool, user_id, Activity::NewLogin { ip, user_agent: &user_agent }).await,
+        Ok(false) => {}
+        Err(e) => eprintln!("Failed to record sign-in for user {}: {}", user_id, e),
+    }
+}
+
+// Job handler: sends one queued email
+pub async fn run(payload: Value) -> Result<Value, String> {
+    let field = |name: &str| payload.get(name).and_then(Value::as_str).ok_or(format!("Email job is missing {}", name));
+    let (to, subject, body) = (field("to")?, field("subject")?, field("body")?);
+    let mailer = mailer().ok_or("Email is not configured; set SMTP_URL")?;
+    mailer.send(to, subject, body).await?;
+    Ok(json!({ "to": to }))
+}
+
+#[derive(Serialize)]
+pub struct NotificationSettings {
+    email: Option<String>,
+    // Whether this server can send email at all
+    email_available: bool,
+    #[serde(flatten)]
+    preferences: NotificationPreferences,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateNotificationSettings {
+    // Blank or null removes the address, which stops all notifications
+    email: Option<String>,
+    #[serde(flatten)]
+    preferences: NotificationPreferences,
+}
+
+pub async fn get_notifications_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<NotificationSettings>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let email: Option<String> = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(user.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    let preferences = NotificationPreferences::load(&pool, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(NotificationSettings { email, email_available: mailer().is_some(), preferences }))
+}
+
+pub async fn update_notifications_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<UpdateNotificationSettings>,
+) -> Result<Json<NotificationSettings>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let email = req.email.map(|email| email.trim().to_string()).filter(|email| !email.is_empty());
+    if let Some(email) = &email {
+        email.parse::<lettre::Address>()
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("{} is not an email address", email) })))?;
+    }
+
+    let mut tx = pool.begin().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    sqlx::query("UPDATE users SET email = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&email)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save notification settings".to_string() })))?;
+    sqlx::query(
+        r#"
+        INSERT INTO notification_preferences (user_id, password_changed, api_key_created, new_login) VALUES (?, ?, ?, ?)
+        ON CONFLICT(user_id) DO UPDATE SET password_changed = excluded.password_changed, api_key_created = excluded.api_key_created,
+            new_login = excluded.new_login, updated_at = CURRENT_TIMESTAMP
+        "#
+    )
+    .bind(user.id)
+    .bind(req.preferences.password_changed)
+    .bind(req.preferences.api_key_created)
+    .bind(req.preferences.new_login)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save notification settings".to_string() })))?;
+    tx.commit().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save notification settings".to_string() })))?;
+
+    Ok(Json(NotificationSettings { email, email_available: mailer().is_some(), preferences: req.preferences }))
+}
@@ -0,0 +1,104 @@
+// The one HTTP client for every request this server makes to other hosts (today the link checker),
+// so a deployment behind a corporate proxy configures it once: HTTPS_PROXY / HTTP_PROXY / NO_PROXY
+// as usual (lowercase works too), OUTBOUND_CA_BUNDLE for a PEM file of extra root certificates,
+// e.g. a TLS-inspecting proxy's, and OUTBOUND_TIMEOUT_SECS. Email goes over SMTP, not HTTP, and
+// connects to SMTP_URL directly
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+#[derive(Clone)]
+pub struct OutboundConfig {
+    pub https_proxy: Option<String>,
+    pub http_proxy: Option<String>,
+    // Comma-separated hosts, domains and CIDR ranges reached directly
+    pub no_proxy: Option<String>,
+    pub ca_bundle: Option<String>,
+    pub timeout: Duration,
+}
+
+// NAME, else name, as curl and most tools read proxy variables
+fn env_either(name: &str) -> Option<String> {
+    [name.to_string(), name.to_lowercase()].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+// A proxy URL without its credentials, for the config listing and logs
+pub fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.to_string()
+        }
+        Err(_) => "(invalid URL)".to_string(),
+    }
+}
+
+impl OutboundConfig {
+    pub fn from_env() -> Self {
+        OutboundConfig {
+            https_proxy: env_either("HTTPS_PROXY"),
+            http_proxy: env_either("HTTP_PROXY"),
+            no_proxy: env_either("NO_PROXY"),
+            ca_bundle: std::env::var("OUTBOUND_CA_BUNDLE").ok().map(|path| path.trim().to_string()).filter(|path| !path.is_empty()),
+            timeout: Duration::from_secs(
+                std::env::var("OUTBOUND_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|s| s.trim().parse::<u64>().ok())
+                    .filter(|&secs| secs > 0)
+                    .unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ),
+        }
+    }
+
+    // Only the settings given here apply; reqwest's own reading of the environment is turned off so
+    // the listing in /api/admin/config is the whole story
+    fn build(&self) -> Result<reqwest::Client, String> {
+        let no_proxy = || self.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+        let mut builder = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(self.timeout)
+            .user_agent(concat!("vcard-qr-generator/", env!("CARGO_PKG_VERSION")));
+        if let Some(url) = &self.https_proxy {
+            let proxy = reqwest::Proxy::https(url).map_err(|e| format!("HTTPS_PROXY is invalid: {}", e))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy()));
+        }
+        if let Some(url) = &self.http_proxy {
+            let proxy = reqwest::Proxy::http(url).map_err(|e| format!("HTTP_PROXY is invalid: {}", e))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy()));
+        }
+        if let Some(path) = &self.ca_bundle {
+            let pem = std::fs::read(path).map_err(|e| format!("Cannot read OUTBOUND_CA_BUNDLE {}: {}", path, e))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("OUTBOUND_CA_BUNDLE {} is not a PEM certificate bundle: {}", path, e))?;
+            if certificates.is_empty() {
+                return Err(format!("OUTBOUND_CA_BUNDLE {} holds no certificates", path));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        builder.build().map_err(|e| format!("Failed to build the outbound HTTP client: {}", e))
+    }
+}
+
+// Builds the shared client at startup, so a bad proxy URL or CA bundle stops the server instead of
+// every outbound request going around the proxy or failing later
+pub fn init(config: &OutboundConfig) -> Result<(), String> {
+    let client = config.build()?;
+    let _ = CLIENT.set(client);
+    Ok(())
+}
+
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        OutboundConfig::from_env().build().expect("outbound HTTP settings are checked at startup")
+    })
+}
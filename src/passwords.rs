@@ -0,0 +1,128 @@
+// Password policy: a minimum length and a minimum zxcvbn score, which estimates how many guesses a
+// password takes from common passwords, words, names, keyboard patterns and dates rather than from
+// character classes. The same assessment backs /api/password-strength, so forms can show the score
+// and suggestions while the user types, and every place that sets a password, which refuses a weak
+// one with the same feedback instead of a bare rejection
+
+use axum::{extract::Json, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::ErrorResponse;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+// Scores run from 0 (guessed at once) to 4 (very unlikely to be guessed)
+pub const MAX_SCORE: u8 = 4;
+const DEFAULT_MIN_SCORE: u8 = 2;
+// zxcvbn's cost grows with length, and nothing longer needs scoring to pass
+const MAX_SCORED_LEN: usize = 128;
+
+// PASSWORD_MIN_SCORE, 0 to 4; 2 by default
+pub fn min_score() -> u8 {
+    std::env::var("PASSWORD_MIN_SCORE")
+        .ok()
+        .and_then(|s| s.parse::<u8>().ok())
+        .map(|score| score.min(MAX_SCORE))
+        .unwrap_or(DEFAULT_MIN_SCORE)
+}
+
+#[derive(Serialize)]
+pub struct Strength {
+    pub score: u8,
+    pub min_score: u8,
+    // Whether the policy accepts the password: long enough and scoring at least min_score
+    pub acceptable: bool,
+    // What makes the password weak, e.g. "This is a top-100 common password"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+    // Rough time to crack offline against a slow hash like ours, e.g. "3 hours"
+    pub crack_time: String,
+}
+
+impl Strength {
+    // The assessment as one sentence, for error messages
+    pub fn explain(&self) -> String {
+        let mut message = format!("Password is too weak (score {} of {}, needs {})", self.score, MAX_SCORE, self.min_score);
+        let advice: Vec<&str> = self.warning.iter().chain(&self.suggestions).map(String::as_str).collect();
+        if !advice.is_empty() {
+            message.push_str(": ");
+            message.push_str(&advice.join(" "));
+        }
+        message
+    }
+}
+
+// Scores a password. `user_inputs` are words a guesser would try first for this account, such as
+// the username, which count against the password when it contains them
+pub fn assess(password: &str, user_inputs: &[&str]) -> Strength {
+    let min_score = min_score();
+    let too_short = password.chars().count() < MIN_PASSWORD_LEN;
+    let scored: String = password.chars().take(MAX_SCORED_LEN).collect();
+
+    // zxcvbn refuses an empty password; that is the weakest there is
+    let Ok(entropy) = zxcvbn::zxcvbn(&scored, user_inputs) else {
+        return Strength {
+            score: 0,
+            min_score,
+            acceptable: false,
+            warning: None,
+            suggestions: vec![format!("Use at least {} characters.", MIN_PASSWORD_LEN)],
+            crack_time: "less than a second".to_string(),
+        };
+    };
+
+    let (warning, mut suggestions) = match entropy.feedback() {
+        Some(feedback) => (
+            feedback.warning().map(|warning| warning.to_string()),
+            feedback.suggestions().iter().map(|suggestion| suggestion.to_string()).collect(),
+        ),
+        None => (None, Vec::new()),
+    };
+    if too_short {
+        suggestions.insert(0, format!("Use at least {} characters.", MIN_PASSWORD_LEN));
+    }
+
+    Strength {
+        score: entropy.score(),
+        min_score,
+        acceptable: !too_short && entropy.score() >= min_score,
+        warning,
+        suggestions,
+        crack_time: entropy.crack_times().offline_slow_hashing_1e4_per_second().to_string(),
+    }
+}
+
+// The policy check for the places that set a password; the error carries the feedback
+pub fn check(password: &str, user_inputs: &[&str]) -> Result<(), String> {
+    let strength = assess(password, user_inputs);
+    if strength.acceptable {
+        Ok(())
+    } else {
+        Err(strength.explain())
+    }
+}
+
+// check as a 400, for handlers
+pub fn require_strong(password: &str, user_inputs: &[&str]) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    check(password, user_inputs).map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))
+}
+
+#[derive(Deserialize)]
+pub struct StrengthRequest {
+    password: String,
+    // The account the password is for, when known; the signed-in user's name is always counted
+    username: Option<String>,
+}
+
+// Public, since setup scores the first admin's password before anyone can sign in. It only scores
+// what it is sent and looks nothing up, so it tells a caller nothing about the accounts
+pub async fn password_strength_handler(
+    session: Session,
+    Json(req): Json<StrengthRequest>,
+) -> Json<Strength> {
+    let current = get_current_user(&session).await.map(|user| user.username);
+    let inputs: Vec<&str> = req.username.as_deref().into_iter().chain(current.as_deref()).collect();
+    Json(assess(&req.password, &inputs))
+}
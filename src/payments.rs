@@ -0,0 +1,84 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::cache::ResponseCache;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::public::find_public_vcard;
+use crate::{render_qr_png, ErrorResponse, RenderOptions};
+use vcard_core::symbology::Symbology;
+use vcard_core::ErrorCorrection;
+
+// Limits from the EPC quick response code guidelines (EPC069-12)
+const MAX_NAME_LEN: usize = 70;
+const MAX_TEXT_LEN: usize = 140;
+const MAX_AMOUNT_CENTS: u64 = 99_999_999_999;
+
+#[derive(Deserialize)]
+pub struct PaymentQrQuery {
+    // In euros, e.g. 12.50; the payer fills it in when unset
+    amount: Option<String>,
+    // Remittance text shown to the payee, e.g. an invoice number
+    text: Option<String>,
+}
+
+// Euro amount in cents, with at most two decimals
+fn parse_amount(amount: &str) -> Option<u64> {
+    let (euros, cents) = amount.split_once('.').unwrap_or((amount, ""));
+    if euros.is_empty() || cents.len() > 2 || !euros.chars().chain(cents.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let cents = format!("{:0<2}", cents);
+    let total = euros.parse::<u64>().ok()?.checked_mul(100)?.checked_add(cents.parse().ok()?)?;
+    (1..=MAX_AMOUNT_CENTS).contains(&total).then_some(total)
+}
+
+// A SEPA credit transfer ("Giro-Code") that banking apps prefill from a scan
+fn epc_payload(name: &str, iban: &str, amount_cents: Option<u64>, text: Option<&str>) -> String {
+    let name: String = name.chars().take(MAX_NAME_LEN).collect();
+    let amount = amount_cents.map(|cents| format!("EUR{}.{:02}", cents / 100, cents % 100)).unwrap_or_default();
+    // Version 002 makes the BIC optional; purpose and structured reference stay empty
+    ["BCD", "002", "1", "SCT", "", &name, iban, &amount, "", "", text.unwrap_or_default()].join("\n")
+}
+
+// Public: a "pay me" QR code for the card's IBAN, separate from the contact code since banking
+// apps do not read vCards
+pub async fn payment_qr_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    State(cache): State<ResponseCache>,
+    Path(slug): Path<String>,
+    Query(query): Query<PaymentQrQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = find_public_vcard(&pool, &slug).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))?;
+
+    let iban = vcard.payment.valid_iban()
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "This card has no IBAN".to_string() })))?;
+
+    let amount = match query.amount.as_deref().map(str::trim).filter(|amount| !amount.is_empty()) {
+        Some(amount) => Some(parse_amount(amount)
+            .ok_or((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "amount must be between 0.01 and 999999999.99 euros".to_string() })))?),
+        None => None,
+    };
+    let text = query.text.as_deref().map(str::trim).filter(|text| !text.is_empty());
+    if text.is_some_and(|text| text.chars().count() > MAX_TEXT_LEN) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("text must be at most {} characters", MAX_TEXT_LEN) })));
+    }
+
+    let payload = epc_payload(&format!("{} {}", vcard.first_name, vcard.last_name), &iban, amount, text);
+    let png = cache.get_or_render(&vcard, &format!("payment.png {}", payload), || async {
+        // The guidelines ask for a QR code at level M, whatever symbology the card itself is drawn in
+        let options = RenderOptions { ec_level: Some(ErrorCorrection::M), symbology: Symbology::Qr, ..vcard.render.clone() };
+        let _reservation = budget.reserve(qr_image_bytes(&options)).await?;
+        render_qr_png(&payload, &options)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() })))
+    }).await?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png".to_string()), (header::CACHE_CONTROL, cache.cache_control())], png).into_response())
+}
@@ -0,0 +1,198 @@
+use flate2::{write::ZlibEncoder, Compression};
+use image::RgbImage;
+use std::io::Write;
+
+// Minimal PDF writer: pages of images and Helvetica text, positioned in
+// millimetres from the top-left corner of the page
+
+pub const A4_WIDTH_MM: f32 = 210.0;
+pub const A4_HEIGHT_MM: f32 = 297.0;
+
+const PT_PER_MM: f32 = 72.0 / 25.4;
+
+// Helvetica advance widths (1/1000 em) for ASCII 32..=126, from the standard AFM
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+struct Page {
+    width_mm: f32,
+    height_mm: f32,
+    content: String,
+}
+
+#[derive(Default)]
+pub struct Document {
+    pages: Vec<Page>,
+    images: Vec<RgbImage>,
+}
+
+fn pt(mm: f32) -> f32 {
+    mm * PT_PER_MM
+}
+
+// Helvetica only covers WinAnsi; anything outside Latin-1 becomes '?'
+fn encode_text(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => out.push_str(&format!("\\{:03o}", c as u32)),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+// Width of text set in Helvetica, in millimetres
+pub fn text_width_mm(text: &str, size_pt: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - 32] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f32 / 1000.0 * size_pt / PT_PER_MM
+}
+
+// Shorten text with an ellipsis so it fits within max_width_mm
+pub fn fit_text(text: &str, size_pt: f32, max_width_mm: f32) -> String {
+    if text_width_mm(text, size_pt) <= max_width_mm {
+        return text.to_string();
+    }
+    let mut fitted: String = text.to_string();
+    while !fitted.is_empty() && text_width_mm(&format!("{}...", fitted), size_pt) > max_width_mm {
+        fitted.pop();
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_page(&mut self, width_mm: f32, height_mm: f32) {
+        self.pages.push(Page { width_mm, height_mm, content: String::new() });
+    }
+
+    fn current_page(&mut self) -> &mut Page {
+        if self.pages.is_empty() {
+            self.add_page(A4_WIDTH_MM, A4_HEIGHT_MM);
+        }
+        self.pages.last_mut().expect("a page was just added")
+    }
+
+    // Draw an image into the box whose top-left corner is (x, y)
+    pub fn image(&mut self, image: RgbImage, x_mm: f32, y_mm: f32, width_mm: f32, height_mm: f32) {
+        self.images.push(image);
+        let index = self.images.len();
+        let page = self.current_page();
+        let bottom = page.height_mm - y_mm - height_mm;
+        page.content.push_str(&format!(
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q\n",
+            pt(width_mm), pt(height_mm), pt(x_mm), pt(bottom), index
+        ));
+    }
+
+    // Draw text with its baseline at y
+    pub fn text(&mut self, text: &str, x_mm: f32, y_mm: f32, size_pt: f32) {
+        let page = self.current_page();
+        let baseline = page.height_mm - y_mm;
+        page.content.push_str(&format!(
+            "BT /F1 {:.1} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+            size_pt, pt(x_mm), pt(baseline), encode_text(text)
+        ));
+    }
+
+    pub fn text_centered(&mut self, text: &str, center_x_mm: f32, y_mm: f32, size_pt: f32) {
+        let x = center_x_mm - text_width_mm(text, size_pt) / 2.0;
+        self.text(text, x, y_mm, size_pt);
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).expect("writing to a Vec cannot fail");
+        encoder.finish().expect("writing to a Vec cannot fail")
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.pages.is_empty() {
+            self.add_page(A4_WIDTH_MM, A4_HEIGHT_MM);
+        }
+
+        // Object numbers: catalog, page tree, font, images, then a page and its content per page
+        let font_id = 3;
+        let first_image_id = 4;
+        let first_page_id = first_image_id + self.images.len();
+        let page_ids: Vec<usize> = (0..self.pages.len()).map(|i| first_page_id + i * 2).collect();
+
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_ids.len()).into_bytes());
+
+        objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec());
+
+        for image in &self.images {
+            let data = Self::compress(image.as_raw());
+            let mut object = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+                image.width(), image.height(), data.len()
+            ).into_bytes();
+            object.extend(data);
+            object.extend(b"\nendstream");
+            objects.push(object);
+        }
+
+        let xobjects: Vec<String> = (0..self.images.len())
+            .map(|i| format!("/Im{} {} 0 R", i + 1, first_image_id + i))
+            .collect();
+        let resources = format!("<< /Font << /F1 {} 0 R >> /XObject << {} >> >>", font_id, xobjects.join(" "));
+
+        for (page, &page_id) in self.pages.iter().zip(&page_ids) {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources {} /Contents {} 0 R >>",
+                pt(page.width_mm), pt(page.height_mm), resources, page_id + 1
+            ).into_bytes());
+
+            let data = Self::compress(page.content.as_bytes());
+            let mut object = format!("<< /Length {} /Filter /FlateDecode >>\nstream\n", data.len()).into_bytes();
+            object.extend(data);
+            object.extend(b"\nendstream");
+            objects.push(object);
+        }
+
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+            out.extend(object);
+            out.extend(b"\nendobj\n");
+        }
+
+        let xref_offset = out.len();
+        out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+        for offset in offsets {
+            out.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+        }
+        out.extend(format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1, xref_offset
+        ).into_bytes());
+
+        out
+    }
+}
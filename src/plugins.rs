@@ -0,0 +1,117 @@
+use image::DynamicImage;
+use std::sync::OnceLock;
+use vcard_core::{escape_text, is_extension_name};
+
+use crate::signing::SigningKey;
+use crate::verify::{self, SIGNATURE_PROPERTY};
+use crate::VCardData;
+
+// A post-processing hook for generated cards. Plugins are registered once at startup and run in
+// registration order; each default method passes its input through unchanged
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // The finished vCard text of a card, before it is encoded or downloaded
+    fn transform_vcard(&self, vcard: String, _data: &VCardData) -> String {
+        vcard
+    }
+
+    // A rendered QR code encoding `content`, before it is decoded back and encoded as PNG, so an
+    // image change that breaks scanning is refused like any other unreadable code
+    fn transform_image(&self, image: DynamicImage, _content: &str) -> DynamicImage {
+        image
+    }
+}
+
+static REGISTRY: OnceLock<Vec<Box<dyn Plugin>>> = OnceLock::new();
+
+// Later calls are ignored: the set of plugins is fixed for the life of the process
+pub fn install(plugins: Vec<Box<dyn Plugin>>) {
+    if REGISTRY.set(plugins).is_err() {
+        eprintln!("Plugins are already installed; ignoring a second registration");
+    }
+}
+
+fn installed() -> &'static [Box<dyn Plugin>] {
+    REGISTRY.get().map_or(&[], Vec::as_slice)
+}
+
+pub fn names() -> Vec<&'static str> {
+    installed().iter().map(|plugin| plugin.name()).collect()
+}
+
+pub fn transform_vcard(vcard: String, data: &VCardData) -> String {
+    installed().iter().fold(vcard, |vcard, plugin| plugin.transform_vcard(vcard, data))
+}
+
+pub fn transform_image(image: DynamicImage, content: &str) -> DynamicImage {
+    installed().iter().fold(image, |image, plugin| plugin.transform_image(image, content))
+}
+
+// The plugins this build registers at startup. Add company-specific ones here, before the
+// signature, which must see the finished card
+pub fn builtin(key: &SigningKey) -> Vec<Box<dyn Plugin>> {
+    let mut plugins: Vec<Box<dyn Plugin>> = Vec::new();
+    if let Some(properties) = ExtraProperties::from_env() {
+        plugins.push(Box::new(properties));
+    }
+    if verify::signing_enabled() {
+        plugins.push(Box::new(CardSignature { key: key.for_purpose(verify::SIGNATURE_PURPOSE) }));
+    }
+    plugins
+}
+
+// Appends the same X- properties to every card, from
+// VCARD_EXTRA_PROPERTIES="X-COMPANY-ID=acme;X-SOURCE=badge-desk"
+struct ExtraProperties {
+    lines: Vec<String>,
+}
+
+impl ExtraProperties {
+    fn from_env() -> Option<Self> {
+        let spec = std::env::var("VCARD_EXTRA_PROPERTIES").ok()?;
+        let mut lines = Vec::new();
+        for entry in spec.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+            match entry.split_once('=') {
+                Some((name, value)) if is_extension_name(name.trim()) => {
+                    lines.push(format!("{}:{}", name.trim().to_ascii_uppercase(), escape_text(value.trim())));
+                }
+                _ => eprintln!("Ignoring VCARD_EXTRA_PROPERTIES entry {:?}: expected X-NAME=value", entry),
+            }
+        }
+        (!lines.is_empty()).then_some(ExtraProperties { lines })
+    }
+}
+
+impl Plugin for ExtraProperties {
+    fn name(&self) -> &'static str {
+        "extra-properties"
+    }
+
+    fn transform_vcard(&self, vcard: String, _data: &VCardData) -> String {
+        match vcard.strip_suffix("END:VCARD") {
+            Some(body) => format!("{}{}\nEND:VCARD", body, self.lines.join("\n")),
+            None => vcard,
+        }
+    }
+}
+
+// Signs every card with a key derived from the instance key (SIGN_VCARDS=true) so recipients can
+// check it at /api/verify
+struct CardSignature {
+    key: SigningKey,
+}
+
+impl Plugin for CardSignature {
+    fn name(&self) -> &'static str {
+        "signature"
+    }
+
+    fn transform_vcard(&self, vcard: String, _data: &VCardData) -> String {
+        let signature = self.key.sign(verify::canonical_vcard(&vcard).as_bytes());
+        match vcard.strip_suffix("END:VCARD") {
+            Some(body) => format!("{}{}:{}\nEND:VCARD", body, SIGNATURE_PROPERTY, signature),
+            None => vcard,
+        }
+    }
+}
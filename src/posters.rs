@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::contact_book::printable_qr;
+use crate::db::DbRouter;
+use crate::embed::{encode_query_value, EmbedConfig};
+use crate::memory::{embedded_qr_bytes, qr_image_bytes, MemoryBudget};
+use crate::pdf;
+use crate::render_pool::{Priority, RenderPool};
+use crate::vcards;
+use crate::ErrorResponse;
+
+// One A3 landscape sheet, in millimetres
+const PAGE_WIDTH_MM: f32 = 420.0;
+const PAGE_HEIGHT_MM: f32 = 297.0;
+const MARGIN_MM: f32 = 15.0;
+const HEADER_MM: f32 = 20.0;
+// Below the QR in each cell, for the name
+const CAPTION_MM: f32 = 10.0;
+const GUTTER_MM: f32 = 6.0;
+
+// Smaller codes stop scanning reliably from a step or two away from the board
+const MIN_QR_MM: f32 = 25.0;
+const MAX_QR_MM: f32 = 90.0;
+// 12 columns by 6 rows, the most that fit at MIN_QR_MM
+const MAX_ENTRIES: i64 = 72;
+const MAX_TITLE_LEN: usize = 100;
+
+const TITLE_PT: f32 = 24.0;
+const NAME_PT: f32 = 11.0;
+
+#[derive(Deserialize)]
+pub struct PosterQuery {
+    tag: Option<String>,
+    // Matches the card's company field, ignoring case
+    company: Option<String>,
+    title: Option<String>,
+}
+
+// The column count that gives the biggest codes for this many entries, with that size
+fn grid(entries: usize) -> (usize, f32) {
+    let width = PAGE_WIDTH_MM - 2.0 * MARGIN_MM;
+    let height = PAGE_HEIGHT_MM - 2.0 * MARGIN_MM - HEADER_MM;
+    (1..=entries.max(1))
+        .map(|columns| {
+            let rows = entries.div_ceil(columns).max(1);
+            let cell_width = width / columns as f32 - GUTTER_MM;
+            let cell_height = height / rows as f32 - GUTTER_MM - CAPTION_MM;
+            (columns, cell_width.min(cell_height).min(MAX_QR_MM))
+        })
+        .fold((1, 0.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+}
+
+// A one-page notice board poster: every selected person's share-link QR code with their name. The
+// codes point at the public card page rather than holding the card, so they stay current after edits
+pub async fn poster_handler(
+    State(db): State<DbRouter>,
+    State(config): State<EmbedConfig>,
+    State(budget): State<MemoryBudget>,
+    State(render_pool): State<RenderPool>,
+    session: Session,
+    headers: HeaderMap,
+    Query(query): Query<PosterQuery>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if query.title.as_ref().is_some_and(|title| title.len() > MAX_TITLE_LEN) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Title must be at most {} characters", MAX_TITLE_LEN),
+        })));
+    }
+
+    let tag = query.tag.as_deref().filter(|tag| !tag.is_empty());
+    let company = query.company.as_deref().filter(|company| !company.is_empty());
+
+    // Only approved cards have a public page to link to
+    let entries = vcards::list_vcards_by_name(db.reader(), tag, company, MAX_ENTRIES + 1).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    if entries.is_empty() {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "No vCards match".to_string() })));
+    }
+    let (columns, qr_mm) = grid(entries.len());
+    if entries.len() as i64 > MAX_ENTRIES || qr_mm < MIN_QR_MM {
+        // Splitting across sheets would defeat the point of one board
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Too many vCards match to fit one poster (at most {}); narrow the tag or company", MAX_ENTRIES),
+        })));
+    }
+
+    let title = query.title.as_deref().map(str::trim).filter(|title| !title.is_empty())
+        .or(company)
+        .or(tag)
+        .unwrap_or("Our team");
+    let title = pdf::fit_text(title, TITLE_PT, PAGE_WIDTH_MM - 2.0 * MARGIN_MM);
+
+    let rows = entries.len().div_ceil(columns);
+    let cell_width = (PAGE_WIDTH_MM - 2.0 * MARGIN_MM) / columns as f32;
+    let cell_height = (PAGE_HEIGHT_MM - 2.0 * MARGIN_MM - HEADER_MM) / rows as f32;
+    let base_url = config.base_url(&headers);
+    let links: Vec<String> = entries.iter()
+        .map(|vcard| format!("{}/embed/{}", base_url, encode_query_value(&vcard.slug)))
+        .collect();
+
+    // The document keeps every code until it is written out, next to the one being rendered
+    let bytes = entries.iter().zip(&links).map(|(vcard, link)| embedded_qr_bytes(link, &vcard.render)).sum::<u64>()
+        + entries.iter().map(|vcard| qr_image_bytes(&vcard.render)).max().unwrap_or_default();
+    let _reservation = budget.reserve(bytes).await?;
+
+    let mut doc = pdf::Document::new();
+    doc.add_page(PAGE_WIDTH_MM, PAGE_HEIGHT_MM);
+    doc.text_centered(&title, PAGE_WIDTH_MM / 2.0, MARGIN_MM + 10.0, TITLE_PT);
+
+    for (index, (vcard, link)) in entries.iter().zip(&links).enumerate() {
+        let center_x = MARGIN_MM + (index % columns) as f32 * cell_width + cell_width / 2.0;
+        let y = MARGIN_MM + HEADER_MM + (index / columns) as f32 * cell_height;
+
+        // One code at a time, so interactive renders can go in between
+        let (link, render) = (link.clone(), vcard.render.clone());
+        let image = render_pool.run(Priority::Batch, move || printable_qr(&link, &render)).await?
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render poster".to_string() })))?;
+        let name = pdf::fit_text(&format!("{} {}", vcard.first_name, vcard.last_name), NAME_PT, cell_width - 2.0);
+
+        doc.image(image.to_rgb8(), center_x - qr_mm / 2.0, y, qr_mm, qr_mm);
+        doc.text_centered(&name, center_x, y + qr_mm + 5.0, NAME_PT);
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/pdf".to_string()), (header::CONTENT_DISPOSITION, "attachment; filename=\"team-poster.pdf\"".to_string())],
+        doc.finish(),
+    ).into_response())
+}
@@ -0,0 +1,221 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use image::{imageops, DynamicImage, Rgb, RgbImage};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::approvals::ReviewStatus;
+use crate::auth::get_current_user;
+use crate::compose::{self, draw_text_centered, fit_text_px};
+use crate::filenames;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::pdf::{self, A4_HEIGHT_MM, A4_WIDTH_MM};
+use crate::scan;
+use crate::vcards::fetch_vcard;
+use crate::{encode_png, generate_vcard, render_qr_image, ErrorResponse, RenderOptions, VCardData};
+use vcard_core::is_hex_color;
+
+const MAX_VARIANTS: usize = 12;
+const MAX_LABEL_LEN: usize = 60;
+
+// A4 portrait grid, in millimetres
+const COLUMNS: usize = 3;
+const MARGIN_MM: f32 = 15.0;
+const HEADER_MM: f32 = 20.0;
+const CELL_WIDTH_MM: f32 = (A4_WIDTH_MM - 2.0 * MARGIN_MM) / COLUMNS as f32;
+const CELL_HEIGHT_MM: f32 = (A4_HEIGHT_MM - 2.0 * MARGIN_MM - HEADER_MM) / 4.0;
+const QR_SIZE_MM: f32 = 45.0;
+
+const TITLE_PT: f32 = 16.0;
+const LABEL_PT: f32 = 10.0;
+const DETAIL_PT: f32 = 8.0;
+
+// PNG sheets are rendered at print resolution
+const PNG_DPI: f32 = 150.0;
+
+// Dark grey text over the page
+const TEXT_COLOR: Rgb<u8> = Rgb([34, 34, 34]);
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofFormat {
+    #[default]
+    Png,
+    Pdf,
+}
+
+#[derive(Deserialize)]
+pub struct ProofVariant {
+    // Hex color such as "#1a73e8"; black when omitted
+    color: Option<String>,
+    label: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ProofSheetRequest {
+    variants: Vec<ProofVariant>,
+    #[serde(default)]
+    format: ProofFormat,
+}
+
+// A variant ready to place on the sheet
+struct ProofCell {
+    image: RgbImage,
+    label: String,
+    detail: String,
+}
+
+// Top-left corner of a grid cell
+fn cell_origin(index: usize) -> (f32, f32) {
+    let column = index % COLUMNS;
+    let row = index / COLUMNS;
+    (
+        MARGIN_MM + column as f32 * CELL_WIDTH_MM,
+        MARGIN_MM + HEADER_MM + row as f32 * CELL_HEIGHT_MM,
+    )
+}
+
+fn mm_to_px(mm: f32) -> f32 {
+    mm / 25.4 * PNG_DPI
+}
+
+fn pt_to_px(size_pt: f32) -> f32 {
+    size_pt / 72.0 * PNG_DPI
+}
+
+fn render_png_sheet(title: &str, cells: &[ProofCell]) -> Result<Vec<u8>, String> {
+    let font = compose::font()?;
+    let mut page = RgbImage::from_pixel(
+        mm_to_px(A4_WIDTH_MM).round() as u32,
+        mm_to_px(A4_HEIGHT_MM).round() as u32,
+        Rgb([255, 255, 255]),
+    );
+
+    let title = fit_text_px(&font, title, pt_to_px(TITLE_PT), mm_to_px(A4_WIDTH_MM - 2.0 * MARGIN_MM));
+    draw_text_centered(&mut page, &font, &title, mm_to_px(A4_WIDTH_MM / 2.0), mm_to_px(MARGIN_MM + 8.0), pt_to_px(TITLE_PT), TEXT_COLOR);
+
+    let qr_px = mm_to_px(QR_SIZE_MM).round() as u32;
+    for (index, cell) in cells.iter().enumerate() {
+        let (x, y) = cell_origin(index);
+        let center_x = x + CELL_WIDTH_MM / 2.0;
+
+        // Nearest-neighbour keeps module edges sharp
+        let qr = imageops::resize(&cell.image, qr_px, qr_px, imageops::FilterType::Nearest);
+        let qr_x = mm_to_px(center_x - QR_SIZE_MM / 2.0).round() as i64;
+        imageops::overlay(&mut page, &qr, qr_x, mm_to_px(y).round() as i64);
+
+        let label = fit_text_px(&font, &cell.label, pt_to_px(LABEL_PT), mm_to_px(CELL_WIDTH_MM - 4.0));
+        draw_text_centered(&mut page, &font, &label, mm_to_px(center_x), mm_to_px(y + QR_SIZE_MM + 5.0), pt_to_px(LABEL_PT), TEXT_COLOR);
+        draw_text_centered(&mut page, &font, &cell.detail, mm_to_px(center_x), mm_to_px(y + QR_SIZE_MM + 9.0), pt_to_px(DETAIL_PT), TEXT_COLOR);
+    }
+
+    encode_png(&DynamicImage::ImageRgb8(page)).map_err(|e| e.to_string())
+}
+
+fn render_pdf_sheet(title: &str, cells: Vec<ProofCell>) -> Vec<u8> {
+    let mut doc = pdf::Document::new();
+    doc.add_page(A4_WIDTH_MM, A4_HEIGHT_MM);
+
+    let title = pdf::fit_text(title, TITLE_PT, A4_WIDTH_MM - 2.0 * MARGIN_MM);
+    doc.text_centered(&title, A4_WIDTH_MM / 2.0, MARGIN_MM + 8.0, TITLE_PT);
+
+    for (index, cell) in cells.into_iter().enumerate() {
+        let (x, y) = cell_origin(index);
+        let center_x = x + CELL_WIDTH_MM / 2.0;
+
+        doc.image(cell.image, center_x - QR_SIZE_MM / 2.0, y, QR_SIZE_MM, QR_SIZE_MM);
+
+        let label = pdf::fit_text(&cell.label, LABEL_PT, CELL_WIDTH_MM - 4.0);
+        doc.text_centered(&label, center_x, y + QR_SIZE_MM + 5.0, LABEL_PT);
+        doc.text_centered(&cell.detail, center_x, y + QR_SIZE_MM + 9.0, DETAIL_PT);
+    }
+
+    doc.finish()
+}
+
+// Render one stored card in several colors on a printable A4 sheet
+pub async fn proof_sheet_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Json(req): Json<ProofSheetRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if req.variants.is_empty() || req.variants.len() > MAX_VARIANTS {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Between 1 and {} variants are required", MAX_VARIANTS),
+        })));
+    }
+
+    for variant in &req.variants {
+        if let Some(color) = &variant.color {
+            if !is_hex_color(color) {
+                return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("Invalid color: {}", color) })));
+            }
+        }
+        if variant.label.as_ref().is_some_and(|label| label.len() > MAX_LABEL_LEN) {
+            return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+                error: format!("Labels must be at most {} characters", MAX_LABEL_LEN),
+            })));
+        }
+    }
+
+    let vcard = fetch_vcard(&pool, vcard_id).await?;
+    if vcard.status != ReviewStatus::Approved {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "vCard is awaiting approval".to_string() })));
+    }
+    let data: VCardData = vcard.into();
+    let content = generate_vcard(&data);
+
+    // Every variant stays in memory until the sheet is drawn, on an RGB page for PNG sheets
+    let page_px = (mm_to_px(A4_WIDTH_MM) * mm_to_px(A4_HEIGHT_MM)) as u64;
+    let _reservation = budget.reserve(req.variants.len() as u64 * qr_image_bytes(&data.render) + page_px * 3).await?;
+
+    let mut cells = Vec::with_capacity(req.variants.len());
+    for (index, variant) in req.variants.into_iter().enumerate() {
+        // Variants swap the color only, in place of any gradient; the card's other stored options still apply
+        let options = RenderOptions { color: variant.color.clone(), gradient: None, ..data.render.clone() };
+        let image = render_qr_image(&content, &options)
+            .map_err(|_| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error: "Contact cannot be encoded as a QR code".to_string() })))?;
+
+        // Failing variants stay on the sheet, marked, since comparing them is the point of a proof
+        let report = scan::score(&image, &content);
+        let color = format!("#{}", variant.color.as_deref().unwrap_or("000000").trim_start_matches('#'));
+        let detail = match report.min_size_mm {
+            Some(min_size_mm) => format!("{} - scan {}%, down to {} mm", color, report.score, min_size_mm),
+            None => format!("{} - does not decode", color),
+        };
+        let label = variant.label
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .unwrap_or_else(|| format!("Variant {}", index + 1));
+
+        cells.push(ProofCell { image: image.to_rgb8(), label, detail });
+    }
+
+    let title = format!("Proof sheet - {} {}", data.first_name, data.last_name);
+
+    let (content_type, extension, body) = match req.format {
+        ProofFormat::Png => {
+            let png = render_png_sheet(&title, &cells)
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render proof sheet".to_string() })))?;
+            ("image/png", "png", png)
+        }
+        ProofFormat::Pdf => ("application/pdf", "pdf", render_pdf_sheet(&title, cells)),
+    };
+
+    let filename = filenames::download_name(&pool, &data, vcard_id, format!("vcard-{}", vcard_id)).await;
+    let disposition = format!("attachment; filename=\"{}-proof.{}\"", filename, extension);
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    ).into_response())
+}
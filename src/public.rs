@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::alt_text::{self, AltText};
+use crate::auth::get_current_user;
+use crate::cache::ResponseCache;
+use crate::metrics;
+use crate::repo::VcardRepo;
+use crate::approvals::ReviewStatus;
+use crate::vcards::{fetch_vcard, VCard};
+use crate::ErrorResponse;
+use vcard_core::MessengerLink;
+
+// Contact fields that can be withheld from public views; the name is always shown
+pub const HIDEABLE_FIELDS: &[&str] = &["mobile", "work", "email", "company", "role", "street", "city", "state", "postal_code", "country", "website", "whatsapp", "telegram", "signal", "paypal", "iban"];
+
+#[derive(Deserialize, Serialize)]
+pub struct Visibility {
+    hidden_fields: Vec<String>,
+}
+
+// What anyone holding a card's slug may see
+#[derive(Serialize)]
+pub struct PublicVCard {
+    slug: String,
+    first_name: String,
+    last_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mobile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    work: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    company: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    street: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postal_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    country: Option<String>,
+    // The visible address parts laid out for the card's country
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    address: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    website: Option<String>,
+    // Deep links for the card's messenger handles
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    messengers: Vec<MessengerLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paypal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iban: Option<String>,
+    color: Option<String>,
+    updated_at: String,
+    // alt_text and description of the card's QR code, for landing pages that show it
+    #[serde(flatten)]
+    alt: AltText,
+}
+
+// Empty values are dropped too, so public views never show blank fields
+fn visible(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+// The card's address laid out for its country, one line per entry
+pub fn address_lines(vcard: &VCard) -> Vec<String> {
+    vcard_core::address_lines(&vcard_core::Contact {
+        street: vcard.street.as_deref(),
+        city: vcard.city.as_deref(),
+        state: vcard.state.as_deref(),
+        postal_code: vcard.postal_code.as_deref(),
+        country: vcard.country.as_deref(),
+        ..Default::default()
+    })
+}
+
+impl From<VCard> for PublicVCard {
+    fn from(vcard: VCard) -> Self {
+        let address = address_lines(&vcard);
+        let alt = alt_text::describe(&vcard.clone().into());
+        PublicVCard {
+            slug: vcard.slug,
+            first_name: vcard.first_name,
+            last_name: vcard.last_name,
+            mobile: visible(vcard.mobile),
+            work: visible(vcard.work),
+            email: visible(vcard.email),
+            company: visible(vcard.company),
+            role: visible(vcard.role),
+            street: visible(vcard.street),
+            city: visible(vcard.city),
+            state: visible(vcard.state),
+            postal_code: visible(vcard.postal_code),
+            country: visible(vcard.country),
+            address,
+            website: visible(vcard.website),
+            messengers: vcard.messengers.links(),
+            paypal: vcard.payment.paypal_url(),
+            iban: vcard.payment.valid_iban(),
+            color: vcard.render.color,
+            updated_at: vcard.updated_at,
+            alt,
+        }
+    }
+}
+
+async fn hidden_fields(pool: &SqlitePool, vcard_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    let hidden: Option<String> = metrics::timed("hidden_fields", sqlx::query_scalar("SELECT hidden_fields FROM vcards WHERE id = ?")
+        .bind(vcard_id)
+        .fetch_optional(pool))
+        .await?;
+    Ok(hidden.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default())
+}
+
+fn redact(vcard: &mut VCard, hidden: &[String]) {
+    for field in hidden {
+        let value = match field.as_str() {
+            "mobile" => &mut vcard.mobile,
+            "work" => &mut vcard.work,
+            "email" => &mut vcard.email,
+            "company" => &mut vcard.company,
+            "role" => &mut vcard.role,
+            "street" => &mut vcard.street,
+            "city" => &mut vcard.city,
+            "state" => &mut vcard.state,
+            "postal_code" => &mut vcard.postal_code,
+            "country" => &mut vcard.country,
+            "website" => &mut vcard.website,
+            "whatsapp" => &mut vcard.messengers.whatsapp,
+            "telegram" => &mut vcard.messengers.telegram,
+            "signal" => &mut vcard.messengers.signal,
+            "paypal" => &mut vcard.payment.paypal,
+            "iban" => &mut vcard.payment.iban,
+            _ => continue,
+        };
+        *value = None;
+    }
+}
+
+// An approved card by slug with its hidden fields already removed; use this for anything served
+// publicly. Cards still in review have no public links yet
+pub async fn find_public_vcard(pool: &SqlitePool, slug: &str) -> Result<Option<VCard>, sqlx::Error> {
+    let Some(mut vcard) = pool.find_vcard_by_slug(slug).await?.filter(|v| v.status == ReviewStatus::Approved) else {
+        return Ok(None);
+    };
+    redact_hidden_fields(pool, &mut vcard).await?;
+    Ok(Some(vcard))
+}
+
+// Clears the fields the card's owner hid from public views
+pub async fn redact_hidden_fields(pool: &SqlitePool, vcard: &mut VCard) -> Result<(), sqlx::Error> {
+    let hidden = hidden_fields(pool, vcard.id).await?;
+    redact(vcard, &hidden);
+    Ok(())
+}
+
+pub async fn get_visibility_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+) -> Result<Json<Visibility>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    fetch_vcard(&pool, vcard_id).await?;
+
+    let hidden_fields = hidden_fields(&pool, vcard_id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(Visibility { hidden_fields }))
+}
+
+pub async fn update_visibility_handler(
+    State(pool): State<SqlitePool>,
+    State(cache): State<ResponseCache>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Json(mut req): Json<Visibility>,
+) -> Result<Json<Visibility>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if let Some(unknown) = req.hidden_fields.iter().find(|field| !HIDEABLE_FIELDS.contains(&field.as_str())) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("'{}' cannot be hidden; expected one of {}", unknown, HIDEABLE_FIELDS.join(", ")),
+        })));
+    }
+    req.hidden_fields.sort();
+    req.hidden_fields.dedup();
+
+    let hidden = serde_json::to_string(&req.hidden_fields)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update visibility".to_string() })))?;
+
+    let result = sqlx::query("UPDATE vcards SET hidden_fields = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&hidden)
+        .bind(vcard_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update visibility".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "vCard not found".to_string() })));
+    }
+    cache.invalidate(vcard_id);
+
+    Ok(Json(req))
+}
+
+// Public: landing page data for custom frontends; readable from any origin
+pub async fn public_vcard_handler(
+    State(pool): State<SqlitePool>,
+    State(cache): State<ResponseCache>,
+    Path(slug): Path<String>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = find_public_vcard(&pool, &slug).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))?;
+
+    Ok((
+        [(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".to_string()), (header::CACHE_CONTROL, cache.cache_control())],
+        Json(PublicVCard::from(vcard)),
+    ).into_response())
+}
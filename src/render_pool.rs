@@ -0,0 +1,166 @@
+use axum::{extract::Json, http::StatusCode};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex};
+use tokio::sync::oneshot;
+
+use crate::memory::MemoryBudget;
+use crate::{render_qr_png, ErrorResponse, RenderOptions};
+
+// Which queue a render waits in. Workers always take interactive renders first
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    // Someone waiting on a single code: generating in the UI, previews, embeds, kiosks
+    Interactive,
+    // One code of a document or batch holding many, submitted one at a time so interactive
+    // renders can go in between
+    Batch,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Queues {
+    interactive: VecDeque<Job>,
+    batch: VecDeque<Job>,
+    batch_running: usize,
+    running: usize,
+}
+
+// A snapshot of the pool's load, for the metrics endpoint
+#[derive(Serialize)]
+pub struct QueueStats {
+    workers: usize,
+    busy: usize,
+    queued_interactive: usize,
+    queued_batch: usize,
+}
+
+struct Shared {
+    queues: Mutex<Queues>,
+    ready: Condvar,
+    // Batch renders may occupy this many workers at once
+    batch_limit: usize,
+}
+
+// Renders run on their own threads, started with the server, instead of on the async runtime's,
+// so a batch of large codes neither stalls unrelated requests nor delays a user's click
+#[derive(Clone)]
+pub struct RenderPool {
+    shared: Arc<Shared>,
+    workers: usize,
+}
+
+// What a handler that draws on request needs: the memory budget to reserve from and the pool to
+// run on, taken together as one piece of state
+#[derive(Clone)]
+pub struct Renderer {
+    pub budget: MemoryBudget,
+    pub pool: RenderPool,
+}
+
+impl RenderPool {
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        let shared = Arc::new(Shared {
+            queues: Mutex::new(Queues { interactive: VecDeque::new(), batch: VecDeque::new(), batch_running: 0, running: 0 }),
+            ready: Condvar::new(),
+            // One worker is kept free of batch renders, unless it is the only one
+            batch_limit: (workers - 1).max(1),
+        });
+        for index in 0..workers {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name(format!("render-{}", index))
+                .spawn(move || work(&shared))
+                .expect("Failed to start render worker");
+        }
+        RenderPool { shared, workers }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let queues = self.shared.queues.lock().unwrap();
+        QueueStats {
+            workers: self.workers,
+            busy: queues.running,
+            queued_interactive: queues.interactive.len(),
+            queued_batch: queues.batch.len(),
+        }
+    }
+
+    // Runs `job` on a worker and waits for its result. A job that panics fails with 500, the
+    // same as any other render failure
+    pub async fn run<T, F>(&self, priority: Priority, job: F) -> Result<T, (StatusCode, Json<ErrorResponse>)>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = sender.send(job());
+        });
+        {
+            let mut queues = self.shared.queues.lock().unwrap();
+            match priority {
+                Priority::Interactive => queues.interactive.push_back(job),
+                Priority::Batch => queues.batch.push_back(job),
+            }
+        }
+        self.shared.ready.notify_one();
+
+        receiver.await.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
+            error: "Failed to render QR code".to_string(),
+        })))
+    }
+
+    // A finished, self-checked PNG, as `render_qr_png` makes it
+    pub async fn png(&self, priority: Priority, content: String, options: RenderOptions) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+        self.run(priority, move || render_qr_png(&content, &options)).await?
+            .map_err(|e| {
+                eprintln!("Failed to render QR code: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to render QR code".to_string() }))
+            })
+    }
+}
+
+fn work(shared: &Shared) {
+    // Pre-warmed: the first real render on this thread should not pay for page faults and
+    // allocator growth
+    let _ = render_qr_png("warm-up", &RenderOptions::default());
+
+    loop {
+        let (job, batch) = {
+            let mut queues = shared.queues.lock().unwrap();
+            let taken = loop {
+                if let Some(job) = queues.interactive.pop_front() {
+                    break (job, false);
+                }
+                if queues.batch_running < shared.batch_limit {
+                    if let Some(job) = queues.batch.pop_front() {
+                        queues.batch_running += 1;
+                        break (job, true);
+                    }
+                }
+                queues = shared.ready.wait(queues).unwrap();
+            };
+            queues.running += 1;
+            taken
+        };
+
+        // The job's sender is dropped on a panic, which fails the waiting request
+        let _ = std::panic::catch_unwind(AssertUnwindSafe(job));
+
+        let mut queues = shared.queues.lock().unwrap();
+        queues.running -= 1;
+        if batch {
+            queues.batch_running -= 1;
+            drop(queues);
+            // A batch render held back by the limit can go now
+            shared.ready.notify_one();
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::auth::{User, UserInfo};
+use crate::vcards::VCard;
+use crate::VCardData;
+
+// Storage for cards. SqlitePool is the real implementation (see vcards.rs); code written against
+// the trait also runs on the in-memory test double below, and new backends can be added one trait
+// at a time
+pub trait VcardRepo {
+    async fn insert_vcard(&self, data: &VCardData, created_by: Option<i64>) -> Result<i64, sqlx::Error>;
+    async fn find_vcard(&self, vcard_id: i64) -> Result<Option<VCard>, sqlx::Error>;
+    async fn find_vcard_by_slug(&self, slug: &str) -> Result<Option<VCard>, sqlx::Error>;
+    // Overwrite a card's contact and render fields, returning whether it existed
+    async fn update_vcard(&self, vcard_id: i64, data: &VCardData) -> Result<bool, sqlx::Error>;
+    async fn delete_vcard(&self, vcard_id: i64) -> Result<bool, sqlx::Error>;
+    // Newest first, optionally only those carrying a tag; page size is clamped
+    async fn list_vcards(&self, tag: Option<&str>, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<VCard>, sqlx::Error>;
+}
+
+// Storage for user accounts; SqlitePool implements it in auth.rs
+pub trait UserRepo {
+    async fn find_user(&self, user_id: i64) -> Result<Option<User>, sqlx::Error>;
+    async fn find_user_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error>;
+    async fn list_users(&self) -> Result<Vec<UserInfo>, sqlx::Error>;
+    // None when the username is already taken
+    async fn create_user(&self, username: &str, password_hash: &str, is_admin: bool) -> Result<Option<i64>, sqlx::Error>;
+    async fn update_user(&self, user_id: i64, username: &str, is_admin: bool) -> Result<bool, sqlx::Error>;
+    async fn set_password_hash(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error>;
+    async fn delete_user(&self, user_id: i64) -> Result<bool, sqlx::Error>;
+}
+
+// Test double: keeps everything in memory and never fails
+#[cfg(test)]
+pub mod in_memory {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::{UserRepo, VcardRepo};
+    use crate::approvals::ReviewStatus;
+    use crate::auth::{User, UserInfo};
+    use crate::vcards::VCard;
+    use crate::{clean_field, VCardData};
+
+    #[derive(Default)]
+    struct Store {
+        vcards: Vec<VCard>,
+        // Tag names per vcard ID, for list filtering
+        tags: HashMap<i64, Vec<String>>,
+        users: Vec<User>,
+        next_id: i64,
+    }
+
+    impl Store {
+        fn next_id(&mut self) -> i64 {
+            self.next_id += 1;
+            self.next_id
+        }
+    }
+
+    #[derive(Default)]
+    pub struct InMemoryRepo {
+        store: Mutex<Store>,
+    }
+
+    impl InMemoryRepo {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn tag_vcard(&self, vcard_id: i64, tag: &str) {
+            self.store.lock().unwrap().tags.entry(vcard_id).or_default().push(tag.to_string());
+        }
+    }
+
+    fn now() -> String {
+        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    // Applies the same cleaning as the SQLite implementation
+    fn fill_vcard(vcard: &mut VCard, data: &VCardData) {
+        vcard.first_name = data.first_name.trim().to_string();
+        vcard.last_name = data.last_name.trim().to_string();
+        vcard.mobile = clean_field(&data.mobile).map(str::to_string);
+        vcard.work = clean_field(&data.work).map(str::to_string);
+        vcard.email = clean_field(&data.email).map(str::to_string);
+        vcard.company = clean_field(&data.company).map(str::to_string);
+        vcard.role = clean_field(&data.role).map(str::to_string);
+        vcard.street = clean_field(&data.street).map(str::to_string);
+        vcard.city = clean_field(&data.city).map(str::to_string);
+        vcard.state = clean_field(&data.state).map(str::to_string);
+        vcard.postal_code = clean_field(&data.postal_code).map(str::to_string);
+        vcard.country = clean_field(&data.country).map(str::to_ascii_uppercase);
+        vcard.website = clean_field(&data.website).map(str::to_string);
+        vcard.extensions = data.extensions.clone();
+        vcard.labels = data.labels.clone();
+        vcard.messengers = data.messengers.cleaned();
+        vcard.payment = data.payment.cleaned();
+        vcard.render = data.render.clone();
+        vcard.updated_at = now();
+    }
+
+    impl VcardRepo for InMemoryRepo {
+        async fn insert_vcard(&self, data: &VCardData, created_by: Option<i64>) -> Result<i64, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            let id = store.next_id();
+            let mut vcard = VCard {
+                id,
+                slug: format!("{:016x}", id),
+                first_name: String::new(),
+                last_name: String::new(),
+                mobile: None,
+                work: None,
+                email: None,
+                company: None,
+                role: None,
+                street: None,
+                city: None,
+                state: None,
+                postal_code: None,
+                country: None,
+                website: None,
+                extensions: Default::default(),
+                labels: Default::default(),
+                messengers: Default::default(),
+                payment: Default::default(),
+                render: Default::default(),
+                status: ReviewStatus::Approved,
+                reviewed_by: None,
+                reviewed_at: None,
+                review_note: None,
+                broken_links: Vec::new(),
+                created_by,
+                created_at: now(),
+                updated_at: String::new(),
+            };
+            fill_vcard(&mut vcard, data);
+            store.vcards.push(vcard);
+            Ok(id)
+        }
+
+        async fn find_vcard(&self, vcard_id: i64) -> Result<Option<VCard>, sqlx::Error> {
+            Ok(self.store.lock().unwrap().vcards.iter().find(|v| v.id == vcard_id).cloned())
+        }
+
+        async fn find_vcard_by_slug(&self, slug: &str) -> Result<Option<VCard>, sqlx::Error> {
+            Ok(self.store.lock().unwrap().vcards.iter().find(|v| v.slug == slug).cloned())
+        }
+
+        async fn update_vcard(&self, vcard_id: i64, data: &VCardData) -> Result<bool, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            match store.vcards.iter_mut().find(|v| v.id == vcard_id) {
+                Some(vcard) => {
+                    fill_vcard(vcard, data);
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        async fn delete_vcard(&self, vcard_id: i64) -> Result<bool, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            let before = store.vcards.len();
+            store.vcards.retain(|v| v.id != vcard_id);
+            store.tags.remove(&vcard_id);
+            Ok(store.vcards.len() < before)
+        }
+
+        async fn list_vcards(&self, tag: Option<&str>, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<VCard>, sqlx::Error> {
+            let (limit, offset) = crate::vcards::page_bounds(limit, offset);
+            let store = self.store.lock().unwrap();
+            // IDs only grow, so newest first is highest ID first
+            Ok(store.vcards.iter().rev()
+                .filter(|v| tag.is_none_or(|tag| store.tags.get(&v.id).is_some_and(|tags| tags.iter().any(|t| t == tag))))
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+    }
+
+    impl UserRepo for InMemoryRepo {
+        async fn find_user(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+            Ok(self.store.lock().unwrap().users.iter().find(|u| u.id == user_id).cloned())
+        }
+
+        async fn find_user_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+            Ok(self.store.lock().unwrap().users.iter().find(|u| u.username == username).cloned())
+        }
+
+        async fn list_users(&self) -> Result<Vec<UserInfo>, sqlx::Error> {
+            Ok(self.store.lock().unwrap().users.iter().cloned().map(UserInfo::from).collect())
+        }
+
+        async fn create_user(&self, username: &str, password_hash: &str, is_admin: bool) -> Result<Option<i64>, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            if store.users.iter().any(|u| u.username == username) {
+                return Ok(None);
+            }
+            let id = store.next_id();
+            store.users.push(User {
+                id,
+                username: username.to_string(),
+                password_hash: password_hash.to_string(),
+                is_admin,
+            });
+            Ok(Some(id))
+        }
+
+        async fn update_user(&self, user_id: i64, username: &str, is_admin: bool) -> Result<bool, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            match store.users.iter_mut().find(|u| u.id == user_id) {
+                Some(user) => {
+                    user.username = username.to_string();
+                    user.is_admin = is_admin;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        async fn set_password_hash(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            match store.users.iter_mut().find(|u| u.id == user_id) {
+                Some(user) => {
+                    user.password_hash = password_hash.to_string();
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        async fn delete_user(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+            let mut store = self.store.lock().unwrap();
+            let before = store.users.len();
+            store.users.retain(|u| u.id != user_id);
+            Ok(store.users.len() < before)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::in_memory::InMemoryRepo;
+    use super::{UserRepo, VcardRepo};
+    use crate::VCardData;
+
+    fn card(first_name: &str, last_name: &str) -> VCardData {
+        VCardData { first_name: first_name.to_string(), last_name: last_name.to_string(), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn stored_cards_are_cleaned() {
+        let repo = InMemoryRepo::new();
+        let data = VCardData {
+            mobile: Some("  ".to_string()),
+            email: Some(" ada@example.com ".to_string()),
+            country: Some("de".to_string()),
+            ..card(" Ada ", "Lovelace\n")
+        };
+        let id = repo.insert_vcard(&data, Some(7)).await.unwrap();
+
+        let vcard = repo.find_vcard(id).await.unwrap().unwrap();
+        assert_eq!(vcard.first_name, "Ada");
+        assert_eq!(vcard.last_name, "Lovelace");
+        assert_eq!(vcard.mobile, None);
+        assert_eq!(vcard.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(vcard.country.as_deref(), Some("DE"));
+        assert_eq!(vcard.created_by, Some(7));
+        assert_eq!(repo.find_vcard_by_slug(&vcard.slug).await.unwrap().map(|v| v.id), Some(id));
+    }
+
+    #[tokio::test]
+    async fn update_and_delete_report_missing_cards() {
+        let repo = InMemoryRepo::new();
+        let id = repo.insert_vcard(&card("Ada", "Lovelace"), None).await.unwrap();
+
+        assert!(repo.update_vcard(id, &card("Ada", "King")).await.unwrap());
+        assert_eq!(repo.find_vcard(id).await.unwrap().unwrap().last_name, "King");
+        assert!(!repo.update_vcard(id + 100, &card("Grace", "Hopper")).await.unwrap());
+
+        assert!(repo.delete_vcard(id).await.unwrap());
+        assert!(!repo.delete_vcard(id).await.unwrap());
+        assert!(repo.find_vcard(id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn listing_is_newest_first_paged_and_filtered_by_tag() {
+        let repo = InMemoryRepo::new();
+        let mut ids = Vec::new();
+        for name in ["Ada", "Grace", "Edsger", "Barbara"] {
+            ids.push(repo.insert_vcard(&card(name, "Test"), None).await.unwrap());
+        }
+        repo.tag_vcard(ids[0], "speaker");
+        repo.tag_vcard(ids[2], "speaker");
+
+        let names = |vcards: Vec<crate::vcards::VCard>| vcards.into_iter().map(|v| v.first_name).collect::<Vec<_>>();
+        assert_eq!(names(repo.list_vcards(None, None, None).await.unwrap()), ["Barbara", "Edsger", "Grace", "Ada"]);
+        assert_eq!(names(repo.list_vcards(None, Some(2), Some(1)).await.unwrap()), ["Edsger", "Grace"]);
+        assert_eq!(names(repo.list_vcards(Some("speaker"), None, None).await.unwrap()), ["Edsger", "Ada"]);
+        // Out-of-range paging is clamped rather than refused
+        assert_eq!(repo.list_vcards(None, Some(0), Some(-5)).await.unwrap().len(), 1);
+
+        repo.delete_vcard(ids[2]).await.unwrap();
+        assert_eq!(names(repo.list_vcards(Some("speaker"), None, None).await.unwrap()), ["Ada"]);
+    }
+
+    #[tokio::test]
+    async fn usernames_are_unique() {
+        let repo = InMemoryRepo::new();
+        let id = repo.create_user("ada", "hash", false).await.unwrap().unwrap();
+        assert_eq!(repo.create_user("ada", "other", true).await.unwrap(), None);
+
+        assert!(repo.update_user(id, "ada.lovelace", true).await.unwrap());
+        assert!(repo.set_password_hash(id, "new-hash").await.unwrap());
+        let user = repo.find_user_by_username("ada.lovelace").await.unwrap().unwrap();
+        assert!(user.is_admin);
+        assert_eq!(user.password_hash, "new-hash");
+        assert!(repo.find_user_by_username("ada").await.unwrap().is_none());
+
+        assert!(repo.delete_user(id).await.unwrap());
+        assert!(repo.list_users().await.unwrap().is_empty());
+        assert!(!repo.set_password_hash(id, "hash").await.unwrap());
+    }
+}
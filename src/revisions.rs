@@ -0,0 +1,310 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use tower_sessions::Session;
+
+use crate::approvals;
+use crate::auth::get_current_user;
+use crate::cache::ResponseCache;
+use crate::events::EventBus;
+use crate::repo::VcardRepo;
+use crate::vcards::{fetch_vcard, VCard};
+use crate::{ErrorResponse, Messengers, Payment, RenderOptions, VCardData};
+
+// A card's stored fields at one point in its history
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    first_name: String,
+    last_name: String,
+    mobile: Option<String>,
+    work: Option<String>,
+    email: Option<String>,
+    company: Option<String>,
+    role: Option<String>,
+    street: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    postal_code: Option<String>,
+    country: Option<String>,
+    website: Option<String>,
+    #[serde(default)]
+    extensions: BTreeMap<String, String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(flatten)]
+    messengers: Messengers,
+    #[serde(flatten)]
+    payment: Payment,
+    #[serde(flatten)]
+    render: RenderOptions,
+}
+
+impl From<&VCard> for Snapshot {
+    fn from(vcard: &VCard) -> Self {
+        Snapshot {
+            first_name: vcard.first_name.clone(),
+            last_name: vcard.last_name.clone(),
+            mobile: vcard.mobile.clone(),
+            work: vcard.work.clone(),
+            email: vcard.email.clone(),
+            company: vcard.company.clone(),
+            role: vcard.role.clone(),
+            street: vcard.street.clone(),
+            city: vcard.city.clone(),
+            state: vcard.state.clone(),
+            postal_code: vcard.postal_code.clone(),
+            country: vcard.country.clone(),
+            website: vcard.website.clone(),
+            extensions: vcard.extensions.clone(),
+            labels: vcard.labels.clone(),
+            messengers: vcard.messengers.clone(),
+            payment: vcard.payment.clone(),
+            render: vcard.render.clone(),
+        }
+    }
+}
+
+impl From<Snapshot> for VCardData {
+    fn from(snapshot: Snapshot) -> Self {
+        VCardData {
+            first_name: snapshot.first_name,
+            last_name: snapshot.last_name,
+            mobile: snapshot.mobile,
+            work: snapshot.work,
+            email: snapshot.email,
+            company: snapshot.company,
+            role: snapshot.role,
+            street: snapshot.street,
+            city: snapshot.city,
+            state: snapshot.state,
+            postal_code: snapshot.postal_code,
+            country: snapshot.country,
+            website: snapshot.website,
+            extensions: snapshot.extensions,
+            labels: snapshot.labels,
+            messengers: snapshot.messengers,
+            payment: snapshot.payment,
+            render: snapshot.render,
+            draft_id: None,
+            format: Default::default(),
+            pdf_size_mm: None,
+            caption: None,
+            encrypt: false,
+            logo_upload_id: None,
+            style_id: None,
+        }
+    }
+}
+
+// Snapshot fields that are maps, diffed by entry
+const MAP_FIELDS: &[&str] = &["extensions", "labels"];
+
+#[derive(sqlx::FromRow)]
+struct RevisionRow {
+    revision: i64,
+    #[sqlx(json)]
+    data: Snapshot,
+    edited_by: Option<i64>,
+    edited_by_username: Option<String>,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct FieldChange {
+    field: String,
+    old: Value,
+    new: Value,
+}
+
+#[derive(Serialize)]
+pub struct Revision {
+    revision: i64,
+    edited_by: Option<i64>,
+    edited_by_username: Option<String>,
+    created_at: String,
+    data: Snapshot,
+    // Against the revision before; empty for the first
+    changes: Vec<FieldChange>,
+}
+
+// Fields that differ between two snapshots, in field order. Render options, extensions and labels
+// compare one key at a time, so a changed color or CRM ID is named on its own
+fn diff(old: &Snapshot, new: &Snapshot) -> Vec<FieldChange> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new();
+    };
+
+    let mut changes = Vec::new();
+    let mut push = |field: String, old: Option<&Value>, new: Option<&Value>| {
+        let (old, new) = (old.cloned().unwrap_or(Value::Null), new.cloned().unwrap_or(Value::Null));
+        if old != new {
+            changes.push(FieldChange { field, old, new });
+        }
+    };
+
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).filter(|key| !MAP_FIELDS.contains(&key.as_str())).collect();
+    fields.sort_unstable();
+    fields.dedup();
+    for field in fields {
+        push(field.clone(), old.get(field), new.get(field));
+    }
+
+    let empty = serde_json::Map::new();
+    for map in MAP_FIELDS {
+        let old_map = old.get(*map).and_then(Value::as_object).unwrap_or(&empty);
+        let new_map = new.get(*map).and_then(Value::as_object).unwrap_or(&empty);
+        let mut names: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+        names.sort_unstable();
+        names.dedup();
+        for name in names {
+            push(format!("{}.{}", map, name), old_map.get(name), new_map.get(name));
+        }
+    }
+
+    changes
+}
+
+async fn append(pool: &SqlitePool, vcard: &VCard, edited_by: Option<i64>, created_at: Option<&str>) -> Result<(), sqlx::Error> {
+    // Numbered in the same statement that inserts, so concurrent edits cannot share a number
+    sqlx::query(
+        r#"
+        INSERT INTO vcard_revisions (vcard_id, revision, data, edited_by, created_at)
+        SELECT ?1, COALESCE(MAX(revision), 0) + 1, ?2, ?3, COALESCE(?4, CURRENT_TIMESTAMP)
+        FROM vcard_revisions WHERE vcard_id = ?1
+        "#
+    )
+    .bind(vcard.id)
+    .bind(sqlx::types::Json(Snapshot::from(vcard)))
+    .bind(edited_by)
+    .bind(created_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Every edit of a card's fields goes through here so its history stays complete. Cards get their
+// first revision, the state before their first edit, lazily rather than on every insert
+pub async fn update_vcard(pool: &SqlitePool, vcard_id: i64, data: &VCardData, edited_by: Option<i64>) -> Result<bool, sqlx::Error> {
+    let Some(before) = pool.find_vcard(vcard_id).await? else {
+        return Ok(false);
+    };
+
+    let has_history: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM vcard_revisions WHERE vcard_id = ?)")
+        .bind(vcard_id)
+        .fetch_one(pool)
+        .await?;
+    if !has_history {
+        append(pool, &before, before.created_by, Some(&before.updated_at)).await?;
+    }
+
+    if !pool.update_vcard(vcard_id, data).await? {
+        return Ok(false);
+    }
+
+    match pool.find_vcard(vcard_id).await? {
+        Some(after) => append(pool, &after, edited_by, None).await?,
+        None => return Ok(false),
+    }
+    Ok(true)
+}
+
+async fn load_revisions(pool: &SqlitePool, vcard_id: i64) -> Result<Vec<RevisionRow>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT r.revision, r.data, r.edited_by, u.username AS edited_by_username, r.created_at
+        FROM vcard_revisions r LEFT JOIN users u ON u.id = r.edited_by
+        WHERE r.vcard_id = ?
+        ORDER BY r.revision
+        "#
+    )
+    .bind(vcard_id)
+    .fetch_all(pool)
+    .await
+}
+
+// A card's history, newest first, each revision with what it changed
+pub async fn list_revisions_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+) -> Result<Json<Vec<Revision>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let vcard = fetch_vcard(&pool, vcard_id).await?;
+    let rows = load_revisions(&pool, vcard_id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    // A card never edited has its current state as its only revision
+    if rows.is_empty() {
+        return Ok(Json(vec![Revision {
+            revision: 1,
+            edited_by: vcard.created_by,
+            edited_by_username: None,
+            created_at: vcard.updated_at.clone(),
+            data: Snapshot::from(&vcard),
+            changes: Vec::new(),
+        }]));
+    }
+
+    let mut revisions = Vec::with_capacity(rows.len());
+    let mut previous: Option<Snapshot> = None;
+    for row in rows {
+        let changes = previous.as_ref().map(|previous| diff(previous, &row.data)).unwrap_or_default();
+        previous = Some(row.data.clone());
+        revisions.push(Revision {
+            revision: row.revision,
+            edited_by: row.edited_by,
+            edited_by_username: row.edited_by_username,
+            created_at: row.created_at,
+            data: row.data,
+            changes,
+        });
+    }
+    revisions.reverse();
+
+    Ok(Json(revisions))
+}
+
+// Restore a card's fields as of an earlier revision. The rollback is itself a new revision, so it
+// can be undone the same way, and goes through review like any other edit
+pub async fn rollback_handler(
+    State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    session: Session,
+    State(cache): State<ResponseCache>,
+    Path((vcard_id, revision)): Path<(i64, i64)>,
+) -> Result<Json<VCard>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    fetch_vcard(&pool, vcard_id).await?;
+
+    let snapshot: Option<sqlx::types::Json<Snapshot>> = sqlx::query_scalar("SELECT data FROM vcard_revisions WHERE vcard_id = ? AND revision = ?")
+        .bind(vcard_id)
+        .bind(revision)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    let snapshot = snapshot
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Revision not found".to_string() })))?;
+
+    // Restored as recorded: field rules added since then do not block undoing a mistake
+    let data = VCardData::from(snapshot.0);
+    let updated = update_vcard(&pool, vcard_id, &data, Some(user.id)).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to roll back vCard".to_string() })))?;
+    if !updated {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "vCard not found".to_string() })));
+    }
+    cache.invalidate(vcard_id);
+
+    approvals::submit(&pool, &events, vcard_id, &user).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(fetch_vcard(&pool, vcard_id).await?))
+}
@@ -0,0 +1,98 @@
+use image::{imageops, DynamicImage, GrayImage, Luma};
+use serde::Serialize;
+
+// Self-check of rendered codes: decode them back as a phone camera would see them in print
+
+// Camera resolution assumed when simulating a printed code, in pixels per millimetre
+const SCAN_PX_PER_MM: f32 = 8.0;
+
+// Printed edge lengths (quiet zone included) a code is checked at, largest first
+const PRINT_SIZES_MM: [u32; 5] = [50, 35, 25, 18, 12];
+
+#[derive(Serialize)]
+pub struct SizeCheck {
+    pub size_mm: u32,
+    pub decoded: bool,
+}
+
+#[derive(Serialize)]
+pub struct ScanReport {
+    // Percentage of simulated print sizes that decoded
+    pub score: u8,
+    // Smallest print size that still decoded
+    pub min_size_mm: Option<u32>,
+    pub sizes: Vec<SizeCheck>,
+}
+
+fn decodes(gray: &GrayImage, expected: &str) -> bool {
+    decodes_qr(gray, expected) || decodes_other(gray, expected)
+}
+
+fn decodes_qr(gray: &GrayImage, expected: &str) -> bool {
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        gray.width() as usize,
+        gray.height() as usize,
+        |x, y| gray.get_pixel(x as u32, y as u32)[0],
+    );
+    prepared
+        .detect_grids()
+        .iter()
+        .any(|grid| grid.decode().is_ok_and(|(_, content)| content == expected))
+}
+
+// Data Matrix and Aztec codes
+fn decodes_other(gray: &GrayImage, expected: &str) -> bool {
+    let formats = [rxing::BarcodeFormat::DATA_MATRIX, rxing::BarcodeFormat::AZTEC];
+    formats.into_iter().any(|format| {
+        rxing::helpers::detect_in_luma(gray.as_raw().clone(), gray.width(), gray.height(), Some(format))
+            .is_ok_and(|result| result.getText() == expected)
+    })
+}
+
+// Codes drawn with a narrow quiet zone (or none) rely on the page around them being light, which
+// scanners need to find the corner markers; such an image is checked on a white margin wider than
+// the standard four modules would be
+fn with_margin(gray: GrayImage) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let edge_is_light = (0..width).all(|x| gray.get_pixel(x, 0)[0] >= 128 && gray.get_pixel(x, height - 1)[0] >= 128)
+        && (0..height).all(|y| gray.get_pixel(0, y)[0] >= 128 && gray.get_pixel(width - 1, y)[0] >= 128);
+    if edge_is_light {
+        return gray;
+    }
+    let margin = width.max(height) / 4;
+    let mut padded = GrayImage::from_pixel(width + 2 * margin, height + 2 * margin, Luma([255]));
+    imageops::replace(&mut padded, &gray, margin as i64, margin as i64);
+    padded
+}
+
+// Fail unless the image decodes back to exactly its content at full resolution
+pub fn verify(image: &DynamicImage, expected: &str) -> Result<(), String> {
+    if decodes(&with_margin(image.to_luma8()), expected) {
+        Ok(())
+    } else {
+        Err("Rendered code failed to decode".to_string())
+    }
+}
+
+pub fn score(image: &DynamicImage, expected: &str) -> ScanReport {
+    let gray = with_margin(image.to_luma8());
+
+    let sizes: Vec<SizeCheck> = PRINT_SIZES_MM
+        .iter()
+        .map(|&size_mm| {
+            let px = (size_mm as f32 * SCAN_PX_PER_MM).round() as u32;
+            // Filtered downscaling stands in for print and lens blur at that size
+            let scaled = imageops::resize(&gray, px, px, imageops::FilterType::Triangle);
+            SizeCheck { size_mm, decoded: decodes(&scaled, expected) }
+        })
+        .collect();
+
+    let passed = sizes.iter().filter(|check| check.decoded).count();
+    let min_size_mm = sizes.iter().filter(|check| check.decoded).map(|check| check.size_mm).min();
+
+    ScanReport {
+        score: (passed * 100 / sizes.len()) as u8,
+        min_size_mm,
+        sizes,
+    }
+}
@@ -0,0 +1,230 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::jobs;
+use crate::{ErrorResponse, MessageResponse};
+
+// Matches SQLite's CURRENT_TIMESTAMP so stored times compare as text
+const SQL_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    kind: String,
+    payload: Value,
+    // Exactly one of: a one-off time, or a recurring cron expression (UTC)
+    run_at: Option<DateTime<Utc>>,
+    cron: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ScheduleResponse {
+    Once { job_id: i64, run_at: String },
+    Recurring(JobSchedule),
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct JobSchedule {
+    id: i64,
+    kind: String,
+    cron: String,
+    next_run_at: Option<String>,
+    last_job_id: Option<i64>,
+    created_at: String,
+}
+
+// Accept standard 5-field cron as well as the crate's seconds-first form
+fn parse_cron(expression: &str) -> Result<Schedule, String> {
+    let expression = expression.trim();
+    let normalized = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+
+    Schedule::from_str(&normalized).map_err(|e| format!("Invalid cron expression: {}", e))
+}
+
+fn next_run(schedule: &Schedule) -> Option<String> {
+    schedule
+        .upcoming(Utc)
+        .next()
+        .map(|t| t.format(SQL_DATETIME_FORMAT).to_string())
+}
+
+// Called by the job worker: queue a job for every schedule that has come due
+pub async fn enqueue_due(pool: &SqlitePool) {
+    #[derive(sqlx::FromRow)]
+    struct DueSchedule {
+        id: i64,
+        kind: String,
+        payload: String,
+        cron: String,
+        next_run_at: String,
+        created_by: Option<i64>,
+    }
+
+    let due: Vec<DueSchedule> = match sqlx::query_as(
+        "SELECT id, kind, payload, cron, next_run_at, created_by FROM job_schedules WHERE next_run_at <= CURRENT_TIMESTAMP"
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(due) => due,
+        Err(e) => {
+            eprintln!("Failed to load due schedules: {}", e);
+            return;
+        }
+    };
+
+    for schedule in due {
+        // A schedule that no longer parses is disabled rather than retried forever
+        let next_run_at = match parse_cron(&schedule.cron) {
+            Ok(cron) => next_run(&cron),
+            Err(e) => {
+                eprintln!("Disabling schedule {}: {}", schedule.id, e);
+                None
+            }
+        };
+
+        // Advance the schedule first; only the caller that moves it gets to enqueue
+        let advanced = sqlx::query("UPDATE job_schedules SET next_run_at = ? WHERE id = ? AND next_run_at = ?")
+            .bind(&next_run_at)
+            .bind(schedule.id)
+            .bind(&schedule.next_run_at)
+            .execute(pool)
+            .await;
+
+        match advanced {
+            Ok(result) if result.rows_affected() == 1 => {}
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("Failed to advance schedule {}: {}", schedule.id, e);
+                continue;
+            }
+        }
+
+        let payload = serde_json::from_str(&schedule.payload).unwrap_or(Value::Null);
+        match jobs::enqueue_at(pool, &schedule.kind, &payload, schedule.created_by, None).await {
+            Ok(job_id) => {
+                if let Err(e) = sqlx::query("UPDATE job_schedules SET last_job_id = ? WHERE id = ?")
+                    .bind(job_id)
+                    .bind(schedule.id)
+                    .execute(pool)
+                    .await
+                {
+                    eprintln!("Failed to record job for schedule {}: {}", schedule.id, e);
+                }
+            }
+            Err(e) => eprintln!("Failed to enqueue job for schedule {}: {}", schedule.id, e),
+        }
+    }
+}
+
+pub async fn create_schedule_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<ScheduleRequest>,
+) -> Result<(StatusCode, Json<ScheduleResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    jobs::validate_payload(&req.kind, &req.payload)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+    match (req.run_at, req.cron) {
+        (Some(run_at), None) => {
+            if run_at <= Utc::now() {
+                return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "run_at must be in the future".to_string() })));
+            }
+
+            let run_at = run_at.format(SQL_DATETIME_FORMAT).to_string();
+            let job_id = jobs::enqueue_at(&pool, &req.kind, &req.payload, Some(user.id), Some(&run_at)).await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to queue job".to_string() })))?;
+
+            Ok((StatusCode::CREATED, Json(ScheduleResponse::Once { job_id, run_at })))
+        }
+        (None, Some(cron)) => {
+            let schedule = parse_cron(&cron)
+                .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+
+            let next_run_at = next_run(&schedule)
+                .ok_or((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Cron expression never fires".to_string() })))?;
+
+            let result = sqlx::query("INSERT INTO job_schedules (kind, payload, cron, next_run_at, created_by) VALUES (?, ?, ?, ?, ?)")
+                .bind(&req.kind)
+                .bind(req.payload.to_string())
+                .bind(cron.trim())
+                .bind(&next_run_at)
+                .bind(user.id)
+                .execute(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create schedule".to_string() })))?;
+
+            let schedule: JobSchedule = sqlx::query_as("SELECT id, kind, cron, next_run_at, last_job_id, created_at FROM job_schedules WHERE id = ?")
+                .bind(result.last_insert_rowid())
+                .fetch_one(&pool)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+            Ok((StatusCode::CREATED, Json(ScheduleResponse::Recurring(schedule))))
+        }
+        _ => Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Provide exactly one of run_at or cron".to_string() }))),
+    }
+}
+
+pub async fn list_schedules_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<JobSchedule>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let schedules: Vec<JobSchedule> = sqlx::query_as("SELECT id, kind, cron, next_run_at, last_job_id, created_at FROM job_schedules ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(schedules))
+}
+
+pub async fn delete_schedule_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(schedule_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    sqlx::query("DELETE FROM job_schedules WHERE id = ?")
+        .bind(schedule_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete schedule".to_string() })))?;
+
+    Ok(Json(MessageResponse {
+        message: "Schedule deleted successfully".to_string(),
+    }))
+}
@@ -0,0 +1,106 @@
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::compose::FONT_DATA;
+use crate::directory::{DIRECTORY_PLACEHOLDERS, DIRECTORY_TEMPLATE};
+use crate::embed::{EMBED_PLACEHOLDERS, EMBED_TEMPLATE};
+
+// Served from the working directory at /static, so it has to exist next to the binary
+const STATIC_DIR: &str = "static";
+
+// Hash of a migration's SQL; line endings are normalized so a CRLF checkout is not an edit
+pub fn migration_checksum(sql: &str) -> String {
+    let digest = Sha256::digest(sql.replace("\r\n", "\n").as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Make sure the migrations table can record checksums; databases from before this have no column for it
+pub async fn ensure_checksum_column(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let has_column: bool = sqlx::query_scalar("SELECT COUNT(*) > 0 FROM pragma_table_info('migrations') WHERE name = 'checksum'")
+        .fetch_one(pool)
+        .await?;
+    if !has_column {
+        sqlx::query("ALTER TABLE migrations ADD COLUMN checksum TEXT").execute(pool).await?;
+    }
+    Ok(())
+}
+
+// Compare every applied migration with the file compiled into this build. Migrations applied
+// before checksums were recorded are trusted and given one now; any other difference is an error
+pub async fn verify_migrations(pool: &SqlitePool, migrations: &[(&str, &str)]) -> Result<(), String> {
+    let applied: Vec<(String, Option<String>)> = sqlx::query_as("SELECT name, checksum FROM migrations ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Could not read applied migrations: {}", e))?;
+
+    let mut problems = Vec::new();
+    for (name, stored) in &applied {
+        let Some((_, sql)) = migrations.iter().find(|(known, _)| known == name) else {
+            eprintln!("Database has migration {} which this build does not know about; it may be from a newer release", name);
+            continue;
+        };
+        let checksum = migration_checksum(sql);
+        match stored {
+            Some(stored) if *stored != checksum => problems.push(format!(
+                "migrations/{}.sql has changed since it was applied; restore the original file and put the change in a new migration",
+                name
+            )),
+            Some(_) => {}
+            None => {
+                sqlx::query("UPDATE migrations SET checksum = ? WHERE name = ?")
+                    .bind(&checksum)
+                    .bind(name)
+                    .execute(pool)
+                    .await
+                    .map_err(|e| format!("Could not record checksum of {}: {}", name, e))?;
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        println!("✓ Applied migrations match this build");
+        Ok(())
+    } else {
+        for problem in &problems {
+            eprintln!("Migration check failed: {}", problem);
+        }
+        Err(format!("{} applied migrations no longer match their files", problems.len()))
+    }
+}
+
+// Templates and fonts are compiled in, but can still be broken by an edit; the static
+// directory is read at request time. Returns every problem found rather than the first
+pub fn check_assets() -> Result<(), Vec<String>> {
+    let mut problems = Vec::new();
+
+    for placeholder in EMBED_PLACEHOLDERS {
+        if !EMBED_TEMPLATE.contains(&format!("{{{{{}}}}}", placeholder)) {
+            problems.push(format!("static/embed.html is missing the {{{{{}}}}} placeholder", placeholder));
+        }
+    }
+    for placeholder in DIRECTORY_PLACEHOLDERS {
+        if !DIRECTORY_TEMPLATE.contains(&format!("{{{{{}}}}}", placeholder)) {
+            problems.push(format!("static/directory.html is missing the {{{{{}}}}} placeholder", placeholder));
+        }
+    }
+
+    if let Err(e) = ab_glyph::FontRef::try_from_slice(FONT_DATA) {
+        problems.push(format!("assets/fonts/DejaVuSans.ttf is not a usable font ({}); proof sheets and captions would fail to render", e));
+    }
+
+    if !Path::new(STATIC_DIR).is_dir() {
+        let cwd = std::env::current_dir().map(|dir| dir.display().to_string()).unwrap_or_default();
+        problems.push(format!(
+            "no {}/ directory in {}; start the server from the directory that contains it",
+            STATIC_DIR, cwd
+        ));
+    }
+
+    if problems.is_empty() {
+        println!("✓ Static assets present");
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
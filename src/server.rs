@@ -0,0 +1,121 @@
+use axum::extract::Request;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::{Service, ServiceExt};
+
+// Connection-level tuning. Batch clients open many short connections, often through a proxy that
+// speaks HTTP/2 to us, so these are worth adjusting per deployment
+#[derive(Clone)]
+pub struct ServerConfig {
+    // Cleartext HTTP/2 (prior knowledge, as proxies use it) next to HTTP/1.1
+    pub http2: bool,
+    pub http2_max_concurrent_streams: u32,
+    // Pings idle HTTP/2 connections so dead peers are noticed; None leaves them unpinged
+    pub http2_keepalive_interval: Option<Duration>,
+    // How long a ping may go unanswered before the connection is closed
+    pub http2_keepalive_timeout: Duration,
+    pub http1_keepalive: bool,
+    // How long an HTTP/1 connection may wait for the next request's headers, idle keep-alive included
+    pub http1_keepalive_timeout: Duration,
+    pub tcp_nodelay: bool,
+}
+
+fn env_flag(name: &str, default: bool) -> bool {
+    std::env::var(name).map(|v| v == "true" || v == "1").unwrap_or(default)
+}
+
+fn env_secs(name: &str) -> Option<u64> {
+    std::env::var(name).ok().and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        ServerConfig {
+            http2: env_flag("HTTP2_ENABLED", true),
+            http2_max_concurrent_streams: std::env::var("HTTP2_MAX_CONCURRENT_STREAMS")
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok())
+                .filter(|&streams| streams > 0)
+                .unwrap_or(200),
+            http2_keepalive_interval: env_secs("HTTP2_KEEPALIVE_INTERVAL_SECS").filter(|&secs| secs > 0).map(Duration::from_secs),
+            http2_keepalive_timeout: Duration::from_secs(env_secs("HTTP2_KEEPALIVE_TIMEOUT_SECS").filter(|&secs| secs > 0).unwrap_or(20)),
+            http1_keepalive: env_flag("HTTP_KEEPALIVE", true),
+            http1_keepalive_timeout: Duration::from_secs(env_secs("HTTP_KEEPALIVE_TIMEOUT_SECS").filter(|&secs| secs > 0).unwrap_or(30)),
+            tcp_nodelay: env_flag("TCP_NODELAY", false),
+        }
+    }
+
+    // Serves HTTP/1 or HTTP/2, whichever the client speaks
+    fn auto_builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder.http1()
+            .timer(TokioTimer::new())
+            .keep_alive(self.http1_keepalive)
+            .header_read_timeout(self.http1_keepalive_timeout);
+        builder.http2()
+            .timer(TokioTimer::new())
+            .max_concurrent_streams(self.http2_max_concurrent_streams)
+            .keep_alive_interval(self.http2_keepalive_interval)
+            .keep_alive_timeout(self.http2_keepalive_timeout);
+        builder
+    }
+
+    // With HTTP/2 off. The auto builder's http1_only is ignored once upgrades are enabled, and /ws needs them
+    fn http1_builder(&self) -> http1::Builder {
+        let mut builder = http1::Builder::new();
+        builder
+            .timer(TokioTimer::new())
+            .keep_alive(self.http1_keepalive)
+            .header_read_timeout(self.http1_keepalive_timeout);
+        builder
+    }
+}
+
+// Like axum::serve, but with the connection settings above applied
+pub async fn serve(listener: TcpListener, app: Router, config: ServerConfig) {
+    let auto_builder = config.auto_builder();
+    let http1_builder = config.http1_builder();
+    let mut make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    loop {
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Usually out of file descriptors; back off instead of spinning
+                eprintln!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        if config.tcp_nodelay {
+            if let Err(e) = stream.set_nodelay(true) {
+                eprintln!("Failed to set TCP_NODELAY: {}", e);
+            }
+        }
+
+        let tower_service = make_service.call(remote_addr).await.unwrap_or_else(|never| match never {});
+        let hyper_service = hyper::service::service_fn(move |request: Request<Incoming>| {
+            tower_service.clone().oneshot(request)
+        });
+
+        // Errors from a connection are clients going away mid-request, which is routine
+        let io = TokioIo::new(stream);
+        if config.http2 {
+            let builder = auto_builder.clone();
+            tokio::spawn(async move {
+                let _ = builder.serve_connection_with_upgrades(io, hyper_service).await;
+            });
+        } else {
+            let connection = http1_builder.serve_connection(io, hyper_service).with_upgrades();
+            tokio::spawn(async move {
+                let _ = connection.await;
+            });
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::{clean_field, ErrorResponse, VCardData};
+
+const FIELD_RULES_KEY: &str = "field_rules";
+
+// Contact fields an organization can make mandatory; first and last name always are
+pub const OPTIONAL_FIELDS: &[&str] = &["mobile", "work", "email", "company", "role", "street", "city", "state", "postal_code", "country", "website"];
+
+// Organization-wide settings are JSON values stored under a key; unset keys read as None
+pub async fn get_setting<T: DeserializeOwned>(pool: &SqlitePool, key: &str) -> Result<Option<T>, sqlx::Error> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    value.map(|value| serde_json::from_str(&value))
+        .transpose()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+pub async fn put_setting<T: Serialize>(pool: &SqlitePool, key: &str, value: &T, updated_by: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO settings (key, value, updated_by) VALUES (?, ?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_by = excluded.updated_by, updated_at = CURRENT_TIMESTAMP
+        "#
+    )
+    .bind(key)
+    .bind(sqlx::types::Json(value))
+    .bind(updated_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Which contact fields cards must have filled in, set by admins
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct FieldRules {
+    // Entries of OPTIONAL_FIELDS, in that order
+    pub required: Vec<String>,
+}
+
+fn field_value<'a>(data: &'a VCardData, field: &str) -> Option<&'a str> {
+    match field {
+        "mobile" => clean_field(&data.mobile),
+        "work" => clean_field(&data.work),
+        "email" => clean_field(&data.email),
+        "company" => clean_field(&data.company),
+        "role" => clean_field(&data.role),
+        "street" => clean_field(&data.street),
+        "city" => clean_field(&data.city),
+        "state" => clean_field(&data.state),
+        "postal_code" => clean_field(&data.postal_code),
+        "country" => clean_field(&data.country),
+        "website" => clean_field(&data.website),
+        _ => None,
+    }
+}
+
+impl FieldRules {
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(get_setting(pool, FIELD_RULES_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn save(&self, pool: &SqlitePool, updated_by: i64) -> Result<(), sqlx::Error> {
+        put_setting(pool, FIELD_RULES_KEY, self, updated_by).await
+    }
+
+    // Rules for the named fields, in form order without duplicates; unknown names are an error
+    pub fn from_fields(fields: &[String]) -> Result<Self, String> {
+        if let Some(unknown) = fields.iter().find(|field| !OPTIONAL_FIELDS.contains(&field.as_str())) {
+            return Err(format!("Unknown field {:?}; expected one of {}", unknown, OPTIONAL_FIELDS.join(", ")));
+        }
+        Ok(FieldRules {
+            required: OPTIONAL_FIELDS.iter()
+                .filter(|field| fields.iter().any(|f| f == *field))
+                .map(|field| field.to_string())
+                .collect(),
+        })
+    }
+
+    // Names every missing field at once, so a form can be fixed in one pass
+    pub fn check(&self, data: &VCardData) -> Result<(), String> {
+        let mut missing = Vec::new();
+        if data.first_name.trim().is_empty() {
+            missing.push("first_name");
+        }
+        if data.last_name.trim().is_empty() {
+            missing.push("last_name");
+        }
+        missing.extend(self.required.iter().map(String::as_str).filter(|field| field_value(data, field).is_none()));
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Missing required fields: {}", missing.join(", ")))
+        }
+    }
+}
+
+// Load the rules and check a submission, as a 422 naming the missing fields
+pub async fn check_required_fields(pool: &SqlitePool, data: &VCardData) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let rules = FieldRules::load(pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    rules.check(data)
+        .map_err(|error| (StatusCode::UNPROCESSABLE_ENTITY, Json(ErrorResponse { error })))
+}
+
+// Readable by every user, so forms can mark the required fields
+pub async fn get_field_rules_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<FieldRules>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let rules = FieldRules::load(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(rules))
+}
+
+pub async fn update_field_rules_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(rules): Json<FieldRules>,
+) -> Result<Json<FieldRules>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let rules = FieldRules::from_fields(&rules.required)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+
+    rules.save(&pool, user.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to save field rules".to_string() })))?;
+
+    Ok(Json(rules))
+}
@@ -0,0 +1,135 @@
+// First-run setup. A new database has no users, and until the first admin exists POST /api/setup
+// is open to anyone who can reach the server: it creates that admin with the password they choose,
+// stores a few instance settings and signs them in. Once any user exists it answers 409 for good,
+// so there are no default credentials to forget to change
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::approvals::ApprovalSettings;
+use crate::auth::{hash_password, set_user_session, User, UserInfo};
+use crate::directory::DirectorySettings;
+use crate::passwords;
+use crate::repo::UserRepo;
+use crate::settings::FieldRules;
+use crate::ErrorResponse;
+
+const MAX_USERNAME_LEN: usize = 64;
+
+#[derive(Serialize)]
+pub struct SetupStatus {
+    // Whether the instance still needs its first admin
+    required: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SetupRequest {
+    username: String,
+    password: String,
+    // Hold cards from regular users for review
+    #[serde(default)]
+    require_approval: bool,
+    // Contact fields every card must have, as for /api/field-rules
+    #[serde(default)]
+    required_fields: Vec<String>,
+    #[serde(default)]
+    directory: DirectorySettings,
+}
+
+// True while there are no users at all
+pub async fn setup_required(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users)")
+        .fetch_one(pool)
+        .await?;
+    Ok(!exists)
+}
+
+// Whether the admin seeded by early versions still signs in with admin/admin; that account is only
+// removed on upgrade when nothing was ever done with it
+pub async fn default_admin_present(pool: &SqlitePool) -> bool {
+    match pool.find_user_by_username("admin").await {
+        Ok(Some(user)) => crate::auth::verify_password("admin", &user.password_hash),
+        _ => false,
+    }
+}
+
+async fn save_settings(
+    pool: &SqlitePool,
+    updated_by: i64,
+    approval: &ApprovalSettings,
+    rules: &FieldRules,
+    directory: &DirectorySettings,
+) -> Result<(), sqlx::Error> {
+    approval.save(pool, updated_by).await?;
+    rules.save(pool, updated_by).await?;
+    directory.save(pool, updated_by).await
+}
+
+// Public, so the login page knows to show the setup form instead
+pub async fn setup_status_handler(
+    State(pool): State<SqlitePool>,
+) -> Result<Json<SetupStatus>, (StatusCode, Json<ErrorResponse>)> {
+    let required = setup_required(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(SetupStatus { required }))
+}
+
+pub async fn setup_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<SetupRequest>,
+) -> Result<(StatusCode, Json<UserInfo>), (StatusCode, Json<ErrorResponse>)> {
+    let username = req.username.trim();
+    if username.is_empty() || username.chars().count() > MAX_USERNAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Username must be 1 to {} characters", MAX_USERNAME_LEN),
+        })));
+    }
+    passwords::require_strong(&req.password, &[username])?;
+
+    // Checked before the admin is created, so a bad setting doesn't leave setup half done
+    let rules = FieldRules::from_fields(&req.required_fields)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    let directory = req.directory.normalized()
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+
+    let password_hash = hash_password(&req.password)
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to hash password".to_string() })))?;
+
+    // One statement, so of two concurrent setups only the first creates a user
+    let created = sqlx::query(
+        "INSERT INTO users (username, password_hash, is_admin) SELECT ?, ?, 1 WHERE NOT EXISTS (SELECT 1 FROM users)"
+    )
+    .bind(username)
+    .bind(&password_hash)
+    .execute(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    if created.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: "Setup has already been completed".to_string() })));
+    }
+
+    let user = User {
+        id: created.last_insert_rowid(),
+        username: username.to_string(),
+        password_hash,
+        is_admin: true,
+    };
+
+    let approval = ApprovalSettings { enabled: req.require_approval, approvers: Vec::new() };
+    save_settings(&pool, user.id, &approval, &rules, &directory).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Admin created, but saving the settings failed; set them from the admin page".to_string() })))?;
+
+    set_user_session(&session, &user).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Session error".to_string() })))?;
+
+    println!("✓ Setup complete; created admin {}", user.username);
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
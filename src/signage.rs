@@ -0,0 +1,282 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::approvals::ReviewStatus;
+use crate::auth::get_current_user;
+use crate::memory::{qr_image_bytes, MemoryBudget};
+use crate::repo::VcardRepo;
+use crate::{render_qr_png, render_vcard_png, ErrorResponse, MessageResponse, RenderOptions, VCardData};
+
+const MAX_PLAYLIST_NAME_LEN: usize = 100;
+const MAX_PLAYLIST_ITEMS: usize = 50;
+const MAX_CAPTION_LEN: usize = 120;
+const MAX_URL_LEN: usize = 2000;
+const DEFAULT_INTERVAL_SECONDS: i64 = 10;
+const MIN_INTERVAL_SECONDS: i64 = 3;
+const MAX_INTERVAL_SECONDS: i64 = 300;
+
+// A slide shows either a stored vcard or a URL
+#[derive(Deserialize, Serialize, sqlx::FromRow)]
+pub struct PlaylistItem {
+    vcard_id: Option<i64>,
+    url: Option<String>,
+    caption: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePlaylistRequest {
+    name: String,
+    interval_seconds: Option<i64>,
+    items: Vec<PlaylistItem>,
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct Playlist {
+    id: i64,
+    name: String,
+    token: String,
+    interval_seconds: i64,
+    created_at: String,
+    #[sqlx(skip)]
+    items: Vec<PlaylistItem>,
+    #[sqlx(skip)]
+    signage_url: String,
+}
+
+#[derive(Serialize)]
+pub struct Slide {
+    caption: String,
+    image: String, // base64 encoded
+}
+
+#[derive(Serialize)]
+pub struct SignageResponse {
+    name: String,
+    interval_seconds: i64,
+    slides: Vec<Slide>,
+}
+
+fn check_item(item: &PlaylistItem) -> Result<(), String> {
+    match (&item.vcard_id, &item.url) {
+        (Some(_), None) => {}
+        (None, Some(url)) => {
+            if !(url.starts_with("http://") || url.starts_with("https://")) || url.len() > MAX_URL_LEN {
+                return Err(format!("Invalid URL: {}", url));
+            }
+        }
+        _ => return Err("Each item needs exactly one of vcard_id or url".to_string()),
+    }
+    if item.caption.as_ref().is_some_and(|caption| caption.len() > MAX_CAPTION_LEN) {
+        return Err(format!("Captions must be at most {} characters", MAX_CAPTION_LEN));
+    }
+    Ok(())
+}
+
+async fn load_items(pool: &SqlitePool, playlist_id: i64) -> Result<Vec<PlaylistItem>, sqlx::Error> {
+    sqlx::query_as("SELECT vcard_id, url, caption FROM playlist_items WHERE playlist_id = ? ORDER BY position")
+        .bind(playlist_id)
+        .fetch_all(pool)
+        .await
+}
+
+async fn with_items(pool: &SqlitePool, mut playlist: Playlist) -> Result<Playlist, sqlx::Error> {
+    playlist.items = load_items(pool, playlist.id).await?;
+    playlist.signage_url = format!("/signage/{}", playlist.token);
+    Ok(playlist)
+}
+
+pub async fn create_playlist_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<CreatePlaylistRequest>,
+) -> Result<(StatusCode, Json<Playlist>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_PLAYLIST_NAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Name must be between 1 and {} characters", MAX_PLAYLIST_NAME_LEN),
+        })));
+    }
+
+    let interval_seconds = req.interval_seconds.unwrap_or(DEFAULT_INTERVAL_SECONDS);
+    if !(MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS).contains(&interval_seconds) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("interval_seconds must be between {} and {}", MIN_INTERVAL_SECONDS, MAX_INTERVAL_SECONDS),
+        })));
+    }
+
+    if req.items.is_empty() || req.items.len() > MAX_PLAYLIST_ITEMS {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("A playlist needs between 1 and {} items", MAX_PLAYLIST_ITEMS),
+        })));
+    }
+    for item in &req.items {
+        check_item(item).map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    }
+
+    // The token is all a screen needs to show the playlist
+    let token = uuid::Uuid::new_v4().simple().to_string();
+
+    let mut tx = pool.begin().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let playlist: Playlist = sqlx::query_as(
+        "INSERT INTO playlists (name, token, interval_seconds, created_by) VALUES (?, ?, ?, ?) RETURNING id, name, token, interval_seconds, created_at"
+    )
+    .bind(name)
+    .bind(&token)
+    .bind(interval_seconds)
+    .bind(user.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create playlist".to_string() })))?;
+
+    for (position, item) in req.items.iter().enumerate() {
+        sqlx::query("INSERT INTO playlist_items (playlist_id, position, vcard_id, url, caption) VALUES (?, ?, ?, ?, ?)")
+            .bind(playlist.id)
+            .bind(position as i64)
+            .bind(item.vcard_id)
+            .bind(&item.url)
+            .bind(item.caption.as_deref().map(str::trim).filter(|caption| !caption.is_empty()))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| match e.as_database_error() {
+                Some(db) if db.is_foreign_key_violation() => (StatusCode::BAD_REQUEST, Json(ErrorResponse {
+                    error: format!("vCard {} not found", item.vcard_id.unwrap_or_default()),
+                })),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create playlist".to_string() })),
+            })?;
+    }
+
+    tx.commit().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to create playlist".to_string() })))?;
+
+    let playlist = with_items(&pool, playlist).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok((StatusCode::CREATED, Json(playlist)))
+}
+
+pub async fn list_playlists_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Playlist>>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let rows: Vec<Playlist> = sqlx::query_as("SELECT id, name, token, interval_seconds, created_at FROM playlists ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let mut playlists = Vec::with_capacity(rows.len());
+    for row in rows {
+        playlists.push(with_items(&pool, row).await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?);
+    }
+
+    Ok(Json(playlists))
+}
+
+pub async fn delete_playlist_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(playlist_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let result = sqlx::query("DELETE FROM playlists WHERE id = ?")
+        .bind(playlist_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete playlist".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Playlist not found".to_string() })));
+    }
+
+    Ok(Json(MessageResponse {
+        message: "Playlist deleted successfully".to_string(),
+    }))
+}
+
+// Public: the signage page polls this with the token from its URL
+pub async fn signage_handler(
+    State(pool): State<SqlitePool>,
+    State(budget): State<MemoryBudget>,
+    Path(token): Path<String>,
+) -> Result<Json<SignageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let playlist: Option<Playlist> = sqlx::query_as("SELECT id, name, token, interval_seconds, created_at FROM playlists WHERE token = ?")
+        .bind(&token)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let playlist = playlist
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))?;
+
+    let items = load_items(&pool, playlist.id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let mut slides = Vec::with_capacity(items.len());
+    for item in items {
+        let rendered = match (item.vcard_id, &item.url) {
+            (Some(vcard_id), _) => match pool.find_vcard(vcard_id).await {
+                Ok(Some(vcard)) if vcard.status == ReviewStatus::Approved => {
+                    let name = format!("{} {}", vcard.first_name, vcard.last_name);
+                    let data: VCardData = vcard.into();
+                    let _reservation = budget.reserve(qr_image_bytes(&data.render)).await?;
+                    render_vcard_png(&data).map(|png| (name, png))
+                }
+                Ok(_) => continue,
+                Err(e) => Err(e.to_string()),
+            },
+            (None, Some(url)) => {
+                let _reservation = budget.reserve(qr_image_bytes(&RenderOptions::default())).await?;
+                render_qr_png(url, &RenderOptions::default()).map(|png| (url.clone(), png))
+            }
+            (None, None) => continue,
+        };
+
+        // One bad slide should not blank the screen
+        let (default_caption, png) = match rendered {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                eprintln!("Skipping slide in playlist {}: {}", playlist.id, e);
+                continue;
+            }
+        };
+
+        let base64_img = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+        slides.push(Slide {
+            caption: item.caption.unwrap_or(default_caption),
+            image: format!("data:image/png;base64,{}", base64_img),
+        });
+    }
+
+    Ok(Json(SignageResponse {
+        name: playlist.name,
+        interval_seconds: playlist.interval_seconds,
+        slides,
+    }))
+}
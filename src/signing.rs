@@ -0,0 +1,108 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use tower_sessions::cookie::Key;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Shortest secret accepted, so a placeholder like "changeme" never ends up signing anything
+const MIN_SECRET_LEN: usize = 32;
+
+// The instance's shared secret: signs tokens that must be verifiable without a database lookup and
+// session cookies. Every instance of a deployment has to run with the same SECRET_KEY, so a token
+// or cookie issued by one is accepted by all of them
+#[derive(Clone)]
+pub struct SigningKey {
+    // The first key signs; all of them verify, so tokens and printed signatures keep working while
+    // a rotation rolls out and until the old key is dropped
+    keys: Arc<Vec<Vec<u8>>>,
+}
+
+impl SigningKey {
+    // SECRET_KEY="<current>,<previous>,..."; required, there is no per-process fallback. A legacy
+    // SIGNING_KEY is still accepted for verifying, after the SECRET_KEY entries
+    pub fn from_env() -> Result<Self, String> {
+        let spec = std::env::var("SECRET_KEY").unwrap_or_default();
+        let mut keys: Vec<Vec<u8>> = spec.split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| key.as_bytes().to_vec())
+            .collect();
+        if keys.is_empty() {
+            return Err("SECRET_KEY is not set; generate one with `openssl rand -hex 32` and give every instance the same value".to_string());
+        }
+        if let Some(short) = keys.iter().position(|key| key.len() < MIN_SECRET_LEN) {
+            return Err(format!("SECRET_KEY entry {} is shorter than {} characters", short + 1, MIN_SECRET_LEN));
+        }
+
+        if let Ok(legacy) = std::env::var("SIGNING_KEY") {
+            if !legacy.is_empty() && !keys.contains(&legacy.as_bytes().to_vec()) {
+                eprintln!("SIGNING_KEY is deprecated; accepting it for verification only, add it to SECRET_KEY after the current key");
+                keys.push(legacy.into_bytes());
+            }
+        }
+        Ok(SigningKey { keys: Arc::new(keys) })
+    }
+
+    fn mac(key: &[u8]) -> HmacSha256 {
+        HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length")
+    }
+
+    // Detached base64url signature of `payload`, under the current key
+    pub fn sign(&self, payload: &[u8]) -> String {
+        let mut mac = Self::mac(&self.keys[0]);
+        mac.update(payload);
+        URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+
+    pub fn verify(&self, payload: &[u8], signature: &str) -> bool {
+        let Ok(signature) = URL_SAFE_NO_PAD.decode(signature.trim()) else {
+            return false;
+        };
+        self.keys.iter().any(|key| {
+            let mut mac = Self::mac(key);
+            mac.update(payload);
+            mac.verify_slice(&signature).is_ok()
+        })
+    }
+
+    // "<payload>.<signature>", both base64url
+    pub fn sign_token(&self, payload: &[u8]) -> String {
+        format!("{}.{}", URL_SAFE_NO_PAD.encode(payload), self.sign(payload))
+    }
+
+    // The payload of a token one of the keys signed, or None if it was tampered with
+    pub fn verify_token(&self, token: &str) -> Option<Vec<u8>> {
+        let (payload, signature) = token.trim().split_once('.')?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+        self.verify(&payload, signature).then_some(payload)
+    }
+
+    // The same list of keys, each replaced by HMAC(key, purpose), for signatures that must never
+    // verify as anything else, such as the X-SIGNATURE printed on cards. Rotation works as before
+    pub fn for_purpose(&self, purpose: &str) -> SigningKey {
+        let keys = self.keys.iter()
+            .map(|key| {
+                let mut mac = Self::mac(key);
+                mac.update(purpose.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            })
+            .collect();
+        SigningKey { keys: Arc::new(keys) }
+    }
+
+    // Key for signing session cookies, derived from the current key so it never doubles as a token
+    // key. Cookies only verify under one key, so rotating the current key signs everyone out
+    pub fn session_key(&self) -> Key {
+        let derive = |purpose: &[u8]| {
+            let mut mac = Self::mac(&self.keys[0]);
+            mac.update(purpose);
+            mac.finalize().into_bytes()
+        };
+        let mut master = derive(b"session-cookie:signing").to_vec();
+        master.extend_from_slice(&derive(b"session-cookie:encryption"));
+        Key::from(&master)
+    }
+}
@@ -0,0 +1,140 @@
+// Readable short links for cards, /v/jane-doe, for printed material where people type the URL.
+// Every card starts with a random slug; its owner can pick a readable one instead. The slugs a card
+// had before stay reserved for it and redirect to the current one, so a reprint is never urgent
+
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::Redirect,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::cache::ResponseCache;
+use crate::public::find_public_vcard;
+use crate::ErrorResponse;
+
+const MIN_SLUG_LEN: usize = 3;
+const MAX_SLUG_LEN: usize = 64;
+
+// Words that read as part of the app rather than as someone's card
+const RESERVED_SLUGS: &[&str] = &[
+    "about", "admin", "api", "app", "contact", "directory", "embed", "help", "login", "logout", "new",
+    "oembed", "profile", "settings", "signage", "static", "support", "v", "www",
+];
+
+#[derive(Deserialize, Serialize)]
+pub struct Slug {
+    slug: String,
+}
+
+// Lowercase letters, digits and single hyphens between them, e.g. jane-doe
+fn normalize_slug(slug: &str) -> Result<String, String> {
+    let slug = slug.trim().to_ascii_lowercase();
+    if !(MIN_SLUG_LEN..=MAX_SLUG_LEN).contains(&slug.len()) {
+        return Err(format!("slug must be between {} and {} characters", MIN_SLUG_LEN, MAX_SLUG_LEN));
+    }
+    let well_formed = slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && !slug.contains("--");
+    if !well_formed {
+        return Err("slug may only hold letters, digits and single hyphens between them, e.g. jane-doe".to_string());
+    }
+    if RESERVED_SLUGS.contains(&slug.as_str()) {
+        return Err(format!("{} is reserved", slug));
+    }
+    Ok(slug)
+}
+
+pub async fn update_slug_handler(
+    State(pool): State<SqlitePool>,
+    State(cache): State<ResponseCache>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Json(req): Json<Slug>,
+) -> Result<Json<Slug>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let slug = normalize_slug(&req.slug)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+
+    let mut tx = pool.begin().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    let current: Option<Option<String>> = sqlx::query_scalar("SELECT slug FROM vcards WHERE id = ?")
+        .bind(vcard_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    let current = current
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "vCard not found".to_string() })))?;
+    if current.as_deref() == Some(slug.as_str()) {
+        return Ok(Json(Slug { slug }));
+    }
+
+    // Taken by another card, now or before its own rename; a card may take back one of its old slugs
+    let owner: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM vcards WHERE slug = ? UNION ALL SELECT vcard_id FROM slug_redirects WHERE slug = ?"
+    )
+    .bind(&slug)
+    .bind(&slug)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if owner.is_some_and(|owner| owner != vcard_id) {
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: format!("{} is already taken", slug) })));
+    }
+
+    let saved = async {
+        sqlx::query("DELETE FROM slug_redirects WHERE slug = ?")
+            .bind(&slug)
+            .execute(&mut *tx)
+            .await?;
+        if let Some(previous) = &current {
+            sqlx::query("INSERT INTO slug_redirects (slug, vcard_id) VALUES (?, ?)")
+                .bind(previous)
+                .bind(vcard_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE vcards SET slug = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(&slug)
+            .bind(vcard_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    };
+    saved.await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update slug".to_string() })))?;
+    cache.invalidate(vcard_id);
+
+    Ok(Json(Slug { slug }))
+}
+
+// Public: /v/:slug goes to the card's embed page; an old slug first goes to the card's current one
+pub async fn short_link_handler(
+    State(pool): State<SqlitePool>,
+    Path(slug): Path<String>,
+) -> Result<Redirect, (StatusCode, Json<ErrorResponse>)> {
+    let slug = slug.to_ascii_lowercase();
+    let vcard = find_public_vcard(&pool, &slug).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if vcard.is_some() {
+        return Ok(Redirect::to(&format!("/embed/{}", slug)));
+    }
+
+    let renamed: Option<String> = sqlx::query_scalar(
+        "SELECT v.slug FROM slug_redirects r JOIN vcards v ON v.id = r.vcard_id WHERE r.slug = ? AND v.status = 'approved'"
+    )
+    .bind(&slug)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    renamed
+        .map(|current| Redirect::permanent(&format!("/v/{}", current)))
+        .ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Not found".to_string() })))
+}
@@ -0,0 +1,273 @@
+// Named styling presets: admins define a look once (colors, shapes, error correction, logo) and
+// users pick it with style_id when generating or editing a card, so every card of a brand comes out
+// alike. Like an uploaded logo, the card keeps its own copy of the options, so editing or deleting a
+// style never changes codes already printed
+
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::{ErrorResponse, MessageResponse, RenderOptions, VCardData};
+use vcard_core::gradient::Gradient;
+use vcard_core::style::{FinderShape, ModuleShape};
+use vcard_core::ErrorCorrection;
+
+const MAX_STYLE_NAME_LEN: usize = 50;
+
+// The render options a style may set: how the code looks, not how big it is drawn
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StyleOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gradient: Option<Gradient>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background_color: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    transparent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module_shape: Option<ModuleShape>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finder_shape: Option<FinderShape>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ec_level: Option<ErrorCorrection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quiet_zone: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logo: Option<String>,
+}
+
+impl StyleOptions {
+    // Fills in what the card's own options leave unset; options sent with the card win
+    fn apply(self, render: &mut RenderOptions) {
+        render.color = render.color.take().or(self.color);
+        render.gradient = render.gradient.take().or(self.gradient);
+        render.background_color = render.background_color.take().or(self.background_color);
+        render.transparent |= self.transparent;
+        render.module_shape = render.module_shape.or(self.module_shape);
+        render.finder_shape = render.finder_shape.or(self.finder_shape);
+        render.ec_level = render.ec_level.or(self.ec_level);
+        render.quiet_zone = render.quiet_zone.or(self.quiet_zone);
+        render.logo = render.logo.take().or(self.logo);
+    }
+
+    // Gives a stored card the style's look in place of its own; size and print settings are kept
+    fn replace(self, render: &mut RenderOptions) {
+        render.color = self.color;
+        render.gradient = self.gradient;
+        render.background_color = self.background_color;
+        render.transparent = self.transparent;
+        render.module_shape = self.module_shape;
+        render.finder_shape = self.finder_shape;
+        render.ec_level = self.ec_level;
+        render.quiet_zone = self.quiet_zone;
+        render.logo = self.logo;
+    }
+
+    // Checked as a card's options would be, so a style that can't draw a readable code is refused
+    fn validate(&self) -> Result<(), String> {
+        let mut render = RenderOptions::default();
+        self.clone().apply(&mut render);
+        render.validate_for_card()?;
+        if let Some(logo) = &render.logo {
+            vcard_core::render::check_logo(logo)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct StyleRow {
+    id: i64,
+    name: String,
+    options: String,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct Style {
+    id: i64,
+    name: String,
+    options: StyleOptions,
+    created_at: String,
+    updated_at: String,
+}
+
+impl From<StyleRow> for Style {
+    fn from(row: StyleRow) -> Self {
+        Style {
+            id: row.id,
+            name: row.name,
+            options: serde_json::from_str(&row.options).unwrap_or_default(),
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StyleRequest {
+    name: String,
+    #[serde(default)]
+    options: StyleOptions,
+}
+
+const STYLE_COLUMNS: &str = "id, name, options, created_at, updated_at";
+
+fn validate_request(req: &StyleRequest) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_STYLE_NAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Style name must be between 1 and {} characters", MAX_STYLE_NAME_LEN),
+        })));
+    }
+    req.options.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    Ok(name.to_string())
+}
+
+fn map_style_write_error(e: sqlx::Error) -> (StatusCode, Json<ErrorResponse>) {
+    if e.to_string().contains("UNIQUE") {
+        (StatusCode::CONFLICT, Json(ErrorResponse { error: "A style with that name already exists".to_string() }))
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() }))
+    }
+}
+
+async fn fetch_style(pool: &SqlitePool, style_id: i64) -> Result<Style, (StatusCode, Json<ErrorResponse>)> {
+    let row: Option<StyleRow> = sqlx::query_as(&format!("SELECT {} FROM qr_styles WHERE id = ?", STYLE_COLUMNS))
+        .bind(style_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    row.map(Style::from).ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Style not found".to_string() })))
+}
+
+// Swaps a submission's style_id for the style's options, before anything else looks at them
+pub async fn apply_style(pool: &SqlitePool, data: &mut VCardData) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let Some(style_id) = data.style_id.take() else {
+        return Ok(());
+    };
+    let style = fetch_style(pool, style_id).await
+        .map_err(|(status, e)| if status == StatusCode::NOT_FOUND { (StatusCode::BAD_REQUEST, e) } else { (status, e) })?;
+    style.options.apply(&mut data.render);
+    Ok(())
+}
+
+// Restyles an existing card, as bulk template reassignment does: the style's look replaces the
+// card's rather than filling in around it
+pub async fn assign_style(pool: &SqlitePool, style_id: i64, data: &mut VCardData) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    fetch_style(pool, style_id).await?.options.replace(&mut data.render);
+    Ok(())
+}
+
+async fn require_admin(session: &Session) -> Result<i64, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+    Ok(user.id)
+}
+
+// Every user can list the styles, to pick one
+pub async fn list_styles_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Style>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let rows: Vec<StyleRow> = sqlx::query_as(&format!("SELECT {} FROM qr_styles ORDER BY name COLLATE NOCASE", STYLE_COLUMNS))
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(rows.into_iter().map(Style::from).collect()))
+}
+
+pub async fn get_style_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(style_id): Path<i64>,
+) -> Result<Json<Style>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    Ok(Json(fetch_style(&pool, style_id).await?))
+}
+
+pub async fn create_style_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<StyleRequest>,
+) -> Result<(StatusCode, Json<Style>), (StatusCode, Json<ErrorResponse>)> {
+    let user_id = require_admin(&session).await?;
+    let name = validate_request(&req)?;
+
+    let result = sqlx::query("INSERT INTO qr_styles (name, options, created_by) VALUES (?, ?, ?)")
+        .bind(&name)
+        .bind(serde_json::to_string(&req.options).unwrap_or_else(|_| "{}".to_string()))
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(map_style_write_error)?;
+
+    Ok((StatusCode::CREATED, Json(fetch_style(&pool, result.last_insert_rowid()).await?)))
+}
+
+// Replaces the name and all of the options; cards already made with the style keep their look
+pub async fn update_style_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(style_id): Path<i64>,
+    Json(req): Json<StyleRequest>,
+) -> Result<Json<Style>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+    let name = validate_request(&req)?;
+
+    let result = sqlx::query("UPDATE qr_styles SET name = ?, options = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&name)
+        .bind(serde_json::to_string(&req.options).unwrap_or_else(|_| "{}".to_string()))
+        .bind(style_id)
+        .execute(&pool)
+        .await
+        .map_err(map_style_write_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Style not found".to_string() })));
+    }
+
+    Ok(Json(fetch_style(&pool, style_id).await?))
+}
+
+pub async fn delete_style_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(style_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    require_admin(&session).await?;
+
+    let result = sqlx::query("DELETE FROM qr_styles WHERE id = ?")
+        .bind(style_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete style".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Style not found".to_string() })));
+    }
+
+    Ok(Json(MessageResponse {
+        message: "Style deleted successfully".to_string(),
+    }))
+}
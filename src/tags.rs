@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::vcards::fetch_vcard;
+use crate::{ErrorResponse, MessageResponse};
+
+const MAX_TAG_NAME_LEN: usize = 50;
+
+#[derive(Serialize, sqlx::FromRow, async_graphql::SimpleObject)]
+pub struct Tag {
+    id: i64,
+    name: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct TagRequest {
+    name: String,
+}
+
+fn validate_tag_name(name: &str) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > MAX_TAG_NAME_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Tag name must be between 1 and {} characters", MAX_TAG_NAME_LEN),
+        })));
+    }
+    Ok(name.to_string())
+}
+
+fn map_tag_write_error(e: sqlx::Error) -> (StatusCode, Json<ErrorResponse>) {
+    if e.to_string().contains("UNIQUE") {
+        (StatusCode::CONFLICT, Json(ErrorResponse { error: "Tag already exists".to_string() }))
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() }))
+    }
+}
+
+pub async fn list_tags(pool: &SqlitePool) -> Result<Vec<Tag>, sqlx::Error> {
+    sqlx::query_as("SELECT id, name, created_at FROM tags ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn vcard_tags(pool: &SqlitePool, vcard_id: i64) -> Result<Vec<Tag>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT t.id, t.name, t.created_at FROM tags t JOIN vcard_tags vt ON vt.tag_id = t.id WHERE vt.vcard_id = ? ORDER BY t.name"
+    )
+    .bind(vcard_id)
+    .fetch_all(pool)
+    .await
+}
+
+async fn fetch_tag(pool: &SqlitePool, tag_id: i64) -> Result<Tag, (StatusCode, Json<ErrorResponse>)> {
+    let tag: Option<Tag> = sqlx::query_as("SELECT id, name, created_at FROM tags WHERE id = ?")
+        .bind(tag_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    tag.ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Tag not found".to_string() })))
+}
+
+pub async fn list_tags_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+) -> Result<Json<Vec<Tag>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let tags = list_tags(&pool).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(tags))
+}
+
+pub async fn create_tag_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<TagRequest>,
+) -> Result<(StatusCode, Json<Tag>), (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let name = validate_tag_name(&req.name)?;
+
+    let result = sqlx::query("INSERT INTO tags (name) VALUES (?)")
+        .bind(&name)
+        .execute(&pool)
+        .await
+        .map_err(map_tag_write_error)?;
+
+    Ok((StatusCode::CREATED, Json(fetch_tag(&pool, result.last_insert_rowid()).await?)))
+}
+
+// Renaming or deleting a tag affects every card using it, so admins only
+pub async fn update_tag_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(tag_id): Path<i64>,
+    Json(req): Json<TagRequest>,
+) -> Result<Json<Tag>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    let name = validate_tag_name(&req.name)?;
+
+    let result = sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+        .bind(&name)
+        .bind(tag_id)
+        .execute(&pool)
+        .await
+        .map_err(map_tag_write_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Tag not found".to_string() })));
+    }
+
+    Ok(Json(fetch_tag(&pool, tag_id).await?))
+}
+
+pub async fn delete_tag_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(tag_id): Path<i64>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(ErrorResponse { error: "Admin access required".to_string() })));
+    }
+
+    sqlx::query("DELETE FROM tags WHERE id = ?")
+        .bind(tag_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to delete tag".to_string() })))?;
+
+    Ok(Json(MessageResponse {
+        message: "Tag deleted successfully".to_string(),
+    }))
+}
+
+pub async fn list_vcard_tags_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+) -> Result<Json<Vec<Tag>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    fetch_vcard(&pool, vcard_id).await?;
+
+    let tags = vcard_tags(&pool, vcard_id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(tags))
+}
+
+pub async fn add_vcard_tag_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path((vcard_id, tag_id)): Path<(i64, i64)>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    fetch_vcard(&pool, vcard_id).await?;
+    fetch_tag(&pool, tag_id).await?;
+
+    sqlx::query("INSERT OR IGNORE INTO vcard_tags (vcard_id, tag_id) VALUES (?, ?)")
+        .bind(vcard_id)
+        .bind(tag_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to tag vCard".to_string() })))?;
+
+    Ok(Json(MessageResponse {
+        message: "Tag added".to_string(),
+    }))
+}
+
+pub async fn remove_vcard_tag_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path((vcard_id, tag_id)): Path<(i64, i64)>,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    sqlx::query("DELETE FROM vcard_tags WHERE vcard_id = ? AND tag_id = ?")
+        .bind(vcard_id)
+        .bind(tag_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to untag vCard".to_string() })))?;
+
+    Ok(Json(MessageResponse {
+        message: "Tag removed".to_string(),
+    }))
+}
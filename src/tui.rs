@@ -0,0 +1,185 @@
+use qrcode::render::unicode::Dense1x2;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use vcard_core::{build_qr_code, render_qr_svg};
+
+use crate::{clean_field, generate_vcard, parse_color, render_qr_png, RenderOptions, VCardData};
+
+// Form rows in display order; the first two are required
+const FIELDS: &[&str] = &[
+    "First name", "Last name", "Mobile", "Work phone", "Email", "Company", "Role",
+    "Street", "City", "State", "Postal code", "Country (e.g. US)", "Website", "Color (#rrggbb)",
+];
+const REQUIRED_FIELDS: usize = 2;
+
+struct App {
+    values: Vec<String>,
+    selected: usize,
+    // Result of the last save, shown under the form
+    status: String,
+}
+
+impl App {
+    fn value(&self, index: usize) -> Option<String> {
+        clean_field(&Some(self.values[index].clone())).map(str::to_string)
+    }
+
+    fn data(&self) -> VCardData {
+        VCardData {
+            first_name: self.values[0].trim().to_string(),
+            last_name: self.values[1].trim().to_string(),
+            mobile: self.value(2),
+            work: self.value(3),
+            email: self.value(4),
+            company: self.value(5),
+            role: self.value(6),
+            street: self.value(7),
+            city: self.value(8),
+            state: self.value(9),
+            postal_code: self.value(10),
+            country: self.value(11),
+            website: self.value(12),
+            render: RenderOptions { color: self.value(13), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn complete(&self) -> bool {
+        self.values[..REQUIRED_FIELDS].iter().all(|value| !value.trim().is_empty())
+    }
+
+    // Named after the contact, in the working directory
+    fn file_name(&self, extension: &str) -> String {
+        let data = self.data();
+        let stem: String = format!("{}-{}", data.first_name, data.last_name)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        format!("{}.{}", stem, extension)
+    }
+
+    fn save(&mut self, extension: &str) {
+        if !self.complete() {
+            self.status = "First name and last name are required".to_string();
+            return;
+        }
+        let data = self.data();
+        let content = generate_vcard(&data);
+        let rendered = match extension {
+            "svg" => render_qr_svg(&content, &data.render).map(String::into_bytes),
+            _ => render_qr_png(&content, &data.render),
+        };
+        let path = self.file_name(extension);
+        self.status = match rendered.and_then(|bytes| std::fs::write(&path, bytes).map_err(|e| e.to_string())) {
+            Ok(()) => format!("Saved {}", path),
+            Err(e) => format!("Could not save {}: {}", path, e),
+        };
+    }
+}
+
+// Walk through the contact fields with a live preview; F2 saves a PNG, F3 an SVG
+pub fn run() -> Result<(), String> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal) -> Result<(), String> {
+    let mut app = App {
+        values: vec![String::new(); FIELDS.len()],
+        selected: 0,
+        status: "Tab/↑↓ move · F2 save PNG · F3 save SVG · Esc quit".to_string(),
+    };
+
+    loop {
+        terminal.draw(|frame| draw(frame, &app)).map_err(|e| e.to_string())?;
+
+        let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::F(2) => app.save("png"),
+            KeyCode::F(3) => app.save("svg"),
+            KeyCode::Down | KeyCode::Tab | KeyCode::Enter => app.selected = (app.selected + 1) % FIELDS.len(),
+            KeyCode::Up | KeyCode::BackTab => app.selected = (app.selected + FIELDS.len() - 1) % FIELDS.len(),
+            KeyCode::Backspace => {
+                app.values[app.selected].pop();
+            }
+            KeyCode::Char(c) => app.values[app.selected].push(c),
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [form_area, preview_area] = Layout::horizontal([Constraint::Length(48), Constraint::Min(0)]).areas(frame.area());
+    let [fields_area, status_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(form_area);
+
+    let lines: Vec<Line> = FIELDS.iter().enumerate().map(|(index, label)| {
+        let marker = if index < REQUIRED_FIELDS { "*" } else { " " };
+        let style = if index == app.selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        Line::from(vec![
+            Span::raw(format!("{:>16}{} ", label, marker)),
+            Span::styled(format!("{:<26}", app.values[index]), style),
+        ])
+    }).collect();
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Contact ")), fields_area);
+    frame.render_widget(Paragraph::new(app.status.as_str()).block(Block::bordered()), status_area);
+
+    draw_preview(frame, app, preview_area);
+}
+
+fn draw_preview(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::bordered().title(" Preview ");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if !app.complete() {
+        frame.render_widget(Paragraph::new("Fill in first and last name to see the code"), inner);
+        return;
+    }
+
+    let data = app.data();
+    let code = match build_qr_code(&generate_vcard(&data), &data.render) {
+        Ok(code) => code,
+        Err(e) => {
+            frame.render_widget(Paragraph::new(format!("Cannot encode: {}", e)), inner);
+            return;
+        }
+    };
+
+    // Two modules per character cell vertically
+    let text = code.render::<Dense1x2>().build();
+    let rows: Vec<&str> = text.lines().collect();
+    let width = rows.first().map_or(0, |row| row.chars().count());
+    if rows.len() > inner.height as usize || width > inner.width as usize {
+        frame.render_widget(Paragraph::new(format!(
+            "Enlarge the terminal to preview: the code needs {}x{} cells",
+            width, rows.len()
+        )), inner);
+        return;
+    }
+
+    // Always dark on light, whatever the terminal's theme, so the preview scans
+    let (r, g, b) = data.render.color.as_deref().map(parse_color).unwrap_or((0, 0, 0));
+    let style = Style::default().fg(Color::Rgb(r, g, b)).bg(Color::Rgb(255, 255, 255));
+    let lines: Vec<Line> = rows.into_iter().map(|row| Line::styled(row.to_string(), style)).collect();
+    frame.render_widget(Paragraph::new(lines), inner);
+}
@@ -0,0 +1,348 @@
+use axum::{
+    body::Bytes,
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tower_sessions::Session;
+
+use crate::auth::get_current_user;
+use crate::{ErrorResponse, MessageResponse, VCardData};
+
+// Limits on a single part and on the assembled file
+pub const MAX_PART_BYTES: usize = 5 * 1024 * 1024;
+const MAX_UPLOAD_BYTES: i64 = 100 * 1024 * 1024;
+
+// Room for a few large imports at once per user, counting completed uploads and parts in flight
+const MAX_USER_UPLOAD_BYTES: i64 = 250 * 1024 * 1024;
+
+// Pending uploads older than this are considered abandoned
+const UPLOAD_EXPIRY_HOURS: i64 = 24;
+// Completed uploads are kept this long for imports and logos to pick up, and for import reports
+const COMPLETED_UPLOAD_EXPIRY_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+pub struct InitiateUploadRequest {
+    filename: String,
+}
+
+#[derive(Serialize)]
+pub struct InitiateUploadResponse {
+    upload_id: String,
+    max_part_bytes: usize,
+}
+
+#[derive(Serialize)]
+pub struct UploadStatus {
+    upload_id: String,
+    filename: String,
+    status: String,
+    size: Option<i64>,
+    // Parts received so far, so an interrupted client knows where to resume
+    parts: Vec<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct UploadRow {
+    id: String,
+    filename: String,
+    status: String,
+    size: Option<i64>,
+}
+
+async fn fetch_upload(pool: &SqlitePool, upload_id: &str, user_id: i64) -> Result<UploadRow, (StatusCode, Json<ErrorResponse>)> {
+    let upload: Option<UploadRow> = sqlx::query_as("SELECT id, filename, status, size FROM uploads WHERE id = ? AND user_id = ?")
+        .bind(upload_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    upload.ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "Upload not found".to_string() })))
+}
+
+async fn fetch_pending_upload(pool: &SqlitePool, upload_id: &str, user_id: i64) -> Result<UploadRow, (StatusCode, Json<ErrorResponse>)> {
+    let upload = fetch_upload(pool, upload_id, user_id).await?;
+    if upload.status != "pending" {
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: "Upload is already complete".to_string() })));
+    }
+    Ok(upload)
+}
+
+// Contents of a completed upload, optionally restricted to its owner
+pub async fn read_upload(pool: &SqlitePool, upload_id: &str, user_id: Option<i64>) -> Result<Option<Vec<u8>>, sqlx::Error> {
+    sqlx::query_scalar("SELECT data FROM uploads WHERE id = ?1 AND status = 'completed' AND (?2 IS NULL OR user_id = ?2)")
+        .bind(upload_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Swaps a submission's logo_upload_id for the upload itself as its logo. The card keeps its own
+// copy, so it renders the same after the upload is gone
+pub async fn attach_logo(pool: &SqlitePool, user_id: i64, data: &mut VCardData) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let Some(upload_id) = data.logo_upload_id.take() else {
+        return Ok(());
+    };
+    let png = read_upload(pool, &upload_id, Some(user_id)).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?
+        .ok_or((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Logo upload not found or not completed".to_string() })))?;
+    data.render.logo = Some(format!("data:image/png;base64,{}", base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png)));
+    Ok(())
+}
+
+// Opportunistically drops abandoned uploads, and completed ones nobody has used in a while
+async fn expire_uploads(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "DELETE FROM uploads WHERE (status = 'pending' AND created_at < datetime('now', ?1))
+         OR (status = 'completed' AND completed_at < datetime('now', ?2))",
+    )
+    .bind(format!("-{} hours", UPLOAD_EXPIRY_HOURS))
+    .bind(format!("-{} days", COMPLETED_UPLOAD_EXPIRY_DAYS))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Everything a user has stored, finished or not, leaving out one part that is about to be replaced
+async fn stored_bytes(pool: &SqlitePool, user_id: i64, upload_id: &str, part_number: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COALESCE((SELECT SUM(size) FROM uploads WHERE user_id = ?1 AND status = 'completed'), 0)
+              + COALESCE((SELECT SUM(LENGTH(p.data)) FROM upload_parts p JOIN uploads u ON u.id = p.upload_id
+                          WHERE u.user_id = ?1 AND NOT (p.upload_id = ?2 AND p.part_number = ?3)), 0)",
+    )
+    .bind(user_id)
+    .bind(upload_id)
+    .bind(part_number)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn initiate_upload_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Json(req): Json<InitiateUploadRequest>,
+) -> Result<(StatusCode, Json<InitiateUploadResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    if let Err(e) = expire_uploads(&pool).await {
+        eprintln!("Failed to expire old uploads: {}", e);
+    }
+
+    let upload_id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query("INSERT INTO uploads (id, user_id, filename) VALUES (?, ?, ?)")
+        .bind(&upload_id)
+        .bind(user.id)
+        .bind(req.filename.trim())
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to start upload".to_string() })))?;
+
+    Ok((StatusCode::CREATED, Json(InitiateUploadResponse {
+        upload_id,
+        max_part_bytes: MAX_PART_BYTES,
+    })))
+}
+
+// Re-sending a part overwrites it, so retries after a dropped connection are safe
+pub async fn upload_part_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path((upload_id, part_number)): Path<(String, i64)>,
+    body: Bytes,
+) -> Result<Json<MessageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    fetch_pending_upload(&pool, &upload_id, user.id).await?;
+
+    if part_number < 1 {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "Part numbers start at 1".to_string() })));
+    }
+    if body.is_empty() || body.len() > MAX_PART_BYTES {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Parts must be between 1 and {} bytes", MAX_PART_BYTES),
+        })));
+    }
+
+    let stored: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(LENGTH(data)), 0) FROM upload_parts WHERE upload_id = ? AND part_number != ?")
+        .bind(&upload_id)
+        .bind(part_number)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    if stored + body.len() as i64 > MAX_UPLOAD_BYTES {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, Json(ErrorResponse { error: "Upload is too large".to_string() })));
+    }
+
+    let user_stored = stored_bytes(&pool, user.id, &upload_id, part_number).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+    if user_stored + body.len() as i64 > MAX_USER_UPLOAD_BYTES {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, Json(ErrorResponse {
+            error: format!("Upload quota of {} MB exceeded; finished uploads are removed after {} days", MAX_USER_UPLOAD_BYTES / (1024 * 1024), COMPLETED_UPLOAD_EXPIRY_DAYS),
+        })));
+    }
+
+    sqlx::query("INSERT OR REPLACE INTO upload_parts (upload_id, part_number, data) VALUES (?, ?, ?)")
+        .bind(&upload_id)
+        .bind(part_number)
+        .bind(body.as_ref())
+        .execute(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to store part".to_string() })))?;
+
+    Ok(Json(MessageResponse {
+        message: format!("Part {} stored", part_number),
+    }))
+}
+
+pub async fn get_upload_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadStatus>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let upload = fetch_upload(&pool, &upload_id, user.id).await?;
+
+    let parts: Vec<i64> = sqlx::query_scalar("SELECT part_number FROM upload_parts WHERE upload_id = ? ORDER BY part_number")
+        .bind(&upload_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(UploadStatus {
+        upload_id: upload.id,
+        filename: upload.filename,
+        status: upload.status,
+        size: upload.size,
+        parts,
+    }))
+}
+
+// Assemble parts 1..=n into the final file
+pub async fn complete_upload_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadStatus>, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let upload = fetch_pending_upload(&pool, &upload_id, user.id).await?;
+
+    let parts: Vec<(i64, Vec<u8>)> = sqlx::query_as("SELECT part_number, data FROM upload_parts WHERE upload_id = ? ORDER BY part_number")
+        .bind(&upload_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    if parts.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "No parts uploaded".to_string() })));
+    }
+    if let Some(missing) = parts.iter().zip(1..).find(|((number, _), expected)| number != expected) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: format!("Part {} is missing", missing.1) })));
+    }
+
+    let data: Vec<u8> = parts.into_iter().flat_map(|(_, data)| data).collect();
+    let size = data.len() as i64;
+
+    let mut tx = pool.begin().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    // Only the first of two concurrent completions assembles the file; the other finds it done
+    let completed = sqlx::query("UPDATE uploads SET status = 'completed', size = ?, data = ?, completed_at = CURRENT_TIMESTAMP WHERE id = ? AND status = 'pending'")
+        .bind(size)
+        .bind(&data)
+        .bind(&upload_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to complete upload".to_string() })))?;
+    if completed.rows_affected() == 0 {
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error: "Upload is already complete".to_string() })));
+    }
+
+    sqlx::query("DELETE FROM upload_parts WHERE upload_id = ?")
+        .bind(&upload_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to complete upload".to_string() })))?;
+
+    tx.commit().await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to complete upload".to_string() })))?;
+
+    Ok(Json(UploadStatus {
+        upload_id: upload.id,
+        filename: upload.filename,
+        status: "completed".to_string(),
+        size: Some(size),
+        parts: Vec::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expire_uploads, stored_bytes};
+    use crate::repo::UserRepo;
+
+    async fn add_user(pool: &sqlx::SqlitePool, username: &str) -> i64 {
+        pool.create_user(username, "unused", false).await.unwrap().unwrap()
+    }
+
+    async fn add_upload(pool: &sqlx::SqlitePool, user_id: i64, id: &str, status: &str, age: &str) {
+        sqlx::query(
+            "INSERT INTO uploads (id, user_id, filename, status, size, data, created_at, completed_at)
+             VALUES (?1, ?4, 'staff.csv', ?2, 3, X'616263', datetime('now', ?3), CASE WHEN ?2 = 'completed' THEN datetime('now', ?3) END)",
+        )
+        .bind(id)
+        .bind(status)
+        .bind(age)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn upload_ids(pool: &sqlx::SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("SELECT id FROM uploads ORDER BY id").fetch_all(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn old_uploads_expire_whether_pending_or_completed() {
+        let pool = crate::test_pool().await;
+        let user_id = add_user(&pool, "ada").await;
+        add_upload(&pool, user_id, "a-fresh-pending", "pending", "-1 hours").await;
+        add_upload(&pool, user_id, "b-stale-pending", "pending", "-25 hours").await;
+        add_upload(&pool, user_id, "c-fresh-completed", "completed", "-6 days").await;
+        add_upload(&pool, user_id, "d-stale-completed", "completed", "-8 days").await;
+
+        expire_uploads(&pool).await.unwrap();
+        assert_eq!(upload_ids(&pool).await, ["a-fresh-pending", "c-fresh-completed"]);
+    }
+
+    #[tokio::test]
+    async fn quota_counts_completed_uploads_and_pending_parts() {
+        let pool = crate::test_pool().await;
+        let user_id = add_user(&pool, "ada").await;
+        let other_id = add_user(&pool, "grace").await;
+        add_upload(&pool, user_id, "done", "completed", "-1 hours").await;
+        add_upload(&pool, user_id, "open", "pending", "-1 hours").await;
+        for part in 1..=2 {
+            sqlx::query("INSERT INTO upload_parts (upload_id, part_number, data) VALUES ('open', ?, X'0102')")
+                .bind(part)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // 3 bytes completed, plus two parts of 2 bytes, one of which is being replaced
+        assert_eq!(stored_bytes(&pool, user_id, "open", 2).await.unwrap(), 5);
+        assert_eq!(stored_bytes(&pool, user_id, "open", 3).await.unwrap(), 7);
+        assert_eq!(stored_bytes(&pool, other_id, "open", 3).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,372 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use tower_sessions::Session;
+
+use crate::approvals::{self, ReviewStatus};
+use crate::auth::get_current_user;
+use crate::cache::ResponseCache;
+use crate::capacity;
+use crate::db::DbRouter;
+use crate::events::EventBus;
+use crate::linkcheck;
+use crate::metrics;
+use crate::repo::VcardRepo;
+use crate::revisions;
+use crate::settings;
+use crate::styles;
+use crate::uploads;
+use crate::{clean_field, ErrorResponse, Messengers, Payment, RenderOptions, VCardData};
+
+// Contact and render columns of the vcards table (everything but identity and timestamps)
+const CONTACT_COLUMNS: &str = "first_name, last_name, mobile, work, email, company, role, street, city, state, postal_code, country, website, extensions, labels, messengers, payment, render_options";
+
+// Every column a VCard is read from
+pub const VCARD_COLUMNS: &str = "id, slug, first_name, last_name, mobile, work, email, company, role, street, city, state, postal_code, country, website, extensions, labels, messengers, payment, render_options, status, reviewed_by, reviewed_at, review_note, broken_links, created_by, created_at, updated_at";
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct ListVCardsQuery {
+    tag: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+// A stored vcard record
+#[derive(Clone, Serialize, sqlx::FromRow, async_graphql::SimpleObject)]
+#[graphql(complex)]
+pub struct VCard {
+    pub id: i64,
+    // Public identifier used by embeds and short links; random, so unguessable, until a readable
+    // one is picked
+    pub slug: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub mobile: Option<String>,
+    pub work: Option<String>,
+    pub email: Option<String>,
+    pub company: Option<String>,
+    pub role: Option<String>,
+    pub street: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    // Upper-case ISO 3166-1 alpha-2 code
+    pub country: Option<String>,
+    pub website: Option<String>,
+    // X- properties, stored as a JSON object
+    #[sqlx(json)]
+    pub extensions: BTreeMap<String, String>,
+    // Custom labels by field, stored as a JSON object
+    #[sqlx(json)]
+    pub labels: BTreeMap<String, String>,
+    // Stored as JSON; serialized inline like the render options
+    #[serde(flatten)]
+    #[sqlx(json)]
+    #[graphql(flatten)]
+    pub messengers: Messengers,
+    #[serde(flatten)]
+    #[sqlx(json)]
+    #[graphql(flatten)]
+    pub payment: Payment,
+    // Stored as JSON; serialized inline like the submission it came from
+    #[serde(flatten)]
+    #[sqlx(json, rename = "render_options")]
+    #[graphql(flatten)]
+    pub render: RenderOptions,
+    // Only approved cards are served publicly; see approvals.rs
+    pub status: ReviewStatus,
+    pub reviewed_by: Option<i64>,
+    pub reviewed_at: Option<String>,
+    // Reviewer's reason, mostly for rejections
+    pub review_note: Option<String>,
+    // Fields whose links were broken at the last link check, e.g. ["website", "extensions.X-BOOKING"]
+    #[sqlx(json)]
+    pub broken_links: Vec<String>,
+    // User who generated, imported or cloned the card; unset for kiosk, integration and older cards
+    pub created_by: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// The stored card as submission data, for re-rendering
+impl From<VCard> for VCardData {
+    fn from(vcard: VCard) -> Self {
+        VCardData {
+            first_name: vcard.first_name,
+            last_name: vcard.last_name,
+            mobile: vcard.mobile,
+            work: vcard.work,
+            email: vcard.email,
+            company: vcard.company,
+            role: vcard.role,
+            street: vcard.street,
+            city: vcard.city,
+            state: vcard.state,
+            postal_code: vcard.postal_code,
+            country: vcard.country,
+            website: vcard.website,
+            extensions: vcard.extensions,
+            labels: vcard.labels,
+            messengers: vcard.messengers,
+            payment: vcard.payment,
+            render: vcard.render,
+            draft_id: None,
+            format: Default::default(),
+            pdf_size_mm: None,
+            caption: None,
+            encrypt: false,
+            logo_upload_id: None,
+            style_id: None,
+        }
+    }
+}
+
+// Random slug for a new card, matching the one the slug migration backfills
+fn new_slug() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+// Page size and offset for listings, clamped to sane bounds
+pub fn page_bounds(limit: Option<i64>, offset: Option<i64>) -> (i64, i64) {
+    (limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE), offset.unwrap_or(0).max(0))
+}
+
+impl VcardRepo for SqlitePool {
+    // Fields are stored cleaned, the way generate_vcard reads them
+    async fn insert_vcard(&self, data: &VCardData, created_by: Option<i64>) -> Result<i64, sqlx::Error> {
+        let result = metrics::timed("insert_vcard", sqlx::query(&format!(
+            "INSERT INTO vcards (slug, created_by, {}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            CONTACT_COLUMNS
+        ))
+        .bind(new_slug())
+        .bind(created_by)
+        .bind(data.first_name.trim())
+        .bind(data.last_name.trim())
+        .bind(clean_field(&data.mobile))
+        .bind(clean_field(&data.work))
+        .bind(clean_field(&data.email))
+        .bind(clean_field(&data.company))
+        .bind(clean_field(&data.role))
+        .bind(clean_field(&data.street))
+        .bind(clean_field(&data.city))
+        .bind(clean_field(&data.state))
+        .bind(clean_field(&data.postal_code))
+        .bind(clean_field(&data.country).map(str::to_ascii_uppercase))
+        .bind(clean_field(&data.website))
+        .bind(sqlx::types::Json(&data.extensions))
+        .bind(sqlx::types::Json(&data.labels))
+        .bind(sqlx::types::Json(data.messengers.cleaned()))
+        .bind(sqlx::types::Json(data.payment.cleaned()))
+        .bind(sqlx::types::Json(&data.render))
+        .execute(self))
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn find_vcard(&self, vcard_id: i64) -> Result<Option<VCard>, sqlx::Error> {
+        metrics::timed("find_vcard", sqlx::query_as(&format!("SELECT {} FROM vcards WHERE id = ?", VCARD_COLUMNS))
+            .bind(vcard_id)
+            .fetch_optional(self))
+            .await
+    }
+
+    async fn find_vcard_by_slug(&self, slug: &str) -> Result<Option<VCard>, sqlx::Error> {
+        metrics::timed("find_vcard_by_slug", sqlx::query_as(&format!("SELECT {} FROM vcards WHERE slug = ?", VCARD_COLUMNS))
+            .bind(slug)
+            .fetch_optional(self))
+            .await
+    }
+
+    async fn update_vcard(&self, vcard_id: i64, data: &VCardData) -> Result<bool, sqlx::Error> {
+        let result = metrics::timed("update_vcard", sqlx::query(
+            r#"
+            UPDATE vcards SET first_name = ?, last_name = ?, mobile = ?, work = ?, email = ?, company = ?,
+                role = ?, street = ?, city = ?, state = ?, postal_code = ?, country = ?, website = ?, extensions = ?, labels = ?, messengers = ?, payment = ?, render_options = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#
+        )
+        .bind(data.first_name.trim())
+        .bind(data.last_name.trim())
+        .bind(clean_field(&data.mobile))
+        .bind(clean_field(&data.work))
+        .bind(clean_field(&data.email))
+        .bind(clean_field(&data.company))
+        .bind(clean_field(&data.role))
+        .bind(clean_field(&data.street))
+        .bind(clean_field(&data.city))
+        .bind(clean_field(&data.state))
+        .bind(clean_field(&data.postal_code))
+        .bind(clean_field(&data.country).map(str::to_ascii_uppercase))
+        .bind(clean_field(&data.website))
+        .bind(sqlx::types::Json(&data.extensions))
+        .bind(sqlx::types::Json(&data.labels))
+        .bind(sqlx::types::Json(data.messengers.cleaned()))
+        .bind(sqlx::types::Json(data.payment.cleaned()))
+        .bind(sqlx::types::Json(&data.render))
+        .bind(vcard_id)
+        .execute(self))
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_vcard(&self, vcard_id: i64) -> Result<bool, sqlx::Error> {
+        let result = metrics::timed("delete_vcard", sqlx::query("DELETE FROM vcards WHERE id = ?")
+            .bind(vcard_id)
+            .execute(self))
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_vcards(&self, tag: Option<&str>, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<VCard>, sqlx::Error> {
+        let (limit, offset) = page_bounds(limit, offset);
+
+        metrics::timed("list_vcards", sqlx::query_as(&format!(
+            r#"
+            SELECT {} FROM vcards
+            WHERE ?1 IS NULL OR id IN (
+                SELECT vt.vcard_id FROM vcard_tags vt JOIN tags t ON t.id = vt.tag_id WHERE t.name = ?1
+            )
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?2 OFFSET ?3
+            "#,
+            VCARD_COLUMNS
+        ))
+        .bind(tag)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self))
+        .await
+    }
+}
+
+// Approved cards for a printed directory, alphabetical by name; both filters are optional
+pub async fn list_vcards_by_name(pool: &SqlitePool, tag: Option<&str>, company: Option<&str>, limit: i64) -> Result<Vec<VCard>, sqlx::Error> {
+    metrics::timed("list_vcards_by_name", sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM vcards
+        WHERE (?1 IS NULL OR id IN (
+            SELECT vt.vcard_id FROM vcard_tags vt JOIN tags t ON t.id = vt.tag_id WHERE t.name = ?1
+        ))
+        AND (?2 IS NULL OR company = ?2 COLLATE NOCASE)
+        AND status = 'approved'
+        ORDER BY last_name COLLATE NOCASE, first_name COLLATE NOCASE, id
+        LIMIT ?3
+        "#,
+        VCARD_COLUMNS
+    ))
+    .bind(tag)
+    .bind(company)
+    .bind(limit)
+    .fetch_all(pool))
+    .await
+}
+
+pub async fn fetch_vcard(repo: &impl VcardRepo, vcard_id: i64) -> Result<VCard, (StatusCode, Json<ErrorResponse>)> {
+    let vcard = repo.find_vcard(vcard_id).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    vcard.ok_or((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "vCard not found".to_string() })))
+}
+
+pub async fn list_vcards_handler(
+    State(db): State<DbRouter>,
+    session: Session,
+    Query(query): Query<ListVCardsQuery>,
+) -> Result<Json<Vec<VCard>>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let vcards = db.reader().list_vcards(query.tag.as_deref(), query.limit, query.offset).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(vcards))
+}
+
+pub async fn get_vcard_handler(
+    State(pool): State<SqlitePool>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+) -> Result<Json<VCard>, (StatusCode, Json<ErrorResponse>)> {
+    get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    Ok(Json(fetch_vcard(&pool, vcard_id).await?))
+}
+
+pub async fn update_vcard_handler(
+    State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    State(cache): State<ResponseCache>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+    Json(mut data): Json<VCardData>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    styles::apply_style(&pool, &mut data).await?;
+    uploads::attach_logo(&pool, user.id, &mut data).await?;
+    data.validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e })))?;
+    settings::check_required_fields(&pool, &data).await?;
+    // Stored cards are rendered later, so one that can never be drawn is refused now
+    if let Err(e) = capacity::check(&crate::generate_vcard(&data), &data) {
+        return Ok(e.into_response());
+    }
+
+    let updated = revisions::update_vcard(&pool, vcard_id, &data, Some(user.id)).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to update vCard".to_string() })))?;
+
+    if !updated {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "vCard not found".to_string() })));
+    }
+    cache.invalidate(vcard_id);
+    linkcheck::forget_changed(&pool, vcard_id, &data).await;
+
+    // An edit by a regular user goes back through review
+    approvals::submit(&pool, &events, vcard_id, &user).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    Ok(Json(fetch_vcard(&pool, vcard_id).await?).into_response())
+}
+
+// Copy an existing card as the starting point for a new one
+pub async fn clone_vcard_handler(
+    State(pool): State<SqlitePool>,
+    State(events): State<EventBus>,
+    session: Session,
+    Path(vcard_id): Path<i64>,
+) -> Result<(StatusCode, Json<VCard>), (StatusCode, Json<ErrorResponse>)> {
+    let user = get_current_user(&session).await
+        .ok_or((StatusCode::UNAUTHORIZED, Json(ErrorResponse { error: "Not authenticated".to_string() })))?;
+
+    let result = sqlx::query(&format!(
+        "INSERT INTO vcards (slug, created_by, {0}) SELECT ?, ?, {0} FROM vcards WHERE id = ?",
+        CONTACT_COLUMNS
+    ))
+    .bind(new_slug())
+    .bind(user.id)
+    .bind(vcard_id)
+    .execute(&pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Failed to clone vCard".to_string() })))?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error: "vCard not found".to_string() })));
+    }
+
+    let clone_id = result.last_insert_rowid();
+    approvals::submit(&pool, &events, clone_id, &user).await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: "Database error".to_string() })))?;
+
+    let vcard = fetch_vcard(&pool, clone_id).await?;
+    Ok((StatusCode::CREATED, Json(vcard)))
+}
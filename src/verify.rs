@@ -0,0 +1,59 @@
+use axum::extract::{Json, State};
+use serde::Serialize;
+
+use crate::signing::SigningKey;
+
+pub const SIGNATURE_PROPERTY: &str = "X-SIGNATURE";
+// Card signatures use their own key derived from the secret, so no token the server hands out can
+// pass for one, nor the other way round
+pub const SIGNATURE_PURPOSE: &str = "vcard-signature";
+
+pub fn signing_enabled() -> bool {
+    std::env::var("SIGN_VCARDS").is_ok_and(|v| v == "true" || v == "1")
+}
+
+// The text a signature covers: the card without its signature line, with line endings and
+// trailing whitespace normalized, since scanner apps and mail clients rewrite both
+pub fn canonical_vcard(vcard: &str) -> String {
+    vcard
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !is_signature_line(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Property names are case-insensitive and may carry a group or parameters
+fn is_signature_line(line: &str) -> bool {
+    let name = line.split([':', ';']).next().unwrap_or_default();
+    name.rsplit('.').next().unwrap_or_default().eq_ignore_ascii_case(SIGNATURE_PROPERTY)
+}
+
+fn signature(vcard: &str) -> Option<&str> {
+    vcard
+        .lines()
+        .find(|line| is_signature_line(line))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, value)| value.trim())
+}
+
+#[derive(Serialize)]
+pub struct VerifyResponse {
+    // Whether the card carries a signature at all
+    signed: bool,
+    // Whether it was signed by this instance and not changed since
+    valid: bool,
+}
+
+// Public: check a scanned card, sent as the raw vCard text, against the instance key
+pub async fn verify_handler(
+    State(key): State<SigningKey>,
+    body: String,
+) -> Json<VerifyResponse> {
+    let Some(signature) = signature(&body) else {
+        return Json(VerifyResponse { signed: false, valid: false });
+    };
+
+    let valid = key.for_purpose(SIGNATURE_PURPOSE).verify(canonical_vcard(&body).as_bytes(), signature);
+    Json(VerifyResponse { signed: true, valid })
+}
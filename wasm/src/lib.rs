@@ -0,0 +1,25 @@
+// JavaScript bindings for offline previews in the browser. Contacts are passed as JSON in the
+// shape of the server's /api/generate body, and go through the same vcard-core code the
+// server uses, so a preview is exactly what the server will produce
+
+use vcard_core::{generate_vcard, render_qr_svg, ContactRecord};
+use wasm_bindgen::prelude::*;
+
+fn parse(contact_json: &str) -> Result<ContactRecord, JsError> {
+    serde_json::from_str(contact_json).map_err(|e| JsError::new(&format!("Invalid contact: {}", e)))
+}
+
+// The vCard text the QR code encodes
+#[wasm_bindgen(js_name = generateVcard)]
+pub fn generate_vcard_js(contact_json: &str) -> Result<String, JsError> {
+    let record = parse(contact_json)?;
+    Ok(generate_vcard(&record.contact()))
+}
+
+// The contact's QR code as SVG markup, honoring its colors, transparency, module shapes, size, scale and ec_level
+#[wasm_bindgen(js_name = renderSvg)]
+pub fn render_svg_js(contact_json: &str) -> Result<String, JsError> {
+    let record = parse(contact_json)?;
+    record.validate().map_err(|e| JsError::new(&e))?;
+    render_qr_svg(&generate_vcard(&record.contact()), &record.render).map_err(|e| JsError::new(&e))
+}